@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS ORDEN_ESTADO_HISTORIAL (
+    historial_id INT PRIMARY KEY AUTO_INCREMENT,
+    orden_id INT NOT NULL,
+    estado_anterior VARCHAR(32) NULL,
+    estado_nuevo VARCHAR(32) NOT NULL,
+    usuario_id INT NULL,
+    comentario VARCHAR(1024) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_historial_orden (orden_id, created_at),
+    FOREIGN KEY (orden_id) REFERENCES ORDEN_TRABAJO(orden_id) ON DELETE CASCADE,
+    FOREIGN KEY (usuario_id) REFERENCES USUARIO(usuario_id)
+);
+
+-- Estado inicial de las órdenes existentes para que su línea de tiempo tenga punto de partida
+INSERT INTO ORDEN_ESTADO_HISTORIAL (orden_id, estado_anterior, estado_nuevo, usuario_id, created_at)
+SELECT orden_id, NULL, estado, created_by, created_at FROM ORDEN_TRABAJO;
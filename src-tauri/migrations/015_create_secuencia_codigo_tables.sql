@@ -0,0 +1,49 @@
+-- Formato configurable de los códigos de documentos (OT, COT, INF)
+CREATE TABLE IF NOT EXISTS FORMATO_CODIGO (
+    prefijo VARCHAR(8) PRIMARY KEY,
+    formato VARCHAR(64) NOT NULL,
+    digitos INT NOT NULL DEFAULT 4,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+-- Correlativo por prefijo y año, se incrementa dentro de la transacción de inserción
+CREATE TABLE IF NOT EXISTS SECUENCIA_CODIGO (
+    prefijo VARCHAR(8),
+    anio INT,
+    ultimo_numero INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (prefijo, anio)
+);
+
+INSERT INTO FORMATO_CODIGO (prefijo, formato, digitos) VALUES
+    ('OT', '{prefijo}-{anio}-{numero}', 4),
+    ('COT', '{prefijo}-{anio}-{numero}', 4),
+    ('INF', '{prefijo}-{anio}-{numero}', 4);
+
+-- Los códigos de 12 caracteres no admiten más de 999 documentos por año
+ALTER TABLE COTIZACION MODIFY cotizacion_codigo VARCHAR(50);
+ALTER TABLE INFORME MODIFY informe_codigo VARCHAR(50);
+
+-- Continuar la numeración a partir de los códigos ya emitidos
+INSERT INTO SECUENCIA_CODIGO (prefijo, anio, ultimo_numero)
+SELECT 'OT',
+       CAST(SUBSTRING_INDEX(SUBSTRING_INDEX(orden_codigo, '-', 2), '-', -1) AS UNSIGNED),
+       MAX(CAST(SUBSTRING_INDEX(orden_codigo, '-', -1) AS UNSIGNED))
+FROM ORDEN_TRABAJO
+WHERE orden_codigo LIKE 'OT-%-%'
+GROUP BY 2;
+
+INSERT INTO SECUENCIA_CODIGO (prefijo, anio, ultimo_numero)
+SELECT 'COT',
+       CAST(SUBSTRING_INDEX(SUBSTRING_INDEX(cotizacion_codigo, '-', 2), '-', -1) AS UNSIGNED),
+       MAX(CAST(SUBSTRING_INDEX(cotizacion_codigo, '-', -1) AS UNSIGNED))
+FROM COTIZACION
+WHERE cotizacion_codigo LIKE 'COT-%-%'
+GROUP BY 2;
+
+INSERT INTO SECUENCIA_CODIGO (prefijo, anio, ultimo_numero)
+SELECT 'INF',
+       CAST(SUBSTRING_INDEX(SUBSTRING_INDEX(informe_codigo, '-', 2), '-', -1) AS UNSIGNED),
+       MAX(CAST(SUBSTRING_INDEX(informe_codigo, '-', -1) AS UNSIGNED))
+FROM INFORME
+WHERE informe_codigo LIKE 'INF-%-%'
+GROUP BY 2;
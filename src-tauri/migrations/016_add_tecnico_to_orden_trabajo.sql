@@ -0,0 +1,5 @@
+-- Técnico asignado a la reparación (USUARIO con rol 'tecnico')
+ALTER TABLE ORDEN_TRABAJO
+ADD COLUMN tecnico_id INT NULL AFTER created_by,
+ADD INDEX idx_orden_tecnico (tecnico_id, estado),
+ADD FOREIGN KEY (tecnico_id) REFERENCES USUARIO(usuario_id);
@@ -0,0 +1,41 @@
+-- Objetivos de SLA en horas por prioridad y garantía
+CREATE TABLE IF NOT EXISTS SLA_OBJETIVO (
+    prioridad ENUM('baja', 'media', 'alta') NOT NULL,
+    has_garantia BOOLEAN NOT NULL,
+    horas INT NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (prioridad, has_garantia)
+);
+
+INSERT INTO SLA_OBJETIVO (prioridad, has_garantia, horas) VALUES
+    ('alta', FALSE, 48),
+    ('alta', TRUE, 72),
+    ('media', FALSE, 120),
+    ('media', TRUE, 168),
+    ('baja', FALSE, 240),
+    ('baja', TRUE, 240);
+
+-- fecha_compromiso: plazo comprometido al recibir la orden
+-- fecha_resolucion: primera vez que la orden dejó de depender del taller
+ALTER TABLE ORDEN_TRABAJO
+ADD COLUMN fecha_compromiso TIMESTAMP NULL DEFAULT NULL,
+ADD COLUMN fecha_resolucion TIMESTAMP NULL DEFAULT NULL;
+
+UPDATE ORDEN_TRABAJO ot
+JOIN SLA_OBJETIVO s ON s.prioridad = ot.prioridad AND s.has_garantia = COALESCE(ot.has_garantia, FALSE)
+SET ot.fecha_compromiso = DATE_ADD(ot.created_at, INTERVAL s.horas HOUR);
+
+UPDATE ORDEN_TRABAJO ot
+JOIN (
+    SELECT orden_id, MIN(created_at) AS resuelta_at
+    FROM ORDEN_ESTADO_HISTORIAL
+    WHERE estado_anterior IS NOT NULL
+      AND estado_nuevo IN ('espera_de_retiro', 'entregado', 'abandonado', 'equipo_no_reparable', 'cotizacion_rechazada')
+    GROUP BY orden_id
+) h ON h.orden_id = ot.orden_id
+SET ot.fecha_resolucion = h.resuelta_at;
+
+UPDATE ORDEN_TRABAJO
+SET fecha_resolucion = COALESCE(finished_at, created_at)
+WHERE fecha_resolucion IS NULL
+  AND estado IN ('espera_de_retiro', 'entregado', 'abandonado', 'equipo_no_reparable', 'cotizacion_rechazada');
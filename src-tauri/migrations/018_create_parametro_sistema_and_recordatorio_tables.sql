@@ -0,0 +1,28 @@
+-- Parámetros del sistema editables por el administrador
+CREATE TABLE IF NOT EXISTS PARAMETRO_SISTEMA (
+    parametro_clave VARCHAR(64) PRIMARY KEY,
+    parametro_valor VARCHAR(255) NOT NULL,
+    parametro_desc VARCHAR(255),
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('abandono_dias', '90', 'Días en espera de retiro antes de marcar la orden como abandonada'),
+    ('abandono_recordatorios_dias', '30,60,85', 'Días en espera de retiro en que se envía cada recordatorio, separados por coma');
+
+-- Recordatorios de retiro enviados por orden (uno por nivel de escalamiento)
+CREATE TABLE IF NOT EXISTS RECORDATORIO_RETIRO (
+    orden_id INT NOT NULL,
+    nivel INT NOT NULL,
+    correo VARCHAR(256) NULL,
+    enviado BOOLEAN NOT NULL,
+    detalle VARCHAR(255) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (orden_id, nivel),
+    FOREIGN KEY (orden_id) REFERENCES ORDEN_TRABAJO(orden_id) ON DELETE CASCADE
+);
+
+-- Los valores auditados de parámetros y tareas automáticas superan los 32 caracteres
+ALTER TABLE AUDIT_LOG
+MODIFY log_prev_v VARCHAR(255),
+MODIFY log_new_v VARCHAR(255);
@@ -0,0 +1,15 @@
+-- Notas de seguimiento por orden de trabajo (solo se agregan; las anuladas se conservan)
+CREATE TABLE IF NOT EXISTS ORDEN_NOTA (
+    nota_id INT AUTO_INCREMENT PRIMARY KEY,
+    orden_id INT NOT NULL,
+    nota_texto TEXT NOT NULL,
+    es_interna BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    anulada_by INT NULL,
+    anulada_at TIMESTAMP NULL,
+    FOREIGN KEY (orden_id) REFERENCES ORDEN_TRABAJO(orden_id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id) ON DELETE SET NULL,
+    FOREIGN KEY (anulada_by) REFERENCES USUARIO(usuario_id) ON DELETE SET NULL,
+    INDEX idx_orden_nota_orden (orden_id, created_at)
+);
@@ -0,0 +1,25 @@
+-- Archivos adjuntos de órdenes de trabajo, equipos e informes
+CREATE TABLE IF NOT EXISTS ADJUNTO (
+    adjunto_id INT PRIMARY KEY AUTO_INCREMENT,
+    entidad VARCHAR(32) NOT NULL,
+    entidad_id INT NOT NULL,
+    nombre_archivo VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(128) NOT NULL,
+    tamano_bytes BIGINT NOT NULL,
+    hash_sha256 CHAR(64) NOT NULL,
+    -- 'archivo': contenido en el directorio de datos de la aplicación; 'base_datos': en contenido
+    almacenamiento VARCHAR(16) NOT NULL,
+    ruta VARCHAR(255) NULL,
+    contenido LONGBLOB NULL,
+    descripcion VARCHAR(512) NULL,
+    es_visible_cliente BOOLEAN NOT NULL DEFAULT FALSE,
+    uploaded_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_adjunto_entidad (entidad, entidad_id),
+    INDEX idx_adjunto_hash (hash_sha256),
+    FOREIGN KEY (uploaded_by) REFERENCES USUARIO(usuario_id) ON DELETE SET NULL
+);
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('adjuntos_almacenamiento', 'archivo', 'Dónde se guardan los adjuntos nuevos: archivo o base_datos'),
+    ('adjuntos_max_mb', '10', 'Tamaño máximo de cada adjunto en megabytes');
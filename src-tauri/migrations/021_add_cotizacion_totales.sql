@@ -0,0 +1,29 @@
+-- Precio unitario de cada pieza al momento de cotizar
+ALTER TABLE PIEZAS_COTIZACION
+ADD COLUMN precio_unitario INT NULL;
+
+UPDATE PIEZAS_COTIZACION pc
+JOIN PIEZA p ON pc.pieza_id = p.pieza_id
+SET pc.precio_unitario = COALESCE(p.pieza_precio, 0);
+
+-- Totales calculados por el backend y marca de diferencia con el total enviado
+ALTER TABLE COTIZACION
+ADD COLUMN subtotal_piezas INT NULL AFTER costo_reparacion,
+ADD COLUMN costo_total_informado INT NULL AFTER costo_total,
+ADD COLUMN total_con_diferencia BOOLEAN NOT NULL DEFAULT FALSE AFTER costo_total_informado;
+
+UPDATE COTIZACION c
+SET c.subtotal_piezas = (
+    SELECT COALESCE(SUM(COALESCE(pc.cantidad, 1) * pc.precio_unitario), 0)
+    FROM PIEZAS_COTIZACION pc WHERE pc.cotizacion_id = c.cotizacion_id
+);
+
+-- Las cotizaciones existentes conservan su total; las que no cuadran quedan marcadas
+UPDATE COTIZACION
+SET total_con_diferencia = TRUE,
+    costo_total_informado = costo_total
+WHERE costo_total IS NOT NULL
+  AND costo_total <> COALESCE(costo_revision, 0) + COALESCE(costo_reparacion, 0) + subtotal_piezas;
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('cotizacion_diferencia_total', 'rechazar', 'Si el total enviado no coincide con el calculado: rechazar o marcar');
@@ -0,0 +1,23 @@
+-- Descuento y exención por línea de piezas
+ALTER TABLE PIEZAS_COTIZACION
+ADD COLUMN descuento_pct INT NOT NULL DEFAULT 0,
+ADD COLUMN es_exenta BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Descuento global, tasa de IVA aplicada y desglose neto/IVA/exento.
+-- Las cotizaciones existentes quedan con IVA 0 para conservar sus totales.
+ALTER TABLE COTIZACION
+ADD COLUMN descuento_global_pct INT NOT NULL DEFAULT 0 AFTER subtotal_piezas,
+ADD COLUMN iva_porcentaje INT NOT NULL DEFAULT 0 AFTER descuento_global_pct,
+ADD COLUMN monto_descuento INT NULL AFTER iva_porcentaje,
+ADD COLUMN monto_neto INT NULL AFTER monto_descuento,
+ADD COLUMN monto_exento INT NULL AFTER monto_neto,
+ADD COLUMN monto_iva INT NULL AFTER monto_exento;
+
+UPDATE COTIZACION
+SET monto_descuento = 0,
+    monto_neto = COALESCE(costo_total, 0),
+    monto_exento = 0,
+    monto_iva = 0;
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('iva_porcentaje', '19', 'Tasa de IVA en porcentaje aplicada a las cotizaciones nuevas');
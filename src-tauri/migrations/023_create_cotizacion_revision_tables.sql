@@ -0,0 +1,42 @@
+-- Revisión vigente de cada cotización y quién la generó
+ALTER TABLE COTIZACION
+ADD COLUMN revision INT NOT NULL DEFAULT 1 AFTER cotizacion_codigo,
+ADD COLUMN revision_by INT NULL,
+ADD COLUMN revision_at TIMESTAMP NULL,
+ADD FOREIGN KEY (revision_by) REFERENCES USUARIO(usuario_id);
+
+-- Revisiones anteriores de cotizaciones enviadas (solo lectura)
+CREATE TABLE IF NOT EXISTS COTIZACION_REVISION (
+    cotizacion_id INT NOT NULL,
+    revision INT NOT NULL,
+    costo_revision INT,
+    costo_reparacion INT,
+    subtotal_piezas INT,
+    descuento_global_pct INT NOT NULL DEFAULT 0,
+    iva_porcentaje INT NOT NULL DEFAULT 0,
+    monto_descuento INT,
+    monto_neto INT,
+    monto_exento INT,
+    monto_iva INT,
+    costo_total INT,
+    informe TEXT NOT NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP NULL,
+    archivada_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (cotizacion_id, revision),
+    FOREIGN KEY (cotizacion_id) REFERENCES COTIZACION(cotizacion_id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id)
+);
+
+CREATE TABLE IF NOT EXISTS PIEZAS_COTIZACION_REVISION (
+    cotizacion_id INT NOT NULL,
+    revision INT NOT NULL,
+    pieza_id INT NOT NULL,
+    cantidad INT,
+    precio_unitario INT,
+    descuento_pct INT NOT NULL DEFAULT 0,
+    es_exenta BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (cotizacion_id, revision, pieza_id),
+    FOREIGN KEY (cotizacion_id, revision) REFERENCES COTIZACION_REVISION(cotizacion_id, revision) ON DELETE CASCADE,
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id)
+);
@@ -0,0 +1,33 @@
+-- Enlaces firmados enviados al cliente para aprobar o rechazar una cotización.
+-- Cada envío genera un enlace de un solo uso; el primero que se responde gana.
+CREATE TABLE IF NOT EXISTS COTIZACION_ENLACE_RESPUESTA (
+    enlace_id INT AUTO_INCREMENT PRIMARY KEY,
+    cotizacion_id INT NOT NULL,
+    orden_id INT NOT NULL,
+    revision INT NOT NULL,
+    destinatario VARCHAR(256) NOT NULL,
+    expira_at TIMESTAMP NOT NULL,
+    respuesta VARCHAR(16) NULL,
+    respondido_at TIMESTAMP NULL,
+    respondido_ip VARCHAR(45) NULL,
+    respondido_agente VARCHAR(255) NULL,
+    anulado_at TIMESTAMP NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (cotizacion_id) REFERENCES COTIZACION(cotizacion_id) ON DELETE CASCADE,
+    FOREIGN KEY (orden_id) REFERENCES ORDEN_TRABAJO(orden_id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id),
+    INDEX idx_enlace_cotizacion (cotizacion_id)
+);
+
+-- Claves internas generadas por la aplicación (no editables desde parámetros)
+CREATE TABLE IF NOT EXISTS CLAVE_FIRMA (
+    clave_nombre VARCHAR(64) PRIMARY KEY,
+    clave_valor VARBINARY(64) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('aprobacion_validez_horas', '72', 'Horas de validez de los enlaces para aprobar o rechazar una cotización'),
+    ('aprobacion_puerto', '8765', 'Puerto local del servidor de respuestas a cotizaciones (0 lo desactiva; requiere reiniciar)'),
+    ('aprobacion_url_base', 'http://localhost:8765', 'Dirección con la que el cliente llega al servidor de respuestas');
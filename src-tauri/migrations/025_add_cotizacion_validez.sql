@@ -0,0 +1,15 @@
+-- Vigencia de las cotizaciones: los precios de las piezas cambian cada mes
+ALTER TABLE COTIZACION
+ADD COLUMN valido_hasta DATE NULL AFTER informe,
+ADD COLUMN vencida_at TIMESTAMP NULL AFTER valido_hasta;
+
+-- Solo las cotizaciones abiertas (no aprobadas) reciben vigencia, contada
+-- desde la fecha de esta migración y no desde su emisión: así las que esperan
+-- respuesta no vencen de golpe al actualizar. Las aprobadas quedan sin fecha y
+-- por lo tanto no vencen.
+UPDATE COTIZACION
+SET valido_hasta = DATE_ADD(CURRENT_DATE, INTERVAL 30 DAY)
+WHERE valido_hasta IS NULL AND COALESCE(is_aprobada, FALSE) = FALSE;
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('cotizacion_validez_dias', '30', 'Días de validez de una cotización desde su emisión');
@@ -0,0 +1,24 @@
+-- Stock por pieza. stock_actual es el saldo del registro de movimientos y solo
+-- se modifica junto con un movimiento nuevo.
+ALTER TABLE PIEZA
+ADD COLUMN stock_actual INT NOT NULL DEFAULT 0 AFTER pieza_precio;
+
+-- Registro de movimientos de stock: solo se agregan filas, nunca se editan ni borran
+CREATE TABLE IF NOT EXISTS MOVIMIENTO_STOCK (
+    movimiento_id INT AUTO_INCREMENT PRIMARY KEY,
+    pieza_id INT NOT NULL,
+    tipo VARCHAR(20) NOT NULL,
+    cantidad INT NOT NULL,
+    stock_resultante INT NOT NULL,
+    informe_id INT NULL,
+    motivo VARCHAR(255) NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id),
+    FOREIGN KEY (informe_id) REFERENCES INFORME(informe_id) ON DELETE SET NULL,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id),
+    INDEX idx_movimiento_pieza (pieza_id, created_at)
+);
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('stock_negativo', 'rechazar', 'Qué hacer si un movimiento deja el stock bajo cero: rechazar o advertir');
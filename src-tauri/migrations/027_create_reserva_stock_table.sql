@@ -0,0 +1,26 @@
+-- Piezas reservadas por cotizaciones aprobadas. Lo disponible de una pieza es
+-- PIEZA.stock_actual menos la suma de sus reservas activas.
+CREATE TABLE IF NOT EXISTS RESERVA_STOCK (
+    reserva_id INT AUTO_INCREMENT PRIMARY KEY,
+    pieza_id INT NOT NULL,
+    cotizacion_id INT NULL,
+    cantidad INT NOT NULL,
+    estado VARCHAR(16) NOT NULL DEFAULT 'activa',
+    motivo_cierre VARCHAR(255) NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    cerrada_at TIMESTAMP NULL,
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id),
+    FOREIGN KEY (cotizacion_id) REFERENCES COTIZACION(cotizacion_id) ON DELETE SET NULL,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id),
+    INDEX idx_reserva_pieza (pieza_id, estado),
+    INDEX idx_reserva_cotizacion (cotizacion_id, estado)
+);
+
+-- Las cotizaciones ya aprobadas cuya orden sigue en reparación reservan sus piezas
+INSERT INTO RESERVA_STOCK (pieza_id, cotizacion_id, cantidad)
+SELECT pc.pieza_id, pc.cotizacion_id, COALESCE(pc.cantidad, 1)
+FROM PIEZAS_COTIZACION pc
+INNER JOIN COTIZACION c ON pc.cotizacion_id = c.cotizacion_id
+INNER JOIN ORDEN_TRABAJO ot ON ot.cotizacion_id = c.cotizacion_id
+WHERE c.is_aprobada = TRUE AND ot.estado IN ('aprobacion_pendiente', 'en_reparacion');
@@ -0,0 +1,18 @@
+-- Nivel mínimo y cantidad de reposición por pieza (NULL = sin alerta)
+ALTER TABLE PIEZA
+ADD COLUMN stock_minimo INT NULL AFTER stock_actual,
+ADD COLUMN cantidad_reposicion INT NULL AFTER stock_minimo;
+
+-- Un resumen de stock bajo por día; la fila se toma antes de enviar para que
+-- dos instancias de la aplicación no manden el mismo correo
+CREATE TABLE IF NOT EXISTS RESUMEN_STOCK_ENVIO (
+    fecha DATE PRIMARY KEY,
+    destinatario VARCHAR(256) NOT NULL,
+    piezas INT NOT NULL DEFAULT 0,
+    enviado_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('stock_consumo_dias', '30', 'Días de informes usados para calcular el consumo de cada pieza'),
+    ('stock_resumen_destinatario', '', 'Correo que recibe el resumen diario de piezas bajo el mínimo (vacío lo desactiva)');
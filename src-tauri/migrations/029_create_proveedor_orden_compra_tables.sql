@@ -0,0 +1,59 @@
+-- Proveedores de piezas
+CREATE TABLE IF NOT EXISTS PROVEEDOR (
+    proveedor_id INT AUTO_INCREMENT PRIMARY KEY,
+    proveedor_rut VARCHAR(20) NULL UNIQUE,
+    proveedor_nombre VARCHAR(100) NOT NULL,
+    proveedor_correo VARCHAR(256) NULL,
+    proveedor_telefono VARCHAR(30) NULL,
+    proveedor_contacto VARCHAR(100) NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id)
+);
+
+-- Código y costo con que cada proveedor vende una pieza
+CREATE TABLE IF NOT EXISTS PIEZA_PROVEEDOR (
+    proveedor_id INT NOT NULL,
+    pieza_id INT NOT NULL,
+    codigo_proveedor VARCHAR(64) NULL,
+    costo INT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    PRIMARY KEY (proveedor_id, pieza_id),
+    FOREIGN KEY (proveedor_id) REFERENCES PROVEEDOR(proveedor_id) ON DELETE CASCADE,
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id) ON DELETE CASCADE
+);
+
+-- Órdenes de compra: borrador -> enviada -> recibida_parcial -> recibida
+CREATE TABLE IF NOT EXISTS ORDEN_COMPRA (
+    orden_compra_id INT AUTO_INCREMENT PRIMARY KEY,
+    orden_compra_codigo VARCHAR(50) NOT NULL UNIQUE,
+    proveedor_id INT NOT NULL,
+    estado VARCHAR(20) NOT NULL DEFAULT 'borrador',
+    observaciones TEXT NULL,
+    enviada_at TIMESTAMP NULL,
+    recibida_at TIMESTAMP NULL,
+    created_by INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (proveedor_id) REFERENCES PROVEEDOR(proveedor_id),
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id),
+    INDEX idx_orden_compra_estado (estado)
+);
+
+CREATE TABLE IF NOT EXISTS PIEZAS_ORDEN_COMPRA (
+    orden_compra_id INT NOT NULL,
+    pieza_id INT NOT NULL,
+    cantidad INT NOT NULL,
+    cantidad_recibida INT NOT NULL DEFAULT 0,
+    costo_unitario INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (orden_compra_id, pieza_id),
+    FOREIGN KEY (orden_compra_id) REFERENCES ORDEN_COMPRA(orden_compra_id) ON DELETE CASCADE,
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id)
+);
+
+-- Las recepciones de compra quedan ligadas a su orden en el registro de stock
+ALTER TABLE MOVIMIENTO_STOCK
+ADD COLUMN orden_compra_id INT NULL AFTER informe_id,
+ADD FOREIGN KEY (orden_compra_id) REFERENCES ORDEN_COMPRA(orden_compra_id);
+
+INSERT INTO FORMATO_CODIGO (prefijo, formato, digitos) VALUES
+    ('OC', '{prefijo}-{anio}-{numero}', 3);
@@ -0,0 +1,30 @@
+-- Momento en que la pieza se agregó al informe; el margen usa los precios vigentes a esa fecha
+ALTER TABLE PIEZAS_INFORME
+ADD COLUMN usado_at TIMESTAMP NULL DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE PIEZAS_INFORME pi
+INNER JOIN INFORME i ON pi.informe_id = i.informe_id
+SET pi.usado_at = i.created_at;
+
+-- Historial de precios de venta y costos por pieza. La fila vigente tiene
+-- vigente_hasta en NULL; PIEZA.pieza_precio refleja siempre la venta vigente.
+CREATE TABLE IF NOT EXISTS PRECIO_PIEZA (
+    precio_id INT PRIMARY KEY AUTO_INCREMENT,
+    pieza_id INT NOT NULL,
+    tipo VARCHAR(10) NOT NULL,
+    precio INT NOT NULL,
+    vigente_desde TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    vigente_hasta TIMESTAMP NULL,
+    created_by INT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_precio_pieza_vigencia (pieza_id, tipo, vigente_desde),
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id)
+);
+
+-- Los cambios anteriores solo quedaron en la auditoría: el precio actual se
+-- toma como vigente desde la creación de la pieza
+INSERT INTO PRECIO_PIEZA (pieza_id, tipo, precio, vigente_desde)
+SELECT pieza_id, 'venta', pieza_precio, COALESCE(created_at, CURRENT_TIMESTAMP)
+FROM PIEZA
+WHERE pieza_precio IS NOT NULL;
@@ -0,0 +1,24 @@
+-- Piezas de alto valor (placas, amplificadores) que se siguen por unidad
+ALTER TABLE PIEZA
+ADD COLUMN requiere_serie BOOLEAN NOT NULL DEFAULT FALSE AFTER cantidad_reposicion;
+
+-- Cada unidad física con su número de serie. Al instalarse queda ligada al
+-- informe, y por él a la orden de trabajo y al equipo del cliente.
+CREATE TABLE IF NOT EXISTS UNIDAD_PIEZA (
+    unidad_id INT PRIMARY KEY AUTO_INCREMENT,
+    pieza_id INT NOT NULL,
+    numero_serie VARCHAR(64) NOT NULL,
+    estado VARCHAR(16) NOT NULL DEFAULT 'disponible',
+    informe_id INT NULL,
+    instalada_at TIMESTAMP NULL,
+    orden_compra_id INT NULL,
+    created_by INT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uk_unidad_pieza_serie (pieza_id, numero_serie),
+    INDEX idx_unidad_serie (numero_serie),
+    INDEX idx_unidad_informe (informe_id, pieza_id),
+    FOREIGN KEY (pieza_id) REFERENCES PIEZA(pieza_id),
+    FOREIGN KEY (informe_id) REFERENCES INFORME(informe_id) ON DELETE SET NULL,
+    FOREIGN KEY (orden_compra_id) REFERENCES ORDEN_COMPRA(orden_compra_id),
+    FOREIGN KEY (created_by) REFERENCES USUARIO(usuario_id)
+);
@@ -0,0 +1,12 @@
+-- Un informe finalizado queda congelado: su contenido canónico (campos y
+-- líneas de PIEZAS_INFORME) se resume en un SHA-256 para detectar cambios.
+-- Las correcciones se hacen con una enmienda que apunta al original.
+ALTER TABLE INFORME
+ADD COLUMN finalizado_at TIMESTAMP NULL,
+ADD COLUMN finalizado_by INT NULL,
+ADD COLUMN contenido_sha256 CHAR(64) NULL,
+ADD COLUMN enmienda_de INT NULL,
+ADD COLUMN motivo_enmienda VARCHAR(512) NULL,
+ADD UNIQUE KEY uk_informe_enmienda_de (enmienda_de),
+ADD FOREIGN KEY (finalizado_by) REFERENCES USUARIO(usuario_id),
+ADD FOREIGN KEY (enmienda_de) REFERENCES INFORME(informe_id);
@@ -0,0 +1,8 @@
+-- Datos de la empresa que encabezan los documentos PDF (informes,
+-- cotizaciones y comprobantes de recepción). Los vacíos no se imprimen.
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('empresa_nombre', 'Toscanini', 'Nombre de la empresa en el encabezado de los documentos PDF'),
+    ('empresa_rut', '', 'RUT de la empresa impreso en los documentos (vacío lo omite)'),
+    ('empresa_direccion', '', 'Dirección de la empresa impresa en los documentos (vacío la omite)'),
+    ('empresa_telefono', '', 'Teléfono de contacto impreso en los documentos (vacío lo omite)'),
+    ('empresa_correo', '', 'Correo de contacto impreso en los documentos (vacío lo omite)');
@@ -0,0 +1,4 @@
+-- Tope para el total de archivos (PDF y adjuntos visibles para el cliente)
+-- que se envían con un correo al cliente
+INSERT INTO PARAMETRO_SISTEMA (parametro_clave, parametro_valor, parametro_desc) VALUES
+    ('correo_adjuntos_max_mb', '20', 'Tamaño total máximo de los archivos adjuntos a un correo al cliente, en megabytes (hasta 40)');
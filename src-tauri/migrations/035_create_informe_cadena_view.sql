@@ -0,0 +1,13 @@
+-- Cada informe con sus antecesores por enmienda_de, incluido él mismo. Las
+-- unidades con serie quedan ligadas al informe donde se instalaron; las
+-- enmiendas las heredan a través de esta vista.
+CREATE OR REPLACE VIEW INFORME_CADENA AS
+WITH RECURSIVE cadena (informe_id, ancestro_id) AS (
+    SELECT informe_id, informe_id FROM INFORME
+    UNION ALL
+    SELECT c.informe_id, i.enmienda_de
+    FROM cadena c
+    INNER JOIN INFORME i ON i.informe_id = c.ancestro_id
+    WHERE i.enmienda_de IS NOT NULL
+)
+SELECT informe_id, ancestro_id FROM cadena;
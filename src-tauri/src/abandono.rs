@@ -0,0 +1,173 @@
+/// Política de abandono para órdenes en espera de retiro
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoliticaAbandono {
+    /// Días en espera de retiro tras los cuales la orden se marca como abandonada
+    pub dias_abandono: i64,
+    /// Días en espera de retiro en que se envía cada recordatorio (nivel 1, 2, ...)
+    pub recordatorios: Vec<i64>,
+}
+
+/// Días mínimos entre el último recordatorio que llegó al cliente y el abandono
+pub const DIAS_GRACIA_ULTIMO_AVISO: i64 = 7;
+
+/// Recordatorios registrados para una orden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistorialRecordatorios {
+    /// Nivel más alto registrado, se haya enviado o no
+    pub ultimo_nivel: i64,
+    /// Días desde el último recordatorio que se envió, si alguno se envió
+    pub dias_desde_entregado: Option<i64>,
+    /// Días desde el último intento de envío
+    pub dias_desde_intento: Option<i64>,
+}
+
+/// Próximo paso para una orden en espera de retiro
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccionAbandono {
+    Ninguna,
+    Recordatorio(i64),
+    Abandonar,
+}
+
+impl PoliticaAbandono {
+    /// Construye la política desde los parámetros `abandono_dias` y
+    /// `abandono_recordatorios_dias` (lista separada por comas)
+    pub fn from_parametros(dias_abandono: &str, recordatorios: &str) -> Result<Self, String> {
+        let dias_abandono = dias_abandono
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| format!("Días de abandono no válidos: {}", dias_abandono))?;
+
+        let recordatorios = recordatorios
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(|d| d.parse::<i64>().map_err(|_| format!("Día de recordatorio no válido: {}", d)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let politica = PoliticaAbandono { dias_abandono, recordatorios };
+        politica.validar()?;
+        Ok(politica)
+    }
+
+    pub fn validar(&self) -> Result<(), String> {
+        if self.dias_abandono <= 0 {
+            return Err("Los días de abandono deben ser mayores a cero".to_string());
+        }
+        if self.recordatorios.windows(2).any(|par| par[0] >= par[1]) {
+            return Err("Los días de recordatorio deben ser crecientes".to_string());
+        }
+        if self.recordatorios.iter().any(|d| *d <= 0 || *d >= self.dias_abandono) {
+            return Err("Los recordatorios deben enviarse antes del día de abandono".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn total_recordatorios(&self) -> i64 {
+        self.recordatorios.len() as i64
+    }
+
+    /// Decide el siguiente paso para una orden. Se envía a lo más un recordatorio por
+    /// revisión (el nivel más alto que corresponda). Cumplido el plazo, la orden solo
+    /// se abandona con el último aviso registrado y pasados `DIAS_GRACIA_ULTIMO_AVISO`
+    /// desde el último recordatorio que efectivamente llegó al cliente; si ninguno
+    /// llegó, el último aviso se reintenta una vez al día.
+    pub fn siguiente_accion(&self, dias_en_espera: i64, historial: &HistorialRecordatorios) -> AccionAbandono {
+        let total = self.total_recordatorios();
+
+        if dias_en_espera >= self.dias_abandono {
+            if total == 0 {
+                return AccionAbandono::Abandonar;
+            }
+            if historial.ultimo_nivel < total {
+                return AccionAbandono::Recordatorio(total);
+            }
+            return match (historial.dias_desde_entregado, historial.dias_desde_intento) {
+                (Some(dias), _) if dias >= DIAS_GRACIA_ULTIMO_AVISO => AccionAbandono::Abandonar,
+                (Some(_), _) => AccionAbandono::Ninguna,
+                (None, Some(dias)) if dias < 1 => AccionAbandono::Ninguna,
+                (None, _) => AccionAbandono::Recordatorio(total),
+            };
+        }
+
+        let nivel_debido = self.recordatorios.iter().filter(|d| dias_en_espera >= **d).count() as i64;
+        if nivel_debido > historial.ultimo_nivel {
+            AccionAbandono::Recordatorio(nivel_debido)
+        } else {
+            AccionAbandono::Ninguna
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn politica() -> PoliticaAbandono {
+        PoliticaAbandono::from_parametros("90", "30, 60,85").unwrap()
+    }
+
+    fn historial(ultimo_nivel: i64) -> HistorialRecordatorios {
+        HistorialRecordatorios { ultimo_nivel, dias_desde_entregado: Some(0), dias_desde_intento: Some(0) }
+    }
+
+    fn entregado_hace(ultimo_nivel: i64, dias: i64) -> HistorialRecordatorios {
+        HistorialRecordatorios { ultimo_nivel, dias_desde_entregado: Some(dias), dias_desde_intento: Some(dias) }
+    }
+
+    #[test]
+    fn test_from_parametros() {
+        assert_eq!(politica().recordatorios, vec![30, 60, 85]);
+        assert!(PoliticaAbandono::from_parametros("90", "").unwrap().recordatorios.is_empty());
+        assert!(PoliticaAbandono::from_parametros("90", "60,30").is_err());
+        assert!(PoliticaAbandono::from_parametros("90", "30,95").is_err());
+        assert!(PoliticaAbandono::from_parametros("0", "").is_err());
+        assert!(PoliticaAbandono::from_parametros("noventa", "30").is_err());
+    }
+
+    #[test]
+    fn test_recordatorios_escalonados() {
+        let p = politica();
+        let sin_avisos = HistorialRecordatorios::default();
+        assert_eq!(p.siguiente_accion(10, &sin_avisos), AccionAbandono::Ninguna);
+        assert_eq!(p.siguiente_accion(30, &sin_avisos), AccionAbandono::Recordatorio(1));
+        assert_eq!(p.siguiente_accion(45, &historial(1)), AccionAbandono::Ninguna);
+        assert_eq!(p.siguiente_accion(61, &historial(1)), AccionAbandono::Recordatorio(2));
+
+        // Si la revisión estuvo detenida solo se envía el nivel más alto pendiente
+        assert_eq!(p.siguiente_accion(70, &sin_avisos), AccionAbandono::Recordatorio(2));
+    }
+
+    #[test]
+    fn test_abandono_requiere_ultimo_aviso() {
+        let p = politica();
+        assert_eq!(p.siguiente_accion(95, &entregado_hace(1, 65)), AccionAbandono::Recordatorio(3));
+        assert_eq!(p.siguiente_accion(95, &entregado_hace(3, 10)), AccionAbandono::Abandonar);
+
+        let sin_recordatorios = PoliticaAbandono::from_parametros("90", "").unwrap();
+        assert_eq!(sin_recordatorios.siguiente_accion(90, &HistorialRecordatorios::default()), AccionAbandono::Abandonar);
+    }
+
+    #[test]
+    fn test_abandono_espera_tras_el_ultimo_aviso_entregado() {
+        let p = politica();
+        // Avisos atrasados: el último sale el día 95 y la orden no se abandona en la revisión siguiente
+        assert_eq!(p.siguiente_accion(95, &entregado_hace(3, 0)), AccionAbandono::Ninguna);
+        assert_eq!(p.siguiente_accion(95 + DIAS_GRACIA_ULTIMO_AVISO - 1, &entregado_hace(3, DIAS_GRACIA_ULTIMO_AVISO - 1)), AccionAbandono::Ninguna);
+        assert_eq!(p.siguiente_accion(95 + DIAS_GRACIA_ULTIMO_AVISO, &entregado_hace(3, DIAS_GRACIA_ULTIMO_AVISO)), AccionAbandono::Abandonar);
+    }
+
+    #[test]
+    fn test_avisos_fallidos_no_cuentan_para_el_abandono() {
+        let p = politica();
+        // El último aviso falló pero uno anterior llegó: el plazo corre desde ese
+        let fallo_el_ultimo = HistorialRecordatorios { ultimo_nivel: 3, dias_desde_entregado: Some(3), dias_desde_intento: Some(0) };
+        assert_eq!(p.siguiente_accion(95, &fallo_el_ultimo), AccionAbandono::Ninguna);
+
+        // Ninguno llegó: se reintenta el último aviso una vez al día y no se abandona
+        let nunca_llegaron = HistorialRecordatorios { ultimo_nivel: 3, dias_desde_entregado: None, dias_desde_intento: Some(0) };
+        assert_eq!(p.siguiente_accion(120, &nunca_llegaron), AccionAbandono::Ninguna);
+        let reintento = HistorialRecordatorios { dias_desde_intento: Some(1), ..nunca_llegaron };
+        assert_eq!(p.siguiente_accion(120, &reintento), AccionAbandono::Recordatorio(3));
+    }
+}
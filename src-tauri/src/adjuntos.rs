@@ -0,0 +1,403 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use sha2::{Digest, Sha256};
+use crate::auth::Permiso;
+
+/// Tamaño máximo por defecto de un adjunto, en megabytes
+pub const MAX_MB_POR_DEFECTO: u64 = 10;
+/// Límite absoluto configurable para un adjunto, en megabytes
+pub const MAX_MB_LIMITE: u64 = 64;
+/// Tamaño total por defecto de los archivos adjuntos a un correo, en megabytes
+pub const CORREO_MAX_MB_POR_DEFECTO: u64 = 20;
+/// El servicio de correo rechaza mensajes de más de 40 MB
+pub const CORREO_MAX_MB_LIMITE: u64 = 40;
+/// Largo máximo de la columna nombre_archivo
+const LARGO_MAXIMO_NOMBRE: usize = 255;
+
+/// Entidades que pueden tener archivos adjuntos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntidadAdjunto {
+    OrdenTrabajo,
+    Equipo,
+    Informe,
+}
+
+impl EntidadAdjunto {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntidadAdjunto::OrdenTrabajo => "ORDEN_TRABAJO",
+            EntidadAdjunto::Equipo => "EQUIPO",
+            EntidadAdjunto::Informe => "INFORME",
+        }
+    }
+
+    /// Permiso necesario para ver los adjuntos de la entidad
+    pub fn permiso_ver(&self) -> Permiso {
+        match self {
+            EntidadAdjunto::OrdenTrabajo => Permiso::VerOrdenes,
+            EntidadAdjunto::Equipo => Permiso::VerEquipos,
+            EntidadAdjunto::Informe => Permiso::VerInformes,
+        }
+    }
+
+    /// Permiso necesario para subir o eliminar adjuntos de la entidad
+    pub fn permiso_gestionar(&self) -> Permiso {
+        match self {
+            EntidadAdjunto::OrdenTrabajo => Permiso::EditarOrdenes,
+            EntidadAdjunto::Equipo => Permiso::GestionarEquipos,
+            EntidadAdjunto::Informe => Permiso::GestionarInformes,
+        }
+    }
+
+    /// Consulta que devuelve una fila si la entidad existe
+    pub fn consulta_existencia(&self) -> &'static str {
+        match self {
+            EntidadAdjunto::OrdenTrabajo => "SELECT orden_id FROM ORDEN_TRABAJO WHERE orden_id = ?",
+            EntidadAdjunto::Equipo => "SELECT equipo_id FROM EQUIPO WHERE equipo_id = ?",
+            EntidadAdjunto::Informe => "SELECT informe_id FROM INFORME WHERE informe_id = ?",
+        }
+    }
+}
+
+impl FromStr for EntidadAdjunto {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ORDEN_TRABAJO" => Ok(EntidadAdjunto::OrdenTrabajo),
+            "EQUIPO" => Ok(EntidadAdjunto::Equipo),
+            "INFORME" => Ok(EntidadAdjunto::Informe),
+            _ => Err(format!("Entidad no válida para adjuntos: {}", s)),
+        }
+    }
+}
+
+/// Dónde se guarda el contenido de los adjuntos nuevos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Almacenamiento {
+    /// Archivo en el directorio de datos de la aplicación
+    Archivo,
+    /// Columna BLOB en la base de datos
+    BaseDatos,
+}
+
+impl Almacenamiento {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Almacenamiento::Archivo => "archivo",
+            Almacenamiento::BaseDatos => "base_datos",
+        }
+    }
+}
+
+impl FromStr for Almacenamiento {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "archivo" => Ok(Almacenamiento::Archivo),
+            "base_datos" => Ok(Almacenamiento::BaseDatos),
+            _ => Err(format!("Almacenamiento no válido: {} (use 'archivo' o 'base_datos')", s)),
+        }
+    }
+}
+
+/// Interpreta el parámetro `adjuntos_max_mb` y lo devuelve en bytes
+pub fn limite_bytes(max_mb: &str) -> Result<u64, String> {
+    let mb = max_mb
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("Tamaño máximo no válido: {}", max_mb))?;
+    if mb == 0 || mb > MAX_MB_LIMITE {
+        return Err(format!("El tamaño máximo debe estar entre 1 y {} MB", MAX_MB_LIMITE));
+    }
+    Ok(mb * 1024 * 1024)
+}
+
+/// Interpreta el parámetro `correo_adjuntos_max_mb` y lo devuelve en bytes
+pub fn limite_correo_bytes(max_mb: &str) -> Result<u64, String> {
+    let mb = max_mb
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("Tamaño máximo no válido: {}", max_mb))?;
+    if mb == 0 || mb > CORREO_MAX_MB_LIMITE {
+        return Err(format!("El total de adjuntos por correo debe estar entre 1 y {} MB", CORREO_MAX_MB_LIMITE));
+    }
+    Ok(mb * 1024 * 1024)
+}
+
+/// Archivo que se envía adjunto a un correo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivoCorreo {
+    pub nombre_archivo: String,
+    pub mime_type: String,
+    pub contenido: Vec<u8>,
+}
+
+/// Largo máximo del detalle que queda en AUDIT_LOG
+const DETALLE_AUDITORIA_MAX_LARGO: usize = 255;
+
+/// Verifica que los archivos quepan en un correo
+pub fn verificar_tamano_correo(archivos: &[ArchivoCorreo], limite: u64) -> Result<(), String> {
+    let total = total_bytes(archivos);
+    if total > limite {
+        return Err(format!(
+            "Los adjuntos suman {} y el máximo por correo es {}: {}",
+            formatear_mb(total),
+            formatear_mb(limite),
+            archivos.iter()
+                .map(|a| format!("{} ({})", a.nombre_archivo, formatear_mb(a.contenido.len() as u64)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Verifica que los archivos quepan en un correo y renombra los repetidos
+/// ("foto.jpg", "foto (2).jpg"), porque el cliente los guarda con ese nombre
+pub fn preparar_archivos_correo(mut archivos: Vec<ArchivoCorreo>, limite: u64) -> Result<Vec<ArchivoCorreo>, String> {
+    verificar_tamano_correo(&archivos, limite)?;
+
+    let mut usados: Vec<String> = Vec::with_capacity(archivos.len());
+    for archivo in &mut archivos {
+        let ruta = Path::new(&archivo.nombre_archivo);
+        let base = ruta.file_stem().and_then(|b| b.to_str()).unwrap_or(&archivo.nombre_archivo).to_string();
+        let extension = ruta.extension().and_then(|e| e.to_str()).map(|e| format!(".{}", e)).unwrap_or_default();
+
+        let mut nombre = archivo.nombre_archivo.clone();
+        let mut n = 2;
+        while usados.iter().any(|u| u.eq_ignore_ascii_case(&nombre)) {
+            nombre = format!("{} ({}){}", base, n, extension);
+            n += 1;
+        }
+        usados.push(nombre.clone());
+        archivo.nombre_archivo = nombre;
+    }
+
+    Ok(archivos)
+}
+
+/// Detalle de los adjuntos de un correo para el registro de auditoría
+pub fn resumen_archivos_correo(archivos: &[ArchivoCorreo], limite: u64) -> String {
+    if archivos.is_empty() {
+        return "sin adjuntos".to_string();
+    }
+    format!(
+        "adjuntos: {}; total {} de {} bytes permitidos",
+        archivos.iter()
+            .map(|a| format!("{} ({} bytes)", a.nombre_archivo, a.contenido.len()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        total_bytes(archivos),
+        limite
+    )
+}
+
+/// Detalle de un correo rechazado por tamaño para el registro de auditoría.
+/// El total y el límite van primero porque los nombres pueden no caber.
+pub fn resumen_rechazo_correo(archivos: &[ArchivoCorreo], limite: u64) -> String {
+    format!(
+        "total {} de {} bytes permitidos; rechazados: {}",
+        total_bytes(archivos),
+        limite,
+        archivos.iter()
+            .map(|a| format!("{} ({} bytes)", a.nombre_archivo, a.contenido.len()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+    .chars()
+    .take(DETALLE_AUDITORIA_MAX_LARGO)
+    .collect()
+}
+
+fn total_bytes(archivos: &[ArchivoCorreo]) -> u64 {
+    archivos.iter().map(|a| a.contenido.len() as u64).sum()
+}
+
+fn formatear_mb(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Hash SHA-256 del contenido en hexadecimal
+pub fn hash_sha256(contenido: &[u8]) -> String {
+    Sha256::digest(contenido)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Deja solo el nombre del archivo, sin directorios ni caracteres de control
+pub fn sanitizar_nombre_archivo(nombre: &str) -> Result<String, String> {
+    let base = nombre.rsplit(['/', '\\']).next().unwrap_or_default();
+    let limpio: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(LARGO_MAXIMO_NOMBRE)
+        .collect();
+    let limpio = limpio.trim();
+
+    if limpio.is_empty() || limpio == "." || limpio == ".." {
+        return Err("Nombre de archivo no válido".to_string());
+    }
+    Ok(limpio.to_string())
+}
+
+/// Tipo MIME según la firma del contenido y, en su defecto, la extensión
+pub fn detectar_mime(nombre: &str, contenido: &[u8]) -> &'static str {
+    const FIRMAS: [(&[u8], &str); 5] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+
+    if contenido.len() >= 12 && &contenido[..4] == b"RIFF" && &contenido[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if let Some((_, mime)) = FIRMAS.iter().find(|(firma, _)| contenido.starts_with(firma)) {
+        // Los documentos de Office también son archivos zip
+        if *mime != "application/zip" {
+            return mime;
+        }
+    }
+
+    let extension = Path::new(nombre)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "txt" | "log" => "text/plain",
+        "csv" => "text/csv",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Ruta relativa del archivo dentro del directorio de adjuntos.
+/// El contenido se guarda por hash, así un mismo archivo subido dos veces se
+/// almacena una sola vez.
+pub fn ruta_relativa(hash: &str) -> String {
+    let prefijo: String = hash.chars().take(2).collect();
+    format!("{}/{}", prefijo, hash)
+}
+
+/// Directorio base de los adjuntos guardados como archivo
+pub fn directorio_adjuntos() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join(crate::config::APP_NAME).join("adjuntos"))
+        .ok_or_else(|| "No se encontró el directorio de datos de la aplicación".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entidad_desde_texto() {
+        for entidad in [EntidadAdjunto::OrdenTrabajo, EntidadAdjunto::Equipo, EntidadAdjunto::Informe] {
+            assert_eq!(entidad.as_str().parse::<EntidadAdjunto>(), Ok(entidad));
+        }
+        assert!("CLIENTE".parse::<EntidadAdjunto>().is_err());
+    }
+
+    #[test]
+    fn test_limite_bytes() {
+        assert_eq!(limite_bytes("10"), Ok(10 * 1024 * 1024));
+        assert!(limite_bytes("0").is_err());
+        assert!(limite_bytes("1000").is_err());
+        assert!(limite_bytes("diez").is_err());
+    }
+
+    fn archivo(nombre: &str, bytes: usize) -> ArchivoCorreo {
+        ArchivoCorreo {
+            nombre_archivo: nombre.to_string(),
+            mime_type: "application/octet-stream".to_string(),
+            contenido: vec![0; bytes],
+        }
+    }
+
+    #[test]
+    fn test_limite_correo_bytes() {
+        assert_eq!(limite_correo_bytes("20"), Ok(20 * 1024 * 1024));
+        assert!(limite_correo_bytes("0").is_err());
+        assert!(limite_correo_bytes("41").is_err());
+    }
+
+    #[test]
+    fn test_preparar_archivos_correo() {
+        let archivos = preparar_archivos_correo(
+            vec![archivo("INF-2026-001.pdf", 10), archivo("foto.jpg", 10), archivo("FOTO.jpg", 10), archivo("foto.jpg", 10)],
+            100,
+        ).unwrap();
+        let nombres: Vec<&str> = archivos.iter().map(|a| a.nombre_archivo.as_str()).collect();
+        assert_eq!(nombres, vec!["INF-2026-001.pdf", "foto.jpg", "FOTO (2).jpg", "foto (3).jpg"]);
+
+        assert!(preparar_archivos_correo(vec![archivo("a.pdf", 60), archivo("b.jpg", 50)], 100).is_err());
+        assert_eq!(preparar_archivos_correo(vec![archivo("a.pdf", 100)], 100).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_resumen_archivos_correo() {
+        assert_eq!(resumen_archivos_correo(&[], 100), "sin adjuntos");
+        assert_eq!(
+            resumen_archivos_correo(&[archivo("a.pdf", 60), archivo("b.jpg", 30)], 100),
+            "adjuntos: a.pdf (60 bytes), b.jpg (30 bytes); total 90 de 100 bytes permitidos"
+        );
+    }
+
+    #[test]
+    fn test_resumen_rechazo_correo() {
+        assert_eq!(
+            resumen_rechazo_correo(&[archivo("a.pdf", 60), archivo("b.jpg", 50)], 100),
+            "total 110 de 100 bytes permitidos; rechazados: a.pdf (60 bytes), b.jpg (50 bytes)"
+        );
+
+        let muchos: Vec<ArchivoCorreo> = (0..40).map(|i| archivo(&format!("foto-{}.jpg", i), 10)).collect();
+        let resumen = resumen_rechazo_correo(&muchos, 100);
+        assert_eq!(resumen.chars().count(), DETALLE_AUDITORIA_MAX_LARGO);
+        assert!(resumen.starts_with("total 400 de 100 bytes permitidos"));
+    }
+
+    #[test]
+    fn test_hash_sha256() {
+        assert_eq!(
+            hash_sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_sanitizar_nombre_archivo() {
+        assert_eq!(sanitizar_nombre_archivo("C:\\fotos\\radio.jpg").unwrap(), "radio.jpg");
+        assert_eq!(sanitizar_nombre_archivo("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitizar_nombre_archivo(" recibo\n.pdf ").unwrap(), "recibo.pdf");
+        assert!(sanitizar_nombre_archivo("fotos/").is_err());
+        assert!(sanitizar_nombre_archivo("..").is_err());
+    }
+
+    #[test]
+    fn test_detectar_mime() {
+        assert_eq!(detectar_mime("foto", b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(detectar_mime("foto.png", b"\xff\xd8\xff\xe0"), "image/jpeg");
+        assert_eq!(detectar_mime("medicion.docx", b"PK\x03\x04"), "application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+        assert_eq!(detectar_mime("notas.TXT", b"hola"), "text/plain");
+        assert_eq!(detectar_mime("datos.bin", b"\x00\x01"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_ruta_relativa() {
+        assert_eq!(ruta_relativa("ab12cd"), "ab/ab12cd");
+    }
+}
@@ -0,0 +1,451 @@
+use base64::{Engine as _, engine::general_purpose};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// Horas de validez de los enlaces si el parámetro no está definido
+pub const VALIDEZ_HORAS_POR_DEFECTO: i64 = 72;
+/// Puerto del servidor de respuestas si el parámetro no está definido
+pub const PUERTO_POR_DEFECTO: u16 = 8765;
+/// Ruta del servidor local que recibe las respuestas del cliente
+pub const RUTA_RESPUESTA: &str = "/cotizacion/respuesta";
+/// Ruta de verificación del servidor local
+pub const RUTA_ESTADO: &str = "/estado";
+
+/// Tamaño máximo de una solicitud HTTP aceptada por el servidor local
+pub const MAX_BYTES_SOLICITUD: usize = 16 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Respuesta del cliente a una cotización
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccionRespuesta {
+    Aprobar,
+    Rechazar,
+}
+
+impl FromStr for AccionRespuesta {
+    type Err = String;
+
+    fn from_str(accion: &str) -> Result<Self, Self::Err> {
+        match accion {
+            "aprobar" => Ok(AccionRespuesta::Aprobar),
+            "rechazar" => Ok(AccionRespuesta::Rechazar),
+            _ => Err("Acción no válida".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AccionRespuesta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AccionRespuesta {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccionRespuesta::Aprobar => "aprobar",
+            AccionRespuesta::Rechazar => "rechazar",
+        }
+    }
+
+    /// Valor guardado en COTIZACION_ENLACE_RESPUESTA.respuesta
+    pub fn resultado(&self) -> &'static str {
+        match self {
+            AccionRespuesta::Aprobar => "aprobada",
+            AccionRespuesta::Rechazar => "rechazada",
+        }
+    }
+}
+
+/// Contenido verificado de un token de respuesta
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRespuesta {
+    pub enlace_id: i32,
+    pub accion: AccionRespuesta,
+    pub expira: i64,
+}
+
+fn firma(secreto: &[u8], datos: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secreto).expect("HMAC acepta claves de cualquier largo");
+    mac.update(datos.as_bytes());
+    mac
+}
+
+/// Genera el token `enlace.accion.expira.firma` que va en el enlace del correo.
+/// La firma cubre los tres campos, así el cliente no puede cambiar la acción
+/// ni extender la vigencia.
+pub fn firmar_token(secreto: &[u8], enlace_id: i32, accion: AccionRespuesta, expira: i64) -> String {
+    let datos = format!("{}.{}.{}", enlace_id, accion, expira);
+    let firmado = firma(secreto, &datos).finalize().into_bytes();
+    format!("{}.{}", datos, general_purpose::URL_SAFE_NO_PAD.encode(firmado))
+}
+
+/// Verifica la firma y la vigencia de un token. `ahora` es un timestamp Unix.
+pub fn verificar_token(secreto: &[u8], token: &str, ahora: i64) -> Result<TokenRespuesta, String> {
+    let invalido = || "El enlace no es válido".to_string();
+
+    let (datos, firma_b64) = token.rsplit_once('.').ok_or_else(invalido)?;
+    let firma_recibida = general_purpose::URL_SAFE_NO_PAD.decode(firma_b64).map_err(|_| invalido())?;
+    firma(secreto, datos).verify_slice(&firma_recibida).map_err(|_| invalido())?;
+
+    let mut partes = datos.split('.');
+    let (Some(enlace), Some(accion), Some(expira), None) = (partes.next(), partes.next(), partes.next(), partes.next()) else {
+        return Err(invalido());
+    };
+
+    let token = TokenRespuesta {
+        enlace_id: enlace.parse().map_err(|_| invalido())?,
+        accion: accion.parse()?,
+        expira: expira.parse().map_err(|_| invalido())?,
+    };
+
+    if token.expira < ahora {
+        return Err("El enlace expiró. Solicite una nueva cotización al taller".to_string());
+    }
+    Ok(token)
+}
+
+/// URL del enlace de respuesta a partir de la dirección pública del servidor
+pub fn url_respuesta(url_base: &str, token: &str) -> String {
+    format!("{}{}?token={}", url_base.trim_end_matches('/'), RUTA_RESPUESTA, token)
+}
+
+/// Valida la URL base con la que el cliente llega al servidor local
+pub fn validar_url_base(url: &str) -> Result<String, String> {
+    let url = url.trim().trim_end_matches('/');
+    let resto = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://"));
+    match resto {
+        Some(host) if !host.is_empty() && !host.contains(char::is_whitespace) => Ok(url.to_string()),
+        _ => Err("La URL de respuesta debe comenzar con http:// o https://".to_string()),
+    }
+}
+
+/// Valida las horas de validez de los enlaces (entre 1 hora y 30 días)
+pub fn validar_validez_horas(valor: &str) -> Result<i64, String> {
+    match valor.trim().parse::<i64>() {
+        Ok(horas) if (1..=720).contains(&horas) => Ok(horas),
+        _ => Err("La validez de los enlaces debe estar entre 1 y 720 horas".to_string()),
+    }
+}
+
+/// Valida el puerto del servidor local; 0 deja el servidor desactivado
+pub fn validar_puerto(valor: &str) -> Result<u16, String> {
+    valor.trim().parse::<u16>()
+        .map_err(|_| "El puerto debe ser un número entre 0 y 65535".to_string())
+}
+
+/// Solicitud HTTP mínima recibida por el servidor local
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolicitudHttp {
+    pub metodo: String,
+    pub ruta: String,
+    pub parametros: HashMap<String, String>,
+    pub encabezados: HashMap<String, String>,
+}
+
+impl SolicitudHttp {
+    pub fn parametro(&self, nombre: &str) -> Option<&str> {
+        self.parametros.get(nombre).map(String::as_str)
+    }
+
+    pub fn encabezado(&self, nombre: &str) -> Option<&str> {
+        self.encabezados.get(nombre).map(String::as_str)
+    }
+}
+
+/// Interpreta los bytes recibidos. Devuelve `Ok(None)` si la solicitud aún no
+/// llegó completa (faltan encabezados o parte del cuerpo).
+pub fn parsear_solicitud(datos: &[u8]) -> Result<Option<SolicitudHttp>, String> {
+    let Some(fin_encabezados) = datos.windows(4).position(|w| w == b"\r\n\r\n") else {
+        if datos.len() >= MAX_BYTES_SOLICITUD {
+            return Err("Solicitud demasiado grande".to_string());
+        }
+        return Ok(None);
+    };
+
+    let cabecera = std::str::from_utf8(&datos[..fin_encabezados])
+        .map_err(|_| "Encabezados no válidos".to_string())?;
+    let mut lineas = cabecera.split("\r\n");
+
+    let linea_inicial = lineas.next().unwrap_or_default();
+    let mut partes = linea_inicial.split(' ');
+    let (Some(metodo), Some(destino), Some(version)) = (partes.next(), partes.next(), partes.next()) else {
+        return Err("Línea de solicitud no válida".to_string());
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err("Versión HTTP no soportada".to_string());
+    }
+
+    let mut encabezados = HashMap::new();
+    for linea in lineas {
+        if let Some((nombre, valor)) = linea.split_once(':') {
+            encabezados.insert(nombre.trim().to_ascii_lowercase(), valor.trim().to_string());
+        }
+    }
+
+    let largo_cuerpo: usize = match encabezados.get("content-length") {
+        Some(valor) => valor.parse().map_err(|_| "Content-Length no válido".to_string())?,
+        None => 0,
+    };
+    let inicio_cuerpo = fin_encabezados + 4;
+    if largo_cuerpo > MAX_BYTES_SOLICITUD || inicio_cuerpo + largo_cuerpo > MAX_BYTES_SOLICITUD {
+        return Err("Solicitud demasiado grande".to_string());
+    }
+    if datos.len() < inicio_cuerpo + largo_cuerpo {
+        return Ok(None);
+    }
+
+    let (ruta, consulta) = destino.split_once('?').unwrap_or((destino, ""));
+    let mut parametros = decodificar_formulario(consulta);
+
+    let es_formulario = encabezados
+        .get("content-type")
+        .is_some_and(|tipo| tipo.starts_with("application/x-www-form-urlencoded"));
+    if es_formulario {
+        let cuerpo = String::from_utf8_lossy(&datos[inicio_cuerpo..inicio_cuerpo + largo_cuerpo]);
+        parametros.extend(decodificar_formulario(&cuerpo));
+    }
+
+    Ok(Some(SolicitudHttp {
+        metodo: metodo.to_string(),
+        ruta: ruta.to_string(),
+        parametros,
+        encabezados,
+    }))
+}
+
+/// Decodifica pares `clave=valor` en formato application/x-www-form-urlencoded
+pub fn decodificar_formulario(texto: &str) -> HashMap<String, String> {
+    texto
+        .split('&')
+        .filter(|par| !par.is_empty())
+        .map(|par| {
+            let (clave, valor) = par.split_once('=').unwrap_or((par, ""));
+            (decodificar_porcentaje(clave), decodificar_porcentaje(valor))
+        })
+        .collect()
+}
+
+fn decodificar_porcentaje(texto: &str) -> String {
+    let bytes = texto.as_bytes();
+    let mut salida = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => salida.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        salida.push(byte);
+                        i += 2;
+                    }
+                    None => salida.push(b'%'),
+                }
+            }
+            byte => salida.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&salida).into_owned()
+}
+
+/// IP desde la que respondió el cliente. Si la conexión llega desde el propio
+/// equipo (túnel o proxy inverso) se usa la primera IP de X-Forwarded-For.
+pub fn ip_cliente(origen: SocketAddr, reenviado_por: Option<&str>) -> String {
+    if origen.ip().is_loopback() {
+        let reenviada = reenviado_por
+            .and_then(|valor| valor.split(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        if let Some(ip) = reenviada {
+            return ip.to_string();
+        }
+    }
+    origen.ip().to_string()
+}
+
+/// Escapa texto para incluirlo en las páginas del servidor local
+pub fn escapar_html(texto: &str) -> String {
+    let mut salida = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        match c {
+            '&' => salida.push_str("&amp;"),
+            '<' => salida.push_str("&lt;"),
+            '>' => salida.push_str("&gt;"),
+            '"' => salida.push_str("&quot;"),
+            '\'' => salida.push_str("&#39;"),
+            _ => salida.push(c),
+        }
+    }
+    salida
+}
+
+/// Página HTML simple con el formato de los correos del taller
+pub fn pagina(titulo: &str, contenido: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="es">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{titulo} - Toscanini</title>
+</head>
+<body style="font-family: Arial, sans-serif; max-width: 600px; margin: 0 auto; padding: 20px;">
+    <div style="text-align: center; margin-bottom: 30px;">
+        <h1 style="color: #333; margin: 0;">Toscanini</h1>
+        <p style="color: #666; margin: 5px 0;">Servicio Técnico Especializado</p>
+    </div>
+    <h2 style="color: #007bff; border-bottom: 2px solid #007bff; padding-bottom: 10px;">{titulo}</h2>
+    {contenido}
+</body>
+</html>"#,
+        titulo = escapar_html(titulo),
+        contenido = contenido
+    )
+}
+
+/// Página que pide confirmar la respuesta. Se responde con un formulario POST
+/// para que los filtros de correo que abren los enlaces no consuman el token.
+pub fn pagina_confirmacion(codigo_cotizacion: &str, total: i32, accion: AccionRespuesta, token: &str) -> String {
+    let (pregunta, boton, color) = match accion {
+        AccionRespuesta::Aprobar => ("¿Confirma que aprueba la cotización", "Aprobar cotización", "#28a745"),
+        AccionRespuesta::Rechazar => ("¿Confirma que rechaza la cotización", "Rechazar cotización", "#dc3545"),
+    };
+    let contenido = format!(
+        r#"<p>{pregunta} <strong>{codigo}</strong> por un total de <strong>${total}</strong>?</p>
+    <form method="post" action="{ruta}">
+        <input type="hidden" name="token" value="{token}">
+        <button type="submit" style="background-color: {color}; color: #fff; border: none; padding: 12px 24px; border-radius: 5px; font-size: 16px; cursor: pointer;">{boton}</button>
+    </form>"#,
+        pregunta = pregunta,
+        codigo = escapar_html(codigo_cotizacion),
+        total = total,
+        ruta = RUTA_RESPUESTA,
+        token = escapar_html(token),
+        color = color,
+        boton = boton
+    );
+    pagina("Respuesta a cotización", &contenido)
+}
+
+/// Página con un mensaje final (resultado o error)
+pub fn pagina_mensaje(titulo: &str, mensaje: &str) -> String {
+    pagina(titulo, &format!("<p>{}</p>", escapar_html(mensaje)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRETO: &[u8] = b"secreto-de-prueba";
+
+    #[test]
+    fn test_token_valido() {
+        let token = firmar_token(SECRETO, 42, AccionRespuesta::Aprobar, 2_000);
+        let verificado = verificar_token(SECRETO, &token, 1_000).unwrap();
+        assert_eq!(verificado, TokenRespuesta { enlace_id: 42, accion: AccionRespuesta::Aprobar, expira: 2_000 });
+    }
+
+    #[test]
+    fn test_token_expirado() {
+        let token = firmar_token(SECRETO, 42, AccionRespuesta::Rechazar, 2_000);
+        assert!(verificar_token(SECRETO, &token, 2_001).unwrap_err().contains("expiró"));
+    }
+
+    #[test]
+    fn test_token_alterado() {
+        let token = firmar_token(SECRETO, 42, AccionRespuesta::Rechazar, 2_000);
+        // Cambiar la acción o la vigencia invalida la firma
+        let alterado = token.replacen("rechazar", "aprobar", 1);
+        assert!(verificar_token(SECRETO, &alterado, 1_000).is_err());
+        let extendido = token.replacen("2000", "9000", 1);
+        assert!(verificar_token(SECRETO, &extendido, 1_000).is_err());
+        // Otra clave tampoco sirve
+        assert!(verificar_token(b"otra-clave", &token, 1_000).is_err());
+        assert!(verificar_token(SECRETO, "basura", 1_000).is_err());
+    }
+
+    #[test]
+    fn test_url_base() {
+        assert_eq!(validar_url_base("http://localhost:8765/").unwrap(), "http://localhost:8765");
+        assert!(validar_url_base("https://taller.example.cl").is_ok());
+        assert!(validar_url_base("localhost:8765").is_err());
+        assert!(validar_url_base("http://").is_err());
+        assert_eq!(
+            url_respuesta("http://localhost:8765/", "abc"),
+            "http://localhost:8765/cotizacion/respuesta?token=abc"
+        );
+    }
+
+    #[test]
+    fn test_parametros() {
+        assert_eq!(validar_validez_horas(" 48 ").unwrap(), 48);
+        assert!(validar_validez_horas("0").is_err());
+        assert!(validar_validez_horas("721").is_err());
+        assert_eq!(validar_puerto("0").unwrap(), 0);
+        assert_eq!(validar_puerto("8765").unwrap(), 8765);
+        assert!(validar_puerto("70000").is_err());
+    }
+
+    #[test]
+    fn test_parsear_get() {
+        let datos = b"GET /cotizacion/respuesta?token=1.aprobar.2.x%2By&otro HTTP/1.1\r\nHost: localhost\r\nX-Forwarded-For: 10.0.0.5\r\n\r\n";
+        let solicitud = parsear_solicitud(datos).unwrap().unwrap();
+        assert_eq!(solicitud.metodo, "GET");
+        assert_eq!(solicitud.ruta, RUTA_RESPUESTA);
+        assert_eq!(solicitud.parametro("token"), Some("1.aprobar.2.x+y"));
+        assert_eq!(solicitud.parametro("otro"), Some(""));
+        assert_eq!(solicitud.encabezado("x-forwarded-for"), Some("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_parsear_post_incompleto_y_completo() {
+        let cabecera = "POST /cotizacion/respuesta HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 12\r\n\r\n";
+        assert_eq!(parsear_solicitud(cabecera.as_bytes()).unwrap(), None);
+        assert_eq!(parsear_solicitud(b"GET / HTTP/1.1\r\nHost: x").unwrap(), None);
+
+        let completo = format!("{}token=a+b%21", cabecera);
+        let solicitud = parsear_solicitud(completo.as_bytes()).unwrap().unwrap();
+        assert_eq!(solicitud.metodo, "POST");
+        assert_eq!(solicitud.parametro("token"), Some("a b!"));
+    }
+
+    #[test]
+    fn test_parsear_invalido() {
+        assert!(parsear_solicitud(b"HOLA\r\n\r\n").is_err());
+        assert!(parsear_solicitud(b"GET / SPDY/3\r\n\r\n").is_err());
+        let grande = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BYTES_SOLICITUD);
+        assert!(parsear_solicitud(grande.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_decodificar_porcentaje_truncado() {
+        let parametros = decodificar_formulario("a=%4&b=%zz&c=%41");
+        assert_eq!(parametros["a"], "%4");
+        assert_eq!(parametros["b"], "%zz");
+        assert_eq!(parametros["c"], "A");
+    }
+
+    #[test]
+    fn test_ip_cliente() {
+        let local: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let remota: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+        assert_eq!(ip_cliente(local, None), "127.0.0.1");
+        assert_eq!(ip_cliente(local, Some("203.0.113.7, 10.0.0.1")), "203.0.113.7");
+        assert_eq!(ip_cliente(local, Some("no-es-ip")), "127.0.0.1");
+        // Un equipo de la red no puede suplantar su IP con el encabezado
+        assert_eq!(ip_cliente(remota, Some("203.0.113.7")), "192.168.1.20");
+    }
+
+    #[test]
+    fn test_escapar_html() {
+        assert_eq!(escapar_html("<a href=\"x\">'&'</a>"), "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;");
+    }
+}
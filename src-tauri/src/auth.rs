@@ -0,0 +1,241 @@
+use crate::commands::logs::log_action;
+use crate::commands::users::{find_usuario_by_session, Usuario};
+use std::str::FromStr;
+
+/// Error devuelto cuando el token de sesión no existe o ya expiró
+pub const SESSION_INVALID: &str = "SESSION_INVALID";
+/// Error devuelto cuando el rol del usuario no tiene el permiso requerido
+pub const PERMISSION_DENIED: &str = "PERMISSION_DENIED";
+
+/// Roles definidos en USUARIO.usuario_rol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rol {
+    Admin,
+    Tecnico,
+    Recepcion,
+}
+
+impl FromStr for Rol {
+    type Err = String;
+
+    fn from_str(rol: &str) -> Result<Self, Self::Err> {
+        match rol {
+            "admin" => Ok(Rol::Admin),
+            "tecnico" => Ok(Rol::Tecnico),
+            "recepcion" => Ok(Rol::Recepcion),
+            _ => Err(format!("Rol no válido: {}", rol)),
+        }
+    }
+}
+
+impl Rol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rol::Admin => "admin",
+            Rol::Tecnico => "tecnico",
+            Rol::Recepcion => "recepcion",
+        }
+    }
+
+    /// Matriz de permisos por rol
+    pub fn permite(&self, permiso: Permiso) -> bool {
+        use Permiso::*;
+        match self {
+            Rol::Admin => true,
+            Rol::Tecnico => matches!(
+                permiso,
+                VerClientes
+                    | VerEquipos
+                    | VerOrdenes
+                    | EditarOrdenes
+                    | CambiarEstadoOrden
+                    | VerCotizaciones
+                    | GestionarCotizaciones
+                    | VerInformes
+                    | GestionarInformes
+                    | VerPiezas
+                    | GestionarPiezas
+                    | VerCompras
+                    | EnviarNotificaciones
+            ),
+            Rol::Recepcion => matches!(
+                permiso,
+                VerClientes
+                    | GestionarClientes
+                    | VerEquipos
+                    | GestionarEquipos
+                    | VerOrdenes
+                    | CrearOrdenes
+                    | EditarOrdenes
+                    | CambiarEstadoOrden
+                    | AsignarTecnicos
+                    | VerCotizaciones
+                    | GestionarCotizaciones
+                    | VerInformes
+                    | VerPiezas
+                    | EnviarNotificaciones
+            ),
+        }
+    }
+}
+
+/// Acciones protegidas por los comandos de Tauri
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permiso {
+    VerUsuarios,
+    GestionarUsuarios,
+    VerAuditoria,
+    GestionarAuditoria,
+    Mantenimiento,
+    Configuracion,
+    VerClientes,
+    GestionarClientes,
+    EliminarClientes,
+    VerEquipos,
+    GestionarEquipos,
+    EliminarEquipos,
+    VerOrdenes,
+    CrearOrdenes,
+    EditarOrdenes,
+    CambiarEstadoOrden,
+    AsignarTecnicos,
+    EliminarOrdenes,
+    VerCotizaciones,
+    GestionarCotizaciones,
+    EliminarCotizaciones,
+    VerInformes,
+    GestionarInformes,
+    EliminarInformes,
+    VerPiezas,
+    GestionarPiezas,
+    EliminarPiezas,
+    VerCompras,
+    GestionarCompras,
+    VerMargenes,
+    EnviarNotificaciones,
+}
+
+impl Permiso {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permiso::VerUsuarios => "ver_usuarios",
+            Permiso::GestionarUsuarios => "gestionar_usuarios",
+            Permiso::VerAuditoria => "ver_auditoria",
+            Permiso::GestionarAuditoria => "gestionar_auditoria",
+            Permiso::Mantenimiento => "mantenimiento",
+            Permiso::Configuracion => "configuracion",
+            Permiso::VerClientes => "ver_clientes",
+            Permiso::GestionarClientes => "gestionar_clientes",
+            Permiso::EliminarClientes => "eliminar_clientes",
+            Permiso::VerEquipos => "ver_equipos",
+            Permiso::GestionarEquipos => "gestionar_equipos",
+            Permiso::EliminarEquipos => "eliminar_equipos",
+            Permiso::VerOrdenes => "ver_ordenes",
+            Permiso::CrearOrdenes => "crear_ordenes",
+            Permiso::EditarOrdenes => "editar_ordenes",
+            Permiso::CambiarEstadoOrden => "cambiar_estado_orden",
+            Permiso::AsignarTecnicos => "asignar_tecnicos",
+            Permiso::EliminarOrdenes => "eliminar_ordenes",
+            Permiso::VerCotizaciones => "ver_cotizaciones",
+            Permiso::GestionarCotizaciones => "gestionar_cotizaciones",
+            Permiso::EliminarCotizaciones => "eliminar_cotizaciones",
+            Permiso::VerInformes => "ver_informes",
+            Permiso::GestionarInformes => "gestionar_informes",
+            Permiso::EliminarInformes => "eliminar_informes",
+            Permiso::VerPiezas => "ver_piezas",
+            Permiso::GestionarPiezas => "gestionar_piezas",
+            Permiso::EliminarPiezas => "eliminar_piezas",
+            Permiso::VerCompras => "ver_compras",
+            Permiso::GestionarCompras => "gestionar_compras",
+            Permiso::VerMargenes => "ver_margenes",
+            Permiso::EnviarNotificaciones => "enviar_notificaciones",
+        }
+    }
+}
+
+/// Resuelve el token de sesión al usuario autenticado.
+/// Los tokens inválidos o expirados se registran en el log de auditoría.
+pub async fn require_session(session_token: &str) -> Result<Usuario, String> {
+    match find_usuario_by_session(session_token).await? {
+        Some(usuario) => Ok(usuario),
+        None => {
+            let _ = log_action(
+                "UNAUTHORIZED_ACCESS",
+                None,
+                "USUARIO",
+                None,
+                None,
+                Some(SESSION_INVALID)
+            ).await;
+            Err(SESSION_INVALID.to_string())
+        }
+    }
+}
+
+/// Verifica que el rol del usuario tenga el permiso solicitado.
+/// Los accesos denegados se registran en el log de auditoría.
+pub async fn authorize(usuario: &Usuario, permiso: Permiso) -> Result<(), String> {
+    let permitido = usuario.usuario_rol
+        .as_deref()
+        .and_then(|rol| rol.parse::<Rol>().ok())
+        .is_some_and(|rol| rol.permite(permiso));
+
+    if !permitido {
+        let _ = log_action(
+            "ACCESS_DENIED",
+            Some(usuario.usuario_id),
+            "USUARIO",
+            Some(usuario.usuario_id),
+            usuario.usuario_rol.as_deref(),
+            Some(permiso.as_str())
+        ).await;
+        return Err(PERMISSION_DENIED.to_string());
+    }
+
+    Ok(())
+}
+
+/// Resuelve la sesión y verifica el permiso en un solo paso
+pub async fn require_permission(session_token: &str, permiso: Permiso) -> Result<Usuario, String> {
+    let usuario = require_session(session_token).await?;
+    authorize(&usuario, permiso).await?;
+    Ok(usuario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matriz_de_permisos() {
+        // El administrador puede realizar cualquier acción
+        assert!(Rol::Admin.permite(Permiso::GestionarUsuarios));
+        assert!(Rol::Admin.permite(Permiso::GestionarAuditoria));
+
+        // Solo el administrador elimina registros o gestiona usuarios
+        assert!(!Rol::Tecnico.permite(Permiso::EliminarClientes));
+        assert!(!Rol::Recepcion.permite(Permiso::GestionarUsuarios));
+        assert!(!Rol::Recepcion.permite(Permiso::GestionarAuditoria));
+
+        // Cada rol conserva las acciones propias de su trabajo
+        assert!(Rol::Tecnico.permite(Permiso::GestionarInformes));
+        assert!(!Rol::Tecnico.permite(Permiso::CrearOrdenes));
+        assert!(Rol::Recepcion.permite(Permiso::CrearOrdenes));
+        assert!(Rol::Recepcion.permite(Permiso::AsignarTecnicos));
+        assert!(!Rol::Tecnico.permite(Permiso::AsignarTecnicos));
+        assert!(!Rol::Recepcion.permite(Permiso::GestionarInformes));
+
+        // Las compras las gestiona solo el administrador
+        assert!(Rol::Tecnico.permite(Permiso::VerCompras));
+        assert!(!Rol::Tecnico.permite(Permiso::GestionarCompras));
+        assert!(!Rol::Recepcion.permite(Permiso::VerCompras));
+        assert!(!Rol::Tecnico.permite(Permiso::VerMargenes));
+    }
+
+    #[test]
+    fn test_rol_from_str() {
+        assert_eq!("tecnico".parse::<Rol>(), Ok(Rol::Tecnico));
+        assert!("cliente".parse::<Rol>().is_err());
+        assert_eq!(Rol::Recepcion.as_str(), "recepcion");
+    }
+}
@@ -0,0 +1,450 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use chrono::{Duration, NaiveDate};
+
+/// Tasa de IVA usada cuando no hay una configurada
+pub const IVA_POR_DEFECTO: i32 = 19;
+/// Días de validez de una cotización nueva si el parámetro no está definido
+pub const VALIDEZ_DIAS_POR_DEFECTO: i64 = 30;
+
+/// Línea de piezas de una cotización con el precio unitario registrado al cotizar.
+/// Los precios son netos; las líneas exentas no pagan IVA.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineaCotizacion {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    pub precio_unitario: i32,
+    pub descuento_pct: i32,
+    pub es_exenta: bool,
+}
+
+impl LineaCotizacion {
+    /// Total de la línea antes del descuento
+    pub fn bruto(&self) -> i64 {
+        self.cantidad as i64 * self.precio_unitario as i64
+    }
+
+    pub fn descuento(&self) -> i64 {
+        porcentaje_de(self.bruto(), self.descuento_pct)
+    }
+
+    /// Total neto de la línea con su descuento aplicado
+    pub fn total(&self) -> i64 {
+        self.bruto() - self.descuento()
+    }
+}
+
+/// Costos y condiciones generales de una cotización. La revisión y la
+/// reparación son servicios afectos a IVA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CondicionesCotizacion {
+    pub costo_revision: Option<i32>,
+    pub costo_reparacion: Option<i32>,
+    pub descuento_global_pct: i32,
+    pub iva_porcentaje: i32,
+}
+
+/// Totales de una cotización calculados por el backend.
+/// `costo_total` = `monto_neto` + `monto_iva` + `monto_exento`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TotalesCotizacion {
+    /// Suma de las líneas de piezas con sus descuentos
+    pub subtotal_piezas: i32,
+    /// Descuentos de línea más el descuento global
+    pub monto_descuento: i32,
+    /// Base afecta a IVA después de descuentos
+    pub monto_neto: i32,
+    pub monto_exento: i32,
+    pub monto_iva: i32,
+    pub costo_total: i32,
+}
+
+/// Qué hacer cuando el total enviado no coincide con el calculado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoliticaDiferenciaTotal {
+    /// Rechazar la solicitud
+    Rechazar,
+    /// Guardar el total calculado y marcar la cotización con el total informado
+    Marcar,
+}
+
+impl PoliticaDiferenciaTotal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PoliticaDiferenciaTotal::Rechazar => "rechazar",
+            PoliticaDiferenciaTotal::Marcar => "marcar",
+        }
+    }
+}
+
+impl FromStr for PoliticaDiferenciaTotal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "rechazar" => Ok(PoliticaDiferenciaTotal::Rechazar),
+            "marcar" => Ok(PoliticaDiferenciaTotal::Marcar),
+            _ => Err(format!("Política no válida: {} (use 'rechazar' o 'marcar')", s)),
+        }
+    }
+}
+
+/// Porcentaje de un monto en pesos, redondeado al peso más cercano
+fn porcentaje_de(monto: i64, porcentaje: i32) -> i64 {
+    (monto * porcentaje as i64 + 50).div_euclid(100)
+}
+
+fn validar_porcentaje(porcentaje: i32, campo: &str) -> Result<(), String> {
+    if !(0..=100).contains(&porcentaje) {
+        return Err(format!("El {} debe estar entre 0 y 100", campo));
+    }
+    Ok(())
+}
+
+/// Valida una tasa de IVA configurada
+pub fn validar_iva(iva_porcentaje: &str) -> Result<i32, String> {
+    let iva = iva_porcentaje
+        .trim()
+        .parse::<i32>()
+        .map_err(|_| format!("Tasa de IVA no válida: {}", iva_porcentaje))?;
+    validar_porcentaje(iva, "IVA")?;
+    Ok(iva)
+}
+
+/// Valida los días de validez configurados para las cotizaciones
+pub fn validar_dias_validez(dias: &str) -> Result<i64, String> {
+    match dias.trim().parse::<i64>() {
+        Ok(dias) if (1..=365).contains(&dias) => Ok(dias),
+        _ => Err("La validez de las cotizaciones debe estar entre 1 y 365 días".to_string()),
+    }
+}
+
+/// Último día en que la cotización emitida en `desde` sigue vigente
+pub fn fecha_vencimiento(desde: NaiveDate, dias: i64) -> NaiveDate {
+    desde + Duration::days(dias)
+}
+
+/// Una cotización vence al día siguiente de `valido_hasta`; sin fecha no vence
+pub fn esta_vencida(valido_hasta: Option<NaiveDate>, hoy: NaiveDate) -> bool {
+    valido_hasta.is_some_and(|fecha| fecha < hoy)
+}
+
+fn a_i32(valor: i64, campo: &str) -> Result<i32, String> {
+    i32::try_from(valor).map_err(|_| format!("El {} excede el máximo permitido", campo))
+}
+
+/// Valida las líneas de piezas antes de guardarlas
+pub fn validar_lineas(lineas: &[LineaCotizacion]) -> Result<(), String> {
+    for (i, linea) in lineas.iter().enumerate() {
+        if linea.cantidad <= 0 {
+            return Err(format!("La cantidad de la pieza {} debe ser mayor a cero", linea.pieza_id));
+        }
+        if linea.precio_unitario < 0 {
+            return Err(format!("El precio de la pieza {} no puede ser negativo", linea.pieza_id));
+        }
+        validar_porcentaje(linea.descuento_pct, &format!("descuento de la pieza {}", linea.pieza_id))?;
+        if lineas[..i].iter().any(|l| l.pieza_id == linea.pieza_id) {
+            return Err(format!("La pieza {} está repetida en la cotización", linea.pieza_id));
+        }
+    }
+    Ok(())
+}
+
+/// Calcula los totales de la cotización. Primero se aplican los descuentos de
+/// cada línea, luego el descuento global sobre la base afecta y la exenta por
+/// separado, y finalmente el IVA sobre la base afecta.
+pub fn calcular_totales(condiciones: &CondicionesCotizacion, lineas: &[LineaCotizacion]) -> Result<TotalesCotizacion, String> {
+    let costo_revision = condiciones.costo_revision.unwrap_or(0);
+    let costo_reparacion = condiciones.costo_reparacion.unwrap_or(0);
+    if costo_revision < 0 || costo_reparacion < 0 {
+        return Err("Los costos de revisión y reparación no pueden ser negativos".to_string());
+    }
+    validar_porcentaje(condiciones.descuento_global_pct, "descuento global")?;
+    validar_porcentaje(condiciones.iva_porcentaje, "IVA")?;
+    validar_lineas(lineas)?;
+
+    let subtotal_piezas: i64 = lineas.iter().map(LineaCotizacion::total).sum();
+    let descuento_lineas: i64 = lineas.iter().map(LineaCotizacion::descuento).sum();
+
+    let afecto: i64 = costo_revision as i64
+        + costo_reparacion as i64
+        + lineas.iter().filter(|l| !l.es_exenta).map(LineaCotizacion::total).sum::<i64>();
+    let exento: i64 = lineas.iter().filter(|l| l.es_exenta).map(LineaCotizacion::total).sum();
+
+    let descuento_afecto = porcentaje_de(afecto, condiciones.descuento_global_pct);
+    let descuento_exento = porcentaje_de(exento, condiciones.descuento_global_pct);
+
+    let monto_neto = afecto - descuento_afecto;
+    let monto_exento = exento - descuento_exento;
+    let monto_iva = porcentaje_de(monto_neto, condiciones.iva_porcentaje);
+    let costo_total = monto_neto + monto_iva + monto_exento;
+
+    Ok(TotalesCotizacion {
+        subtotal_piezas: a_i32(subtotal_piezas, "subtotal de piezas")?,
+        monto_descuento: a_i32(descuento_lineas + descuento_afecto + descuento_exento, "descuento")?,
+        monto_neto: a_i32(monto_neto, "monto neto")?,
+        monto_exento: a_i32(monto_exento, "monto exento")?,
+        monto_iva: a_i32(monto_iva, "IVA")?,
+        costo_total: a_i32(costo_total, "total de la cotización")?,
+    })
+}
+
+/// Cambio de una línea de piezas entre dos revisiones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoCambioLinea {
+    Agregada,
+    Eliminada,
+    Modificada,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CambioLinea {
+    pub pieza_id: i32,
+    pub tipo: TipoCambioLinea,
+    pub antes: Option<LineaCotizacion>,
+    pub despues: Option<LineaCotizacion>,
+}
+
+/// Compara las líneas de dos revisiones por pieza. Devuelve primero las
+/// líneas eliminadas o modificadas en el orden original y luego las agregadas.
+pub fn diferencias_lineas(antes: &[LineaCotizacion], despues: &[LineaCotizacion]) -> Vec<CambioLinea> {
+    let mut cambios = Vec::new();
+
+    for anterior in antes {
+        match despues.iter().find(|l| l.pieza_id == anterior.pieza_id) {
+            None => cambios.push(CambioLinea {
+                pieza_id: anterior.pieza_id,
+                tipo: TipoCambioLinea::Eliminada,
+                antes: Some(anterior.clone()),
+                despues: None,
+            }),
+            Some(nueva) if nueva != anterior => cambios.push(CambioLinea {
+                pieza_id: anterior.pieza_id,
+                tipo: TipoCambioLinea::Modificada,
+                antes: Some(anterior.clone()),
+                despues: Some(nueva.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for nueva in despues {
+        if !antes.iter().any(|l| l.pieza_id == nueva.pieza_id) {
+            cambios.push(CambioLinea {
+                pieza_id: nueva.pieza_id,
+                tipo: TipoCambioLinea::Agregada,
+                antes: None,
+                despues: Some(nueva.clone()),
+            });
+        }
+    }
+
+    cambios
+}
+
+/// Diferencia entre la cantidad cotizada y la usada en el informe para una pieza
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiferenciaPiezaInforme {
+    pub pieza_id: i32,
+    pub cantidad_cotizada: i32,
+    pub cantidad_usada: i32,
+    pub precio_unitario: i32,
+    // Positivo si se usó más de lo cotizado
+    pub diferencia_monto: i64,
+}
+
+/// Compara las piezas cotizadas con las usadas y devuelve solo las que cambian.
+/// Las piezas cotizadas se valorizan al precio de la cotización; las que no se
+/// cotizaron, al precio con que vienen en `usadas` (el del catálogo).
+pub fn comparar_cotizado_usado(cotizadas: &[LineaCotizacion], usadas: &[LineaCotizacion]) -> Vec<DiferenciaPiezaInforme> {
+    let diferencia = |pieza_id: i32, cotizada: i32, usada: i32, precio: i32| DiferenciaPiezaInforme {
+        pieza_id,
+        cantidad_cotizada: cotizada,
+        cantidad_usada: usada,
+        precio_unitario: precio,
+        diferencia_monto: (usada as i64 - cotizada as i64) * precio as i64,
+    };
+
+    let mut diferencias = Vec::new();
+    for cotizada in cotizadas {
+        let usada = usadas.iter()
+            .find(|l| l.pieza_id == cotizada.pieza_id)
+            .map_or(0, |l| l.cantidad);
+        if usada != cotizada.cantidad {
+            diferencias.push(diferencia(cotizada.pieza_id, cotizada.cantidad, usada, cotizada.precio_unitario));
+        }
+    }
+    for usada in usadas {
+        if !cotizadas.iter().any(|l| l.pieza_id == usada.pieza_id) {
+            diferencias.push(diferencia(usada.pieza_id, 0, usada.cantidad, usada.precio_unitario));
+        }
+    }
+
+    diferencias
+}
+
+/// Contrasta el total enviado con el calculado. Devuelve el total informado
+/// cuando difiere y la política permite guardarlo marcado.
+pub fn verificar_total_informado(informado: Option<i32>, calculado: i32, politica: PoliticaDiferenciaTotal) -> Result<Option<i32>, String> {
+    match informado {
+        Some(informado) if informado != calculado => match politica {
+            PoliticaDiferenciaTotal::Rechazar => Err(format!(
+                "El total enviado ({}) no coincide con el calculado ({})",
+                informado, calculado
+            )),
+            PoliticaDiferenciaTotal::Marcar => Ok(Some(informado)),
+        },
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linea(pieza_id: i32, cantidad: i32, precio_unitario: i32) -> LineaCotizacion {
+        LineaCotizacion { pieza_id, cantidad, precio_unitario, descuento_pct: 0, es_exenta: false }
+    }
+
+    fn condiciones(costo_revision: Option<i32>, costo_reparacion: Option<i32>) -> CondicionesCotizacion {
+        CondicionesCotizacion { costo_revision, costo_reparacion, descuento_global_pct: 0, iva_porcentaje: 0 }
+    }
+
+    #[test]
+    fn test_calcular_totales() {
+        let totales = calcular_totales(&condiciones(Some(10000), Some(25000)), &[linea(1, 2, 3500), linea(2, 1, 12000)]).unwrap();
+        assert_eq!(totales.subtotal_piezas, 19000);
+        assert_eq!(totales.monto_neto, 54000);
+        assert_eq!(totales.costo_total, 54000);
+
+        let sin_piezas = calcular_totales(&condiciones(None, Some(5000)), &[]).unwrap();
+        assert_eq!(sin_piezas.subtotal_piezas, 0);
+        assert_eq!(sin_piezas.costo_total, 5000);
+    }
+
+    #[test]
+    fn test_iva_descuentos_y_exentos() {
+        let mut exenta = linea(2, 1, 10000);
+        exenta.es_exenta = true;
+        let mut con_descuento = linea(1, 2, 5000);
+        con_descuento.descuento_pct = 10;
+
+        let totales = calcular_totales(
+            &CondicionesCotizacion {
+                costo_revision: Some(10000),
+                costo_reparacion: None,
+                descuento_global_pct: 5,
+                iva_porcentaje: IVA_POR_DEFECTO,
+            },
+            &[con_descuento, exenta],
+        ).unwrap();
+
+        // Afecto: 10000 + (10000 - 1000) = 19000, con 5% global queda 18050
+        // Exento: 10000, con 5% global queda 9500
+        assert_eq!(totales.subtotal_piezas, 19000);
+        assert_eq!(totales.monto_descuento, 1000 + 950 + 500);
+        assert_eq!(totales.monto_neto, 18050);
+        assert_eq!(totales.monto_exento, 9500);
+        assert_eq!(totales.monto_iva, 3430); // 3429,5 redondeado
+        assert_eq!(totales.costo_total, 18050 + 3430 + 9500);
+    }
+
+    #[test]
+    fn test_lineas_no_validas() {
+        let sin_costos = condiciones(None, None);
+        assert!(calcular_totales(&sin_costos, &[linea(1, 0, 100)]).is_err());
+        assert!(calcular_totales(&sin_costos, &[linea(1, 1, -100)]).is_err());
+        assert!(calcular_totales(&sin_costos, &[linea(1, 1, 100), linea(1, 2, 100)]).is_err());
+        assert!(calcular_totales(&condiciones(Some(-1), None), &[]).is_err());
+        assert!(calcular_totales(&sin_costos, &[linea(1, i32::MAX, i32::MAX)]).is_err());
+
+        let mut descuento_excesivo = linea(1, 1, 100);
+        descuento_excesivo.descuento_pct = 120;
+        assert!(calcular_totales(&sin_costos, &[descuento_excesivo]).is_err());
+
+        let iva_negativo = CondicionesCotizacion { iva_porcentaje: -19, ..sin_costos };
+        assert!(calcular_totales(&iva_negativo, &[]).is_err());
+    }
+
+    #[test]
+    fn test_diferencias_lineas() {
+        let antes = [linea(1, 1, 1000), linea(2, 1, 500), linea(3, 2, 200)];
+        let mut modificada = linea(2, 3, 500);
+        modificada.descuento_pct = 10;
+        let despues = [modificada.clone(), linea(3, 2, 200), linea(4, 1, 900)];
+
+        let cambios = diferencias_lineas(&antes, &despues);
+        assert_eq!(cambios.len(), 3);
+        assert_eq!((cambios[0].pieza_id, &cambios[0].tipo), (1, &TipoCambioLinea::Eliminada));
+        assert_eq!((cambios[1].pieza_id, &cambios[1].tipo), (2, &TipoCambioLinea::Modificada));
+        assert_eq!(cambios[1].despues, Some(modificada));
+        assert_eq!((cambios[2].pieza_id, &cambios[2].tipo), (4, &TipoCambioLinea::Agregada));
+
+        assert!(diferencias_lineas(&antes, &antes).is_empty());
+    }
+
+    #[test]
+    fn test_comparar_cotizado_usado() {
+        let cotizadas = vec![linea(1, 2, 1000), linea(2, 1, 500), linea(3, 1, 300)];
+        // Se usó una unidad más de la pieza 1, no se usó la 2 y se agregó la 4
+        let usadas = vec![linea(1, 3, 1200), linea(3, 1, 300), linea(4, 2, 250)];
+
+        let diferencias = comparar_cotizado_usado(&cotizadas, &usadas);
+        assert_eq!(diferencias.len(), 3);
+        // La pieza cotizada se valoriza al precio cotizado aunque el catálogo cambió
+        assert_eq!((diferencias[0].pieza_id, diferencias[0].precio_unitario, diferencias[0].diferencia_monto), (1, 1000, 1000));
+        assert_eq!((diferencias[1].pieza_id, diferencias[1].cantidad_usada, diferencias[1].diferencia_monto), (2, 0, -500));
+        assert_eq!((diferencias[2].pieza_id, diferencias[2].cantidad_cotizada, diferencias[2].diferencia_monto), (4, 0, 500));
+
+        assert!(comparar_cotizado_usado(&cotizadas, &cotizadas).is_empty());
+    }
+
+    #[test]
+    fn test_validar_iva() {
+        assert_eq!(validar_iva("19"), Ok(19));
+        assert_eq!(validar_iva(" 0 "), Ok(0));
+        assert!(validar_iva("101").is_err());
+        assert!(validar_iva("19%").is_err());
+    }
+
+    #[test]
+    fn test_validez() {
+        let hoy = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_eq!(fecha_vencimiento(hoy, 30), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert!(!esta_vencida(Some(hoy), hoy));
+        assert!(esta_vencida(hoy.pred_opt(), hoy));
+        assert!(!esta_vencida(None, hoy));
+
+        assert_eq!(validar_dias_validez(" 15 "), Ok(15));
+        assert!(validar_dias_validez("0").is_err());
+        assert!(validar_dias_validez("366").is_err());
+        assert!(validar_dias_validez("treinta").is_err());
+    }
+
+    #[test]
+    fn test_verificar_total_informado() {
+        use PoliticaDiferenciaTotal::*;
+        assert_eq!(verificar_total_informado(None, 1000, Rechazar), Ok(None));
+        assert_eq!(verificar_total_informado(Some(1000), 1000, Rechazar), Ok(None));
+        assert!(verificar_total_informado(Some(900), 1000, Rechazar).is_err());
+        assert_eq!(verificar_total_informado(Some(900), 1000, Marcar), Ok(Some(900)));
+    }
+
+    #[test]
+    fn test_solicitud_de_la_interfaz() {
+        // La interfaz envía costos y piezas sin total; con los parámetros por
+        // defecto (IVA 19% y política 'rechazar') la cotización se acepta
+        let condiciones = CondicionesCotizacion {
+            costo_revision: Some(25000),
+            costo_reparacion: Some(0),
+            descuento_global_pct: 0,
+            iva_porcentaje: IVA_POR_DEFECTO,
+        };
+        let totales = calcular_totales(&condiciones, &[linea(1, 2, 3500)]).unwrap();
+        assert_eq!(totales.costo_total, 38080);
+        assert_eq!(verificar_total_informado(None, totales.costo_total, PoliticaDiferenciaTotal::Rechazar), Ok(None));
+        // El neto sin IVA no es el total y se rechazaría si se enviara
+        assert!(verificar_total_informado(Some(32000), totales.costo_total, PoliticaDiferenciaTotal::Rechazar).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{Datelike, Utc};
+use sqlx::{MySql, Transaction};
+
+/// Formato usado cuando un prefijo no tiene fila en FORMATO_CODIGO
+pub const FORMATO_POR_DEFECTO: &str = "{prefijo}-{anio}-{numero}";
+pub const DIGITOS_POR_DEFECTO: i32 = 4;
+/// Largo máximo de las columnas *_codigo
+pub const LARGO_MAXIMO_CODIGO: usize = 50;
+
+/// Documentos que reciben un código correlativo por año
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipoDocumento {
+    OrdenTrabajo,
+    Cotizacion,
+    Informe,
+    OrdenCompra,
+}
+
+impl TipoDocumento {
+    pub fn prefijo(&self) -> &'static str {
+        match self {
+            TipoDocumento::OrdenTrabajo => "OT",
+            TipoDocumento::Cotizacion => "COT",
+            TipoDocumento::Informe => "INF",
+            TipoDocumento::OrdenCompra => "OC",
+        }
+    }
+
+    pub fn from_prefijo(prefijo: &str) -> Result<Self, String> {
+        match prefijo {
+            "OT" => Ok(TipoDocumento::OrdenTrabajo),
+            "COT" => Ok(TipoDocumento::Cotizacion),
+            "INF" => Ok(TipoDocumento::Informe),
+            "OC" => Ok(TipoDocumento::OrdenCompra),
+            _ => Err(format!("Prefijo de documento no válido: {}", prefijo)),
+        }
+    }
+}
+
+/// Reemplaza {prefijo}, {anio} y {numero} en el formato.
+/// El número se rellena con ceros hasta `digitos`.
+pub fn formatear_codigo(formato: &str, digitos: i32, prefijo: &str, anio: i32, numero: i32) -> String {
+    let ancho = digitos.max(1) as usize;
+    formato
+        .replace("{prefijo}", prefijo)
+        .replace("{anio}", &anio.to_string())
+        .replace("{numero}", &format!("{:0ancho$}", numero, ancho = ancho))
+}
+
+/// Valida que un formato genere códigos únicos y que quepan en la columna
+pub fn validar_formato(formato: &str, digitos: i32) -> Result<(), String> {
+    if !formato.contains("{numero}") {
+        return Err("El formato debe incluir {numero}".to_string());
+    }
+    // El correlativo se reinicia cada año, sin el año los códigos se repetirían
+    if !formato.contains("{anio}") {
+        return Err("El formato debe incluir {anio}".to_string());
+    }
+    if !(1..=10).contains(&digitos) {
+        return Err("La cantidad de dígitos debe estar entre 1 y 10".to_string());
+    }
+
+    let ejemplo = formatear_codigo(formato, digitos, "XXXXXXXX", 9999, 0);
+    if ejemplo.len() > LARGO_MAXIMO_CODIGO {
+        return Err(format!(
+            "El formato genera códigos de más de {} caracteres",
+            LARGO_MAXIMO_CODIGO
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reserva el siguiente correlativo del año para el tipo de documento y devuelve
+/// el código formateado. Debe llamarse dentro de la transacción que inserta el
+/// documento: la fila de SECUENCIA_CODIGO queda bloqueada hasta el commit y un
+/// rollback libera el número.
+pub async fn siguiente_codigo(tx: &mut Transaction<'_, MySql>, tipo: TipoDocumento) -> Result<String, String> {
+    let prefijo = tipo.prefijo();
+    let anio = Utc::now().year();
+
+    let (formato, digitos) = sqlx::query_as::<_, (String, i32)>(
+        "SELECT formato, digitos FROM FORMATO_CODIGO WHERE prefijo = ?"
+    )
+    .bind(prefijo)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .unwrap_or_else(|| (FORMATO_POR_DEFECTO.to_string(), DIGITOS_POR_DEFECTO));
+
+    sqlx::query(
+        "INSERT INTO SECUENCIA_CODIGO (prefijo, anio, ultimo_numero) VALUES (?, ?, 1)
+         ON DUPLICATE KEY UPDATE ultimo_numero = ultimo_numero + 1"
+    )
+    .bind(prefijo)
+    .bind(anio)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let numero = sqlx::query_scalar::<_, i32>(
+        "SELECT ultimo_numero FROM SECUENCIA_CODIGO WHERE prefijo = ? AND anio = ?"
+    )
+    .bind(prefijo)
+    .bind(anio)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(formatear_codigo(&formato, digitos, prefijo, anio, numero))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatear_codigo() {
+        assert_eq!(formatear_codigo(FORMATO_POR_DEFECTO, 4, "OT", 2025, 7), "OT-2025-0007");
+        assert_eq!(formatear_codigo(FORMATO_POR_DEFECTO, 3, "COT", 2025, 1000), "COT-2025-1000");
+        assert_eq!(formatear_codigo("{anio}/{prefijo}{numero}", 5, "INF", 2026, 42), "2026/INF00042");
+        assert_eq!(formatear_codigo(FORMATO_POR_DEFECTO, 3, "OC", 2026, 5), "OC-2026-005");
+    }
+
+    #[test]
+    fn test_validar_formato() {
+        assert!(validar_formato(FORMATO_POR_DEFECTO, 4).is_ok());
+        assert!(validar_formato("{prefijo}-{anio}", 4).is_err());
+        assert!(validar_formato("{prefijo}-{numero}", 4).is_err());
+        assert!(validar_formato(FORMATO_POR_DEFECTO, 0).is_err());
+        assert!(validar_formato(&format!("{}-{{anio}}-{{numero}}", "X".repeat(50)), 4).is_err());
+    }
+
+    #[test]
+    fn test_tipo_documento_prefijo() {
+        for tipo in [
+            TipoDocumento::OrdenTrabajo,
+            TipoDocumento::Cotizacion,
+            TipoDocumento::Informe,
+            TipoDocumento::OrdenCompra,
+        ] {
+            assert_eq!(TipoDocumento::from_prefijo(tipo.prefijo()), Ok(tipo));
+        }
+        assert!(TipoDocumento::from_prefijo("XX").is_err());
+    }
+}
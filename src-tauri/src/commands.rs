@@ -9,3 +9,18 @@ pub mod database;
 pub mod config;
 
 
+pub mod codigos;
+pub mod sla;
+pub mod parametros;
+pub mod abandono;
+pub mod notas_orden;
+pub mod adjuntos;
+pub mod revisiones_cotizacion;
+pub mod aprobacion_cotizacion;
+pub mod vencimiento_cotizacion;
+pub mod inventario;
+pub mod compras;
+pub mod precios;
+pub mod series;
+pub mod finalizacion_informe;
+pub mod documentos_pdf;
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::abandono::{AccionAbandono, HistorialRecordatorios, PoliticaAbandono};
+use crate::commands::ordenes_trabajo::{aplicar_cambio_estado, fetch_orden_trabajo_by_id};
+use crate::commands::parametros::obtener_politica_abandono;
+use crate::email::EmailService;
+use crate::estado_orden::EstadoOrden;
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResumenRevisionAbandono {
+    pub revisadas: i64,
+    pub recordatorios_enviados: i64,
+    pub recordatorios_fallidos: i64,
+    pub abandonadas: i64,
+    pub errores: Vec<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct OrdenEnEspera {
+    orden_id: i32,
+    cliente_nombre: Option<String>,
+    cliente_correo: Option<String>,
+    en_espera_desde: DateTime<Utc>,
+    ultimo_nivel: i64,
+    ultimo_entregado_at: Option<DateTime<Utc>>,
+    ultimo_intento_at: Option<DateTime<Utc>>,
+}
+
+/// Ejecutar manualmente la revisión de órdenes abandonadas
+#[tauri::command]
+pub async fn ejecutar_revision_abandono(session_token: String) -> Result<ResumenRevisionAbandono, String> {
+    require_permission(&session_token, Permiso::Mantenimiento).await?;
+    procesar_ordenes_en_espera_de_retiro().await
+}
+
+/// Revisa las órdenes en espera de retiro: envía el recordatorio que corresponda
+/// o, agotados los avisos y cumplido el plazo, las marca como abandonadas.
+/// La ejecuta periódicamente la tarea de fondo, sin sesión de usuario.
+pub(crate) async fn procesar_ordenes_en_espera_de_retiro() -> Result<ResumenRevisionAbandono, String> {
+    let pool = get_db_pool_safe()?;
+    let politica = obtener_politica_abandono().await?;
+
+    // La espera se cuenta desde la última entrada al estado según el historial
+    let ordenes = sqlx::query_as::<_, OrdenEnEspera>(
+        "SELECT ot.orden_id, c.cliente_nombre, c.cliente_correo,
+                COALESCE(
+                    (SELECT MAX(h.created_at) FROM ORDEN_ESTADO_HISTORIAL h
+                     WHERE h.orden_id = ot.orden_id AND h.estado_nuevo = 'espera_de_retiro'),
+                    ot.fecha_resolucion,
+                    ot.created_at
+                ) as en_espera_desde,
+                CAST(COALESCE(
+                    (SELECT MAX(r.nivel) FROM RECORDATORIO_RETIRO r WHERE r.orden_id = ot.orden_id),
+                    0
+                ) AS SIGNED) as ultimo_nivel,
+                (SELECT MAX(r.created_at) FROM RECORDATORIO_RETIRO r
+                 WHERE r.orden_id = ot.orden_id AND r.enviado = TRUE) as ultimo_entregado_at,
+                (SELECT MAX(r.created_at) FROM RECORDATORIO_RETIRO r
+                 WHERE r.orden_id = ot.orden_id) as ultimo_intento_at
+         FROM ORDEN_TRABAJO ot
+         LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+         LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         WHERE ot.estado = 'espera_de_retiro'"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut resumen = ResumenRevisionAbandono::default();
+    let ahora = Utc::now();
+
+    for orden in ordenes {
+        resumen.revisadas += 1;
+        let dias_en_espera = (ahora - orden.en_espera_desde).num_days();
+        let historial = HistorialRecordatorios {
+            ultimo_nivel: orden.ultimo_nivel,
+            dias_desde_entregado: orden.ultimo_entregado_at.map(|t| (ahora - t).num_days()),
+            dias_desde_intento: orden.ultimo_intento_at.map(|t| (ahora - t).num_days()),
+        };
+
+        match politica.siguiente_accion(dias_en_espera, &historial) {
+            AccionAbandono::Ninguna => {}
+            AccionAbandono::Recordatorio(nivel) => {
+                match enviar_recordatorio(&orden, nivel, &politica).await {
+                    Ok(true) => resumen.recordatorios_enviados += 1,
+                    Ok(false) => resumen.recordatorios_fallidos += 1,
+                    Err(e) => resumen.errores.push(format!("Orden {}: {}", orden.orden_id, e)),
+                }
+            }
+            AccionAbandono::Abandonar => {
+                let comentario = format!(
+                    "Marcada automáticamente tras {} días en espera de retiro",
+                    dias_en_espera
+                );
+                match aplicar_cambio_estado(orden.orden_id, EstadoOrden::Abandonado, None, Some(&comentario)).await {
+                    Ok(()) => {
+                        resumen.abandonadas += 1;
+                        let _ = log_action(
+                            "AUTO_ABANDONO",
+                            None,
+                            "ORDEN_TRABAJO",
+                            Some(orden.orden_id),
+                            Some(EstadoOrden::EsperaDeRetiro.as_str()),
+                            Some(&format!("{} días sin retiro", dias_en_espera))
+                        ).await;
+                    }
+                    Err(e) => resumen.errores.push(format!("Orden {}: {}", orden.orden_id, e)),
+                }
+            }
+        }
+    }
+
+    Ok(resumen)
+}
+
+/// Envía y registra un recordatorio. Un envío fallido o un cliente sin correo
+/// también quedan registrados para que el escalamiento no se detenga; el
+/// reintento de un nivel fallido reemplaza su registro. Devuelve si el correo se envió.
+async fn enviar_recordatorio(orden: &OrdenEnEspera, nivel: i64, politica: &PoliticaAbandono) -> Result<bool, String> {
+    let pool = get_db_pool_safe()?;
+    let total = politica.total_recordatorios();
+
+    let orden_trabajo = fetch_orden_trabajo_by_id(orden.orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    let fecha_limite = orden.en_espera_desde + Duration::days(politica.dias_abandono);
+
+    let resultado = match orden.cliente_correo.as_deref().filter(|c| !c.trim().is_empty()) {
+        None => Err("El cliente no tiene un correo electrónico registrado".to_string()),
+        Some(correo) => match EmailService::new() {
+            Ok(email_service) => email_service.send_recordatorio_retiro_email(
+                correo,
+                orden.cliente_nombre.as_deref().unwrap_or("Cliente"),
+                &orden_trabajo,
+                nivel,
+                total,
+                fecha_limite,
+            ).await,
+            Err(e) => Err(format!("Error inicializando servicio de email: {}", e)),
+        },
+    };
+
+    sqlx::query(
+        "INSERT INTO RECORDATORIO_RETIRO (orden_id, nivel, correo, enviado, detalle) VALUES (?, ?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE correo = VALUES(correo), enviado = VALUES(enviado),
+                                 detalle = VALUES(detalle), created_at = CURRENT_TIMESTAMP"
+    )
+    .bind(orden.orden_id)
+    .bind(nivel)
+    .bind(&orden.cliente_correo)
+    .bind(resultado.is_ok())
+    .bind(resultado.as_ref().err().map(|e| e.chars().take(255).collect::<String>()))
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let accion = if resultado.is_ok() { "SEND_RECORDATORIO_RETIRO" } else { "RECORDATORIO_RETIRO_FALLIDO" };
+    let _ = log_action(
+        accion,
+        None,
+        "ORDEN_TRABAJO",
+        Some(orden.orden_id),
+        None,
+        Some(&format!("Recordatorio {}/{}", nivel, total))
+    ).await;
+
+    Ok(resultado.is_ok())
+}
@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use base64::{Engine as _, engine::general_purpose};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{authorize, require_session};
+use crate::adjuntos::{
+    detectar_mime, directorio_adjuntos, hash_sha256, preparar_archivos_correo, resumen_rechazo_correo,
+    ruta_relativa, sanitizar_nombre_archivo, verificar_tamano_correo, Almacenamiento, ArchivoCorreo, EntidadAdjunto,
+};
+use crate::commands::parametros::{obtener_almacenamiento_adjuntos, obtener_limite_adjuntos};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Adjunto {
+    pub adjunto_id: i32,
+    pub entidad: String,
+    pub entidad_id: i32,
+    pub nombre_archivo: String,
+    pub mime_type: String,
+    pub tamano_bytes: i64,
+    pub hash_sha256: String,
+    pub almacenamiento: String,
+    // Ruta relativa al directorio de adjuntos, solo para uso interno
+    #[serde(skip)]
+    pub ruta: Option<String>,
+    pub descripcion: Option<String>,
+    pub es_visible_cliente: bool,
+    pub uploaded_by: Option<i32>,
+    pub uploader_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdjuntoContenido {
+    pub adjunto: Adjunto,
+    pub contenido_base64: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadAdjuntoRequest {
+    pub entidad: String,
+    pub entidad_id: i32,
+    pub nombre_archivo: String,
+    pub contenido_base64: String,
+    pub descripcion: Option<String>,
+    pub es_visible_cliente: Option<bool>,
+}
+
+const SELECT_ADJUNTO: &str =
+    "SELECT a.adjunto_id, a.entidad, a.entidad_id, a.nombre_archivo, a.mime_type, a.tamano_bytes,
+            a.hash_sha256, a.almacenamiento, a.ruta, a.descripcion, a.es_visible_cliente,
+            a.uploaded_by, u.usuario_nombre as uploader_nombre, a.created_at
+     FROM ADJUNTO a
+     LEFT JOIN USUARIO u ON a.uploaded_by = u.usuario_id";
+
+/// Obtener los adjuntos de una orden de trabajo, equipo o informe
+#[tauri::command]
+pub async fn get_adjuntos(session_token: String, entidad: String, entidad_id: i32) -> Result<Vec<Adjunto>, String> {
+    let entidad = entidad.parse::<EntidadAdjunto>()?;
+    let usuario = require_session(&session_token).await?;
+    authorize(&usuario, entidad.permiso_ver()).await?;
+
+    fetch_adjuntos_entidad(entidad, entidad_id, false).await
+}
+
+/// Obtener un adjunto con su contenido codificado en base64
+#[tauri::command]
+pub async fn get_adjunto_contenido(session_token: String, adjunto_id: i32) -> Result<AdjuntoContenido, String> {
+    let usuario = require_session(&session_token).await?;
+    let adjunto = fetch_adjunto_by_id(adjunto_id).await?
+        .ok_or_else(|| "Adjunto no encontrado".to_string())?;
+    authorize(&usuario, adjunto.entidad.parse::<EntidadAdjunto>()?.permiso_ver()).await?;
+
+    let contenido = leer_contenido_adjunto(&adjunto).await?;
+
+    Ok(AdjuntoContenido {
+        contenido_base64: general_purpose::STANDARD.encode(&contenido),
+        adjunto,
+    })
+}
+
+/// Subir un archivo adjunto. El contenido se guarda como archivo o en la base
+/// de datos según el parámetro `adjuntos_almacenamiento`.
+#[tauri::command]
+pub async fn upload_adjunto(session_token: String, request: UploadAdjuntoRequest) -> Result<Adjunto, String> {
+    let entidad = request.entidad.parse::<EntidadAdjunto>()?;
+    let usuario = require_session(&session_token).await?;
+    authorize(&usuario, entidad.permiso_gestionar()).await?;
+    let pool = get_db_pool_safe()?;
+
+    let nombre_archivo = sanitizar_nombre_archivo(&request.nombre_archivo)?;
+    let contenido = general_purpose::STANDARD
+        .decode(request.contenido_base64.trim())
+        .map_err(|_| "El contenido del archivo no es base64 válido".to_string())?;
+
+    if contenido.is_empty() {
+        return Err("El archivo está vacío".to_string());
+    }
+    let limite = obtener_limite_adjuntos().await?;
+    if contenido.len() as u64 > limite {
+        return Err(format!(
+            "El archivo supera el tamaño máximo permitido de {} MB",
+            limite / (1024 * 1024)
+        ));
+    }
+
+    let existe = sqlx::query_scalar::<_, i32>(entidad.consulta_existencia())
+        .bind(request.entidad_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if existe.is_none() {
+        return Err(format!("No existe {} con ID {}", entidad.as_str(), request.entidad_id));
+    }
+
+    let hash = hash_sha256(&contenido);
+    let mime_type = detectar_mime(&nombre_archivo, &contenido);
+    let almacenamiento = obtener_almacenamiento_adjuntos().await?;
+
+    let (ruta, contenido_db) = match almacenamiento {
+        Almacenamiento::Archivo => {
+            let ruta = ruta_relativa(&hash);
+            guardar_archivo(&ruta, &contenido).await?;
+            (Some(ruta), None)
+        }
+        Almacenamiento::BaseDatos => (None, Some(contenido.as_slice())),
+    };
+
+    let result = sqlx::query(
+        "INSERT INTO ADJUNTO (entidad, entidad_id, nombre_archivo, mime_type, tamano_bytes, hash_sha256,
+                              almacenamiento, ruta, contenido, descripcion, es_visible_cliente, uploaded_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(entidad.as_str())
+    .bind(request.entidad_id)
+    .bind(&nombre_archivo)
+    .bind(mime_type)
+    .bind(contenido.len() as i64)
+    .bind(&hash)
+    .bind(almacenamiento.as_str())
+    .bind(&ruta)
+    .bind(contenido_db)
+    .bind(&request.descripcion)
+    .bind(request.es_visible_cliente.unwrap_or(false))
+    .bind(usuario.usuario_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let adjunto_id = result.last_insert_id() as i32;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "UPLOAD_ADJUNTO",
+        Some(usuario.usuario_id),
+        entidad.as_str(),
+        Some(request.entidad_id),
+        None,
+        Some(&format!("{} ({} bytes)", nombre_archivo, contenido.len()))
+    ).await;
+
+    fetch_adjunto_by_id(adjunto_id).await?
+        .ok_or_else(|| "Failed to retrieve uploaded attachment".to_string())
+}
+
+/// Eliminar un adjunto
+#[tauri::command]
+pub async fn delete_adjunto(session_token: String, adjunto_id: i32) -> Result<bool, String> {
+    let usuario = require_session(&session_token).await?;
+    let pool = get_db_pool_safe()?;
+
+    let adjunto = match fetch_adjunto_by_id(adjunto_id).await? {
+        Some(adjunto) => adjunto,
+        None => return Ok(false),
+    };
+    let entidad = adjunto.entidad.parse::<EntidadAdjunto>()?;
+    authorize(&usuario, entidad.permiso_gestionar()).await?;
+
+    let result = sqlx::query("DELETE FROM ADJUNTO WHERE adjunto_id = ?")
+        .bind(adjunto_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let was_deleted = result.rows_affected() > 0;
+
+    if was_deleted {
+        if let Some(ruta) = &adjunto.ruta {
+            liberar_archivo(ruta).await?;
+        }
+
+        // Registrar la acción en el log de auditoría
+        let _ = log_action(
+            "DELETE_ADJUNTO",
+            Some(usuario.usuario_id),
+            entidad.as_str(),
+            Some(adjunto.entidad_id),
+            Some(&format!("{} ({} bytes)", adjunto.nombre_archivo, adjunto.tamano_bytes)),
+            None
+        ).await;
+    }
+
+    Ok(was_deleted)
+}
+
+/// Adjuntos de una entidad sin verificar sesión (uso interno)
+pub(crate) async fn fetch_adjuntos_entidad(entidad: EntidadAdjunto, entidad_id: i32, solo_visibles_cliente: bool) -> Result<Vec<Adjunto>, String> {
+    let pool = get_db_pool_safe()?;
+
+    let filtro = if solo_visibles_cliente { " AND a.es_visible_cliente = TRUE" } else { "" };
+    let sql = format!(
+        "{} WHERE a.entidad = ? AND a.entidad_id = ?{} ORDER BY a.created_at ASC, a.adjunto_id ASC",
+        SELECT_ADJUNTO, filtro
+    );
+
+    sqlx::query_as::<_, Adjunto>(&sql)
+        .bind(entidad.as_str())
+        .bind(entidad_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Lee el contenido de un adjunto y verifica que coincida con su hash
+pub(crate) async fn leer_contenido_adjunto(adjunto: &Adjunto) -> Result<Vec<u8>, String> {
+    let contenido = match adjunto.almacenamiento.parse::<Almacenamiento>()? {
+        Almacenamiento::Archivo => {
+            let ruta = adjunto.ruta.as_deref()
+                .ok_or_else(|| "El adjunto no tiene una ruta de archivo".to_string())?;
+            tokio::fs::read(directorio_adjuntos()?.join(ruta))
+                .await
+                .map_err(|e| format!("Error leyendo el adjunto {}: {}", adjunto.nombre_archivo, e))?
+        }
+        Almacenamiento::BaseDatos => {
+            let pool = get_db_pool_safe()?;
+            sqlx::query_scalar::<_, Option<Vec<u8>>>("SELECT contenido FROM ADJUNTO WHERE adjunto_id = ?")
+                .bind(adjunto.adjunto_id)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .unwrap_or_default()
+        }
+    };
+
+    if hash_sha256(&contenido) != adjunto.hash_sha256 {
+        return Err(format!("El contenido del adjunto {} no coincide con su hash", adjunto.nombre_archivo));
+    }
+
+    Ok(contenido)
+}
+
+/// Contenido de los adjuntos visibles para el cliente de cada entidad, en
+/// orden, para enviarlos con un correo
+pub(crate) async fn fetch_archivos_visibles_cliente(entidades: &[(EntidadAdjunto, i32)]) -> Result<Vec<ArchivoCorreo>, String> {
+    let mut archivos = Vec::new();
+    for (entidad, entidad_id) in entidades {
+        for adjunto in fetch_adjuntos_entidad(*entidad, *entidad_id, true).await? {
+            let contenido = leer_contenido_adjunto(&adjunto).await?;
+            archivos.push(ArchivoCorreo {
+                nombre_archivo: adjunto.nombre_archivo,
+                mime_type: adjunto.mime_type,
+                contenido,
+            });
+        }
+    }
+    Ok(archivos)
+}
+
+/// Prepara los archivos de un correo al cliente. Si superan el límite, el
+/// rechazo queda en la auditoría de la entidad con los archivos y sus tamaños.
+pub(crate) async fn preparar_archivos_correo_auditado(
+    archivos: Vec<ArchivoCorreo>,
+    limite: u64,
+    usuario_id: i32,
+    entidad_tabla: &str,
+    entidad_id: i32,
+) -> Result<Vec<ArchivoCorreo>, String> {
+    if let Err(e) = verificar_tamano_correo(&archivos, limite) {
+        let _ = log_action(
+            "CORREO_ADJUNTOS_RECHAZADO",
+            Some(usuario_id),
+            entidad_tabla,
+            Some(entidad_id),
+            None,
+            Some(&resumen_rechazo_correo(&archivos, limite))
+        ).await;
+        return Err(e);
+    }
+    preparar_archivos_correo(archivos, limite)
+}
+
+/// Elimina los adjuntos de una entidad que se está eliminando
+pub(crate) async fn eliminar_adjuntos_entidad(entidad: EntidadAdjunto, entidad_id: i32) -> Result<(), String> {
+    let pool = get_db_pool_safe()?;
+    let adjuntos = fetch_adjuntos_entidad(entidad, entidad_id, false).await?;
+
+    sqlx::query("DELETE FROM ADJUNTO WHERE entidad = ? AND entidad_id = ?")
+        .bind(entidad.as_str())
+        .bind(entidad_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    for ruta in adjuntos.iter().filter_map(|a| a.ruta.as_deref()) {
+        liberar_archivo(ruta).await?;
+    }
+
+    Ok(())
+}
+
+async fn fetch_adjunto_by_id(adjunto_id: i32) -> Result<Option<Adjunto>, String> {
+    let pool = get_db_pool_safe()?;
+    let sql = format!("{} WHERE a.adjunto_id = ?", SELECT_ADJUNTO);
+
+    sqlx::query_as::<_, Adjunto>(&sql)
+        .bind(adjunto_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Escribe el contenido en el directorio de adjuntos si aún no existe
+async fn guardar_archivo(ruta: &str, contenido: &[u8]) -> Result<(), String> {
+    let destino = directorio_adjuntos()?.join(ruta);
+    if tokio::fs::try_exists(&destino).await.unwrap_or(false) {
+        return Ok(());
+    }
+    if let Some(directorio) = destino.parent() {
+        tokio::fs::create_dir_all(directorio)
+            .await
+            .map_err(|e| format!("Error creando el directorio de adjuntos: {}", e))?;
+    }
+
+    // Se escribe a un temporal y se renombra para no dejar archivos a medias
+    let temporal = destino.with_extension("tmp");
+    tokio::fs::write(&temporal, contenido)
+        .await
+        .map_err(|e| format!("Error guardando el adjunto: {}", e))?;
+    tokio::fs::rename(&temporal, &destino)
+        .await
+        .map_err(|e| format!("Error guardando el adjunto: {}", e))
+}
+
+/// Borra el archivo si ningún otro adjunto lo referencia
+async fn liberar_archivo(ruta: &str) -> Result<(), String> {
+    let pool = get_db_pool_safe()?;
+
+    let referencias = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM ADJUNTO WHERE ruta = ?")
+        .bind(ruta)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if referencias > 0 {
+        return Ok(());
+    }
+
+    match tokio::fs::remove_file(directorio_adjuntos()?.join(ruta)).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Error eliminando el archivo adjunto: {}", e)),
+    }
+}
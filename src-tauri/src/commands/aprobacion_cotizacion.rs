@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::aprobacion::{
+    firmar_token, pagina_confirmacion, pagina_mensaje, url_respuesta, verificar_token,
+    AccionRespuesta, TokenRespuesta,
+};
+use crate::calculo_cotizacion::esta_vencida;
+use crate::commands::inventario::{liberar_reservas_cotizacion, reservar_piezas_cotizacion};
+use crate::commands::ordenes_trabajo::{escribir_cambio_estado, registrar_cambio_estado};
+use crate::commands::parametros::{obtener_url_aprobacion, obtener_validez_enlaces};
+use crate::estado_orden::EstadoOrden;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::{MySql, Transaction};
+use rand::RngCore;
+
+/// Nombre de la clave con que se firman los enlaces en CLAVE_FIRMA
+const CLAVE_ENLACES: &str = "respuesta_cotizacion";
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct EnlaceRespuesta {
+    pub enlace_id: i32,
+    pub cotizacion_id: i32,
+    pub orden_id: i32,
+    pub revision: i32,
+    pub destinatario: String,
+    pub expira_at: DateTime<Utc>,
+    // Respuesta del cliente: "aprobada" o "rechazada"
+    pub respuesta: Option<String>,
+    pub respondido_at: Option<DateTime<Utc>>,
+    pub respondido_ip: Option<String>,
+    pub respondido_agente: Option<String>,
+    // Enlace reemplazado por un envío posterior o por otra respuesta
+    pub anulado_at: Option<DateTime<Utc>>,
+    pub created_by: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Enlaces incluidos en el correo de la cotización
+#[derive(Debug, Clone)]
+pub struct EnlacesCotizacion {
+    pub url_aprobar: String,
+    pub url_rechazar: String,
+    pub expira_at: DateTime<Utc>,
+}
+
+/// Motivo por el que no se pudo atender una respuesta del cliente
+#[derive(Debug)]
+pub enum FalloRespuesta {
+    /// El enlace no sirve (vencido, usado, reemplazado...); el mensaje es para el cliente
+    Rechazada(String),
+    /// Error interno; el detalle queda solo en el registro del servidor
+    Interna(String),
+}
+
+/// Obtener los enlaces de respuesta enviados para una cotización
+#[tauri::command]
+pub async fn get_enlaces_respuesta_cotizacion(session_token: String, cotizacion_id: i32) -> Result<Vec<EnlaceRespuesta>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, EnlaceRespuesta>(
+        "SELECT enlace_id, cotizacion_id, orden_id, revision, destinatario, expira_at, respuesta,
+                respondido_at, respondido_ip, respondido_agente, anulado_at, created_by, created_at
+         FROM COTIZACION_ENLACE_RESPUESTA
+         WHERE cotizacion_id = ?
+         ORDER BY created_at DESC, enlace_id DESC"
+    )
+    .bind(cotizacion_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Registra un nuevo enlace de respuesta para la revisión vigente de la
+/// cotización y devuelve las URL firmadas para aprobar y rechazar.
+/// Los enlaces enviados antes para la misma cotización quedan anulados.
+pub(crate) async fn crear_enlaces_respuesta(
+    cotizacion_id: i32,
+    orden_id: i32,
+    destinatario: &str,
+    usuario_id: i32,
+) -> Result<EnlacesCotizacion, String> {
+    let pool = get_db_pool_safe()?;
+    let validez = obtener_validez_enlaces().await?;
+    let url_base = obtener_url_aprobacion().await?;
+    let secreto = obtener_secreto_firma().await?;
+
+    // Sin fracciones de segundo, igual que el TIMESTAMP guardado
+    let expira_at = DateTime::from_timestamp((Utc::now() + Duration::hours(validez)).timestamp(), 0)
+        .ok_or_else(|| "Fecha de expiración no válida".to_string())?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "UPDATE COTIZACION_ENLACE_RESPUESTA SET anulado_at = CURRENT_TIMESTAMP
+         WHERE cotizacion_id = ? AND respuesta IS NULL AND anulado_at IS NULL"
+    )
+    .bind(cotizacion_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let result = sqlx::query(
+        "INSERT INTO COTIZACION_ENLACE_RESPUESTA (cotizacion_id, orden_id, revision, destinatario, expira_at, created_by)
+         SELECT cotizacion_id, ?, revision, ?, ?, ? FROM COTIZACION WHERE cotizacion_id = ?"
+    )
+    .bind(orden_id)
+    .bind(destinatario)
+    .bind(expira_at)
+    .bind(usuario_id)
+    .bind(cotizacion_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Cotización no encontrada".to_string());
+    }
+    let enlace_id = result.last_insert_id() as i32;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let expira = expira_at.timestamp();
+    Ok(EnlacesCotizacion {
+        url_aprobar: url_respuesta(&url_base, &firmar_token(&secreto, enlace_id, AccionRespuesta::Aprobar, expira)),
+        url_rechazar: url_respuesta(&url_base, &firmar_token(&secreto, enlace_id, AccionRespuesta::Rechazar, expira)),
+        expira_at,
+    })
+}
+
+/// Página de confirmación para un enlace recibido por GET. No modifica nada.
+pub(crate) async fn preparar_respuesta(token: &str) -> Result<String, FalloRespuesta> {
+    let (verificado, enlace) = validar_enlace(token).await?;
+    Ok(pagina_confirmacion(
+        enlace.cotizacion_codigo.as_deref().unwrap_or("N/A"),
+        enlace.costo_total.unwrap_or(0),
+        verificado.accion,
+        token,
+    ))
+}
+
+/// Registra la respuesta del cliente y mueve la orden de trabajo al estado que corresponda
+pub(crate) async fn registrar_respuesta(token: &str, ip: &str, agente: Option<&str>) -> Result<String, FalloRespuesta> {
+    let (token, enlace) = validar_enlace(token).await?;
+    let pool = get_db_pool_safe().map_err(FalloRespuesta::Interna)?;
+    let interna = |e: sqlx::Error| FalloRespuesta::Interna(format!("Database error: {}", e));
+    let agente: Option<String> = agente.map(|a| a.chars().take(255).collect());
+
+    let mut tx = pool.begin().await.map_err(interna)?;
+
+    // La orden pudo cambiar desde que se validó el enlace: se bloquea y se revisa
+    // de nuevo para que ningún cambio de estado se cruce con la respuesta
+    let (orden_cotizacion_id, orden_estado) = sqlx::query_as::<_, (Option<i32>, Option<String>)>(
+        "SELECT cotizacion_id, estado FROM ORDEN_TRABAJO WHERE orden_id = ? FOR UPDATE"
+    )
+    .bind(enlace.orden_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(interna)?
+    .ok_or_else(|| FalloRespuesta::Rechazada("La orden de trabajo ya no existe. Contacte al taller".to_string()))?;
+    let estado = verificar_orden_en_espera(enlace.cotizacion_id, orden_cotizacion_id, orden_estado.as_deref())?;
+
+    // La condición sobre `respuesta` evita que dos respuestas simultáneas se registren
+    let marcado = sqlx::query(
+        "UPDATE COTIZACION_ENLACE_RESPUESTA
+         SET respuesta = ?, respondido_at = CURRENT_TIMESTAMP, respondido_ip = ?, respondido_agente = ?
+         WHERE enlace_id = ? AND respuesta IS NULL AND anulado_at IS NULL"
+    )
+    .bind(token.accion.resultado())
+    .bind(ip)
+    .bind(&agente)
+    .bind(token.enlace_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(interna)?;
+
+    if marcado.rows_affected() == 0 {
+        return Err(FalloRespuesta::Rechazada("Esta cotización ya fue respondida".to_string()));
+    }
+
+    sqlx::query(
+        "UPDATE COTIZACION_ENLACE_RESPUESTA SET anulado_at = CURRENT_TIMESTAMP
+         WHERE cotizacion_id = ? AND enlace_id <> ? AND respuesta IS NULL AND anulado_at IS NULL"
+    )
+    .bind(enlace.cotizacion_id)
+    .bind(token.enlace_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(interna)?;
+
+    sqlx::query("UPDATE COTIZACION SET is_aprobada = ? WHERE cotizacion_id = ?")
+        .bind(token.accion == AccionRespuesta::Aprobar)
+        .bind(enlace.cotizacion_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(interna)?;
+
+    match token.accion {
+        AccionRespuesta::Aprobar => reservar_piezas_cotizacion(&mut tx, enlace.cotizacion_id, None).await,
+        AccionRespuesta::Rechazar => liberar_reservas_cotizacion(&mut tx, enlace.cotizacion_id, "Rechazada por el cliente").await,
+    }
+    .map_err(FalloRespuesta::Interna)?;
+
+    let detalle = format!("Cotización {} por {} desde {}", token.accion.resultado(), enlace.destinatario, ip);
+    let pasos = mover_orden(&mut tx, enlace.orden_id, estado, token.accion, &detalle).await
+        .map_err(FalloRespuesta::Interna)?;
+
+    tx.commit().await.map_err(interna)?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "RESPUESTA_CLIENTE_COTIZACION",
+        None,
+        "COTIZACION",
+        Some(enlace.cotizacion_id),
+        None,
+        Some(&detalle)
+    ).await;
+    for (desde, hacia) in pasos {
+        registrar_cambio_estado(enlace.orden_id, desde, hacia, None).await;
+    }
+
+    let mensaje = match token.accion {
+        AccionRespuesta::Aprobar => "Gracias. Registramos su aprobación y comenzaremos la reparación de su equipo.",
+        AccionRespuesta::Rechazar => "Registramos que no desea realizar la reparación. Le avisaremos cuando pueda retirar su equipo.",
+    };
+    Ok(pagina_mensaje("Respuesta registrada", mensaje))
+}
+
+/// Datos del enlace y de su cotización necesarios para validar una respuesta
+#[derive(Debug, FromRow)]
+struct EnlaceVigente {
+    cotizacion_id: i32,
+    orden_id: i32,
+    revision: i32,
+    destinatario: String,
+    respuesta: Option<String>,
+    anulado_at: Option<DateTime<Utc>>,
+    revision_vigente: i32,
+    cotizacion_codigo: Option<String>,
+    costo_total: Option<i32>,
+    valido_hasta: Option<NaiveDate>,
+    orden_cotizacion_id: Option<i32>,
+    orden_estado: Option<String>,
+}
+
+async fn validar_enlace(token: &str) -> Result<(TokenRespuesta, EnlaceVigente), FalloRespuesta> {
+    let secreto = obtener_secreto_firma().await.map_err(FalloRespuesta::Interna)?;
+    let verificado = verificar_token(&secreto, token, Utc::now().timestamp())
+        .map_err(FalloRespuesta::Rechazada)?;
+
+    let pool = get_db_pool_safe().map_err(FalloRespuesta::Interna)?;
+    let enlace = sqlx::query_as::<_, EnlaceVigente>(
+        "SELECT e.cotizacion_id, e.orden_id, e.revision, e.destinatario, e.respuesta, e.anulado_at,
+                c.revision as revision_vigente, c.cotizacion_codigo, c.costo_total, c.valido_hasta,
+                ot.cotizacion_id as orden_cotizacion_id, ot.estado as orden_estado
+         FROM COTIZACION_ENLACE_RESPUESTA e
+         INNER JOIN COTIZACION c ON e.cotizacion_id = c.cotizacion_id
+         INNER JOIN ORDEN_TRABAJO ot ON e.orden_id = ot.orden_id
+         WHERE e.enlace_id = ?"
+    )
+    .bind(verificado.enlace_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| FalloRespuesta::Interna(format!("Database error: {}", e)))?
+    .ok_or_else(|| FalloRespuesta::Rechazada("El enlace no es válido".to_string()))?;
+
+    let rechazo = |mensaje: &str| Err(FalloRespuesta::Rechazada(mensaje.to_string()));
+    if let Some(ref respuesta) = enlace.respuesta {
+        return Err(FalloRespuesta::Rechazada(format!("Esta cotización ya fue {}", respuesta)));
+    }
+    if enlace.anulado_at.is_some() {
+        return rechazo("Este enlace fue reemplazado por uno más reciente. Revise el último correo recibido");
+    }
+    if enlace.revision != enlace.revision_vigente {
+        return rechazo("La cotización fue modificada después de este envío. Revise el último correo recibido");
+    }
+    if esta_vencida(enlace.valido_hasta, Utc::now().date_naive()) {
+        return rechazo("La cotización venció. Contacte al taller para recibir una cotización actualizada");
+    }
+    verificar_orden_en_espera(enlace.cotizacion_id, enlace.orden_cotizacion_id, enlace.orden_estado.as_deref())?;
+
+    Ok((verificado, enlace))
+}
+
+/// La orden debe seguir asociada a la cotización y esperando la respuesta del
+/// cliente. Devuelve su estado actual.
+fn verificar_orden_en_espera(
+    cotizacion_id: i32,
+    orden_cotizacion_id: Option<i32>,
+    orden_estado: Option<&str>,
+) -> Result<EstadoOrden, FalloRespuesta> {
+    if orden_cotizacion_id != Some(cotizacion_id) {
+        return Err(FalloRespuesta::Rechazada("La cotización ya no está asociada a la orden de trabajo".to_string()));
+    }
+    match orden_estado.and_then(|e| e.parse::<EstadoOrden>().ok()) {
+        Some(estado @ (EstadoOrden::CotizacionEnviada | EstadoOrden::AprobacionPendiente)) => Ok(estado),
+        _ => Err(FalloRespuesta::Rechazada(
+            "La orden de trabajo ya no está esperando su respuesta. Contacte al taller".to_string()
+        )),
+    }
+}
+
+/// Lleva la orden a `en_reparacion` o `cotizacion_rechazada` dentro de la
+/// transacción de la respuesta. Si el cliente responde antes de que la orden
+/// pase a `aprobacion_pendiente`, se registra también ese paso para respetar la
+/// secuencia de estados. Devuelve los pasos aplicados para auditarlos.
+///
+/// La condición de `en_reparacion` (cotización aprobada) la cumple la misma
+/// transacción, que acaba de marcar la cotización como aprobada.
+async fn mover_orden(
+    tx: &mut Transaction<'_, MySql>,
+    orden_id: i32,
+    estado: EstadoOrden,
+    accion: AccionRespuesta,
+    detalle: &str,
+) -> Result<Vec<(EstadoOrden, EstadoOrden)>, String> {
+    let destino = match accion {
+        AccionRespuesta::Aprobar => EstadoOrden::EnReparacion,
+        AccionRespuesta::Rechazar => EstadoOrden::CotizacionRechazada,
+    };
+
+    let mut pasos = Vec::new();
+    let mut actual = estado;
+    if accion == AccionRespuesta::Aprobar && actual == EstadoOrden::CotizacionEnviada {
+        pasos.push((actual, EstadoOrden::AprobacionPendiente));
+        actual = EstadoOrden::AprobacionPendiente;
+    }
+    pasos.push((actual, destino));
+
+    for &(desde, hacia) in &pasos {
+        desde.validar_transicion(hacia)?;
+        let comentario = if hacia == destino { detalle } else { "Respuesta del cliente recibida" };
+        escribir_cambio_estado(tx, orden_id, desde, hacia, None, Some(comentario)).await?;
+    }
+    Ok(pasos)
+}
+
+/// Clave HMAC de los enlaces. Se genera al primer uso y se guarda en la base de
+/// datos para que cualquier equipo del taller pueda firmar y verificar enlaces.
+async fn obtener_secreto_firma() -> Result<Vec<u8>, String> {
+    let pool = get_db_pool_safe()?;
+
+    let consulta = "SELECT clave_valor FROM CLAVE_FIRMA WHERE clave_nombre = ?";
+    if let Some(secreto) = sqlx::query_scalar::<_, Vec<u8>>(consulta)
+        .bind(CLAVE_ENLACES)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+    {
+        return Ok(secreto);
+    }
+
+    let mut nuevo = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut nuevo);
+
+    // Si otro equipo la generó al mismo tiempo, se conserva la primera
+    sqlx::query("INSERT IGNORE INTO CLAVE_FIRMA (clave_nombre, clave_valor) VALUES (?, ?)")
+        .bind(CLAVE_ENLACES)
+        .bind(&nuevo[..])
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query_scalar::<_, Vec<u8>>(consulta)
+        .bind(CLAVE_ENLACES)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
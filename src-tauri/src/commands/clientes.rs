@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
 use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -23,7 +24,6 @@ pub struct CreateClienteRequest {
     pub cliente_correo: String,
     pub cliente_telefono: Option<String>,
     pub cliente_direccion: Option<String>,
-    pub created_by: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,7 +36,8 @@ pub struct UpdateClienteRequest {
 }
 
 #[tauri::command]
-pub async fn get_clientes() -> Result<Vec<Cliente>, String> {
+pub async fn get_clientes(session_token: String) -> Result<Vec<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let clientes = sqlx::query_as::<_, Cliente>(
         "SELECT cliente_id, cliente_rut, cliente_nombre, cliente_correo, cliente_telefono, cliente_direccion, created_by, created_at FROM CLIENTE ORDER BY cliente_nombre"
@@ -49,7 +50,8 @@ pub async fn get_clientes() -> Result<Vec<Cliente>, String> {
 }
 
 #[tauri::command]
-pub async fn get_cliente_by_id(cliente_id: i32) -> Result<Option<Cliente>, String> {
+pub async fn get_cliente_by_id(session_token: String, cliente_id: i32) -> Result<Option<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let cliente = sqlx::query_as::<_, Cliente>(
         "SELECT cliente_id, cliente_rut, cliente_nombre, cliente_correo, cliente_telefono, cliente_direccion, created_by, created_at FROM CLIENTE WHERE cliente_id = ?"
@@ -63,7 +65,8 @@ pub async fn get_cliente_by_id(cliente_id: i32) -> Result<Option<Cliente>, Strin
 }
 
 #[tauri::command]
-pub async fn get_cliente_by_rut(cliente_rut: String) -> Result<Option<Cliente>, String> {
+pub async fn get_cliente_by_rut(session_token: String, cliente_rut: String) -> Result<Option<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let cliente = sqlx::query_as::<_, Cliente>(
         "SELECT cliente_id, cliente_rut, cliente_nombre, cliente_correo, cliente_telefono, cliente_direccion, created_by, created_at FROM CLIENTE WHERE cliente_rut = ?"
@@ -77,7 +80,8 @@ pub async fn get_cliente_by_rut(cliente_rut: String) -> Result<Option<Cliente>,
 }
 
 #[tauri::command]
-pub async fn get_clientes_by_created_by(created_by: i32) -> Result<Vec<Cliente>, String> {
+pub async fn get_clientes_by_created_by(session_token: String, created_by: i32) -> Result<Vec<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let clientes = sqlx::query_as::<_, Cliente>(
         "SELECT cliente_id, cliente_rut, cliente_nombre, cliente_correo, cliente_telefono, cliente_direccion, created_by, created_at FROM CLIENTE WHERE created_by = ? ORDER BY cliente_nombre"
@@ -91,7 +95,8 @@ pub async fn get_clientes_by_created_by(created_by: i32) -> Result<Vec<Cliente>,
 }
 
 #[tauri::command]
-pub async fn search_clientes(search_term: String) -> Result<Vec<Cliente>, String> {
+pub async fn search_clientes(session_token: String, search_term: String) -> Result<Vec<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let search_pattern = format!("%{}%", search_term);
     
@@ -112,11 +117,12 @@ pub async fn search_clientes(search_term: String) -> Result<Vec<Cliente>, String
 }
 
 #[tauri::command]
-pub async fn create_cliente(request: CreateClienteRequest) -> Result<Cliente, String> {
+pub async fn create_cliente(session_token: String, request: CreateClienteRequest) -> Result<Cliente, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarClientes).await?;
     let pool = get_db_pool_safe()?;
     
     // Verificar que el RUT no existe ya
-    if let Some(_) = get_cliente_by_rut(request.cliente_rut.clone()).await? {
+    if let Some(_) = get_cliente_by_rut(session_token.clone(), request.cliente_rut.clone()).await? {
         return Err("Ya existe un cliente con este RUT".to_string());
     }
     
@@ -128,7 +134,7 @@ pub async fn create_cliente(request: CreateClienteRequest) -> Result<Cliente, St
     .bind(&request.cliente_correo)
     .bind(&request.cliente_telefono)
     .bind(&request.cliente_direccion)
-    .bind(&request.created_by)
+    .bind(usuario.usuario_id)
     .execute(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
@@ -138,7 +144,7 @@ pub async fn create_cliente(request: CreateClienteRequest) -> Result<Cliente, St
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_CLIENTE",
-        Some(request.created_by),
+        Some(usuario.usuario_id),
         "CLIENTE",
         Some(cliente_id),
         None,
@@ -146,21 +152,22 @@ pub async fn create_cliente(request: CreateClienteRequest) -> Result<Cliente, St
     ).await;
     
     // Obtener el cliente recién creado
-    get_cliente_by_id(cliente_id)
+    get_cliente_by_id(session_token, cliente_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created cliente".to_string())
 }
 
 #[tauri::command]
-pub async fn update_cliente(cliente_id: i32, request: UpdateClienteRequest, updated_by: i32) -> Result<Option<Cliente>, String> {
+pub async fn update_cliente(session_token: String, cliente_id: i32, request: UpdateClienteRequest) -> Result<Option<Cliente>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarClientes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el cliente actual para logging
-    let current_cliente = get_cliente_by_id(cliente_id).await?;
+    let current_cliente = get_cliente_by_id(session_token.clone(), cliente_id).await?;
     
     // Verificar que el RUT no está en uso por otro cliente (si se está actualizando)
     if let Some(ref new_rut) = request.cliente_rut {
-        if let Some(existing_cliente) = get_cliente_by_rut(new_rut.clone()).await? {
+        if let Some(existing_cliente) = get_cliente_by_rut(session_token.clone(), new_rut.clone()).await? {
             if existing_cliente.cliente_id != cliente_id {
                 return Err("Ya existe otro cliente con este RUT".to_string());
             }
@@ -207,7 +214,7 @@ pub async fn update_cliente(cliente_id: i32, request: UpdateClienteRequest, upda
         
         let _ = log_action(
             "UPDATE_CLIENTE",
-            Some(updated_by),
+            Some(usuario.usuario_id),
             "CLIENTE",
             Some(cliente_id),
             Some(&prev_data),
@@ -215,15 +222,16 @@ pub async fn update_cliente(cliente_id: i32, request: UpdateClienteRequest, upda
         ).await;
     }
     
-    get_cliente_by_id(cliente_id).await
+    get_cliente_by_id(session_token, cliente_id).await
 }
 
 #[tauri::command]
-pub async fn delete_cliente(cliente_id: i32, deleted_by: i32) -> Result<bool, String> {
+pub async fn delete_cliente(session_token: String, cliente_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarClientes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el cliente antes de eliminarlo para logging
-    let cliente_to_delete = get_cliente_by_id(cliente_id).await?;
+    let cliente_to_delete = get_cliente_by_id(session_token.clone(), cliente_id).await?;
     
     // Verificar si el cliente tiene cotizaciones, informes u órdenes de trabajo asociadas
     let has_dependencies = sqlx::query_scalar::<_, i64>(
@@ -259,7 +267,7 @@ pub async fn delete_cliente(cliente_id: i32, deleted_by: i32) -> Result<bool, St
         if let Some(ref cliente) = cliente_to_delete {
             let _ = log_action(
                 "DELETE_CLIENTE",
-                Some(deleted_by),
+                Some(usuario.usuario_id),
                 "CLIENTE",
                 Some(cliente_id),
                 Some(&format!("Cliente eliminado: {} ({})", 
@@ -275,7 +283,8 @@ pub async fn delete_cliente(cliente_id: i32, deleted_by: i32) -> Result<bool, St
 }
 
 #[tauri::command]
-pub async fn count_clientes() -> Result<i64, String> {
+pub async fn count_clientes(session_token: String) -> Result<i64, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM CLIENTE")
         .fetch_one(pool)
@@ -286,7 +295,8 @@ pub async fn count_clientes() -> Result<i64, String> {
 }
 
 #[tauri::command]
-pub async fn get_clientes_with_pagination(offset: i64, limit: i64) -> Result<Vec<Cliente>, String> {
+pub async fn get_clientes_with_pagination(session_token: String, offset: i64, limit: i64) -> Result<Vec<Cliente>, String> {
+    require_permission(&session_token, Permiso::VerClientes).await?;
     let pool = get_db_pool_safe()?;
     let clientes = sqlx::query_as::<_, Cliente>(
         "SELECT cliente_id, cliente_rut, cliente_nombre, cliente_correo, cliente_telefono, cliente_direccion, created_by, created_at 
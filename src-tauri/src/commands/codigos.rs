@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::codigos::{validar_formato, TipoDocumento};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct FormatoCodigo {
+    pub prefijo: String,
+    pub formato: String,
+    pub digitos: i32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFormatoCodigoRequest {
+    pub formato: String,
+    pub digitos: i32,
+}
+
+/// Obtener los formatos de código configurados
+#[tauri::command]
+pub async fn get_formatos_codigo(session_token: String) -> Result<Vec<FormatoCodigo>, String> {
+    require_permission(&session_token, Permiso::Configuracion).await?;
+    let pool = get_db_pool_safe()?;
+
+    let formatos = sqlx::query_as::<_, FormatoCodigo>(
+        "SELECT prefijo, formato, digitos, updated_at FROM FORMATO_CODIGO ORDER BY prefijo"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(formatos)
+}
+
+/// Actualizar el formato de código de un tipo de documento.
+/// Solo afecta a los documentos creados a partir de ahora.
+#[tauri::command]
+pub async fn update_formato_codigo(session_token: String, prefijo: String, request: UpdateFormatoCodigoRequest) -> Result<FormatoCodigo, String> {
+    let usuario = require_permission(&session_token, Permiso::Configuracion).await?;
+    let pool = get_db_pool_safe()?;
+
+    let tipo = TipoDocumento::from_prefijo(&prefijo)?;
+    validar_formato(&request.formato, request.digitos)?;
+
+    let anterior = sqlx::query_scalar::<_, String>(
+        "SELECT formato FROM FORMATO_CODIGO WHERE prefijo = ?"
+    )
+    .bind(tipo.prefijo())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO FORMATO_CODIGO (prefijo, formato, digitos) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE formato = VALUES(formato), digitos = VALUES(digitos)"
+    )
+    .bind(tipo.prefijo())
+    .bind(&request.formato)
+    .bind(request.digitos)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "UPDATE_FORMATO_CODIGO",
+        Some(usuario.usuario_id),
+        "FORMATO_CODIGO",
+        None,
+        anterior.as_deref(),
+        Some(&request.formato)
+    ).await;
+
+    sqlx::query_as::<_, FormatoCodigo>(
+        "SELECT prefijo, formato, digitos, updated_at FROM FORMATO_CODIGO WHERE prefijo = ?"
+    )
+    .bind(tipo.prefijo())
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
@@ -0,0 +1,866 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::codigos::{siguiente_codigo, TipoDocumento};
+use crate::commands::inventario::{registrar_movimiento, NuevoMovimiento};
+use crate::compras::{estado_segun_recepcion, validar_recepcion, AvanceLinea, EstadoOrdenCompra};
+use crate::commands::precios::registrar_precio;
+use crate::commands::series::{pieza_requiere_serie, registrar_unidades};
+use crate::inventario::validar_series;
+use crate::inventario::{PoliticaStockNegativo, TipoMovimiento};
+use crate::precios::TipoPrecio;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Proveedor {
+    pub proveedor_id: i32,
+    pub proveedor_rut: Option<String>,
+    pub proveedor_nombre: Option<String>,
+    pub proveedor_correo: Option<String>,
+    pub proveedor_telefono: Option<String>,
+    pub proveedor_contacto: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProveedorRequest {
+    pub proveedor_rut: Option<String>,
+    pub proveedor_nombre: String,
+    pub proveedor_correo: Option<String>,
+    pub proveedor_telefono: Option<String>,
+    pub proveedor_contacto: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProveedorRequest {
+    pub proveedor_rut: Option<String>,
+    pub proveedor_nombre: Option<String>,
+    pub proveedor_correo: Option<String>,
+    pub proveedor_telefono: Option<String>,
+    pub proveedor_contacto: Option<String>,
+}
+
+/// Código y costo de una pieza en el catálogo de un proveedor
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PiezaProveedor {
+    pub proveedor_id: i32,
+    pub proveedor_nombre: Option<String>,
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub pieza_marca: Option<String>,
+    pub codigo_proveedor: Option<String>,
+    pub costo: Option<i32>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PiezaProveedorRequest {
+    pub pieza_id: i32,
+    pub codigo_proveedor: Option<String>,
+    pub costo: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrdenCompra {
+    pub orden_compra_id: i32,
+    pub orden_compra_codigo: String,
+    pub proveedor_id: i32,
+    pub proveedor_nombre: Option<String>,
+    pub estado: String,
+    pub observaciones: Option<String>,
+    pub total: i64,
+    pub enviada_at: Option<DateTime<Utc>>,
+    pub recibida_at: Option<DateTime<Utc>>,
+    pub created_by: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct LineaOrdenCompra {
+    pub orden_compra_id: i32,
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub pieza_marca: Option<String>,
+    pub codigo_proveedor: Option<String>,
+    pub cantidad: i32,
+    pub cantidad_recibida: i32,
+    pub costo_unitario: i32,
+    pub total_linea: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrdenCompraDetallada {
+    #[serde(flatten)]
+    pub orden: OrdenCompra,
+    pub lineas: Vec<LineaOrdenCompra>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrdenCompraRequest {
+    pub proveedor_id: i32,
+    pub observaciones: Option<String>,
+    pub lineas: Vec<LineaOrdenCompraRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LineaOrdenCompraRequest {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    /// Si no se indica, se usa el costo del catálogo del proveedor
+    pub costo_unitario: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecepcionLineaRequest {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    // Obligatorio si la pieza requiere número de serie, una serie por unidad recibida
+    pub series: Option<Vec<String>>,
+}
+
+const SELECT_ORDEN_COMPRA: &str =
+    "SELECT oc.orden_compra_id, oc.orden_compra_codigo, oc.proveedor_id, p.proveedor_nombre, oc.estado,
+            oc.observaciones,
+            CAST(COALESCE((SELECT SUM(l.cantidad * l.costo_unitario) FROM PIEZAS_ORDEN_COMPRA l
+                           WHERE l.orden_compra_id = oc.orden_compra_id), 0) AS SIGNED) as total,
+            oc.enviada_at, oc.recibida_at, oc.created_by, oc.created_at
+     FROM ORDEN_COMPRA oc
+     INNER JOIN PROVEEDOR p ON oc.proveedor_id = p.proveedor_id";
+
+// ==================== Proveedores ====================
+
+/// Obtener todos los proveedores
+#[tauri::command]
+pub async fn get_proveedores(session_token: String) -> Result<Vec<Proveedor>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, Proveedor>(
+        "SELECT proveedor_id, proveedor_rut, proveedor_nombre, proveedor_correo, proveedor_telefono,
+                proveedor_contacto, created_by, created_at
+         FROM PROVEEDOR ORDER BY proveedor_nombre"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener un proveedor por su ID
+#[tauri::command]
+pub async fn get_proveedor_by_id(session_token: String, proveedor_id: i32) -> Result<Option<Proveedor>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    fetch_proveedor(proveedor_id).await
+}
+
+async fn fetch_proveedor(proveedor_id: i32) -> Result<Option<Proveedor>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, Proveedor>(
+        "SELECT proveedor_id, proveedor_rut, proveedor_nombre, proveedor_correo, proveedor_telefono,
+                proveedor_contacto, created_by, created_at
+         FROM PROVEEDOR WHERE proveedor_id = ?"
+    )
+    .bind(proveedor_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Crear un proveedor
+#[tauri::command]
+pub async fn create_proveedor(session_token: String, request: CreateProveedorRequest) -> Result<Proveedor, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let nombre = request.proveedor_nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre del proveedor es obligatorio".to_string());
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO PROVEEDOR (proveedor_rut, proveedor_nombre, proveedor_correo, proveedor_telefono,
+                                proveedor_contacto, created_by)
+         VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(request.proveedor_rut.as_deref().map(str::trim).filter(|r| !r.is_empty()))
+    .bind(nombre)
+    .bind(&request.proveedor_correo)
+    .bind(&request.proveedor_telefono)
+    .bind(&request.proveedor_contacto)
+    .bind(usuario.usuario_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let proveedor_id = result.last_insert_id() as i32;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "CREATE_PROVEEDOR",
+        Some(usuario.usuario_id),
+        "PROVEEDOR",
+        Some(proveedor_id),
+        None,
+        Some(&format!("{}|{}", request.proveedor_rut.as_deref().unwrap_or(""), nombre))
+    ).await;
+
+    fetch_proveedor(proveedor_id).await?
+        .ok_or_else(|| "Failed to retrieve created proveedor".to_string())
+}
+
+/// Actualizar un proveedor
+#[tauri::command]
+pub async fn update_proveedor(session_token: String, proveedor_id: i32, request: UpdateProveedorRequest) -> Result<Option<Proveedor>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let Some(anterior) = fetch_proveedor(proveedor_id).await? else {
+        return Ok(None);
+    };
+    if request.proveedor_nombre.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err("El nombre del proveedor es obligatorio".to_string());
+    }
+
+    sqlx::query(
+        "UPDATE PROVEEDOR SET
+         proveedor_rut = COALESCE(?, proveedor_rut),
+         proveedor_nombre = COALESCE(?, proveedor_nombre),
+         proveedor_correo = COALESCE(?, proveedor_correo),
+         proveedor_telefono = COALESCE(?, proveedor_telefono),
+         proveedor_contacto = COALESCE(?, proveedor_contacto)
+         WHERE proveedor_id = ?"
+    )
+    .bind(&request.proveedor_rut)
+    .bind(request.proveedor_nombre.as_deref().map(str::trim))
+    .bind(&request.proveedor_correo)
+    .bind(&request.proveedor_telefono)
+    .bind(&request.proveedor_contacto)
+    .bind(proveedor_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "UPDATE_PROVEEDOR",
+        Some(usuario.usuario_id),
+        "PROVEEDOR",
+        Some(proveedor_id),
+        Some(&format!("{}|{}",
+            anterior.proveedor_rut.as_deref().unwrap_or(""),
+            anterior.proveedor_nombre.as_deref().unwrap_or("")
+        )),
+        Some(&format!("{}|{}",
+            request.proveedor_rut.as_deref().or(anterior.proveedor_rut.as_deref()).unwrap_or(""),
+            request.proveedor_nombre.as_deref().or(anterior.proveedor_nombre.as_deref()).unwrap_or("")
+        ))
+    ).await;
+
+    fetch_proveedor(proveedor_id).await
+}
+
+/// Eliminar un proveedor sin órdenes de compra
+#[tauri::command]
+pub async fn delete_proveedor(session_token: String, proveedor_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let proveedor = fetch_proveedor(proveedor_id).await?;
+
+    let ordenes = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM ORDEN_COMPRA WHERE proveedor_id = ?")
+        .bind(proveedor_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Database error checking dependencies: {}", e))?;
+    if ordenes > 0 {
+        return Err("No se puede eliminar el proveedor porque tiene órdenes de compra asociadas".to_string());
+    }
+
+    // El catálogo del proveedor se elimina en cascada
+    let result = sqlx::query("DELETE FROM PROVEEDOR WHERE proveedor_id = ?")
+        .bind(proveedor_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let deleted = result.rows_affected() > 0;
+    if deleted {
+        let _ = log_action(
+            "DELETE_PROVEEDOR",
+            Some(usuario.usuario_id),
+            "PROVEEDOR",
+            Some(proveedor_id),
+            Some(&format!("Proveedor eliminado: {}",
+                proveedor.as_ref().and_then(|p| p.proveedor_nombre.as_deref()).unwrap_or("N/A")
+            )),
+            None
+        ).await;
+    }
+
+    Ok(deleted)
+}
+
+// ==================== Catálogo por proveedor ====================
+
+/// Obtener las piezas que vende un proveedor, con su código y costo
+#[tauri::command]
+pub async fn get_piezas_proveedor(session_token: String, proveedor_id: i32) -> Result<Vec<PiezaProveedor>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, PiezaProveedor>(
+        "SELECT pp.proveedor_id, pr.proveedor_nombre, pp.pieza_id, p.pieza_nombre, p.pieza_marca,
+                pp.codigo_proveedor, pp.costo, pp.updated_at
+         FROM PIEZA_PROVEEDOR pp
+         INNER JOIN PROVEEDOR pr ON pp.proveedor_id = pr.proveedor_id
+         INNER JOIN PIEZA p ON pp.pieza_id = p.pieza_id
+         WHERE pp.proveedor_id = ?
+         ORDER BY p.pieza_nombre"
+    )
+    .bind(proveedor_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener los proveedores de una pieza, del más barato al más caro
+#[tauri::command]
+pub async fn get_proveedores_pieza(session_token: String, pieza_id: i32) -> Result<Vec<PiezaProveedor>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, PiezaProveedor>(
+        "SELECT pp.proveedor_id, pr.proveedor_nombre, pp.pieza_id, p.pieza_nombre, p.pieza_marca,
+                pp.codigo_proveedor, pp.costo, pp.updated_at
+         FROM PIEZA_PROVEEDOR pp
+         INNER JOIN PROVEEDOR pr ON pp.proveedor_id = pr.proveedor_id
+         INNER JOIN PIEZA p ON pp.pieza_id = p.pieza_id
+         WHERE pp.pieza_id = ?
+         ORDER BY pp.costo IS NULL, pp.costo ASC, pr.proveedor_nombre"
+    )
+    .bind(pieza_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Agregar una pieza al catálogo de un proveedor o cambiar su código y costo
+#[tauri::command]
+pub async fn upsert_pieza_proveedor(session_token: String, proveedor_id: i32, request: PiezaProveedorRequest) -> Result<Vec<PiezaProveedor>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    if request.costo.is_some_and(|c| c < 0) {
+        return Err("El costo no puede ser negativo".to_string());
+    }
+
+    let anterior = sqlx::query_as::<_, (Option<String>, Option<i32>)>(
+        "SELECT codigo_proveedor, costo FROM PIEZA_PROVEEDOR WHERE proveedor_id = ? AND pieza_id = ?"
+    )
+    .bind(proveedor_id)
+    .bind(request.pieza_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO PIEZA_PROVEEDOR (proveedor_id, pieza_id, codigo_proveedor, costo) VALUES (?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE codigo_proveedor = VALUES(codigo_proveedor), costo = VALUES(costo)"
+    )
+    .bind(proveedor_id)
+    .bind(request.pieza_id)
+    .bind(request.codigo_proveedor.as_deref().map(str::trim).filter(|c| !c.is_empty()))
+    .bind(request.costo)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: pieza|código|costo
+    let linea = |codigo: Option<&str>, costo: Option<i32>| format!("{}|{}|{}",
+        request.pieza_id,
+        codigo.unwrap_or(""),
+        costo.map_or("".to_string(), |c| c.to_string())
+    );
+    let _ = log_action(
+        if anterior.is_some() { "UPDATE_PIEZA_PROVEEDOR" } else { "ADD_PIEZA_PROVEEDOR" },
+        Some(usuario.usuario_id),
+        "PROVEEDOR",
+        Some(proveedor_id),
+        anterior.as_ref().map(|(codigo, costo)| linea(codigo.as_deref(), *costo)).as_deref(),
+        Some(&linea(request.codigo_proveedor.as_deref(), request.costo))
+    ).await;
+
+    get_piezas_proveedor(session_token, proveedor_id).await
+}
+
+/// Quitar una pieza del catálogo de un proveedor
+#[tauri::command]
+pub async fn remove_pieza_proveedor(session_token: String, proveedor_id: i32, pieza_id: i32) -> Result<Vec<PiezaProveedor>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let result = sqlx::query("DELETE FROM PIEZA_PROVEEDOR WHERE proveedor_id = ? AND pieza_id = ?")
+        .bind(proveedor_id)
+        .bind(pieza_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    if result.rows_affected() > 0 {
+        let _ = log_action(
+            "REMOVE_PIEZA_PROVEEDOR",
+            Some(usuario.usuario_id),
+            "PROVEEDOR",
+            Some(proveedor_id),
+            Some(&pieza_id.to_string()),
+            None
+        ).await;
+    }
+
+    get_piezas_proveedor(session_token, proveedor_id).await
+}
+
+// ==================== Órdenes de compra ====================
+
+/// Obtener las órdenes de compra, opcionalmente filtradas por estado
+#[tauri::command]
+pub async fn get_ordenes_compra(session_token: String, estado: Option<String>) -> Result<Vec<OrdenCompra>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let estado = estado.as_deref().map(str::parse::<EstadoOrdenCompra>).transpose()?;
+
+    sqlx::query_as::<_, OrdenCompra>(&format!(
+        "{} WHERE (? IS NULL OR oc.estado = ?) ORDER BY oc.created_at DESC, oc.orden_compra_id DESC",
+        SELECT_ORDEN_COMPRA
+    ))
+    .bind(estado.map(|e| e.as_str()))
+    .bind(estado.map(|e| e.as_str()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener una orden de compra con sus líneas
+#[tauri::command]
+pub async fn get_orden_compra(session_token: String, orden_compra_id: i32) -> Result<Option<OrdenCompraDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCompras).await?;
+    fetch_orden_compra_detallada(orden_compra_id).await
+}
+
+/// Crear una orden de compra en borrador
+#[tauri::command]
+pub async fn create_orden_compra(session_token: String, request: CreateOrdenCompraRequest) -> Result<OrdenCompraDetallada, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    if request.lineas.is_empty() {
+        return Err("La orden de compra debe tener al menos una pieza".to_string());
+    }
+    let mut vistas = HashSet::new();
+    if let Some(repetida) = request.lineas.iter().find(|l| !vistas.insert(l.pieza_id)) {
+        return Err(format!("La pieza {} aparece más de una vez", repetida.pieza_id));
+    }
+    if fetch_proveedor(request.proveedor_id).await?.is_none() {
+        return Err("Proveedor no encontrado".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::OrdenCompra).await?;
+    let result = sqlx::query(
+        "INSERT INTO ORDEN_COMPRA (orden_compra_codigo, proveedor_id, estado, observaciones, created_by)
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&codigo)
+    .bind(request.proveedor_id)
+    .bind(EstadoOrdenCompra::Borrador.as_str())
+    .bind(&request.observaciones)
+    .bind(usuario.usuario_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let orden_compra_id = result.last_insert_id() as i32;
+
+    for linea in &request.lineas {
+        guardar_linea(&mut tx, orden_compra_id, request.proveedor_id, linea).await?;
+    }
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "CREATE_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        None,
+        Some(&format!("Orden de compra creada: {} ({} piezas)", codigo, request.lineas.len()))
+    ).await;
+
+    fetch_orden_compra_detallada(orden_compra_id).await?
+        .ok_or_else(|| "Failed to retrieve created orden de compra".to_string())
+}
+
+/// Agregar una pieza a una orden de compra en borrador o cambiar su cantidad y costo
+#[tauri::command]
+pub async fn upsert_linea_orden_compra(session_token: String, orden_compra_id: i32, linea: LineaOrdenCompraRequest) -> Result<OrdenCompraDetallada, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let orden = bloquear_orden_compra(&mut tx, orden_compra_id).await?;
+    if !orden.estado.es_editable() {
+        return Err(format!("La orden de compra {} ya fue enviada y no se puede modificar", orden.codigo));
+    }
+    let (cantidad, costo) = guardar_linea(&mut tx, orden_compra_id, orden.proveedor_id, &linea).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: pieza|cantidad|costo
+    let _ = log_action(
+        "UPSERT_LINEA_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        None,
+        Some(&format!("{}|{}|{}", linea.pieza_id, cantidad, costo))
+    ).await;
+
+    fetch_orden_compra_detallada(orden_compra_id).await?
+        .ok_or_else(|| "Orden de compra no encontrada".to_string())
+}
+
+/// Quitar una pieza de una orden de compra en borrador
+#[tauri::command]
+pub async fn remove_linea_orden_compra(session_token: String, orden_compra_id: i32, pieza_id: i32) -> Result<OrdenCompraDetallada, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let orden = bloquear_orden_compra(&mut tx, orden_compra_id).await?;
+    if !orden.estado.es_editable() {
+        return Err(format!("La orden de compra {} ya fue enviada y no se puede modificar", orden.codigo));
+    }
+
+    let result = sqlx::query("DELETE FROM PIEZAS_ORDEN_COMPRA WHERE orden_compra_id = ? AND pieza_id = ?")
+        .bind(orden_compra_id)
+        .bind(pieza_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err(format!("La pieza {} no está en la orden de compra", pieza_id));
+    }
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let _ = log_action(
+        "REMOVE_LINEA_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        Some(&pieza_id.to_string()),
+        None
+    ).await;
+
+    fetch_orden_compra_detallada(orden_compra_id).await?
+        .ok_or_else(|| "Orden de compra no encontrada".to_string())
+}
+
+/// Marcar una orden de compra en borrador como enviada al proveedor.
+/// Desde ese momento sus líneas quedan fijas.
+#[tauri::command]
+pub async fn enviar_orden_compra(session_token: String, orden_compra_id: i32) -> Result<OrdenCompraDetallada, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let orden = bloquear_orden_compra(&mut tx, orden_compra_id).await?;
+    if orden.estado != EstadoOrdenCompra::Borrador {
+        return Err(format!("La orden de compra {} ya está en estado {}", orden.codigo, orden.estado));
+    }
+    if fetch_avance_lineas(&mut tx, orden_compra_id).await?.is_empty() {
+        return Err("La orden de compra no tiene piezas".to_string());
+    }
+
+    sqlx::query("UPDATE ORDEN_COMPRA SET estado = ?, enviada_at = CURRENT_TIMESTAMP WHERE orden_compra_id = ?")
+        .bind(EstadoOrdenCompra::Enviada.as_str())
+        .bind(orden_compra_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let _ = log_action(
+        "ENVIAR_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        Some(EstadoOrdenCompra::Borrador.as_str()),
+        Some(EstadoOrdenCompra::Enviada.as_str())
+    ).await;
+
+    fetch_orden_compra_detallada(orden_compra_id).await?
+        .ok_or_else(|| "Orden de compra no encontrada".to_string())
+}
+
+/// Registrar la llegada de mercadería de una orden de compra. Cada pieza
+/// recibida entra al stock como recepción de compra y la orden pasa a
+/// recibida parcial o recibida según lo que quede pendiente.
+#[tauri::command]
+pub async fn recibir_orden_compra(session_token: String, orden_compra_id: i32, recepciones: Vec<RecepcionLineaRequest>) -> Result<OrdenCompraDetallada, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    if recepciones.is_empty() {
+        return Err("Debe indicar las piezas recibidas".to_string());
+    }
+    let mut vistas = HashSet::new();
+    if let Some(repetida) = recepciones.iter().find(|r| !vistas.insert(r.pieza_id)) {
+        return Err(format!("La pieza {} aparece más de una vez", repetida.pieza_id));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let orden = bloquear_orden_compra(&mut tx, orden_compra_id).await?;
+    if !orden.estado.admite_recepcion() {
+        return Err(format!("La orden de compra {} está en estado {} y no admite recepciones", orden.codigo, orden.estado));
+    }
+
+    let mut lineas = fetch_avance_lineas(&mut tx, orden_compra_id).await?;
+    let motivo = format!("Recepción {}", orden.codigo);
+
+    for recepcion in &recepciones {
+        let linea = lineas.iter_mut()
+            .find(|l| l.pieza_id == recepcion.pieza_id)
+            .ok_or_else(|| format!("La pieza {} no está en la orden de compra", recepcion.pieza_id))?;
+        validar_recepcion(linea, recepcion.cantidad)?;
+        linea.cantidad_recibida += recepcion.cantidad;
+
+        sqlx::query(
+            "UPDATE PIEZAS_ORDEN_COMPRA SET cantidad_recibida = ? WHERE orden_compra_id = ? AND pieza_id = ?"
+        )
+        .bind(linea.cantidad_recibida)
+        .bind(orden_compra_id)
+        .bind(recepcion.pieza_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+        // Un ingreso nunca deja el stock negativo, la política no influye
+        registrar_movimiento(&mut tx, NuevoMovimiento {
+            pieza_id: recepcion.pieza_id,
+            tipo: TipoMovimiento::RecepcionCompra,
+            cantidad: recepcion.cantidad,
+            informe_id: None,
+            orden_compra_id: Some(orden_compra_id),
+            motivo: Some(&motivo),
+            usuario_id: Some(usuario.usuario_id),
+        }, PoliticaStockNegativo::Rechazar).await?;
+
+        // Lo pagado al proveedor pasa a ser el costo vigente de la pieza
+        let costo_unitario = sqlx::query_scalar::<_, i32>(
+            "SELECT costo_unitario FROM PIEZAS_ORDEN_COMPRA WHERE orden_compra_id = ? AND pieza_id = ?"
+        )
+        .bind(orden_compra_id)
+        .bind(recepcion.pieza_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+        registrar_precio(&mut tx, recepcion.pieza_id, TipoPrecio::Costo, costo_unitario, None, Some(usuario.usuario_id)).await?;
+
+        let (nombre, requiere_serie) = pieza_requiere_serie(&mut tx, recepcion.pieza_id).await?;
+        match recepcion.series.as_deref() {
+            Some(series) if requiere_serie => {
+                let series = validar_series(&nombre, series, recepcion.cantidad)?;
+                registrar_unidades(&mut tx, recepcion.pieza_id, &series, Some(orden_compra_id), Some(usuario.usuario_id)).await?;
+            }
+            None if requiere_serie => return Err(format!("Indique los números de serie de {} recibidos", nombre)),
+            Some(series) if !series.is_empty() => return Err(format!("{} no se controla por número de serie", nombre)),
+            _ => {}
+        }
+    }
+
+    let nuevo_estado = estado_segun_recepcion(&lineas);
+    sqlx::query(
+        "UPDATE ORDEN_COMPRA SET estado = ?,
+         recibida_at = IF(? = 'recibida', CURRENT_TIMESTAMP, recibida_at)
+         WHERE orden_compra_id = ?"
+    )
+    .bind(nuevo_estado.as_str())
+    .bind(nuevo_estado.as_str())
+    .bind(orden_compra_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: pieza:cantidad por cada línea recibida
+    let detalle = recepciones.iter()
+        .map(|r| format!("{}:{}", r.pieza_id, r.cantidad))
+        .collect::<Vec<_>>()
+        .join(",");
+    let _ = log_action(
+        "RECIBIR_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        Some(orden.estado.as_str()),
+        Some(&format!("{}|{}", nuevo_estado, detalle))
+    ).await;
+
+    fetch_orden_compra_detallada(orden_compra_id).await?
+        .ok_or_else(|| "Orden de compra no encontrada".to_string())
+}
+
+/// Eliminar una orden de compra que sigue en borrador
+#[tauri::command]
+pub async fn delete_orden_compra(session_token: String, orden_compra_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCompras).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let orden = bloquear_orden_compra(&mut tx, orden_compra_id).await?;
+    if !orden.estado.es_editable() {
+        return Err(format!("La orden de compra {} ya fue enviada y no se puede eliminar", orden.codigo));
+    }
+
+    // Las líneas se eliminan en cascada
+    sqlx::query("DELETE FROM ORDEN_COMPRA WHERE orden_compra_id = ?")
+        .bind(orden_compra_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let _ = log_action(
+        "DELETE_ORDEN_COMPRA",
+        Some(usuario.usuario_id),
+        "ORDEN_COMPRA",
+        Some(orden_compra_id),
+        Some(&format!("Orden de compra eliminada: {}", orden.codigo)),
+        None
+    ).await;
+
+    Ok(true)
+}
+
+/// Estado y datos de una orden de compra bloqueada para modificarla
+struct OrdenCompraBloqueada {
+    codigo: String,
+    proveedor_id: i32,
+    estado: EstadoOrdenCompra,
+}
+
+async fn bloquear_orden_compra(tx: &mut Transaction<'_, MySql>, orden_compra_id: i32) -> Result<OrdenCompraBloqueada, String> {
+    let (codigo, proveedor_id, estado) = sqlx::query_as::<_, (String, i32, String)>(
+        "SELECT orden_compra_codigo, proveedor_id, estado FROM ORDEN_COMPRA WHERE orden_compra_id = ? FOR UPDATE"
+    )
+    .bind(orden_compra_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Orden de compra no encontrada".to_string())?;
+
+    Ok(OrdenCompraBloqueada { codigo, proveedor_id, estado: estado.parse()? })
+}
+
+/// Inserta o actualiza una línea. Sin costo explícito se toma el del catálogo
+/// del proveedor. Devuelve la cantidad y el costo guardados.
+async fn guardar_linea(
+    tx: &mut Transaction<'_, MySql>,
+    orden_compra_id: i32,
+    proveedor_id: i32,
+    linea: &LineaOrdenCompraRequest,
+) -> Result<(i32, i32), String> {
+    if linea.cantidad <= 0 {
+        return Err(format!("La cantidad de la pieza {} debe ser mayor a cero", linea.pieza_id));
+    }
+
+    let costo = match linea.costo_unitario {
+        Some(costo) if costo < 0 => return Err(format!("El costo de la pieza {} no puede ser negativo", linea.pieza_id)),
+        Some(costo) => costo,
+        None => sqlx::query_scalar::<_, Option<i32>>(
+            "SELECT costo FROM PIEZA_PROVEEDOR WHERE proveedor_id = ? AND pieza_id = ?"
+        )
+        .bind(proveedor_id)
+        .bind(linea.pieza_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .flatten()
+        .ok_or_else(|| format!("Indique el costo de la pieza {}: el proveedor no tiene un costo registrado", linea.pieza_id))?,
+    };
+
+    sqlx::query(
+        "INSERT INTO PIEZAS_ORDEN_COMPRA (orden_compra_id, pieza_id, cantidad, costo_unitario) VALUES (?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE cantidad = VALUES(cantidad), costo_unitario = VALUES(costo_unitario)"
+    )
+    .bind(orden_compra_id)
+    .bind(linea.pieza_id)
+    .bind(linea.cantidad)
+    .bind(costo)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error adding part: {}", e))?;
+
+    Ok((linea.cantidad, costo))
+}
+
+async fn fetch_avance_lineas(tx: &mut Transaction<'_, MySql>, orden_compra_id: i32) -> Result<Vec<AvanceLinea>, String> {
+    let filas = sqlx::query_as::<_, (i32, i32, i32)>(
+        "SELECT pieza_id, cantidad, cantidad_recibida FROM PIEZAS_ORDEN_COMPRA
+         WHERE orden_compra_id = ? ORDER BY pieza_id FOR UPDATE"
+    )
+    .bind(orden_compra_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(filas.into_iter()
+        .map(|(pieza_id, cantidad, cantidad_recibida)| AvanceLinea { pieza_id, cantidad, cantidad_recibida })
+        .collect())
+}
+
+async fn fetch_orden_compra_detallada(orden_compra_id: i32) -> Result<Option<OrdenCompraDetallada>, String> {
+    let pool = get_db_pool_safe()?;
+
+    let orden = sqlx::query_as::<_, OrdenCompra>(&format!("{} WHERE oc.orden_compra_id = ?", SELECT_ORDEN_COMPRA))
+        .bind(orden_compra_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let Some(orden) = orden else {
+        return Ok(None);
+    };
+
+    let lineas = sqlx::query_as::<_, LineaOrdenCompra>(
+        "SELECT l.orden_compra_id, l.pieza_id, p.pieza_nombre, p.pieza_marca, pp.codigo_proveedor,
+                l.cantidad, l.cantidad_recibida, l.costo_unitario,
+                CAST(l.cantidad * l.costo_unitario AS SIGNED) as total_linea
+         FROM PIEZAS_ORDEN_COMPRA l
+         INNER JOIN PIEZA p ON l.pieza_id = p.pieza_id
+         LEFT JOIN PIEZA_PROVEEDOR pp ON pp.pieza_id = l.pieza_id AND pp.proveedor_id = ?
+         WHERE l.orden_compra_id = ?
+         ORDER BY p.pieza_nombre"
+    )
+    .bind(orden.proveedor_id)
+    .bind(orden_compra_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(Some(OrdenCompraDetallada { orden, lineas }))
+}
@@ -2,19 +2,48 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
 use crate::commands::logs::log_action;
-use chrono::{DateTime, Utc};
-use chrono::Datelike;
+use crate::auth::{require_permission, Permiso};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::codigos::{siguiente_codigo, TipoDocumento};
+use crate::calculo_cotizacion::{
+    calcular_totales, fecha_vencimiento, validar_lineas, verificar_total_informado, CondicionesCotizacion,
+    LineaCotizacion, PoliticaDiferenciaTotal, TotalesCotizacion,
+};
+use crate::commands::parametros::{
+    obtener_iva_porcentaje, obtener_politica_diferencia_total, obtener_validez_cotizacion,
+};
+use sqlx::{MySql, Transaction};
+use crate::commands::revisiones_cotizacion::{abrir_nueva_revision, registrar_nueva_revision};
+use crate::commands::inventario::{liberar_reservas_cotizacion, reservar_piezas_cotizacion};
+use crate::commands::precios::registrar_precio;
+use crate::commands::series::verificar_cambio_control_serie;
+use crate::precios::TipoPrecio;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Cotizacion {
     pub cotizacion_id: i32,
     pub cotizacion_codigo: Option<String>,
+    pub revision: Option<i32>,
     pub costo_revision: Option<i32>,
     pub costo_reparacion: Option<i32>,
+    pub subtotal_piezas: Option<i32>,
+    pub descuento_global_pct: Option<i32>,
+    pub iva_porcentaje: Option<i32>,
+    // Desglose: costo_total = monto_neto + monto_iva + monto_exento
+    pub monto_descuento: Option<i32>,
+    pub monto_neto: Option<i32>,
+    pub monto_exento: Option<i32>,
+    pub monto_iva: Option<i32>,
     pub costo_total: Option<i32>,
+    // Total enviado que no coincidía con el calculado (ver total_con_diferencia)
+    pub costo_total_informado: Option<i32>,
+    pub total_con_diferencia: Option<bool>,
     pub is_aprobada: Option<bool>,
     pub is_borrador: Option<bool>,
     pub informe: String,
+    // Último día de vigencia; vencida_at se marca cuando la revisión programada la detecta vencida
+    pub valido_hasta: Option<NaiveDate>,
+    pub vencida_at: Option<DateTime<Utc>>,
     pub created_by: Option<i32>,
     pub created_at: Option<DateTime<Utc>>,
 }
@@ -26,6 +55,9 @@ pub struct Pieza {
     pub pieza_marca: Option<String>,
     pub pieza_desc: Option<String>,
     pub pieza_precio: Option<i32>,
+    pub stock_actual: Option<i32>,
+    // Se controla por unidad: al usarla en un informe hay que elegir sus series
+    pub requiere_serie: Option<bool>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -34,6 +66,13 @@ pub struct PiezaCotizacion {
     pub pieza_id: i32,
     pub cotizacion_id: i32,
     pub cantidad: Option<i32>,
+    // Precio de la pieza al momento de cotizar
+    pub precio_unitario: Option<i32>,
+    pub descuento_pct: Option<i32>,
+    pub es_exenta: Option<bool>,
+    // Total neto de la línea con su descuento
+    #[sqlx(skip)]
+    pub total_linea: Option<i64>,
     // Campos adicionales para JOINs
     pub pieza_nombre: Option<String>,
     pub pieza_marca: Option<String>,
@@ -45,12 +84,27 @@ pub struct PiezaCotizacion {
 pub struct CotizacionDetallada {
     pub cotizacion_id: i32,
     pub cotizacion_codigo: Option<String>,
+    pub revision: Option<i32>,
     pub costo_revision: Option<i32>,
     pub costo_reparacion: Option<i32>,
+    pub subtotal_piezas: Option<i32>,
+    pub descuento_global_pct: Option<i32>,
+    pub iva_porcentaje: Option<i32>,
+    // Desglose: costo_total = monto_neto + monto_iva + monto_exento
+    pub monto_descuento: Option<i32>,
+    pub monto_neto: Option<i32>,
+    pub monto_exento: Option<i32>,
+    pub monto_iva: Option<i32>,
     pub costo_total: Option<i32>,
+    // Total enviado que no coincidía con el calculado (ver total_con_diferencia)
+    pub costo_total_informado: Option<i32>,
+    pub total_con_diferencia: Option<bool>,
     pub is_aprobada: Option<bool>,
     pub is_borrador: Option<bool>,
     pub informe: String,
+    // Último día de vigencia; vencida_at se marca cuando la revisión programada la detecta vencida
+    pub valido_hasta: Option<NaiveDate>,
+    pub vencida_at: Option<DateTime<Utc>>,
     pub created_by: Option<i32>,
     pub created_at: Option<DateTime<Utc>>,
     pub created_by_nombre: Option<String>,
@@ -65,7 +119,9 @@ pub struct CreateCotizacionRequest {
     pub is_aprobada: Option<bool>,
     pub is_borrador: Option<bool>,
     pub informe: String,
-    pub created_by: i32,
+    pub descuento_global_pct: Option<i32>,
+    // Si no se indica, se calcula con el parámetro cotizacion_validez_dias
+    pub valido_hasta: Option<NaiveDate>,
     pub piezas: Option<Vec<PiezaCotizacionRequest>>,
 }
 
@@ -78,6 +134,8 @@ pub struct UpdateCotizacionRequest {
     pub is_aprobada: Option<bool>,
     pub is_borrador: Option<bool>,
     pub informe: Option<String>,
+    pub descuento_global_pct: Option<i32>,
+    pub valido_hasta: Option<NaiveDate>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,22 +144,41 @@ pub struct CreatePiezaRequest {
     pub pieza_marca: Option<String>,
     pub pieza_desc: Option<String>,
     pub pieza_precio: Option<i32>,
+    pub requiere_serie: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PiezaCotizacionRequest {
     pub pieza_id: i32,
     pub cantidad: i32,
+    pub descuento_pct: Option<i32>,
+    pub es_exenta: Option<bool>,
+}
+
+impl PiezaCotizacionRequest {
+    fn linea(&self, precio_unitario: i32) -> LineaCotizacion {
+        LineaCotizacion {
+            pieza_id: self.pieza_id,
+            cantidad: self.cantidad,
+            precio_unitario,
+            descuento_pct: self.descuento_pct.unwrap_or(0),
+            es_exenta: self.es_exenta.unwrap_or(false),
+        }
+    }
 }
 
 /// Obtener todas las cotizaciones
 #[tauri::command]
-pub async fn get_cotizaciones() -> Result<Vec<Cotizacion>, String> {
+pub async fn get_cotizaciones(session_token: String) -> Result<Vec<Cotizacion>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let cotizaciones = sqlx::query_as::<_, Cotizacion>(
-        "SELECT cotizacion_id, cotizacion_codigo, costo_revision, costo_reparacion, \
-                costo_total, is_aprobada, is_borrador, informe, created_by, created_at \
+        "SELECT cotizacion_id, cotizacion_codigo, revision, costo_revision, costo_reparacion, \
+                subtotal_piezas, descuento_global_pct, iva_porcentaje, \
+                monto_descuento, monto_neto, monto_exento, monto_iva, costo_total, \
+                costo_total_informado, total_con_diferencia, is_aprobada, is_borrador, \
+                informe, valido_hasta, vencida_at, created_by, created_at \
          FROM COTIZACION \
          ORDER BY created_at DESC"
     )
@@ -114,12 +191,17 @@ pub async fn get_cotizaciones() -> Result<Vec<Cotizacion>, String> {
 
 /// Obtener cotizaciones con información detallada
 #[tauri::command]
-pub async fn get_cotizaciones_detalladas() -> Result<Vec<CotizacionDetallada>, String> {
+pub async fn get_cotizaciones_detalladas(session_token: String) -> Result<Vec<CotizacionDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let cotizaciones = sqlx::query_as::<_, CotizacionDetallada>(
-        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.costo_revision, c.costo_reparacion,
-                c.costo_total, c.is_aprobada, c.is_borrador, c.created_by, c.created_at,
+        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.revision, c.costo_revision, c.costo_reparacion,
+                c.subtotal_piezas, c.descuento_global_pct, c.iva_porcentaje,
+                c.monto_descuento, c.monto_neto, c.monto_exento, c.monto_iva, c.costo_total,
+                c.costo_total_informado, c.total_con_diferencia,
+                c.is_aprobada, c.is_borrador, c.informe, c.valido_hasta, c.vencida_at,
+                c.created_by, c.created_at,
                 u.usuario_nombre as created_by_nombre
          FROM COTIZACION c
          LEFT JOIN USUARIO u ON c.created_by = u.usuario_id
@@ -134,12 +216,16 @@ pub async fn get_cotizaciones_detalladas() -> Result<Vec<CotizacionDetallada>, S
 
 /// Obtener una cotización por ID
 #[tauri::command]
-pub async fn get_cotizacion_by_id(cotizacion_id: i32) -> Result<Option<Cotizacion>, String> {
+pub async fn get_cotizacion_by_id(session_token: String, cotizacion_id: i32) -> Result<Option<Cotizacion>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let cotizacion = sqlx::query_as::<_, Cotizacion>(
-        "SELECT cotizacion_id, cotizacion_codigo, costo_revision, costo_reparacion,\
-                costo_total, is_aprobada, is_borrador, informe, created_by, created_at \
+        "SELECT cotizacion_id, cotizacion_codigo, revision, costo_revision, costo_reparacion,\
+                subtotal_piezas, descuento_global_pct, iva_porcentaje, \
+                monto_descuento, monto_neto, monto_exento, monto_iva, costo_total, \
+                costo_total_informado, total_con_diferencia, is_aprobada, is_borrador, \
+                informe, valido_hasta, vencida_at, created_by, created_at \
          FROM COTIZACION \
          WHERE cotizacion_id = ?"
     )
@@ -153,12 +239,16 @@ pub async fn get_cotizacion_by_id(cotizacion_id: i32) -> Result<Option<Cotizacio
 
 /// Obtener una cotización por código
 #[tauri::command]
-pub async fn get_cotizacion_by_codigo(cotizacion_codigo: String) -> Result<Option<Cotizacion>, String> {
+pub async fn get_cotizacion_by_codigo(session_token: String, cotizacion_codigo: String) -> Result<Option<Cotizacion>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let cotizacion = sqlx::query_as::<_, Cotizacion>(
-        "SELECT cotizacion_id, cotizacion_codigo, costo_revision, costo_reparacion,\
-                costo_total, is_aprobada, is_borrador, informe, created_by, created_at \
+        "SELECT cotizacion_id, cotizacion_codigo, revision, costo_revision, costo_reparacion,\
+                subtotal_piezas, descuento_global_pct, iva_porcentaje, \
+                monto_descuento, monto_neto, monto_exento, monto_iva, costo_total, \
+                costo_total_informado, total_con_diferencia, is_aprobada, is_borrador, \
+                informe, valido_hasta, vencida_at, created_by, created_at \
          FROM COTIZACION \
          WHERE cotizacion_codigo = ?"
     )
@@ -172,118 +262,153 @@ pub async fn get_cotizacion_by_codigo(cotizacion_codigo: String) -> Result<Optio
 
 /// Crear una nueva cotización
 #[tauri::command]
-pub async fn create_cotizacion(request: CreateCotizacionRequest) -> Result<Cotizacion, String> {
+pub async fn create_cotizacion(session_token: String, request: CreateCotizacionRequest) -> Result<Cotizacion, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCotizaciones).await?;
     let pool = get_db_pool_safe()?;
-    // Generar código automático: COT-YYYY-XXXX
-    let year = chrono::Utc::now().year();
-    // Buscar el mayor número correlativo existente para el año actual
-    let last_codigo: Option<String> = sqlx::query_scalar(
-        "SELECT cotizacion_codigo FROM COTIZACION WHERE cotizacion_codigo LIKE ? ORDER BY cotizacion_id DESC LIMIT 1"
-    )
-    .bind(format!("COT-{}-%", year))
-    .fetch_one(pool)
-    .await
-    .ok();
-    let next_number = if let Some(codigo) = last_codigo {
-        // Extraer el número correlativo actual y sumarle 1
-        let parts: Vec<&str> = codigo.split('-').collect();
-        if parts.len() == 3 {
-            parts[2].parse::<u32>().unwrap_or(0) + 1
-        } else {
-            1
-        }
-    } else {
-        1
+    let politica = obtener_politica_diferencia_total().await?;
+    // La tasa vigente queda registrada en la cotización
+    let iva_porcentaje = obtener_iva_porcentaje().await?;
+    let valido_hasta = match request.valido_hasta {
+        Some(fecha) => fecha,
+        None => fecha_vencimiento(Utc::now().date_naive(), obtener_validez_cotizacion().await?),
     };
-    let codigo = format!("COT-{}-{:03}", year, next_number);
+    
+    if let Some(ref piezas) = request.piezas {
+        let lineas: Vec<LineaCotizacion> = piezas.iter().map(|p| p.linea(0)).collect();
+        validar_lineas(&lineas)?;
+    }
+    
     // Iniciar transacción
     let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
-    // Crear la cotización
+    
+    // Generar código automático con el correlativo compartido
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::Cotizacion).await?;
+    // Crear la cotización; el total se calcula una vez registradas las piezas
     let result = sqlx::query(
         "INSERT INTO COTIZACION (cotizacion_codigo, costo_revision, costo_reparacion, \
-                                costo_total, is_aprobada, is_borrador, informe, created_by) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+                                descuento_global_pct, iva_porcentaje, \
+                                is_aprobada, is_borrador, informe, valido_hasta, created_by) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&codigo)
     .bind(request.costo_revision)
     .bind(request.costo_reparacion)
-    .bind(request.costo_total)
+    .bind(request.descuento_global_pct.unwrap_or(0))
+    .bind(iva_porcentaje)
     .bind(request.is_aprobada.unwrap_or(false))
     .bind(request.is_borrador.unwrap_or(true))
     .bind(&request.informe)
-    .bind(request.created_by)
+    .bind(valido_hasta)
+    .bind(usuario.usuario_id)
     .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     let cotizacion_id = result.last_insert_id() as i32;
-    // Agregar piezas si se proporcionaron
+    // Agregar piezas si se proporcionaron, con el precio vigente del catálogo
     if let Some(ref piezas) = request.piezas {
         for pieza in piezas {
-            sqlx::query(
-                "INSERT INTO PIEZAS_COTIZACION (pieza_id, cotizacion_id, cantidad) VALUES (?, ?, ?)"
-            )
-            .bind(pieza.pieza_id)
-            .bind(cotizacion_id)
-            .bind(pieza.cantidad)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| format!("Database error adding part: {}", e))?;
+            agregar_pieza_cotizacion(&mut tx, cotizacion_id, pieza).await?;
         }
     }
+    let (totales, total_informado) = recalcular_totales_cotizacion(&mut tx, cotizacion_id, request.costo_total, politica).await?;
+    // Una cotización que nace aprobada reserva sus piezas como al aprobarla
+    let reservadas = if request.is_aprobada.unwrap_or(false) {
+        reservar_piezas_cotizacion(&mut tx, cotizacion_id, Some(usuario.usuario_id)).await?
+    } else {
+        0
+    };
     // Confirmar transacción
     tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_COTIZACION",
-        Some(request.created_by),
+        Some(usuario.usuario_id),
         "COTIZACION",
         Some(cotizacion_id),
         None,
         Some(&format!("Cotización creada: {}", codigo))
     ).await;
+    if reservadas > 0 {
+        let _ = log_action(
+            "RESERVAR_STOCK",
+            Some(usuario.usuario_id),
+            "COTIZACION",
+            Some(cotizacion_id),
+            None,
+            Some(&format!("{} piezas", reservadas))
+        ).await;
+    }
+    registrar_diferencia_total(usuario.usuario_id, cotizacion_id, total_informado, &totales).await;
     // Obtener la cotización recién creada
-    get_cotizacion_by_id(cotizacion_id)
+    get_cotizacion_by_id(session_token, cotizacion_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created cotización".to_string())
 }
 
 /// Actualizar una cotización existente
 #[tauri::command]
-pub async fn update_cotizacion(cotizacion_id: i32, request: UpdateCotizacionRequest, updated_by: i32) -> Result<Option<Cotizacion>, String> {
+pub async fn update_cotizacion(session_token: String, cotizacion_id: i32, request: UpdateCotizacionRequest) -> Result<Option<Cotizacion>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener la cotización actual para logging
-    let current_cotizacion = get_cotizacion_by_id(cotizacion_id).await?;
+    let current_cotizacion = get_cotizacion_by_id(session_token.clone(), cotizacion_id).await?;
     
     // Verificar que el código no está en uso por otra cotización (si se está actualizando)
     if let Some(ref new_codigo) = request.cotizacion_codigo {
-        if let Some(existing_cotizacion) = get_cotizacion_by_codigo(new_codigo.clone()).await? {
+        if let Some(existing_cotizacion) = get_cotizacion_by_codigo(session_token.clone(), new_codigo.clone()).await? {
             if existing_cotizacion.cotizacion_id != cotizacion_id {
                 return Err("Ya existe otra cotización con este código".to_string());
             }
         }
     }
     
+    // Los totales solo se recalculan si cambian los montos o se envía un total,
+    // así editar el texto no altera el total de cotizaciones antiguas
+    let cambia_montos = request.costo_revision.is_some()
+        || request.costo_reparacion.is_some()
+        || request.descuento_global_pct.is_some()
+        || request.costo_total.is_some();
+    
+    let politica = obtener_politica_diferencia_total().await?;
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    // Lo aprobado por el cliente no cambia: montos y texto quedan fijos
+    if current_cotizacion.is_some() && (cambia_montos || request.informe.is_some()) {
+        verificar_cotizacion_editable(&mut tx, cotizacion_id).await?;
+    }
+    
+    // Una cotización ya enviada no se sobrescribe: se archiva y se abre una revisión nueva
+    let nueva_revision = if current_cotizacion.is_some() && (cambia_montos || request.informe.is_some()) {
+        abrir_nueva_revision(&mut tx, cotizacion_id, usuario.usuario_id).await?
+    } else {
+        None
+    };
+    
     let result = sqlx::query(
         "UPDATE COTIZACION SET \
          cotizacion_codigo = COALESCE(?, cotizacion_codigo),\
          costo_revision = COALESCE(?, costo_revision),\
          costo_reparacion = COALESCE(?, costo_reparacion),\
-         costo_total = COALESCE(?, costo_total),\
+         descuento_global_pct = COALESCE(?, descuento_global_pct),\
          is_aprobada = COALESCE(?, is_aprobada),\
          is_borrador = COALESCE(?, is_borrador),\
-         informe = COALESCE(?, informe)\
+         informe = COALESCE(?, informe),\
+         valido_hasta = COALESCE(?, valido_hasta),\
+         vencida_at = IF(? IS NULL, vencida_at, NULL)\
          WHERE cotizacion_id = ?"
     )
     .bind(&request.cotizacion_codigo)
     .bind(request.costo_revision)
     .bind(request.costo_reparacion)
-    .bind(request.costo_total)
+    .bind(request.descuento_global_pct)
     .bind(request.is_aprobada)
     .bind(request.is_borrador)
     .bind(&request.informe)
+    .bind(request.valido_hasta)
+    .bind(request.valido_hasta)
     .bind(cotizacion_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
@@ -291,6 +416,42 @@ pub async fn update_cotizacion(cotizacion_id: i32, request: UpdateCotizacionRequ
         return Ok(None);
     }
     
+    let recalculo = if cambia_montos {
+        Some(recalcular_totales_cotizacion(&mut tx, cotizacion_id, request.costo_total, politica).await?)
+    } else {
+        None
+    };
+    
+    // Aprobar reserva las piezas cotizadas; retirar la aprobación las libera
+    let estaba_aprobada = current_cotizacion.as_ref().and_then(|c| c.is_aprobada).unwrap_or(false);
+    let reservas = match request.is_aprobada {
+        Some(true) if !estaba_aprobada => {
+            Some(("RESERVAR_STOCK", reservar_piezas_cotizacion(&mut tx, cotizacion_id, Some(usuario.usuario_id)).await?))
+        }
+        Some(false) if estaba_aprobada => {
+            Some(("LIBERAR_RESERVA_STOCK", liberar_reservas_cotizacion(&mut tx, cotizacion_id, "Cotización rechazada").await?))
+        }
+        _ => None,
+    };
+    
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    if let Some((accion, lineas)) = reservas.filter(|(_, lineas)| *lineas > 0) {
+        let _ = log_action(
+            accion,
+            Some(usuario.usuario_id),
+            "COTIZACION",
+            Some(cotizacion_id),
+            None,
+            Some(&format!("{} piezas", lineas))
+        ).await;
+    }
+    
+    if let Some((ref totales, total_informado)) = recalculo {
+        registrar_diferencia_total(usuario.usuario_id, cotizacion_id, total_informado, totales).await;
+    }
+    registrar_nueva_revision(usuario.usuario_id, cotizacion_id, nueva_revision).await;
+    
     // Registrar la acción en el log de auditoría
     if let Some(ref cotizacion) = current_cotizacion {
         let prev_data = format!("{}|{}|{}|{}|{}|{}", 
@@ -309,7 +470,8 @@ pub async fn update_cotizacion(cotizacion_id: i32, request: UpdateCotizacionRequ
             request.costo_reparacion
                 .or(cotizacion.costo_reparacion)
                 .map_or("".to_string(), |p| p.to_string()),
-            request.costo_total
+            recalculo.as_ref()
+                .map(|(totales, _)| totales.costo_total)
                 .or(cotizacion.costo_total)
                 .map_or("".to_string(), |p| p.to_string()),
             request.is_aprobada
@@ -322,7 +484,7 @@ pub async fn update_cotizacion(cotizacion_id: i32, request: UpdateCotizacionRequ
         
         let _ = log_action(
             "UPDATE_COTIZACION",
-            Some(updated_by),
+            Some(usuario.usuario_id),
             "COTIZACION",
             Some(cotizacion_id),
             Some(&prev_data),
@@ -330,16 +492,17 @@ pub async fn update_cotizacion(cotizacion_id: i32, request: UpdateCotizacionRequ
         ).await;
     }
     
-    get_cotizacion_by_id(cotizacion_id).await
+    get_cotizacion_by_id(session_token, cotizacion_id).await
 }
 
 /// Eliminar una cotización
 #[tauri::command]
-pub async fn delete_cotizacion(cotizacion_id: i32, deleted_by: i32) -> Result<bool, String> {
+pub async fn delete_cotizacion(session_token: String, cotizacion_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener la cotización antes de eliminarla para logging
-    let cotizacion_to_delete = get_cotizacion_by_id(cotizacion_id).await?;
+    let cotizacion_to_delete = get_cotizacion_by_id(session_token.clone(), cotizacion_id).await?;
     
     // Verificar si la cotización tiene órdenes de trabajo asociadas
     let has_dependencies = sqlx::query_scalar::<_, i64>(
@@ -357,6 +520,9 @@ pub async fn delete_cotizacion(cotizacion_id: i32, deleted_by: i32) -> Result<bo
     // Iniciar transacción
     let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
     
+    // Las piezas reservadas vuelven a estar disponibles
+    liberar_reservas_cotizacion(&mut tx, cotizacion_id, "Cotización eliminada").await?;
+    
     // Eliminar primero las relaciones con piezas
     sqlx::query("DELETE FROM PIEZAS_COTIZACION WHERE cotizacion_id = ?")
         .bind(cotizacion_id)
@@ -381,7 +547,7 @@ pub async fn delete_cotizacion(cotizacion_id: i32, deleted_by: i32) -> Result<bo
         if let Some(ref cotizacion) = cotizacion_to_delete {
             let _ = log_action(
                 "DELETE_COTIZACION",
-                Some(deleted_by),
+                Some(usuario.usuario_id),
                 "COTIZACION",
                 Some(cotizacion_id),
                 Some(&format!("Cotización eliminada: {}", 
@@ -397,10 +563,11 @@ pub async fn delete_cotizacion(cotizacion_id: i32, deleted_by: i32) -> Result<bo
 
 /// Obtener todas las piezas
 #[tauri::command]
-pub async fn get_piezas() -> Result<Vec<Pieza>, String> {
+pub async fn get_piezas(session_token: String) -> Result<Vec<Pieza>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
     let pool = get_db_pool_safe()?;
     let piezas = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA ORDER BY pieza_nombre ASC"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA ORDER BY pieza_nombre ASC"
     )
     .fetch_all(pool)
     .await
@@ -410,10 +577,11 @@ pub async fn get_piezas() -> Result<Vec<Pieza>, String> {
 
 /// Obtener una pieza por ID
 #[tauri::command]
-pub async fn get_pieza_by_id(pieza_id: i32) -> Result<Option<Pieza>, String> {
+pub async fn get_pieza_by_id(session_token: String, pieza_id: i32) -> Result<Option<Pieza>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
     let pool = get_db_pool_safe()?;
     let pieza = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA WHERE pieza_id = ?"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA WHERE pieza_id = ?"
     )
     .bind(pieza_id)
     .fetch_optional(pool)
@@ -424,21 +592,28 @@ pub async fn get_pieza_by_id(pieza_id: i32) -> Result<Option<Pieza>, String> {
 
 /// Crear una nueva pieza
 #[tauri::command]
-pub async fn create_pieza(request: CreatePiezaRequest) -> Result<Pieza, String> {
+pub async fn create_pieza(session_token: String, request: CreatePiezaRequest) -> Result<Pieza, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
     let pool = get_db_pool_safe()?;
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
     let result = sqlx::query(
-        "INSERT INTO PIEZA (pieza_nombre, pieza_marca, pieza_desc, pieza_precio) VALUES (?, ?, ?, ?)"
+        "INSERT INTO PIEZA (pieza_nombre, pieza_marca, pieza_desc, requiere_serie) VALUES (?, ?, ?, ?)"
     )
     .bind(&request.pieza_nombre)
     .bind(&request.pieza_marca)
     .bind(&request.pieza_desc)
-    .bind(request.pieza_precio)
-    .execute(pool)
+    .bind(request.requiere_serie.unwrap_or(false))
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     let pieza_id = result.last_insert_id() as i32;
+    // El precio inicial abre el historial de precios de venta
+    if let Some(precio) = request.pieza_precio {
+        registrar_precio(&mut tx, pieza_id, TipoPrecio::Venta, precio, None, Some(usuario.usuario_id)).await?;
+    }
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
     let pieza = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA WHERE pieza_id = ?"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA WHERE pieza_id = ?"
     )
     .bind(pieza_id)
     .fetch_one(pool)
@@ -447,7 +622,7 @@ pub async fn create_pieza(request: CreatePiezaRequest) -> Result<Pieza, String>
     // Log de creación de pieza
     let _ = log_action(
         "CREATE_PIEZA",
-        None,
+        Some(usuario.usuario_id),
         "PIEZA",
         Some(pieza_id),
         None,
@@ -462,52 +637,63 @@ pub struct UpdatePiezaRequest {
     pub pieza_marca: Option<String>,
     pub pieza_desc: Option<String>,
     pub pieza_precio: Option<i32>,
+    pub requiere_serie: Option<bool>,
 }
 
 /// Actualizar una pieza existente
 #[tauri::command]
-pub async fn update_pieza(pieza_id: i32, request: UpdatePiezaRequest) -> Result<Option<Pieza>, String> {
+pub async fn update_pieza(session_token: String, pieza_id: i32, request: UpdatePiezaRequest) -> Result<Option<Pieza>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
     let pool = get_db_pool_safe()?;
     // Obtener datos previos para el log
     let prev_pieza = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA WHERE pieza_id = ?"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA WHERE pieza_id = ?"
     )
     .bind(pieza_id)
     .fetch_optional(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
-    let result = sqlx::query(
+    if prev_pieza.is_none() {
+        return Ok(None);
+    }
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    if let Some(requiere_serie) = request.requiere_serie {
+        verificar_cambio_control_serie(&mut tx, pieza_id, requiere_serie).await?;
+    }
+    sqlx::query(
         "UPDATE PIEZA SET \
             pieza_nombre = COALESCE(?, pieza_nombre),\
             pieza_marca = COALESCE(?, pieza_marca),\
             pieza_desc = COALESCE(?, pieza_desc),\
-            pieza_precio = COALESCE(?, pieza_precio)\
+            requiere_serie = COALESCE(?, requiere_serie)\
          WHERE pieza_id = ?"
     )
     .bind(&request.pieza_nombre)
     .bind(&request.pieza_marca)
     .bind(&request.pieza_desc)
-    .bind(request.pieza_precio)
+    .bind(request.requiere_serie)
     .bind(pieza_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
-    if result.rows_affected() == 0 {
-        return Ok(None);
+    // Un precio distinto cierra el vigente en el historial en vez de sobrescribirlo
+    if let Some(precio) = request.pieza_precio {
+        registrar_precio(&mut tx, pieza_id, TipoPrecio::Venta, precio, None, Some(usuario.usuario_id)).await?;
     }
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
     let pieza = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA WHERE pieza_id = ?"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA WHERE pieza_id = ?"
     )
     .bind(pieza_id)
     .fetch_one(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     // Log de actualización de pieza
-    let prev = prev_pieza.as_ref().map(|p| format!("{}|{}|{}|{}", p.pieza_nombre.as_deref().unwrap_or(""), p.pieza_marca.as_deref().unwrap_or(""), p.pieza_desc.as_deref().unwrap_or(""), p.pieza_precio.map_or("".to_string(), |v| v.to_string())));
-    let newv = format!("{}|{}|{}|{}", pieza.pieza_nombre.as_deref().unwrap_or(""), pieza.pieza_marca.as_deref().unwrap_or(""), pieza.pieza_desc.as_deref().unwrap_or(""), pieza.pieza_precio.map_or("".to_string(), |v| v.to_string()));
+    let prev = prev_pieza.as_ref().map(|p| format!("{}|{}|{}|{}|{}", p.pieza_nombre.as_deref().unwrap_or(""), p.pieza_marca.as_deref().unwrap_or(""), p.pieza_desc.as_deref().unwrap_or(""), p.pieza_precio.map_or("".to_string(), |v| v.to_string()), p.requiere_serie.unwrap_or(false)));
+    let newv = format!("{}|{}|{}|{}|{}", pieza.pieza_nombre.as_deref().unwrap_or(""), pieza.pieza_marca.as_deref().unwrap_or(""), pieza.pieza_desc.as_deref().unwrap_or(""), pieza.pieza_precio.map_or("".to_string(), |v| v.to_string()), pieza.requiere_serie.unwrap_or(false));
     let _ = log_action(
         "UPDATE_PIEZA",
-        None,
+        Some(usuario.usuario_id),
         "PIEZA",
         Some(pieza_id),
         prev.as_deref(),
@@ -518,11 +704,12 @@ pub async fn update_pieza(pieza_id: i32, request: UpdatePiezaRequest) -> Result<
 
 /// Eliminar una pieza
 #[tauri::command]
-pub async fn delete_pieza(pieza_id: i32) -> Result<bool, String> {
+pub async fn delete_pieza(session_token: String, pieza_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarPiezas).await?;
     let pool = get_db_pool_safe()?;
     // Obtener datos previos para el log
     let prev_pieza = sqlx::query_as::<_, Pieza>(
-        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, created_at FROM PIEZA WHERE pieza_id = ?"
+        "SELECT pieza_id, pieza_nombre, pieza_marca, pieza_desc, pieza_precio, stock_actual, requiere_serie, created_at FROM PIEZA WHERE pieza_id = ?"
     )
     .bind(pieza_id)
     .fetch_optional(pool)
@@ -540,7 +727,7 @@ pub async fn delete_pieza(pieza_id: i32) -> Result<bool, String> {
             let prev = format!("{}|{}|{}|{}", p.pieza_nombre.as_deref().unwrap_or(""), p.pieza_marca.as_deref().unwrap_or(""), p.pieza_desc.as_deref().unwrap_or(""), p.pieza_precio.map_or("".to_string(), |v| v.to_string()));
             let _ = log_action(
                 "DELETE_PIEZA",
-                None,
+                Some(usuario.usuario_id),
                 "PIEZA",
                 Some(pieza_id),
                 Some(&prev),
@@ -553,14 +740,19 @@ pub async fn delete_pieza(pieza_id: i32) -> Result<bool, String> {
 
 /// Buscar cotizaciones por texto
 #[tauri::command]
-pub async fn search_cotizaciones(search_term: String) -> Result<Vec<CotizacionDetallada>, String> {
+pub async fn search_cotizaciones(session_token: String, search_term: String) -> Result<Vec<CotizacionDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let search_pattern = format!("%{}%", search_term);
     
     let cotizaciones = sqlx::query_as::<_, CotizacionDetallada>(
-        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.costo_revision, c.costo_reparacion,\
-                c.costo_total, c.is_aprobada, c.is_borrador, c.informe, c.created_by, c.created_at,\
+        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.revision, c.costo_revision, c.costo_reparacion,\
+                c.subtotal_piezas, c.descuento_global_pct, c.iva_porcentaje,\
+                c.monto_descuento, c.monto_neto, c.monto_exento, c.monto_iva, c.costo_total,\
+                c.costo_total_informado, c.total_con_diferencia,\
+                c.is_aprobada, c.is_borrador, c.informe, c.valido_hasta, c.vencida_at,
+                c.created_by, c.created_at,\
                 u.usuario_nombre as created_by_nombre\
          FROM COTIZACION c\
          LEFT JOIN USUARIO u ON c.created_by = u.usuario_id\
@@ -576,7 +768,8 @@ pub async fn search_cotizaciones(search_term: String) -> Result<Vec<CotizacionDe
 
 /// Contar total de cotizaciones
 #[tauri::command]
-pub async fn count_cotizaciones() -> Result<i64, String> {
+pub async fn count_cotizaciones(session_token: String) -> Result<i64, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM COTIZACION")
@@ -589,12 +782,17 @@ pub async fn count_cotizaciones() -> Result<i64, String> {
 
 /// Obtener cotizaciones con paginación
 #[tauri::command]
-pub async fn get_cotizaciones_with_pagination(offset: i64, limit: i64) -> Result<Vec<CotizacionDetallada>, String> {
+pub async fn get_cotizaciones_with_pagination(session_token: String, offset: i64, limit: i64) -> Result<Vec<CotizacionDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
     let pool = get_db_pool_safe()?;
     
     let cotizaciones = sqlx::query_as::<_, CotizacionDetallada>(
-        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.costo_revision, c.costo_reparacion,\
-                c.costo_total, c.is_aprobada, c.is_borrador, c.informe, c.created_by, c.created_at,\
+        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.revision, c.costo_revision, c.costo_reparacion,\
+                c.subtotal_piezas, c.descuento_global_pct, c.iva_porcentaje,\
+                c.monto_descuento, c.monto_neto, c.monto_exento, c.monto_iva, c.costo_total,\
+                c.costo_total_informado, c.total_con_diferencia,\
+                c.is_aprobada, c.is_borrador, c.informe, c.valido_hasta, c.vencida_at,
+                c.created_by, c.created_at,\
                 u.usuario_nombre as created_by_nombre\
          FROM COTIZACION c\
          LEFT JOIN USUARIO u ON c.created_by = u.usuario_id\
@@ -611,10 +809,17 @@ pub async fn get_cotizaciones_with_pagination(offset: i64, limit: i64) -> Result
 
 /// Obtener las piezas asociadas a una cotización
 #[tauri::command]
-pub async fn get_piezas_cotizacion(cotizacion_id: i32) -> Result<Vec<PiezaCotizacion>, String> {
+pub async fn get_piezas_cotizacion(session_token: String, cotizacion_id: i32) -> Result<Vec<PiezaCotizacion>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    fetch_piezas_cotizacion(cotizacion_id).await
+}
+
+/// Piezas de una cotización sin verificar sesión (uso interno)
+pub(crate) async fn fetch_piezas_cotizacion(cotizacion_id: i32) -> Result<Vec<PiezaCotizacion>, String> {
     let pool = get_db_pool_safe()?;
     let piezas = sqlx::query_as::<_, PiezaCotizacion>(
         "SELECT pc.pieza_id, pc.cotizacion_id, COALESCE(pc.cantidad, 1) as cantidad, \
+                pc.precio_unitario, pc.descuento_pct, pc.es_exenta, \
                 p.pieza_nombre, p.pieza_marca, p.pieza_desc, p.pieza_precio \
          FROM PIEZAS_COTIZACION pc \
          LEFT JOIN PIEZA p ON pc.pieza_id = p.pieza_id \
@@ -624,5 +829,437 @@ pub async fn get_piezas_cotizacion(cotizacion_id: i32) -> Result<Vec<PiezaCotiza
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
-    Ok(piezas)
+    Ok(piezas.into_iter().map(con_total_linea).collect())
+}
+
+/// Agregar una pieza a la cotización o cambiar la cantidad, descuento o
+/// exención de una línea existente. Las líneas existentes conservan su precio.
+#[tauri::command]
+pub async fn upsert_pieza_cotizacion(session_token: String, cotizacion_id: i32, pieza: PiezaCotizacionRequest) -> Result<Cotizacion, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCotizaciones).await?;
+    let pool = get_db_pool_safe()?;
+    let politica = obtener_politica_diferencia_total().await?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    verificar_cotizacion_editable(&mut tx, cotizacion_id).await?;
+    let actual = fetch_linea_cotizacion(&mut tx, cotizacion_id, pieza.pieza_id).await?;
+
+    let linea = match actual {
+        Some(ref actual) => LineaCotizacion {
+            pieza_id: pieza.pieza_id,
+            cantidad: pieza.cantidad,
+            precio_unitario: actual.precio_unitario,
+            descuento_pct: pieza.descuento_pct.unwrap_or(actual.descuento_pct),
+            es_exenta: pieza.es_exenta.unwrap_or(actual.es_exenta),
+        },
+        None => pieza.linea(0),
+    };
+    validar_lineas(std::slice::from_ref(&linea))?;
+
+    // Sin cambios no se abre una revisión nueva
+    if actual.as_ref() == Some(&linea) {
+        drop(tx);
+        return get_cotizacion_by_id(session_token, cotizacion_id).await?
+            .ok_or_else(|| "Cotización no encontrada".to_string());
+    }
+
+    let nueva_revision = abrir_nueva_revision(&mut tx, cotizacion_id, usuario.usuario_id).await?;
+
+    if actual.is_some() {
+        sqlx::query(
+            "UPDATE PIEZAS_COTIZACION SET cantidad = ?, descuento_pct = ?, es_exenta = ?
+             WHERE cotizacion_id = ? AND pieza_id = ?"
+        )
+        .bind(linea.cantidad)
+        .bind(linea.descuento_pct)
+        .bind(linea.es_exenta)
+        .bind(cotizacion_id)
+        .bind(linea.pieza_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    } else {
+        agregar_pieza_cotizacion(&mut tx, cotizacion_id, &pieza).await?;
+    }
+
+    let (totales, _) = recalcular_totales_cotizacion(&mut tx, cotizacion_id, None, politica).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    registrar_nueva_revision(usuario.usuario_id, cotizacion_id, nueva_revision).await;
+
+    // Registrar la acción en el log de auditoría: pieza|cantidad antes y después
+    let (accion, prev_data) = match actual {
+        Some(ref actual) => ("UPDATE_PIEZA_COTIZACION", Some(format!("{}|{}", actual.pieza_id, actual.cantidad))),
+        None => ("ADD_PIEZA_COTIZACION", None),
+    };
+    let _ = log_action(
+        accion,
+        Some(usuario.usuario_id),
+        "COTIZACION",
+        Some(cotizacion_id),
+        prev_data.as_deref(),
+        Some(&format!("{}|{}|total {}", linea.pieza_id, linea.cantidad, totales.costo_total))
+    ).await;
+
+    get_cotizacion_by_id(session_token, cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())
+}
+
+/// Quitar una pieza de la cotización
+#[tauri::command]
+pub async fn remove_pieza_cotizacion(session_token: String, cotizacion_id: i32, pieza_id: i32) -> Result<Cotizacion, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCotizaciones).await?;
+    let pool = get_db_pool_safe()?;
+    let politica = obtener_politica_diferencia_total().await?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    verificar_cotizacion_editable(&mut tx, cotizacion_id).await?;
+    let actual = fetch_linea_cotizacion(&mut tx, cotizacion_id, pieza_id).await?
+        .ok_or_else(|| format!("La pieza {} no está en la cotización", pieza_id))?;
+
+    let nueva_revision = abrir_nueva_revision(&mut tx, cotizacion_id, usuario.usuario_id).await?;
+
+    sqlx::query("DELETE FROM PIEZAS_COTIZACION WHERE cotizacion_id = ? AND pieza_id = ?")
+        .bind(cotizacion_id)
+        .bind(pieza_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let (totales, _) = recalcular_totales_cotizacion(&mut tx, cotizacion_id, None, politica).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    registrar_nueva_revision(usuario.usuario_id, cotizacion_id, nueva_revision).await;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "REMOVE_PIEZA_COTIZACION",
+        Some(usuario.usuario_id),
+        "COTIZACION",
+        Some(cotizacion_id),
+        Some(&format!("{}|{}", actual.pieza_id, actual.cantidad)),
+        Some(&format!("{}|0|total {}", actual.pieza_id, totales.costo_total))
+    ).await;
+
+    get_cotizacion_by_id(session_token, cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())
+}
+
+/// Bloquea la cotización para la edición de líneas; una cotización aprobada no se modifica
+async fn verificar_cotizacion_editable(tx: &mut Transaction<'_, MySql>, cotizacion_id: i32) -> Result<(), String> {
+    let is_aprobada = sqlx::query_scalar::<_, Option<bool>>(
+        "SELECT is_aprobada FROM COTIZACION WHERE cotizacion_id = ? FOR UPDATE"
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    if is_aprobada.unwrap_or(false) {
+        return Err("No se puede modificar una cotización aprobada".to_string());
+    }
+    Ok(())
+}
+
+async fn fetch_linea_cotizacion(tx: &mut Transaction<'_, MySql>, cotizacion_id: i32, pieza_id: i32) -> Result<Option<LineaCotizacion>, String> {
+    #[derive(Debug, sqlx::FromRow)]
+    struct LineaGuardada {
+        pieza_id: i32,
+        cantidad: Option<i32>,
+        precio_unitario: Option<i32>,
+        descuento_pct: i32,
+        es_exenta: bool,
+    }
+
+    let linea = sqlx::query_as::<_, LineaGuardada>(
+        "SELECT pieza_id, cantidad, precio_unitario, descuento_pct, es_exenta
+         FROM PIEZAS_COTIZACION WHERE cotizacion_id = ? AND pieza_id = ? FOR UPDATE"
+    )
+    .bind(cotizacion_id)
+    .bind(pieza_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(linea.map(|l| LineaCotizacion {
+        pieza_id: l.pieza_id,
+        cantidad: l.cantidad.unwrap_or(1),
+        precio_unitario: l.precio_unitario.unwrap_or(0),
+        descuento_pct: l.descuento_pct,
+        es_exenta: l.es_exenta,
+    }))
+}
+
+/// Agrega una línea de piezas guardando el precio vigente de la pieza, de modo
+/// que cambios posteriores en el catálogo no alteren la cotización
+pub(crate) async fn agregar_pieza_cotizacion(tx: &mut Transaction<'_, MySql>, cotizacion_id: i32, pieza: &PiezaCotizacionRequest) -> Result<i32, String> {
+    let precio = sqlx::query_scalar::<_, Option<i32>>("SELECT pieza_precio FROM PIEZA WHERE pieza_id = ?")
+        .bind(pieza.pieza_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Pieza {} no encontrada", pieza.pieza_id))?
+        .ok_or_else(|| format!("La pieza {} no tiene un precio definido", pieza.pieza_id))?;
+
+    let linea = pieza.linea(precio);
+    validar_lineas(std::slice::from_ref(&linea))?;
+
+    sqlx::query(
+        "INSERT INTO PIEZAS_COTIZACION (pieza_id, cotizacion_id, cantidad, precio_unitario, descuento_pct, es_exenta)
+         VALUES (?, ?, ?, ?, ?, ?)"
+    )
+    .bind(linea.pieza_id)
+    .bind(cotizacion_id)
+    .bind(linea.cantidad)
+    .bind(linea.precio_unitario)
+    .bind(linea.descuento_pct)
+    .bind(linea.es_exenta)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error adding part: {}", e))?;
+
+    Ok(precio)
+}
+
+/// Recalcula y guarda los totales a partir de los costos de la cotización y de
+/// los precios registrados en sus líneas. Devuelve además el total informado
+/// cuando no coincide con el calculado y la política permite guardarlo marcado.
+pub(crate) async fn recalcular_totales_cotizacion(
+    tx: &mut Transaction<'_, MySql>,
+    cotizacion_id: i32,
+    total_informado: Option<i32>,
+    politica: PoliticaDiferenciaTotal,
+) -> Result<(TotalesCotizacion, Option<i32>), String> {
+    #[derive(Debug, sqlx::FromRow)]
+    struct CostosCotizacion {
+        costo_revision: Option<i32>,
+        costo_reparacion: Option<i32>,
+        descuento_global_pct: i32,
+        iva_porcentaje: i32,
+    }
+
+    #[derive(Debug, sqlx::FromRow)]
+    struct LineaGuardada {
+        pieza_id: i32,
+        cantidad: Option<i32>,
+        precio_unitario: Option<i32>,
+        descuento_pct: i32,
+        es_exenta: bool,
+    }
+
+    let costos = sqlx::query_as::<_, CostosCotizacion>(
+        "SELECT costo_revision, costo_reparacion, descuento_global_pct, iva_porcentaje
+         FROM COTIZACION WHERE cotizacion_id = ? FOR UPDATE"
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    let lineas: Vec<LineaCotizacion> = sqlx::query_as::<_, LineaGuardada>(
+        "SELECT pieza_id, cantidad, precio_unitario, descuento_pct, es_exenta
+         FROM PIEZAS_COTIZACION WHERE cotizacion_id = ?"
+    )
+    .bind(cotizacion_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .into_iter()
+    .map(|l| LineaCotizacion {
+        pieza_id: l.pieza_id,
+        cantidad: l.cantidad.unwrap_or(1),
+        precio_unitario: l.precio_unitario.unwrap_or(0),
+        descuento_pct: l.descuento_pct,
+        es_exenta: l.es_exenta,
+    })
+    .collect();
+
+    let condiciones = CondicionesCotizacion {
+        costo_revision: costos.costo_revision,
+        costo_reparacion: costos.costo_reparacion,
+        descuento_global_pct: costos.descuento_global_pct,
+        iva_porcentaje: costos.iva_porcentaje,
+    };
+    let totales = calcular_totales(&condiciones, &lineas)?;
+    let diferencia = verificar_total_informado(total_informado, totales.costo_total, politica)?;
+
+    sqlx::query(
+        "UPDATE COTIZACION SET subtotal_piezas = ?, monto_descuento = ?, monto_neto = ?, monto_exento = ?,
+                monto_iva = ?, costo_total = ?, costo_total_informado = ?, total_con_diferencia = ?
+         WHERE cotizacion_id = ?"
+    )
+    .bind(totales.subtotal_piezas)
+    .bind(totales.monto_descuento)
+    .bind(totales.monto_neto)
+    .bind(totales.monto_exento)
+    .bind(totales.monto_iva)
+    .bind(totales.costo_total)
+    .bind(diferencia)
+    .bind(diferencia.is_some())
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok((totales, diferencia))
+}
+
+/// Deja constancia en auditoría de un total enviado que no coincidía con el calculado
+async fn registrar_diferencia_total(usuario_id: i32, cotizacion_id: i32, total_informado: Option<i32>, totales: &TotalesCotizacion) {
+    if let Some(informado) = total_informado {
+        let _ = log_action(
+            "COTIZACION_TOTAL_DIFERENCIA",
+            Some(usuario_id),
+            "COTIZACION",
+            Some(cotizacion_id),
+            Some(&format!("Total enviado: {}", informado)),
+            Some(&format!("Total calculado: {}", totales.costo_total))
+        ).await;
+    }
+}
+
+/// Calcula el total neto de la línea con el mismo redondeo que los totales
+fn con_total_linea(mut pieza: PiezaCotizacion) -> PiezaCotizacion {
+    let linea = LineaCotizacion {
+        pieza_id: pieza.pieza_id,
+        cantidad: pieza.cantidad.unwrap_or(1),
+        precio_unitario: pieza.precio_unitario.unwrap_or(0),
+        descuento_pct: pieza.descuento_pct.unwrap_or(0),
+        es_exenta: pieza.es_exenta.unwrap_or(false),
+    };
+    pieza.total_linea = Some(linea.total());
+    pieza
+}
+
+/// Obtener una cotización con su desglose de montos
+#[tauri::command]
+pub async fn get_cotizacion_detallada_by_id(session_token: String, cotizacion_id: i32) -> Result<Option<CotizacionDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    fetch_cotizacion_detallada_by_id(cotizacion_id).await
+}
+
+/// Cotización detallada sin verificar sesión (uso interno)
+pub(crate) async fn fetch_cotizacion_detallada_by_id(cotizacion_id: i32) -> Result<Option<CotizacionDetallada>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, CotizacionDetallada>(
+        "SELECT c.cotizacion_id, c.cotizacion_codigo, c.revision, c.costo_revision, c.costo_reparacion,
+                c.subtotal_piezas, c.descuento_global_pct, c.iva_porcentaje,
+                c.monto_descuento, c.monto_neto, c.monto_exento, c.monto_iva, c.costo_total,
+                c.costo_total_informado, c.total_con_diferencia,
+                c.is_aprobada, c.is_borrador, c.informe, c.valido_hasta, c.vencida_at,
+                c.created_by, c.created_at,
+                u.usuario_nombre as created_by_nombre
+         FROM COTIZACION c
+         LEFT JOIN USUARIO u ON c.created_by = u.usuario_id
+         WHERE c.cotizacion_id = ?"
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Enviar la cotización por email al cliente de la orden de trabajo asociada,
+/// opcionalmente con su PDF y los adjuntos de la orden visibles para el cliente
+#[tauri::command]
+pub async fn send_cotizacion_to_client(
+    session_token: String,
+    cotizacion_id: i32,
+    adjuntar_pdf: Option<bool>,
+    adjuntar_archivos: Option<bool>,
+) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EnviarNotificaciones).await?;
+    use crate::email::EmailService;
+    use crate::adjuntos::{resumen_archivos_correo, EntidadAdjunto};
+    use crate::commands::adjuntos::{fetch_archivos_visibles_cliente, preparar_archivos_correo_auditado};
+    use crate::commands::documentos_pdf::pdf_cotizacion;
+    use crate::commands::parametros::obtener_limite_adjuntos_correo;
+    use crate::commands::ordenes_trabajo::{estado_actual, fetch_orden_trabajo_by_id};
+    use crate::commands::aprobacion_cotizacion::crear_enlaces_respuesta;
+    use crate::estado_orden::EstadoOrden;
+
+    let pool = get_db_pool_safe()?;
+
+    let cotizacion = fetch_cotizacion_detallada_by_id(cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    // Obtener la orden y el cliente a través del equipo
+    let destino = sqlx::query_as::<_, (i32, String, Option<String>)>(
+        "SELECT ot.orden_id, c.cliente_nombre, c.cliente_correo
+         FROM ORDEN_TRABAJO ot
+         INNER JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+         INNER JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         WHERE ot.cotizacion_id = ?
+         LIMIT 1"
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "No se encontró orden de trabajo asociada a la cotización".to_string())?;
+
+    let cliente_email = destino.2
+        .ok_or_else(|| "El cliente no tiene un correo electrónico registrado".to_string())?;
+
+    let orden_trabajo = fetch_orden_trabajo_by_id(destino.0).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    let piezas = fetch_piezas_cotizacion(cotizacion_id).await?;
+
+    let mut archivos = Vec::new();
+    if adjuntar_pdf.unwrap_or(false) {
+        archivos.push(pdf_cotizacion(&cotizacion).await?.archivo_correo());
+    }
+    if adjuntar_archivos.unwrap_or(false) {
+        archivos.extend(fetch_archivos_visibles_cliente(&[(EntidadAdjunto::OrdenTrabajo, orden_trabajo.orden_id)]).await?);
+    }
+    let limite_adjuntos = obtener_limite_adjuntos_correo().await?;
+    let archivos = preparar_archivos_correo_auditado(archivos, limite_adjuntos, usuario.usuario_id, "COTIZACION", cotizacion_id).await?;
+
+    let email_service = EmailService::new()
+        .map_err(|e| format!("Error inicializando servicio de email: {}", e))?;
+
+    // Los enlaces para responder solo se incluyen mientras la orden espera la decisión del cliente
+    let espera_respuesta = matches!(
+        estado_actual(&orden_trabajo),
+        Ok(EstadoOrden::CotizacionEnviada | EstadoOrden::AprobacionPendiente)
+    );
+    let enlaces = if espera_respuesta && !cotizacion.is_aprobada.unwrap_or(false) {
+        Some(crear_enlaces_respuesta(cotizacion_id, orden_trabajo.orden_id, &cliente_email, usuario.usuario_id).await?)
+    } else {
+        None
+    };
+
+    email_service.send_cotizacion_email(
+        &cliente_email,
+        &destino.1,
+        &cotizacion,
+        &orden_trabajo,
+        &piezas,
+        enlaces.as_ref(),
+        &archivos,
+    ).await
+    .map_err(|e| format!("Error enviando email: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "SEND_COTIZACION",
+        Some(usuario.usuario_id),
+        "COTIZACION",
+        Some(cotizacion_id),
+        None,
+        Some(&format!("Cotización {} enviada a {} ({})",
+            cotizacion.cotizacion_codigo.as_deref().unwrap_or("N/A"),
+            cliente_email,
+            resumen_archivos_correo(&archivos, limite_adjuntos)
+        ))
+    ).await;
+
+    Ok(true)
 }
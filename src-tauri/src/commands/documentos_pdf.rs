@@ -0,0 +1,521 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::adjuntos::ArchivoCorreo;
+use crate::commands::cotizacion::{fetch_cotizacion_detallada_by_id, fetch_piezas_cotizacion, CotizacionDetallada};
+use crate::commands::informe::{get_informe_by_id, Informe};
+use crate::commands::parametros::{obtener_empresa, obtener_politica_abandono};
+use crate::documentos_pdf::{
+    formatear_monto, generar_pdf, validar_ruta_pdf, Alineacion, Bloque, Columna, Documento,
+};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Local, Utc};
+
+/// PDF generado en memoria, con el nombre sugerido para guardarlo
+pub(crate) struct PdfGenerado {
+    pub nombre_archivo: String,
+    pub contenido: Vec<u8>,
+}
+
+impl PdfGenerado {
+    pub fn archivo_correo(self) -> ArchivoCorreo {
+        ArchivoCorreo {
+            nombre_archivo: self.nombre_archivo,
+            mime_type: "application/pdf".to_string(),
+            contenido: self.contenido,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchivoPdf {
+    pub nombre_archivo: String,
+    pub contenido_base64: String,
+    pub tamano_bytes: i64,
+}
+
+/// Orden de trabajo con su equipo y cliente, tal como se imprimen
+#[derive(Debug, FromRow)]
+struct DatosOrden {
+    orden_codigo: Option<String>,
+    orden_desc: Option<String>,
+    pre_informe: Option<String>,
+    prioridad: Option<String>,
+    has_garantia: Option<bool>,
+    created_at: Option<DateTime<Utc>>,
+    fecha_compromiso: Option<DateTime<Utc>>,
+    creador_nombre: Option<String>,
+    numero_serie: Option<String>,
+    equipo_marca: Option<String>,
+    equipo_modelo: Option<String>,
+    equipo_tipo: Option<String>,
+    cliente_nombre: Option<String>,
+    cliente_rut: Option<String>,
+    cliente_telefono: Option<String>,
+    cliente_correo: Option<String>,
+    cliente_direccion: Option<String>,
+}
+
+const SELECT_DATOS_ORDEN: &str =
+    "SELECT ot.orden_codigo, ot.orden_desc, ot.pre_informe, ot.prioridad, ot.has_garantia,
+            ot.created_at, ot.fecha_compromiso, u.usuario_nombre as creador_nombre,
+            e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
+            c.cliente_nombre, c.cliente_rut, c.cliente_telefono, c.cliente_correo, c.cliente_direccion
+     FROM ORDEN_TRABAJO ot
+     LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+     LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+     LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id";
+
+/// Pieza usada en un informe, con el precio de venta vigente cuando se usó
+#[derive(Debug, FromRow)]
+struct LineaInformePdf {
+    pieza_nombre: Option<String>,
+    pieza_marca: Option<String>,
+    cantidad: i32,
+    numeros_serie: Option<String>,
+    precio_unitario: Option<i32>,
+}
+
+/// Obtener el PDF de un informe, codificado en base64
+#[tauri::command]
+pub async fn get_informe_pdf(session_token: String, informe_id: i32) -> Result<ArchivoPdf, String> {
+    let usuario = require_permission(&session_token, Permiso::VerInformes).await?;
+    let informe = get_informe_by_id(session_token.clone(), informe_id).await?
+        .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    let pdf = pdf_informe(&informe).await?;
+    registrar_exportacion(usuario.usuario_id, "INFORME", informe_id, &pdf, None).await;
+    Ok(archivo_pdf(pdf))
+}
+
+/// Guardar el PDF de un informe en la ruta elegida
+#[tauri::command]
+pub async fn guardar_informe_pdf(session_token: String, informe_id: i32, ruta: String) -> Result<String, String> {
+    let usuario = require_permission(&session_token, Permiso::VerInformes).await?;
+    let informe = get_informe_by_id(session_token.clone(), informe_id).await?
+        .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    let pdf = pdf_informe(&informe).await?;
+    let ruta = guardar_pdf(&pdf, &ruta).await?;
+    registrar_exportacion(usuario.usuario_id, "INFORME", informe_id, &pdf, Some(&ruta)).await;
+    Ok(ruta)
+}
+
+/// Obtener el PDF de una cotización, codificado en base64
+#[tauri::command]
+pub async fn get_cotizacion_pdf(session_token: String, cotizacion_id: i32) -> Result<ArchivoPdf, String> {
+    let usuario = require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    let cotizacion = fetch_cotizacion_detallada_by_id(cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    let pdf = pdf_cotizacion(&cotizacion).await?;
+    registrar_exportacion(usuario.usuario_id, "COTIZACION", cotizacion_id, &pdf, None).await;
+    Ok(archivo_pdf(pdf))
+}
+
+/// Guardar el PDF de una cotización en la ruta elegida
+#[tauri::command]
+pub async fn guardar_cotizacion_pdf(session_token: String, cotizacion_id: i32, ruta: String) -> Result<String, String> {
+    let usuario = require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    let cotizacion = fetch_cotizacion_detallada_by_id(cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    let pdf = pdf_cotizacion(&cotizacion).await?;
+    let ruta = guardar_pdf(&pdf, &ruta).await?;
+    registrar_exportacion(usuario.usuario_id, "COTIZACION", cotizacion_id, &pdf, Some(&ruta)).await;
+    Ok(ruta)
+}
+
+/// Obtener el comprobante de recepción de una orden, codificado en base64
+#[tauri::command]
+pub async fn get_comprobante_recepcion_pdf(session_token: String, orden_id: i32) -> Result<ArchivoPdf, String> {
+    let usuario = require_permission(&session_token, Permiso::VerOrdenes).await?;
+
+    let pdf = pdf_comprobante_recepcion(orden_id).await?;
+    registrar_exportacion(usuario.usuario_id, "ORDEN_TRABAJO", orden_id, &pdf, None).await;
+    Ok(archivo_pdf(pdf))
+}
+
+/// Guardar el comprobante de recepción de una orden en la ruta elegida
+#[tauri::command]
+pub async fn guardar_comprobante_recepcion_pdf(session_token: String, orden_id: i32, ruta: String) -> Result<String, String> {
+    let usuario = require_permission(&session_token, Permiso::VerOrdenes).await?;
+
+    let pdf = pdf_comprobante_recepcion(orden_id).await?;
+    let ruta = guardar_pdf(&pdf, &ruta).await?;
+    registrar_exportacion(usuario.usuario_id, "ORDEN_TRABAJO", orden_id, &pdf, Some(&ruta)).await;
+    Ok(ruta)
+}
+
+/// Informe técnico con las piezas usadas valorizadas al precio de ese momento.
+/// Si está finalizado, el pie de cada página lleva su huella SHA-256.
+pub(crate) async fn pdf_informe(informe: &Informe) -> Result<PdfGenerado, String> {
+    let pool = get_db_pool_safe()?;
+
+    let orden = fetch_datos_orden_informe(informe.informe_id).await?;
+    let lineas = sqlx::query_as::<_, LineaInformePdf>(
+        "SELECT p.pieza_nombre, p.pieza_marca, COALESCE(pi.cantidad, 1) as cantidad,
+                (SELECT GROUP_CONCAT(u.numero_serie ORDER BY u.numero_serie SEPARATOR ', ')
+                 FROM UNIDAD_PIEZA u
+                 INNER JOIN INFORME_CADENA c ON c.ancestro_id = u.informe_id
+                 WHERE c.informe_id = pi.informe_id AND u.pieza_id = pi.pieza_id) as numeros_serie,
+                (SELECT pp.precio FROM PRECIO_PIEZA pp
+                 WHERE pp.pieza_id = pi.pieza_id AND pp.tipo = 'venta'
+                   AND pp.vigente_desde <= COALESCE(pi.usado_at, i.created_at)
+                 ORDER BY pp.vigente_desde DESC LIMIT 1) as precio_unitario
+         FROM PIEZAS_INFORME pi
+         INNER JOIN INFORME i ON pi.informe_id = i.informe_id
+         LEFT JOIN PIEZA p ON pi.pieza_id = p.pieza_id
+         WHERE pi.informe_id = ?
+         ORDER BY p.pieza_nombre"
+    )
+    .bind(informe.informe_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut datos = Vec::new();
+    if let Some(orden) = &orden {
+        campo(&mut datos, "Orden de trabajo", orden.orden_codigo.clone());
+        campo(&mut datos, "Cliente", orden.cliente_nombre.clone());
+        campo(&mut datos, "RUT", orden.cliente_rut.clone());
+        campo(&mut datos, "Equipo", descripcion_equipo(orden));
+        campo(&mut datos, "N° de serie", orden.numero_serie.clone());
+    }
+    campo(&mut datos, "Técnico responsable", informe.tecnico_responsable.clone());
+    if let Some(original_id) = informe.enmienda_de {
+        let original = sqlx::query_scalar::<_, Option<String>>("SELECT informe_codigo FROM INFORME WHERE informe_id = ?")
+            .bind(original_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .flatten();
+        campo(&mut datos, "Enmienda de", original);
+        campo(&mut datos, "Motivo", informe.motivo_enmienda.clone());
+    }
+
+    let mut bloques = vec![Bloque::Datos { titulo: "Datos generales".to_string(), campos: datos }];
+    for (titulo, texto) in [
+        ("Diagnóstico", &informe.diagnostico),
+        ("Acciones realizadas", &informe.informe_acciones),
+        ("Solución aplicada", &informe.solucion_aplicada),
+        ("Recomendaciones", &informe.recomendaciones),
+        ("Observaciones", &informe.informe_obs),
+    ] {
+        if let Some(texto) = texto.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            bloques.push(Bloque::Texto { titulo: titulo.to_string(), texto: texto.to_string() });
+        }
+    }
+
+    if !lineas.is_empty() {
+        let mut total = 0i64;
+        let filas = lineas.iter()
+            .map(|linea| {
+                let subtotal = linea.precio_unitario.map(|p| p as i64 * linea.cantidad as i64);
+                total += subtotal.unwrap_or(0);
+                vec![
+                    nombre_pieza(linea.pieza_nombre.as_deref(), linea.pieza_marca.as_deref()),
+                    linea.numeros_serie.clone().unwrap_or_default(),
+                    linea.cantidad.to_string(),
+                    linea.precio_unitario.map_or("-".to_string(), |p| formatear_monto(p as i64)),
+                    subtotal.map_or("-".to_string(), formatear_monto),
+                ]
+            })
+            .collect();
+
+        bloques.push(Bloque::Tabla {
+            titulo: Some("Piezas utilizadas".to_string()),
+            columnas: vec![
+                Columna::new("Pieza", 0.36, Alineacion::Izquierda),
+                Columna::new("Series", 0.26, Alineacion::Izquierda),
+                Columna::new("Cant.", 0.08, Alineacion::Derecha),
+                Columna::new("P. unitario", 0.15, Alineacion::Derecha),
+                Columna::new("Total", 0.15, Alineacion::Derecha),
+            ],
+            filas,
+        });
+        bloques.push(Bloque::Totales(vec![("Total piezas".to_string(), formatear_monto(total))]));
+    }
+    bloques.push(Bloque::Firmas(vec!["Técnico responsable".to_string(), "Recibí conforme".to_string()]));
+
+    let pie = match (informe.finalizado_at, &informe.contenido_sha256) {
+        (Some(finalizado_at), Some(sha256)) => Some(format!(
+            "Informe finalizado el {}. Huella SHA-256 del contenido: {}", fecha_hora(finalizado_at), sha256
+        )),
+        _ => None,
+    };
+    let titulo = if informe.is_borrador.unwrap_or(false) {
+        "Informe técnico (borrador)"
+    } else if informe.enmienda_de.is_some() {
+        "Enmienda de informe técnico"
+    } else {
+        "Informe técnico"
+    };
+
+    let documento = Documento {
+        titulo: titulo.to_string(),
+        codigo: informe.informe_codigo.clone(),
+        fecha: informe.created_at.map(fecha),
+        bloques,
+        pie,
+    };
+    renderizar(&documento, informe.informe_codigo.as_deref(), "informe", informe.informe_id).await
+}
+
+/// Cotización con el mismo desglose que el correo: cargos fijos, piezas y totales
+pub(crate) async fn pdf_cotizacion(cotizacion: &CotizacionDetallada) -> Result<PdfGenerado, String> {
+    let orden = fetch_datos_orden("WHERE ot.cotizacion_id = ?", cotizacion.cotizacion_id).await?;
+    let piezas = fetch_piezas_cotizacion(cotizacion.cotizacion_id).await?;
+
+    let mut bloques = Vec::new();
+    if let Some(orden) = &orden {
+        let mut datos = Vec::new();
+        campo(&mut datos, "Cliente", orden.cliente_nombre.clone());
+        campo(&mut datos, "RUT", orden.cliente_rut.clone());
+        campo(&mut datos, "Orden de trabajo", orden.orden_codigo.clone());
+        campo(&mut datos, "Equipo", descripcion_equipo(orden));
+        campo(&mut datos, "N° de serie", orden.numero_serie.clone());
+        bloques.push(Bloque::Datos { titulo: "Datos del cliente".to_string(), campos: datos });
+    }
+    if !cotizacion.informe.trim().is_empty() {
+        bloques.push(Bloque::Texto { titulo: "Detalle".to_string(), texto: cotizacion.informe.trim().to_string() });
+    }
+
+    let mut filas = Vec::new();
+    for (concepto, monto) in [
+        ("Revisión", cotizacion.costo_revision),
+        ("Reparación", cotizacion.costo_reparacion),
+    ] {
+        if let Some(monto) = monto.filter(|m| *m > 0) {
+            let monto = formatear_monto(monto as i64);
+            filas.push(vec![concepto.to_string(), "1".to_string(), monto.clone(), "-".to_string(), monto]);
+        }
+    }
+    for pieza in &piezas {
+        let descuento = pieza.descuento_pct.unwrap_or(0);
+        let mut nombre = nombre_pieza(pieza.pieza_nombre.as_deref(), pieza.pieza_marca.as_deref());
+        if pieza.es_exenta.unwrap_or(false) {
+            nombre.push_str(" (exento)");
+        }
+        filas.push(vec![
+            nombre,
+            pieza.cantidad.unwrap_or(1).to_string(),
+            formatear_monto(pieza.precio_unitario.unwrap_or(0) as i64),
+            if descuento > 0 { format!("{}%", descuento) } else { "-".to_string() },
+            formatear_monto(pieza.total_linea.unwrap_or(0)),
+        ]);
+    }
+    bloques.push(Bloque::Tabla {
+        titulo: None,
+        columnas: vec![
+            Columna::new("Concepto", 0.46, Alineacion::Izquierda),
+            Columna::new("Cant.", 0.09, Alineacion::Derecha),
+            Columna::new("P. unitario", 0.17, Alineacion::Derecha),
+            Columna::new("Dcto.", 0.10, Alineacion::Derecha),
+            Columna::new("Total", 0.18, Alineacion::Derecha),
+        ],
+        filas,
+    });
+
+    let monto = |m: Option<i32>| formatear_monto(m.unwrap_or(0) as i64);
+    let mut totales = Vec::new();
+    if cotizacion.monto_descuento.unwrap_or(0) > 0 {
+        let global = cotizacion.descuento_global_pct.unwrap_or(0);
+        let concepto = if global > 0 { format!("Descuentos (incluye {}% global)", global) } else { "Descuentos".to_string() };
+        totales.push((concepto, format!("-{}", monto(cotizacion.monto_descuento))));
+    }
+    totales.push(("Neto".to_string(), monto(cotizacion.monto_neto)));
+    totales.push((format!("IVA ({}%)", cotizacion.iva_porcentaje.unwrap_or(0)), monto(cotizacion.monto_iva)));
+    if cotizacion.monto_exento.unwrap_or(0) > 0 {
+        totales.push(("Exento".to_string(), monto(cotizacion.monto_exento)));
+    }
+    totales.push(("Total".to_string(), monto(cotizacion.costo_total)));
+    bloques.push(Bloque::Totales(totales));
+
+    if let Some(valido_hasta) = cotizacion.valido_hasta {
+        bloques.push(Bloque::Nota(format!(
+            "Esta cotización es válida hasta el {}.",
+            valido_hasta.format("%d/%m/%Y")
+        )));
+    }
+
+    let codigo = match (&cotizacion.cotizacion_codigo, cotizacion.revision) {
+        (Some(codigo), Some(revision)) if revision > 1 => Some(format!("{} (revisión {})", codigo, revision)),
+        (codigo, _) => codigo.clone(),
+    };
+    let documento = Documento {
+        titulo: if cotizacion.is_borrador.unwrap_or(false) { "Cotización (borrador)" } else { "Cotización" }.to_string(),
+        codigo,
+        fecha: cotizacion.created_at.map(fecha),
+        bloques,
+        pie: None,
+    };
+    renderizar(&documento, cotizacion.cotizacion_codigo.as_deref(), "cotizacion", cotizacion.cotizacion_id).await
+}
+
+/// Comprobante que firma el cliente al dejar el equipo: quién lo entrega, qué
+/// equipo es y en qué estado llega
+pub(crate) async fn pdf_comprobante_recepcion(orden_id: i32) -> Result<PdfGenerado, String> {
+    let orden = fetch_datos_orden("WHERE ot.orden_id = ?", orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    let politica = obtener_politica_abandono().await?;
+
+    let mut cliente = Vec::new();
+    campo(&mut cliente, "Nombre", orden.cliente_nombre.clone());
+    campo(&mut cliente, "RUT", orden.cliente_rut.clone());
+    campo(&mut cliente, "Teléfono", orden.cliente_telefono.clone());
+    campo(&mut cliente, "Correo", orden.cliente_correo.clone());
+    campo(&mut cliente, "Dirección", orden.cliente_direccion.clone());
+
+    let mut equipo = Vec::new();
+    campo(&mut equipo, "Tipo", orden.equipo_tipo.clone());
+    campo(&mut equipo, "Marca", orden.equipo_marca.clone());
+    campo(&mut equipo, "Modelo", orden.equipo_modelo.clone());
+    campo(&mut equipo, "N° de serie", orden.numero_serie.clone());
+
+    let mut ingreso = Vec::new();
+    campo(&mut ingreso, "Fecha de ingreso", orden.created_at.map(fecha_hora));
+    campo(&mut ingreso, "Recibido por", orden.creador_nombre.clone());
+    campo(&mut ingreso, "Prioridad", orden.prioridad.clone());
+    campo(&mut ingreso, "Garantía", orden.has_garantia.map(|g| if g { "Sí" } else { "No" }.to_string()));
+    campo(&mut ingreso, "Fecha comprometida", orden.fecha_compromiso.map(fecha));
+
+    let mut bloques = vec![
+        Bloque::Datos { titulo: "Cliente".to_string(), campos: cliente },
+        Bloque::Datos { titulo: "Equipo".to_string(), campos: equipo },
+        Bloque::Datos { titulo: "Ingreso".to_string(), campos: ingreso },
+    ];
+    for (titulo, texto) in [
+        ("Motivo del ingreso", &orden.orden_desc),
+        ("Estado del equipo al ingresar", &orden.pre_informe),
+    ] {
+        if let Some(texto) = texto.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            bloques.push(Bloque::Texto { titulo: titulo.to_string(), texto: texto.to_string() });
+        }
+    }
+    bloques.push(Bloque::Nota(format!(
+        "El cliente declara que entrega el equipo en las condiciones descritas. Para retirarlo debe presentar \
+         este comprobante. Los equipos que no se retiren dentro de {} días desde el aviso de término se \
+         considerarán abandonados.",
+        politica.dias_abandono
+    )));
+    bloques.push(Bloque::Firmas(vec!["Firma del cliente".to_string(), "Recibido por".to_string()]));
+
+    let documento = Documento {
+        titulo: "Comprobante de recepción".to_string(),
+        codigo: orden.orden_codigo.clone(),
+        fecha: orden.created_at.map(fecha),
+        bloques,
+        pie: None,
+    };
+    let nombre = orden.orden_codigo.as_deref().map(|codigo| format!("recepcion-{}", codigo));
+    renderizar(&documento, nombre.as_deref(), "recepcion", orden_id).await
+}
+
+async fn renderizar(documento: &Documento, codigo: Option<&str>, prefijo: &str, id: i32) -> Result<PdfGenerado, String> {
+    let empresa = obtener_empresa().await?;
+    let contenido = generar_pdf(&empresa, documento)?;
+    let nombre_archivo = match codigo {
+        Some(codigo) => format!("{}.pdf", codigo),
+        None => format!("{}-{}.pdf", prefijo, id),
+    };
+    Ok(PdfGenerado { nombre_archivo, contenido })
+}
+
+async fn fetch_datos_orden(filtro: &str, id: i32) -> Result<Option<DatosOrden>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, DatosOrden>(&format!("{} {} LIMIT 1", SELECT_DATOS_ORDEN, filtro))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// La orden apunta a la última enmienda del informe, así que para un informe
+/// enmendado se sigue la cadena hasta dar con ella
+async fn fetch_datos_orden_informe(informe_id: i32) -> Result<Option<DatosOrden>, String> {
+    let pool = get_db_pool_safe()?;
+    let mut actual = informe_id;
+
+    loop {
+        if let Some(orden) = fetch_datos_orden("WHERE ot.informe_id = ?", actual).await? {
+            return Ok(Some(orden));
+        }
+        let enmienda = sqlx::query_scalar::<_, i32>("SELECT informe_id FROM INFORME WHERE enmienda_de = ?")
+            .bind(actual)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        match enmienda {
+            Some(enmienda_id) => actual = enmienda_id,
+            None => return Ok(None),
+        }
+    }
+}
+
+async fn guardar_pdf(pdf: &PdfGenerado, ruta: &str) -> Result<String, String> {
+    let ruta = validar_ruta_pdf(ruta)?;
+    tokio::fs::write(&ruta, &pdf.contenido)
+        .await
+        .map_err(|e| format!("No se pudo guardar el PDF en {}: {}", ruta.display(), e))?;
+    Ok(ruta.display().to_string())
+}
+
+async fn registrar_exportacion(usuario_id: i32, entidad: &str, entidad_id: i32, pdf: &PdfGenerado, ruta: Option<&str>) {
+    let detalle = match ruta {
+        Some(ruta) => format!("{} ({} bytes) guardado en {}", pdf.nombre_archivo, pdf.contenido.len(), ruta),
+        None => format!("{} ({} bytes)", pdf.nombre_archivo, pdf.contenido.len()),
+    };
+    let _ = log_action(
+        "EXPORT_PDF",
+        Some(usuario_id),
+        entidad,
+        Some(entidad_id),
+        None,
+        Some(&detalle)
+    ).await;
+}
+
+fn archivo_pdf(pdf: PdfGenerado) -> ArchivoPdf {
+    ArchivoPdf {
+        tamano_bytes: pdf.contenido.len() as i64,
+        contenido_base64: general_purpose::STANDARD.encode(&pdf.contenido),
+        nombre_archivo: pdf.nombre_archivo,
+    }
+}
+
+/// Agrega el campo solo si tiene valor, para no imprimir filas vacías
+fn campo(campos: &mut Vec<(String, String)>, etiqueta: &str, valor: Option<String>) {
+    if let Some(valor) = valor.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) {
+        campos.push((etiqueta.to_string(), valor));
+    }
+}
+
+fn descripcion_equipo(orden: &DatosOrden) -> Option<String> {
+    let partes: Vec<&str> = [&orden.equipo_tipo, &orden.equipo_marca, &orden.equipo_modelo]
+        .into_iter()
+        .flatten()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    Some(partes.join(" ")).filter(|d| !d.is_empty())
+}
+
+fn nombre_pieza(nombre: Option<&str>, marca: Option<&str>) -> String {
+    match (nombre, marca.filter(|m| !m.trim().is_empty())) {
+        (Some(nombre), Some(marca)) => format!("{} ({})", nombre, marca),
+        (nombre, _) => nombre.unwrap_or("N/A").to_string(),
+    }
+}
+
+// Los documentos impresos muestran la hora local del taller
+fn fecha(fecha: DateTime<Utc>) -> String {
+    fecha.with_timezone(&Local).format("%d/%m/%Y").to_string()
+}
+
+fn fecha_hora(fecha: DateTime<Utc>) -> String {
+    fecha.with_timezone(&Local).format("%d/%m/%Y %H:%M").to_string()
+}
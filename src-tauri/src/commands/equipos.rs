@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
 use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
 use chrono::{DateTime, Utc};
+use crate::adjuntos::EntidadAdjunto;
+use crate::commands::adjuntos::eliminar_adjuntos_entidad;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Equipo {
@@ -27,7 +30,6 @@ pub struct CreateEquipoRequest {
     pub equipo_precio: Option<i32>,
     pub equipo_ubicacion: Option<String>,
     pub cliente_id: i32,
-    pub created_by: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,7 +45,8 @@ pub struct UpdateEquipoRequest {
 
 /// Obtener todos los equipos
 #[tauri::command]
-pub async fn get_equipos() -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos(session_token: String) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -59,7 +62,8 @@ pub async fn get_equipos() -> Result<Vec<Equipo>, String> {
 
 /// Obtener un equipo por ID
 #[tauri::command]
-pub async fn get_equipo_by_id(equipo_id: i32) -> Result<Option<Equipo>, String> {
+pub async fn get_equipo_by_id(session_token: String, equipo_id: i32) -> Result<Option<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipo = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -76,7 +80,8 @@ pub async fn get_equipo_by_id(equipo_id: i32) -> Result<Option<Equipo>, String>
 
 /// Obtener un equipo por número de serie
 #[tauri::command]
-pub async fn get_equipo_by_numero_serie(numero_serie: String) -> Result<Option<Equipo>, String> {
+pub async fn get_equipo_by_numero_serie(session_token: String, numero_serie: String) -> Result<Option<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipo = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -93,7 +98,8 @@ pub async fn get_equipo_by_numero_serie(numero_serie: String) -> Result<Option<E
 
 /// Obtener equipos por cliente
 #[tauri::command]
-pub async fn get_equipos_by_cliente(cliente_id: i32) -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos_by_cliente(session_token: String, cliente_id: i32) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -111,7 +117,8 @@ pub async fn get_equipos_by_cliente(cliente_id: i32) -> Result<Vec<Equipo>, Stri
 
 /// Obtener equipos por tipo
 #[tauri::command]
-pub async fn get_equipos_by_tipo(equipo_tipo: String) -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos_by_tipo(session_token: String, equipo_tipo: String) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -129,7 +136,8 @@ pub async fn get_equipos_by_tipo(equipo_tipo: String) -> Result<Vec<Equipo>, Str
 
 /// Obtener equipos por usuario que los creó
 #[tauri::command]
-pub async fn get_equipos_by_created_by(created_by: i32) -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos_by_created_by(session_token: String, created_by: i32) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -147,7 +155,8 @@ pub async fn get_equipos_by_created_by(created_by: i32) -> Result<Vec<Equipo>, S
 
 /// Buscar equipos por término de búsqueda
 #[tauri::command]
-pub async fn search_equipos(search_term: String) -> Result<Vec<Equipo>, String> {
+pub async fn search_equipos(session_token: String, search_term: String) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let search_pattern = format!("%{}%", search_term);
     
@@ -173,11 +182,12 @@ pub async fn search_equipos(search_term: String) -> Result<Vec<Equipo>, String>
 
 /// Crear un nuevo equipo
 #[tauri::command]
-pub async fn create_equipo(request: CreateEquipoRequest) -> Result<Equipo, String> {
+pub async fn create_equipo(session_token: String, request: CreateEquipoRequest) -> Result<Equipo, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarEquipos).await?;
     let pool = get_db_pool_safe()?;
     
     // Verificar que el número de serie no existe ya
-    if let Some(_) = get_equipo_by_numero_serie(request.numero_serie.clone()).await? {
+    if let Some(_) = get_equipo_by_numero_serie(session_token.clone(), request.numero_serie.clone()).await? {
         return Err("Ya existe un equipo con este número de serie".to_string());
     }
     
@@ -205,7 +215,7 @@ pub async fn create_equipo(request: CreateEquipoRequest) -> Result<Equipo, Strin
     .bind(&request.equipo_precio)
     .bind(&request.equipo_ubicacion)
     .bind(&request.cliente_id)
-    .bind(&request.created_by)
+    .bind(usuario.usuario_id)
     .execute(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
@@ -215,7 +225,7 @@ pub async fn create_equipo(request: CreateEquipoRequest) -> Result<Equipo, Strin
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_EQUIPO",
-        Some(request.created_by),
+        Some(usuario.usuario_id),
         "EQUIPO",
         Some(equipo_id),
         None,
@@ -227,22 +237,23 @@ pub async fn create_equipo(request: CreateEquipoRequest) -> Result<Equipo, Strin
     ).await;
     
     // Obtener el equipo recién creado
-    get_equipo_by_id(equipo_id)
+    get_equipo_by_id(session_token, equipo_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created equipo".to_string())
 }
 
 /// Actualizar un equipo existente
 #[tauri::command]
-pub async fn update_equipo(equipo_id: i32, request: UpdateEquipoRequest, updated_by: i32) -> Result<Option<Equipo>, String> {
+pub async fn update_equipo(session_token: String, equipo_id: i32, request: UpdateEquipoRequest) -> Result<Option<Equipo>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarEquipos).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el equipo actual para logging
-    let current_equipo = get_equipo_by_id(equipo_id).await?;
+    let current_equipo = get_equipo_by_id(session_token.clone(), equipo_id).await?;
     
     // Verificar que el número de serie no está en uso por otro equipo (si se está actualizando)
     if let Some(ref new_numero_serie) = request.numero_serie {
-        if let Some(existing_equipo) = get_equipo_by_numero_serie(new_numero_serie.clone()).await? {
+        if let Some(existing_equipo) = get_equipo_by_numero_serie(session_token.clone(), new_numero_serie.clone()).await? {
             if existing_equipo.equipo_id != equipo_id {
                 return Err("Ya existe otro equipo con este número de serie".to_string());
             }
@@ -314,7 +325,7 @@ pub async fn update_equipo(equipo_id: i32, request: UpdateEquipoRequest, updated
         
         let _ = log_action(
             "UPDATE_EQUIPO",
-            Some(updated_by),
+            Some(usuario.usuario_id),
             "EQUIPO",
             Some(equipo_id),
             Some(&prev_data),
@@ -322,16 +333,17 @@ pub async fn update_equipo(equipo_id: i32, request: UpdateEquipoRequest, updated
         ).await;
     }
     
-    get_equipo_by_id(equipo_id).await
+    get_equipo_by_id(session_token, equipo_id).await
 }
 
 /// Eliminar un equipo
 #[tauri::command]
-pub async fn delete_equipo(equipo_id: i32, deleted_by: i32) -> Result<bool, String> {
+pub async fn delete_equipo(session_token: String, equipo_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarEquipos).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el equipo antes de eliminarlo para logging
-    let equipo_to_delete = get_equipo_by_id(equipo_id).await?;
+    let equipo_to_delete = get_equipo_by_id(session_token.clone(), equipo_id).await?;
     
     // Verificar si el equipo tiene órdenes de trabajo asociadas
     let has_dependencies = sqlx::query_scalar::<_, i64>(
@@ -354,12 +366,15 @@ pub async fn delete_equipo(equipo_id: i32, deleted_by: i32) -> Result<bool, Stri
     
     let was_deleted = result.rows_affected() > 0;
     
-    // Registrar la acción en el log de auditoría
     if was_deleted {
+        // Eliminar también los adjuntos del equipo
+        let _ = eliminar_adjuntos_entidad(EntidadAdjunto::Equipo, equipo_id).await;
+        
+        // Registrar la acción en el log de auditoría
         if let Some(ref equipo) = equipo_to_delete {
             let _ = log_action(
                 "DELETE_EQUIPO",
-                Some(deleted_by),
+                Some(usuario.usuario_id),
                 "EQUIPO",
                 Some(equipo_id),
                 Some(&format!("Equipo eliminado: {} {} (S/N: {})", 
@@ -377,7 +392,8 @@ pub async fn delete_equipo(equipo_id: i32, deleted_by: i32) -> Result<bool, Stri
 
 /// Contar total de equipos
 #[tauri::command]
-pub async fn count_equipos() -> Result<i64, String> {
+pub async fn count_equipos(session_token: String) -> Result<i64, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM EQUIPO")
         .fetch_one(pool)
@@ -389,7 +405,8 @@ pub async fn count_equipos() -> Result<i64, String> {
 
 /// Obtener equipos con paginación
 #[tauri::command]
-pub async fn get_equipos_with_pagination(offset: i64, limit: i64) -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos_with_pagination(session_token: String, offset: i64, limit: i64) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, Equipo>(
         "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at 
@@ -408,7 +425,8 @@ pub async fn get_equipos_with_pagination(offset: i64, limit: i64) -> Result<Vec<
 
 /// Obtener estadísticas de equipos por tipo
 #[tauri::command]
-pub async fn get_equipos_stats_by_tipo() -> Result<Vec<(String, i64)>, String> {
+pub async fn get_equipos_stats_by_tipo(session_token: String) -> Result<Vec<(String, i64)>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let stats = sqlx::query_as::<_, (String, i64)>(
         "SELECT equipo_tipo, COUNT(*) as count 
@@ -425,7 +443,8 @@ pub async fn get_equipos_stats_by_tipo() -> Result<Vec<(String, i64)>, String> {
 
 /// Obtener equipos por rango de precios
 #[tauri::command]
-pub async fn get_equipos_by_price_range(min_price: Option<i32>, max_price: Option<i32>) -> Result<Vec<Equipo>, String> {
+pub async fn get_equipos_by_price_range(session_token: String, min_price: Option<i32>, max_price: Option<i32>) -> Result<Vec<Equipo>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     
     let mut query = "SELECT equipo_id, numero_serie, equipo_marca, equipo_modelo, equipo_tipo, equipo_precio, equipo_ubicacion, cliente_id, created_by, created_at FROM EQUIPO WHERE 1=1".to_string();
@@ -476,7 +495,8 @@ pub struct EquipoWithCliente {
 
 /// Obtener equipos con información del cliente
 #[tauri::command]
-pub async fn get_equipos_with_cliente() -> Result<Vec<EquipoWithCliente>, String> {
+pub async fn get_equipos_with_cliente(session_token: String) -> Result<Vec<EquipoWithCliente>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let equipos = sqlx::query_as::<_, EquipoWithCliente>(
         "SELECT e.equipo_id, e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo, 
@@ -495,11 +515,12 @@ pub async fn get_equipos_with_cliente() -> Result<Vec<EquipoWithCliente>, String
 
 /// Cambiar el cliente de un equipo
 #[tauri::command]
-pub async fn transfer_equipo_to_cliente(equipo_id: i32, new_cliente_id: i32, updated_by: i32) -> Result<bool, String> {
+pub async fn transfer_equipo_to_cliente(session_token: String, equipo_id: i32, new_cliente_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarEquipos).await?;
     let pool = get_db_pool_safe()?;
     
     // Verificar que el equipo existe
-    let equipo = get_equipo_by_id(equipo_id).await?;
+    let equipo = get_equipo_by_id(session_token.clone(), equipo_id).await?;
     if equipo.is_none() {
         return Err("Equipo no encontrado".to_string());
     }
@@ -533,7 +554,7 @@ pub async fn transfer_equipo_to_cliente(equipo_id: i32, new_cliente_id: i32, upd
         let equipo_info = equipo.unwrap();
         let _ = log_action(
             "TRANSFER_EQUIPO",
-            Some(updated_by),
+            Some(usuario.usuario_id),
             "EQUIPO",
             Some(equipo_id),
             Some(&format!("Cliente anterior: {}", equipo_info.cliente_id.map_or("N/A".to_string(), |id| id.to_string()))),
@@ -546,7 +567,8 @@ pub async fn transfer_equipo_to_cliente(equipo_id: i32, new_cliente_id: i32, upd
 
 /// Obtener marcas únicas de equipos
 #[tauri::command]
-pub async fn get_equipos_marcas() -> Result<Vec<String>, String> {
+pub async fn get_equipos_marcas(session_token: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let marcas = sqlx::query_scalar::<_, String>(
         "SELECT DISTINCT equipo_marca 
@@ -563,7 +585,8 @@ pub async fn get_equipos_marcas() -> Result<Vec<String>, String> {
 
 /// Obtener modelos únicos por marca
 #[tauri::command]
-pub async fn get_equipos_modelos_by_marca(marca: String) -> Result<Vec<String>, String> {
+pub async fn get_equipos_modelos_by_marca(session_token: String, marca: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let modelos = sqlx::query_scalar::<_, String>(
         "SELECT DISTINCT equipo_modelo 
@@ -581,7 +604,8 @@ pub async fn get_equipos_modelos_by_marca(marca: String) -> Result<Vec<String>,
 
 /// Obtener ubicaciones únicas de equipos
 #[tauri::command]
-pub async fn get_equipos_ubicaciones() -> Result<Vec<String>, String> {
+pub async fn get_equipos_ubicaciones(session_token: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerEquipos).await?;
     let pool = get_db_pool_safe()?;
     let ubicaciones = sqlx::query_scalar::<_, String>(
         "SELECT DISTINCT equipo_ubicacion 
@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::codigos::{siguiente_codigo, TipoDocumento};
+use crate::commands::informe::{get_informe_by_id, Informe};
+use crate::commands::inventario::consumir_reservas_informe;
+use crate::commands::series::{piezas_sin_series, series_instaladas_informe};
+use crate::finalizacion_informe::{validar_motivo_enmienda, ContenidoInforme, LineaContenido, VERSION_CONTENIDO};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificacionInforme {
+    pub informe_id: i32,
+    pub informe_codigo: Option<String>,
+    pub finalizado_at: Option<DateTime<Utc>>,
+    // Digest guardado al finalizar y el calculado con el contenido actual
+    pub contenido_sha256: Option<String>,
+    pub sha256_actual: String,
+    pub coincide: bool,
+}
+
+/// Resultado de sellar un informe
+pub(crate) struct SelloInforme {
+    pub contenido_sha256: String,
+    // false si el informe ya estaba finalizado
+    pub nuevo: bool,
+    pub reservas_consumidas: u64,
+}
+
+/// Finalizar un informe: queda congelado junto con sus piezas y se guarda el
+/// SHA-256 de su contenido. Después solo se corrige con una enmienda.
+#[tauri::command]
+pub async fn finalizar_informe(session_token: String, informe_id: i32) -> Result<Informe, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
+
+    let sello = sellar_informe(informe_id, usuario.usuario_id).await?;
+    if !sello.nuevo {
+        return Err("El informe ya está finalizado".to_string());
+    }
+    registrar_sello(usuario.usuario_id, informe_id, &sello).await;
+
+    get_informe_by_id(session_token, informe_id).await?
+        .ok_or_else(|| "Informe no encontrado".to_string())
+}
+
+/// Recalcular el digest de un informe finalizado y compararlo con el guardado
+#[tauri::command]
+pub async fn verificar_informe(session_token: String, informe_id: i32) -> Result<VerificacionInforme, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let (informe_codigo, finalizado_at, contenido_sha256) = sqlx::query_as::<_, (Option<String>, Option<DateTime<Utc>>, Option<String>)>(
+        "SELECT informe_codigo, finalizado_at, contenido_sha256 FROM INFORME WHERE informe_id = ?"
+    )
+    .bind(informe_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    if finalizado_at.is_none() {
+        return Err("El informe no está finalizado".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    let sha256_actual = fetch_contenido(&mut tx, informe_id).await?.digest();
+    drop(tx);
+
+    Ok(VerificacionInforme {
+        informe_id,
+        informe_codigo,
+        finalizado_at,
+        coincide: contenido_sha256.as_deref() == Some(sha256_actual.as_str()),
+        contenido_sha256,
+        sha256_actual,
+    })
+}
+
+/// Crear una enmienda de un informe finalizado. La enmienda parte como
+/// borrador con el contenido y las piezas del original, y pasa a ser el
+/// informe de la orden de trabajo. Las unidades con serie siguen ligadas al
+/// original, que así conserva su digest; la enmienda las hereda.
+#[tauri::command]
+pub async fn create_enmienda_informe(session_token: String, informe_id: i32, motivo: String) -> Result<Informe, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let motivo = validar_motivo_enmienda(&motivo)?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let (codigo_original, finalizado_at) = sqlx::query_as::<_, (Option<String>, Option<DateTime<Utc>>)>(
+        "SELECT informe_codigo, finalizado_at FROM INFORME WHERE informe_id = ? FOR UPDATE"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Informe no encontrado".to_string())?;
+    let codigo_original = codigo_original.unwrap_or_else(|| informe_id.to_string());
+
+    if finalizado_at.is_none() {
+        return Err(format!("El informe {} sigue en edición; corríjalo directamente", codigo_original));
+    }
+    let enmienda_existente = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT informe_codigo FROM INFORME WHERE enmienda_de = ?"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    if let Some(codigo) = enmienda_existente {
+        return Err(format!(
+            "El informe {} ya fue enmendado por {}; registre la corrección sobre ese informe",
+            codigo_original, codigo.unwrap_or_default()
+        ));
+    }
+
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::Informe).await?;
+    let result = sqlx::query(
+        "INSERT INTO INFORME (informe_codigo, informe_acciones, informe_obs, is_borrador, created_by,
+                             diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable,
+                             enmienda_de, motivo_enmienda)
+         SELECT ?, informe_acciones, informe_obs, TRUE, ?,
+                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable,
+                informe_id, ?
+         FROM INFORME WHERE informe_id = ?"
+    )
+    .bind(&codigo)
+    .bind(usuario.usuario_id)
+    .bind(&motivo)
+    .bind(informe_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let enmienda_id = result.last_insert_id() as i32;
+
+    // Las piezas ya se descontaron con el original: la enmienda hereda ese
+    // consumo y solo sus cambios posteriores generan movimientos de stock
+    sqlx::query(
+        "INSERT INTO PIEZAS_INFORME (pieza_id, informe_id, cantidad, usado_at)
+         SELECT pieza_id, ?, cantidad, usado_at FROM PIEZAS_INFORME WHERE informe_id = ?"
+    )
+    .bind(enmienda_id)
+    .bind(informe_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error adding part: {}", e))?;
+
+    // La orden de trabajo sigue al informe vigente
+    let orden_id = sqlx::query_scalar::<_, i32>("SELECT orden_id FROM ORDEN_TRABAJO WHERE informe_id = ? FOR UPDATE")
+        .bind(informe_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if let Some(orden_id) = orden_id {
+        sqlx::query("UPDATE ORDEN_TRABAJO SET informe_id = ? WHERE orden_id = ?")
+            .bind(enmienda_id)
+            .bind(orden_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría, en el original y en la enmienda
+    let _ = log_action(
+        "ENMENDAR_INFORME",
+        Some(usuario.usuario_id),
+        "INFORME",
+        Some(informe_id),
+        None,
+        Some(&format!("Enmendado por {}: {}", codigo, motivo))
+    ).await;
+    let _ = log_action(
+        "CREATE_INFORME",
+        Some(usuario.usuario_id),
+        "INFORME",
+        Some(enmienda_id),
+        None,
+        Some(&format!("Informe {} creado como enmienda de {}", codigo, codigo_original))
+    ).await;
+    if let Some(orden_id) = orden_id {
+        let _ = log_action(
+            "ASSIGN_INFORME",
+            Some(usuario.usuario_id),
+            "ORDEN_TRABAJO",
+            Some(orden_id),
+            Some(&format!("Informe {} asignado", informe_id)),
+            Some(&format!("Informe {} asignado", enmienda_id))
+        ).await;
+    }
+
+    get_informe_by_id(session_token, enmienda_id).await?
+        .ok_or_else(|| "Failed to retrieve created informe".to_string())
+}
+
+/// Finaliza el informe si aún no lo está: calcula el digest, lo marca como no
+/// borrador y da por consumidas las reservas de su orden
+pub(crate) async fn sellar_informe(informe_id: i32, usuario_id: i32) -> Result<SelloInforme, String> {
+    let pool = get_db_pool_safe()?;
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let (finalizado_at, contenido_sha256) = sqlx::query_as::<_, (Option<DateTime<Utc>>, Option<String>)>(
+        "SELECT finalizado_at, contenido_sha256 FROM INFORME WHERE informe_id = ? FOR UPDATE"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    if finalizado_at.is_some() {
+        return Ok(SelloInforme {
+            contenido_sha256: contenido_sha256.unwrap_or_default(),
+            nuevo: false,
+            reservas_consumidas: 0,
+        });
+    }
+
+    let faltantes = piezas_sin_series(&mut tx, informe_id).await?;
+    if !faltantes.is_empty() {
+        return Err(format!("Faltan números de serie para finalizar el informe: {}", faltantes.join(", ")));
+    }
+
+    let contenido_sha256 = fetch_contenido(&mut tx, informe_id).await?.digest();
+
+    sqlx::query(
+        "UPDATE INFORME SET is_borrador = FALSE, finalizado_at = CURRENT_TIMESTAMP, finalizado_by = ?,
+         contenido_sha256 = ?
+         WHERE informe_id = ?"
+    )
+    .bind(usuario_id)
+    .bind(&contenido_sha256)
+    .bind(informe_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let reservas_consumidas = consumir_reservas_informe(&mut tx, informe_id).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(SelloInforme { contenido_sha256, nuevo: true, reservas_consumidas })
+}
+
+/// Digest que tendría el informe si se finalizara ahora, sin finalizarlo
+pub(crate) async fn huella_informe(informe_id: i32) -> Result<String, String> {
+    let pool = get_db_pool_safe()?;
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    let contenido = fetch_contenido(&mut tx, informe_id).await?;
+    tx.rollback().await.map_err(|e| format!("Database error: {}", e))?;
+    Ok(contenido.digest())
+}
+
+/// Auditoría de un informe recién finalizado
+pub(crate) async fn registrar_sello(usuario_id: i32, informe_id: i32, sello: &SelloInforme) {
+    let _ = log_action(
+        "FINALIZAR_INFORME",
+        Some(usuario_id),
+        "INFORME",
+        Some(informe_id),
+        None,
+        Some(&format!("sha256:{}", sello.contenido_sha256))
+    ).await;
+    if sello.reservas_consumidas > 0 {
+        let _ = log_action(
+            "CONSUMIR_RESERVA_STOCK",
+            Some(usuario_id),
+            "INFORME",
+            Some(informe_id),
+            None,
+            Some(&format!("{} piezas", sello.reservas_consumidas))
+        ).await;
+    }
+}
+
+/// Rechaza cambios sobre un informe finalizado. Bloquea la fila del informe
+/// hasta el fin de la transacción para que no se finalice entretanto.
+pub(crate) async fn verificar_informe_editable(tx: &mut Transaction<'_, MySql>, informe_id: i32) -> Result<(), String> {
+    let informe = sqlx::query_as::<_, (Option<String>, Option<DateTime<Utc>>)>(
+        "SELECT informe_codigo, finalizado_at FROM INFORME WHERE informe_id = ? FOR UPDATE"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    match informe {
+        Some((codigo, Some(_))) => Err(format!(
+            "El informe {} está finalizado y no se puede modificar; registre una enmienda",
+            codigo.unwrap_or_else(|| informe_id.to_string())
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn fetch_contenido(tx: &mut Transaction<'_, MySql>, informe_id: i32) -> Result<ContenidoInforme, String> {
+    type Campos = (
+        Option<String>, Option<String>, Option<String>, Option<String>,
+        Option<String>, Option<String>, Option<String>, Option<String>,
+    );
+    let (informe_codigo, enmienda_de, informe_acciones, informe_obs,
+         diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable) = sqlx::query_as::<_, Campos>(
+        "SELECT i.informe_codigo, o.informe_codigo, i.informe_acciones, i.informe_obs,
+                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable
+         FROM INFORME i
+         LEFT JOIN INFORME o ON i.enmienda_de = o.informe_id
+         WHERE i.informe_id = ?"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    let mut series: HashMap<i32, Vec<String>> = HashMap::new();
+    for (pieza_id, numero_serie) in series_instaladas_informe(tx, informe_id).await? {
+        series.entry(pieza_id).or_default().push(numero_serie);
+    }
+
+    let piezas = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT pieza_id, COALESCE(cantidad, 1) FROM PIEZAS_INFORME WHERE informe_id = ?"
+    )
+    .bind(informe_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .into_iter()
+    .map(|(pieza_id, cantidad)| LineaContenido {
+        pieza_id,
+        cantidad,
+        series: series.remove(&pieza_id).unwrap_or_default(),
+    })
+    .collect();
+
+    Ok(ContenidoInforme {
+        version: VERSION_CONTENIDO,
+        informe_codigo,
+        enmienda_de,
+        informe_acciones,
+        informe_obs,
+        diagnostico,
+        recomendaciones,
+        solucion_aplicada,
+        tecnico_responsable,
+        piezas,
+    })
+}
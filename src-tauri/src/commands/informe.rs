@@ -2,8 +2,21 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
 use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
 use chrono::{DateTime, Utc};
-use chrono::Datelike;
+use crate::adjuntos::EntidadAdjunto;
+use crate::commands::adjuntos::eliminar_adjuntos_entidad;
+use crate::codigos::{siguiente_codigo, TipoDocumento};
+use crate::calculo_cotizacion::{comparar_cotizado_usado, DiferenciaPiezaInforme, LineaCotizacion};
+use crate::commands::cotizacion::fetch_piezas_cotizacion;
+use crate::commands::inventario::{
+    consumir_piezas_informe, devolver_piezas_informe, registrar_cambio_pieza_informe,
+};
+use crate::commands::parametros::obtener_politica_stock_negativo;
+use crate::commands::series::{asignar_series_linea, liberar_series_informe, piezas_sin_series};
+use crate::commands::finalizacion_informe::{huella_informe, registrar_sello, sellar_informe, verificar_informe_editable};
+use crate::inventario::PoliticaStockNegativo;
+use sqlx::{MySql, Transaction};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Informe {
@@ -19,6 +32,16 @@ pub struct Informe {
     pub recomendaciones: Option<String>,
     pub solucion_aplicada: Option<String>,
     pub tecnico_responsable: Option<String>,
+    // Finalización: el informe queda congelado y se guarda el SHA-256 de su contenido
+    pub finalizado_at: Option<DateTime<Utc>>,
+    pub contenido_sha256: Option<String>,
+    // Informe original que esta enmienda corrige
+    pub enmienda_de: Option<i32>,
+    pub motivo_enmienda: Option<String>,
+    // Piezas que quedaron con stock negativo al crear el informe
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertencias_stock: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -31,6 +54,12 @@ pub struct PiezaInforme {
     pub pieza_marca: Option<String>,
     pub pieza_desc: Option<String>,
     pub pieza_precio: Option<i32>,
+    // Series instaladas, separadas por coma (solo piezas con control por serie)
+    pub numeros_serie: Option<String>,
+    // Solo en la línea recién modificada, si su stock quedó negativo
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertencia_stock: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -48,6 +77,12 @@ pub struct InformeDetallado {
     pub recomendaciones: Option<String>,
     pub solucion_aplicada: Option<String>,
     pub tecnico_responsable: Option<String>,
+    // Finalización: el informe queda congelado y se guarda el SHA-256 de su contenido
+    pub finalizado_at: Option<DateTime<Utc>>,
+    pub contenido_sha256: Option<String>,
+    // Informe original que esta enmienda corrige
+    pub enmienda_de: Option<i32>,
+    pub motivo_enmienda: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,8 +91,9 @@ pub struct CreateInformeRequest {
     pub informe_acciones: String,
     pub informe_obs: Option<String>,
     pub is_borrador: Option<bool>,
-    pub created_by: i32,
     pub piezas: Option<Vec<PiezaInformeRequest>>,
+    // Orden a la que se asocia el informe, en la misma transacción que el consumo
+    pub orden_id: Option<i32>,
     // Nuevos campos
     pub diagnostico: String,
     pub recomendaciones: Option<String>,
@@ -82,16 +118,30 @@ pub struct UpdateInformeRequest {
 pub struct PiezaInformeRequest {
     pub pieza_id: i32,
     pub cantidad: i32,
+    // Obligatorio si la pieza requiere número de serie, una serie por unidad
+    pub series: Option<Vec<String>>,
+}
+
+impl PiezaInformeRequest {
+    /// Una cantidad cero o negativa no es un consumo: devolvería piezas al stock
+    fn validar_cantidad(&self) -> Result<(), String> {
+        if self.cantidad <= 0 {
+            return Err(format!("La cantidad de la pieza {} debe ser mayor a cero", self.pieza_id));
+        }
+        Ok(())
+    }
 }
 
 /// Obtener todos los informes
 #[tauri::command]
-pub async fn get_informes() -> Result<Vec<Informe>, String> {
+pub async fn get_informes(session_token: String) -> Result<Vec<Informe>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
       let informes = sqlx::query_as::<_, Informe>(
         "SELECT informe_id, informe_codigo, informe_acciones, informe_obs, 
                 is_borrador, created_by, created_at,
-                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable
+                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable,
+                finalizado_at, contenido_sha256, enmienda_de, motivo_enmienda
          FROM INFORME 
          ORDER BY created_at DESC"
     )
@@ -104,13 +154,15 @@ pub async fn get_informes() -> Result<Vec<Informe>, String> {
 
 /// Obtener informes con información detallada
 #[tauri::command]
-pub async fn get_informes_detallados() -> Result<Vec<InformeDetallado>, String> {
+pub async fn get_informes_detallados(session_token: String) -> Result<Vec<InformeDetallado>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
       let informes = sqlx::query_as::<_, InformeDetallado>(
         "SELECT i.informe_id, i.informe_codigo, i.informe_acciones, i.informe_obs,
                 i.is_borrador, i.created_by, i.created_at,
                 u.usuario_nombre as created_by_nombre,
-                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable
+                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable,
+                i.finalizado_at, i.contenido_sha256, i.enmienda_de, i.motivo_enmienda
          FROM INFORME i
          LEFT JOIN USUARIO u ON i.created_by = u.usuario_id
          ORDER BY i.created_at DESC"
@@ -124,13 +176,15 @@ pub async fn get_informes_detallados() -> Result<Vec<InformeDetallado>, String>
 
 /// Obtener un informe por ID
 #[tauri::command]
-pub async fn get_informe_by_id(informe_id: i32) -> Result<Option<Informe>, String> {
+pub async fn get_informe_by_id(session_token: String, informe_id: i32) -> Result<Option<Informe>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     println!("[DEBUG] get_informe_by_id: Recibido informe_id = {}", informe_id);
     let pool = get_db_pool_safe()?;
     let informe = sqlx::query_as::<_, Informe>(
         "SELECT informe_id, informe_codigo, informe_acciones, informe_obs,
                 is_borrador, created_by, created_at,
-                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable
+                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable,
+                finalizado_at, contenido_sha256, enmienda_de, motivo_enmienda
          FROM INFORME 
          WHERE informe_id = ?"
     )
@@ -144,12 +198,14 @@ pub async fn get_informe_by_id(informe_id: i32) -> Result<Option<Informe>, Strin
 
 /// Obtener un informe por código
 #[tauri::command]
-pub async fn get_informe_by_codigo(informe_codigo: String) -> Result<Option<Informe>, String> {
+pub async fn get_informe_by_codigo(session_token: String, informe_codigo: String) -> Result<Option<Informe>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
       let informe = sqlx::query_as::<_, Informe>(
         "SELECT informe_id, informe_codigo, informe_acciones, informe_obs,
                 is_borrador, created_by, created_at,
-                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable
+                diagnostico, recomendaciones, solucion_aplicada, tecnico_responsable,
+                finalizado_at, contenido_sha256, enmienda_de, motivo_enmienda
          FROM INFORME 
          WHERE informe_codigo = ?"
     )
@@ -163,37 +219,23 @@ pub async fn get_informe_by_codigo(informe_codigo: String) -> Result<Option<Info
 
 /// Crear un nuevo informe
 #[tauri::command]
-pub async fn create_informe(request: CreateInformeRequest) -> Result<Informe, String> {
+pub async fn create_informe(session_token: String, request: CreateInformeRequest) -> Result<Informe, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
     let pool = get_db_pool_safe()?;
+    let politica = obtener_politica_stock_negativo().await?;
     
-    // Generar código automático: INF-YYYY-XXX
-    let year = chrono::Utc::now().year();
-    
-    // Buscar el mayor número correlativo existente para el año actual
-    let last_codigo: Option<String> = sqlx::query_scalar(
-        "SELECT informe_codigo FROM INFORME WHERE informe_codigo LIKE ? ORDER BY informe_id DESC LIMIT 1"
-    )
-    .bind(format!("INF-{}-%", year))
-    .fetch_one(pool)
-    .await
-    .ok();
-    
-    let next_number = if let Some(codigo) = last_codigo {
-        // Extraer el número correlativo actual y sumarle 1
-        let parts: Vec<&str> = codigo.split('-').collect();
-        if parts.len() == 3 {
-            parts[2].parse::<u32>().unwrap_or(0) + 1
-        } else {
-            1
-        }
-    } else {
-        1
-    };
+    for pieza in request.piezas.iter().flatten() {
+        pieza.validar_cantidad()?;
+    }
     
-    let codigo = format!("INF-{}-{:03}", year, next_number);
+    // Un informe que nace finalizado se inserta como borrador y se sella al final
+    let finaliza = request.is_borrador == Some(false);
     
     // Iniciar transacción
     let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    // Generar código automático con el correlativo compartido
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::Informe).await?;
       // Crear el informe
     let result = sqlx::query(
         "INSERT INTO INFORME (informe_codigo, informe_acciones, informe_obs, 
@@ -204,8 +246,8 @@ pub async fn create_informe(request: CreateInformeRequest) -> Result<Informe, St
     .bind(&codigo)
     .bind(&request.informe_acciones)
     .bind(&request.informe_obs)
-    .bind(request.is_borrador.unwrap_or(true))
-    .bind(request.created_by)
+    .bind(true)
+    .bind(usuario.usuario_id)
     .bind(&request.diagnostico)
     .bind(&request.recomendaciones)
     .bind(&request.solucion_aplicada)
@@ -216,6 +258,20 @@ pub async fn create_informe(request: CreateInformeRequest) -> Result<Informe, St
     
     let informe_id = result.last_insert_id() as i32;
     
+    // Se asocia antes de consumir stock: así las reservas de la propia orden
+    // no cuentan como de otra orden
+    if let Some(orden_id) = request.orden_id {
+        let asociada = sqlx::query("UPDATE ORDEN_TRABAJO SET informe_id = ? WHERE orden_id = ?")
+            .bind(informe_id)
+            .bind(orden_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if asociada.rows_affected() == 0 {
+            return Err("Orden de trabajo no encontrada".to_string());
+        }
+    }
+    
     // Agregar piezas si se proporcionaron
     if let Some(ref piezas) = request.piezas {
         for pieza in piezas {
@@ -228,6 +284,18 @@ pub async fn create_informe(request: CreateInformeRequest) -> Result<Informe, St
             .execute(&mut *tx)
             .await
             .map_err(|e| format!("Database error adding part: {}", e))?;
+            asignar_series_linea(&mut tx, informe_id, pieza.pieza_id, pieza.cantidad, pieza.series.as_deref()).await?;
+        }
+    }
+    
+    // Descontar del stock las piezas usadas
+    let advertencias = consumir_piezas_informe(&mut tx, informe_id, usuario.usuario_id, politica).await?;
+    
+    // No se crea nada si el informe no se podrá sellar
+    if finaliza {
+        let faltantes = piezas_sin_series(&mut tx, informe_id).await?;
+        if !faltantes.is_empty() {
+            return Err(format!("Faltan números de serie para finalizar el informe: {}", faltantes.join(", ")));
         }
     }
     
@@ -237,41 +305,81 @@ pub async fn create_informe(request: CreateInformeRequest) -> Result<Informe, St
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_INFORME",
-        Some(request.created_by),
+        Some(usuario.usuario_id),
         "INFORME",
         Some(informe_id),
         None,
         Some(&format!("Informe creado: {}", codigo))
     ).await;
+    if let Some(orden_id) = request.orden_id {
+        let _ = log_action(
+            "ASSIGN_INFORME",
+            Some(usuario.usuario_id),
+            "ORDEN_TRABAJO",
+            Some(orden_id),
+            None,
+            Some(&format!("Informe {} asignado", informe_id))
+        ).await;
+    }
+    
+    if finaliza {
+        let sello = sellar_informe(informe_id, usuario.usuario_id).await?;
+        registrar_sello(usuario.usuario_id, informe_id, &sello).await;
+    }
     
     // Obtener el informe recién creado
-    get_informe_by_id(informe_id)
+    let mut informe = get_informe_by_id(session_token, informe_id)
         .await?
-        .ok_or_else(|| "Failed to retrieve created informe".to_string())
+        .ok_or_else(|| "Failed to retrieve created informe".to_string())?;
+    if !advertencias.is_empty() {
+        informe.advertencias_stock = Some(advertencias);
+    }
+    Ok(informe)
 }
 
 /// Actualizar un informe existente
 #[tauri::command]
-pub async fn update_informe(informe_id: i32, request: UpdateInformeRequest, updated_by: i32) -> Result<Option<Informe>, String> {
+pub async fn update_informe(session_token: String, informe_id: i32, request: UpdateInformeRequest) -> Result<Option<Informe>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el informe actual para logging
-    let current_informe = get_informe_by_id(informe_id).await?;
+    let current_informe = get_informe_by_id(session_token.clone(), informe_id).await?;
     
     // Verificar que el código no está en uso por otro informe (si se está actualizando)
     if let Some(ref new_codigo) = request.informe_codigo {
-        if let Some(existing_informe) = get_informe_by_codigo(new_codigo.clone()).await? {
+        if let Some(existing_informe) = get_informe_by_codigo(session_token.clone(), new_codigo.clone()).await? {
             if existing_informe.informe_id != informe_id {
                 return Err("Ya existe otro informe con este código".to_string());
             }
         }
     }
-      let result = sqlx::query(
+    
+    // is_borrador no se escribe directamente: pasar a false sella el informe
+    // y un informe que ya no es borrador no puede volver atrás
+    let ya_finalizado = current_informe.as_ref().and_then(|i| i.is_borrador) == Some(false);
+    if request.is_borrador == Some(true) && ya_finalizado {
+        return Err("Un informe finalizado no puede volver a ser borrador".to_string());
+    }
+    let finaliza = request.is_borrador == Some(false) && !ya_finalizado;
+    
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    verificar_informe_editable(&mut tx, informe_id).await?;
+    
+    // No se finaliza con piezas seriadas sin sus unidades elegidas
+    if finaliza {
+        let faltantes = piezas_sin_series(&mut tx, informe_id).await?;
+        if !faltantes.is_empty() {
+            return Err(format!("Faltan números de serie para finalizar el informe: {}", faltantes.join(", ")));
+        }
+    }
+    
+    let result = sqlx::query(
         "UPDATE INFORME SET 
          informe_codigo = COALESCE(?, informe_codigo),
          informe_acciones = COALESCE(?, informe_acciones),
          informe_obs = COALESCE(?, informe_obs),
-         is_borrador = COALESCE(?, is_borrador),
          diagnostico = COALESCE(?, diagnostico),
          recomendaciones = COALESCE(?, recomendaciones),
          solucion_aplicada = COALESCE(?, solucion_aplicada),
@@ -281,13 +389,12 @@ pub async fn update_informe(informe_id: i32, request: UpdateInformeRequest, upda
     .bind(&request.informe_codigo)
     .bind(&request.informe_acciones)
     .bind(&request.informe_obs)
-    .bind(request.is_borrador)
     .bind(&request.diagnostico)
     .bind(&request.recomendaciones)
     .bind(&request.solucion_aplicada)
     .bind(&request.tecnico_responsable)
     .bind(informe_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
@@ -295,6 +402,14 @@ pub async fn update_informe(informe_id: i32, request: UpdateInformeRequest, upda
         return Ok(None);
     }
     
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    // Finalizar con el mismo sello que finalizar_informe (digest y reservas)
+    if finaliza {
+        let sello = sellar_informe(informe_id, usuario.usuario_id).await?;
+        registrar_sello(usuario.usuario_id, informe_id, &sello).await;
+    }
+    
     // Registrar la acción en el log de auditoría
     if let Some(ref informe) = current_informe {
         let prev_data = format!("{}|{}|{}|{}", 
@@ -314,7 +429,7 @@ pub async fn update_informe(informe_id: i32, request: UpdateInformeRequest, upda
         
         let _ = log_action(
             "UPDATE_INFORME",
-            Some(updated_by),
+            Some(usuario.usuario_id),
             "INFORME",
             Some(informe_id),
             Some(&prev_data),
@@ -322,16 +437,17 @@ pub async fn update_informe(informe_id: i32, request: UpdateInformeRequest, upda
         ).await;
     }
     
-    get_informe_by_id(informe_id).await
+    get_informe_by_id(session_token, informe_id).await
 }
 
 /// Eliminar un informe
 #[tauri::command]
-pub async fn delete_informe(informe_id: i32, deleted_by: i32) -> Result<bool, String> {
+pub async fn delete_informe(session_token: String, informe_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarInformes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el informe antes de eliminarlo para logging
-    let informe_to_delete = get_informe_by_id(informe_id).await?;
+    let informe_to_delete = get_informe_by_id(session_token.clone(), informe_id).await?;
     
     // Verificar si el informe tiene órdenes de trabajo asociadas
     let has_dependencies = sqlx::query_scalar::<_, i64>(
@@ -345,10 +461,23 @@ pub async fn delete_informe(informe_id: i32, deleted_by: i32) -> Result<bool, St
     if has_dependencies > 0 {
         return Err("No se puede eliminar el informe porque tiene órdenes de trabajo asociadas".to_string());
     }
+    // Una enmienda hereda el consumo de stock del original; eliminarla lo devolvería
+    if informe_to_delete.as_ref().is_some_and(|i| i.enmienda_de.is_some()) {
+        return Err("No se puede eliminar una enmienda de informe".to_string());
+    }
     
     // Iniciar transacción
     let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
     
+    verificar_informe_editable(&mut tx, informe_id).await?;
+    
+    // Las piezas del informe vuelven al stock
+    let motivo = format!("Informe {} eliminado",
+        informe_to_delete.as_ref().and_then(|i| i.informe_codigo.as_deref()).unwrap_or("N/A")
+    );
+    devolver_piezas_informe(&mut tx, informe_id, &motivo, usuario.usuario_id).await?;
+    liberar_series_informe(&mut tx, informe_id, None).await?;
+    
     // Eliminar primero las relaciones con piezas
     sqlx::query("DELETE FROM PIEZAS_INFORME WHERE informe_id = ?")
         .bind(informe_id)
@@ -368,12 +497,15 @@ pub async fn delete_informe(informe_id: i32, deleted_by: i32) -> Result<bool, St
     // Confirmar transacción
     tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
     
-    // Registrar la acción en el log de auditoría
     if was_deleted {
+        // Eliminar también los adjuntos del informe
+        let _ = eliminar_adjuntos_entidad(EntidadAdjunto::Informe, informe_id).await;
+        
+        // Registrar la acción en el log de auditoría
         if let Some(ref informe) = informe_to_delete {
             let _ = log_action(
                 "DELETE_INFORME",
-                Some(deleted_by),
+                Some(usuario.usuario_id),
                 "INFORME",
                 Some(informe_id),
                 Some(&format!("Informe eliminado: {}", 
@@ -389,7 +521,8 @@ pub async fn delete_informe(informe_id: i32, deleted_by: i32) -> Result<bool, St
 
 /// Buscar informes por texto
 #[tauri::command]
-pub async fn search_informes(search_term: String) -> Result<Vec<InformeDetallado>, String> {
+pub async fn search_informes(session_token: String, search_term: String) -> Result<Vec<InformeDetallado>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
     
     let search_pattern = format!("%{}%", search_term);
@@ -397,7 +530,8 @@ pub async fn search_informes(search_term: String) -> Result<Vec<InformeDetallado
         "SELECT i.informe_id, i.informe_codigo, i.informe_acciones, i.informe_obs,
                 i.is_borrador, i.created_by, i.created_at,
                 u.usuario_nombre as created_by_nombre,
-                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable
+                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable,
+                i.finalizado_at, i.contenido_sha256, i.enmienda_de, i.motivo_enmienda
          FROM INFORME i
          LEFT JOIN USUARIO u ON i.created_by = u.usuario_id
          WHERE i.informe_codigo LIKE ? 
@@ -413,7 +547,8 @@ pub async fn search_informes(search_term: String) -> Result<Vec<InformeDetallado
 
 /// Contar total de informes
 #[tauri::command]
-pub async fn count_informes() -> Result<i64, String> {
+pub async fn count_informes(session_token: String) -> Result<i64, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
     
     let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM INFORME")
@@ -426,13 +561,15 @@ pub async fn count_informes() -> Result<i64, String> {
 
 /// Obtener informes con paginación
 #[tauri::command]
-pub async fn get_informes_with_pagination(offset: i64, limit: i64) -> Result<Vec<InformeDetallado>, String> {
+pub async fn get_informes_with_pagination(session_token: String, offset: i64, limit: i64) -> Result<Vec<InformeDetallado>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
     let pool = get_db_pool_safe()?;
       let informes = sqlx::query_as::<_, InformeDetallado>(
         "SELECT i.informe_id, i.informe_codigo, i.informe_acciones, i.informe_obs,
                 i.is_borrador, i.created_by, i.created_at,
                 u.usuario_nombre as created_by_nombre,
-                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable
+                i.diagnostico, i.recomendaciones, i.solucion_aplicada, i.tecnico_responsable,
+                i.finalizado_at, i.contenido_sha256, i.enmienda_de, i.motivo_enmienda
          FROM INFORME i
          LEFT JOIN USUARIO u ON i.created_by = u.usuario_id
          ORDER BY i.created_at DESC
@@ -449,37 +586,377 @@ pub async fn get_informes_with_pagination(offset: i64, limit: i64) -> Result<Vec
 
 /// Obtener las piezas asociadas a un informe
 #[tauri::command]
-pub async fn get_piezas_informe(informe_id: i32) -> Result<Vec<PiezaInforme>, String> {
+pub async fn get_piezas_informe(session_token: String, informe_id: i32) -> Result<Vec<PiezaInforme>, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
+    fetch_piezas_informe(informe_id).await
+}
+
+/// Piezas de un informe sin verificar sesión (uso interno)
+pub(crate) async fn fetch_piezas_informe(informe_id: i32) -> Result<Vec<PiezaInforme>, String> {
     let pool = get_db_pool_safe()?;
-    
-    let piezas = sqlx::query_as::<_, PiezaInforme>(
+
+    sqlx::query_as::<_, PiezaInforme>(
         "SELECT pi.pieza_id, pi.informe_id, COALESCE(pi.cantidad, 1) as cantidad, 
-                p.pieza_nombre, p.pieza_marca, p.pieza_desc, p.pieza_precio 
+                p.pieza_nombre, p.pieza_marca, p.pieza_desc, p.pieza_precio,
+                (SELECT GROUP_CONCAT(u.numero_serie ORDER BY u.numero_serie SEPARATOR ', ')
+                 FROM UNIDAD_PIEZA u
+                 INNER JOIN INFORME_CADENA c ON c.ancestro_id = u.informe_id
+                 WHERE c.informe_id = pi.informe_id AND u.pieza_id = pi.pieza_id) as numeros_serie
          FROM PIEZAS_INFORME pi 
          LEFT JOIN PIEZA p ON pi.pieza_id = p.pieza_id 
-         WHERE pi.informe_id = ?"
+         WHERE pi.informe_id = ?
+         ORDER BY pi.pieza_id"
     )
     .bind(informe_id)
     .fetch_all(pool)
     .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Agregar una pieza usada al informe o cambiar su cantidad
+#[tauri::command]
+pub async fn upsert_pieza_informe(session_token: String, informe_id: i32, pieza: PiezaInformeRequest) -> Result<Vec<PiezaInforme>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
+    let pool = get_db_pool_safe()?;
+
+    pieza.validar_cantidad()?;
+    let politica = obtener_politica_stock_negativo().await?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    verificar_informe_editable(&mut tx, informe_id).await?;
+    let anterior = fetch_cantidad_pieza_informe(&mut tx, informe_id, pieza.pieza_id).await?;
+
+    if anterior == Some(pieza.cantidad) && pieza.series.is_none() {
+        drop(tx);
+        return fetch_piezas_informe(informe_id).await;
+    }
+
+    sqlx::query(
+        "INSERT INTO PIEZAS_INFORME (pieza_id, informe_id, cantidad) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE cantidad = VALUES(cantidad)"
+    )
+    .bind(pieza.pieza_id)
+    .bind(informe_id)
+    .bind(pieza.cantidad)
+    .execute(&mut *tx)
+    .await
     .map_err(|e| format!("Database error: {}", e))?;
-    
+
+    asignar_series_linea(&mut tx, informe_id, pieza.pieza_id, pieza.cantidad, pieza.series.as_deref()).await?;
+
+    let advertencia = registrar_cambio_pieza_informe(
+        &mut tx,
+        informe_id,
+        pieza.pieza_id,
+        anterior.unwrap_or(0),
+        pieza.cantidad,
+        usuario.usuario_id,
+        politica,
+    ).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: pieza|cantidad[|series]
+    let _ = log_action(
+        if anterior.is_some() { "UPDATE_PIEZA_INFORME" } else { "ADD_PIEZA_INFORME" },
+        Some(usuario.usuario_id),
+        "INFORME",
+        Some(informe_id),
+        anterior.map(|cantidad| format!("{}|{}", pieza.pieza_id, cantidad)).as_deref(),
+        Some(&match pieza.series {
+            Some(ref series) => format!("{}|{}|{}", pieza.pieza_id, pieza.cantidad, series.join(",")),
+            None => format!("{}|{}", pieza.pieza_id, pieza.cantidad),
+        })
+    ).await;
+
+    let mut piezas = fetch_piezas_informe(informe_id).await?;
+    if let Some(linea) = piezas.iter_mut().find(|p| p.pieza_id == pieza.pieza_id) {
+        linea.advertencia_stock = advertencia;
+    }
     Ok(piezas)
 }
 
-/// Enviar informe por email al cliente
+/// Quitar una pieza del informe
 #[tauri::command]
-pub async fn send_informe_to_client(informe_id: i32, sent_by: i32) -> Result<bool, String> {    use crate::email::EmailService;
+pub async fn remove_pieza_informe(session_token: String, informe_id: i32, pieza_id: i32) -> Result<Vec<PiezaInforme>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    verificar_informe_editable(&mut tx, informe_id).await?;
+    let anterior = fetch_cantidad_pieza_informe(&mut tx, informe_id, pieza_id).await?
+        .ok_or_else(|| format!("La pieza {} no está en el informe", pieza_id))?;
+
+    sqlx::query("DELETE FROM PIEZAS_INFORME WHERE informe_id = ? AND pieza_id = ?")
+        .bind(informe_id)
+        .bind(pieza_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    liberar_series_informe(&mut tx, informe_id, Some(pieza_id)).await?;
+
+    // Una devolución nunca deja el stock negativo, la política no influye
+    registrar_cambio_pieza_informe(
+        &mut tx,
+        informe_id,
+        pieza_id,
+        anterior,
+        0,
+        usuario.usuario_id,
+        PoliticaStockNegativo::Rechazar,
+    ).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "REMOVE_PIEZA_INFORME",
+        Some(usuario.usuario_id),
+        "INFORME",
+        Some(informe_id),
+        Some(&format!("{}|{}", pieza_id, anterior)),
+        Some(&format!("{}|0", pieza_id))
+    ).await;
+
+    fetch_piezas_informe(informe_id).await
+}
+
+async fn fetch_cantidad_pieza_informe(tx: &mut Transaction<'_, MySql>, informe_id: i32, pieza_id: i32) -> Result<Option<i32>, String> {
+    sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT cantidad FROM PIEZAS_INFORME WHERE informe_id = ? AND pieza_id = ? FOR UPDATE"
+    )
+    .bind(informe_id)
+    .bind(pieza_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+    .map(|cantidad| cantidad.map(|c| c.unwrap_or(1)))
+}
+
+/// Cotización aprobada e informe de una orden de trabajo
+#[derive(Debug, FromRow)]
+struct OrdenCotizada {
+    cotizacion_id: Option<i32>,
+    cotizacion_codigo: Option<String>,
+    diagnostico: Option<String>,
+    is_aprobada: Option<bool>,
+    informe_id: Option<i32>,
+    tecnico_nombre: Option<String>,
+}
+
+async fn fetch_orden_cotizada(orden_id: i32) -> Result<OrdenCotizada, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, OrdenCotizada>(
+        "SELECT ot.cotizacion_id, c.cotizacion_codigo, c.informe as diagnostico, c.is_aprobada,
+                ot.informe_id, u.usuario_nombre as tecnico_nombre
+         FROM ORDEN_TRABAJO ot
+         LEFT JOIN COTIZACION c ON ot.cotizacion_id = c.cotizacion_id
+         LEFT JOIN USUARIO u ON ot.tecnico_id = u.usuario_id
+         WHERE ot.orden_id = ?"
+    )
+    .bind(orden_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Orden de trabajo no encontrada".to_string())
+}
+
+/// Crear un informe borrador para la orden con las piezas de su cotización
+/// aprobada. El técnico luego ajusta las piezas a lo que realmente usó y
+/// elige las series de las piezas que las requieren antes de finalizarlo.
+#[tauri::command]
+pub async fn create_informe_desde_cotizacion(session_token: String, orden_id: i32) -> Result<Informe, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarInformes).await?;
+    let pool = get_db_pool_safe()?;
+    let politica = obtener_politica_stock_negativo().await?;
+
+    let orden = fetch_orden_cotizada(orden_id).await?;
+    let cotizacion_id = orden.cotizacion_id
+        .ok_or_else(|| "La orden no tiene una cotización asociada".to_string())?;
+    if !orden.is_aprobada.unwrap_or(false) {
+        return Err("La cotización de la orden no está aprobada".to_string());
+    }
+    if orden.informe_id.is_some() {
+        return Err("La orden ya tiene un informe asociado".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Evita que dos técnicos generen el informe al mismo tiempo
+    let informe_asignado = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT informe_id FROM ORDEN_TRABAJO WHERE orden_id = ? FOR UPDATE"
+    )
+    .bind(orden_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    if informe_asignado.is_some() {
+        return Err("La orden ya tiene un informe asociado".to_string());
+    }
+
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::Informe).await?;
+    let result = sqlx::query(
+        "INSERT INTO INFORME (informe_codigo, informe_acciones, is_borrador, created_by,
+                             diagnostico, tecnico_responsable)
+         VALUES (?, '', TRUE, ?, ?, ?)"
+    )
+    .bind(&codigo)
+    .bind(usuario.usuario_id)
+    .bind(&orden.diagnostico)
+    .bind(orden.tecnico_nombre.as_deref().or(usuario.usuario_nombre.as_deref()))
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let informe_id = result.last_insert_id() as i32;
+
+    sqlx::query(
+        "INSERT INTO PIEZAS_INFORME (pieza_id, informe_id, cantidad)
+         SELECT pieza_id, ?, cantidad FROM PIEZAS_COTIZACION WHERE cotizacion_id = ?"
+    )
+    .bind(informe_id)
+    .bind(cotizacion_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error adding part: {}", e))?;
+
+    // Asociada antes de consumir, las reservas de la orden no cuentan como ajenas
+    sqlx::query("UPDATE ORDEN_TRABAJO SET informe_id = ? WHERE orden_id = ?")
+        .bind(informe_id)
+        .bind(orden_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let advertencias = consumir_piezas_informe(&mut tx, informe_id, usuario.usuario_id, politica).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "CREATE_INFORME",
+        Some(usuario.usuario_id),
+        "INFORME",
+        Some(informe_id),
+        None,
+        Some(&format!("Informe {} creado desde cotización {}",
+            codigo,
+            orden.cotizacion_codigo.as_deref().unwrap_or("N/A")
+        ))
+    ).await;
+    let _ = log_action(
+        "ASSIGN_INFORME",
+        Some(usuario.usuario_id),
+        "ORDEN_TRABAJO",
+        Some(orden_id),
+        None,
+        Some(&format!("Informe {} asignado", informe_id))
+    ).await;
+
+    let mut informe = get_informe_by_id(session_token, informe_id)
+        .await?
+        .ok_or_else(|| "Failed to retrieve created informe".to_string())?;
+    if !advertencias.is_empty() {
+        informe.advertencias_stock = Some(advertencias);
+    }
+    Ok(informe)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiferenciaPiezaDetallada {
+    #[serde(flatten)]
+    pub diferencia: DiferenciaPiezaInforme,
+    pub pieza_nombre: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparacionCotizacionInforme {
+    pub orden_id: i32,
+    pub cotizacion_id: i32,
+    pub informe_id: i32,
+    pub diferencias: Vec<DiferenciaPiezaDetallada>,
+    // Valor de las piezas a precio unitario, sin descuentos ni impuestos
+    pub monto_cotizado: i64,
+    pub monto_usado: i64,
+}
+
+/// Comparar las piezas cotizadas con las usadas en el informe de una orden
+#[tauri::command]
+pub async fn get_diferencias_cotizacion_informe(session_token: String, orden_id: i32) -> Result<ComparacionCotizacionInforme, String> {
+    require_permission(&session_token, Permiso::VerInformes).await?;
+
+    let orden = fetch_orden_cotizada(orden_id).await?;
+    let cotizacion_id = orden.cotizacion_id
+        .ok_or_else(|| "La orden no tiene una cotización asociada".to_string())?;
+    let informe_id = orden.informe_id
+        .ok_or_else(|| "La orden no tiene un informe asociado".to_string())?;
+
+    let cotizadas = fetch_piezas_cotizacion(cotizacion_id).await?;
+    let usadas = fetch_piezas_informe(informe_id).await?;
+
+    let lineas_cotizadas: Vec<LineaCotizacion> = cotizadas.iter().map(|p| LineaCotizacion {
+        pieza_id: p.pieza_id,
+        cantidad: p.cantidad.unwrap_or(1),
+        precio_unitario: p.precio_unitario.unwrap_or(0),
+        descuento_pct: 0,
+        es_exenta: false,
+    }).collect();
+    // Las piezas usadas se valorizan al precio de catálogo
+    let lineas_usadas: Vec<LineaCotizacion> = usadas.iter().map(|p| LineaCotizacion {
+        pieza_id: p.pieza_id,
+        cantidad: p.cantidad.unwrap_or(1),
+        precio_unitario: p.pieza_precio.unwrap_or(0),
+        descuento_pct: 0,
+        es_exenta: false,
+    }).collect();
+
+    let diferencias = comparar_cotizado_usado(&lineas_cotizadas, &lineas_usadas);
+    let monto_cotizado: i64 = lineas_cotizadas.iter().map(LineaCotizacion::bruto).sum();
+    let monto_usado = monto_cotizado + diferencias.iter().map(|d| d.diferencia_monto).sum::<i64>();
+
+    let nombre = |pieza_id: i32| {
+        cotizadas.iter().find(|p| p.pieza_id == pieza_id).and_then(|p| p.pieza_nombre.clone())
+            .or_else(|| usadas.iter().find(|p| p.pieza_id == pieza_id).and_then(|p| p.pieza_nombre.clone()))
+    };
+
+    Ok(ComparacionCotizacionInforme {
+        orden_id,
+        cotizacion_id,
+        informe_id,
+        diferencias: diferencias.into_iter()
+            .map(|diferencia| DiferenciaPiezaDetallada {
+                pieza_nombre: nombre(diferencia.pieza_id),
+                diferencia,
+            })
+            .collect(),
+        monto_cotizado,
+        monto_usado,
+    })
+}
+
+/// Enviar informe por email al cliente, opcionalmente con el PDF del informe
+/// y los adjuntos visibles para el cliente de la orden y del informe
+#[tauri::command]
+pub async fn send_informe_to_client(
+    session_token: String,
+    informe_id: i32,
+    adjuntar_pdf: Option<bool>,
+    adjuntar_archivos: Option<bool>,
+) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EnviarNotificaciones).await?;
+    use crate::email::EmailService;
     use crate::commands::ordenes_trabajo::get_orden_trabajo_by_informe_id;
+    use crate::adjuntos::resumen_archivos_correo;
+    use crate::commands::adjuntos::{fetch_archivos_visibles_cliente, preparar_archivos_correo_auditado};
+    use crate::commands::documentos_pdf::pdf_informe;
+    use crate::commands::parametros::obtener_limite_adjuntos_correo;
     
     let pool = get_db_pool_safe()?;
     
-    // Obtener el informe
-    let informe = get_informe_by_id(informe_id).await?
-        .ok_or_else(|| "Informe no encontrado".to_string())?;
-    
     // Obtener la orden de trabajo asociada al informe
-    let orden_trabajo = get_orden_trabajo_by_informe_id(informe_id).await?
+    let orden_trabajo = get_orden_trabajo_by_informe_id(session_token.clone(), informe_id).await?
         .ok_or_else(|| "No se encontró orden de trabajo asociada al informe".to_string())?;
     
     // Obtener información del cliente desde el equipo
@@ -498,8 +975,51 @@ pub async fn send_informe_to_client(informe_id: i32, sent_by: i32) -> Result<boo
     let cliente_email = cliente_info.2
         .ok_or_else(|| "El cliente no tiene un correo electrónico registrado".to_string())?;
     
+    let adjuntar_pdf = adjuntar_pdf.unwrap_or(false);
+    let mut archivos = Vec::new();
+    if adjuntar_archivos.unwrap_or(false) {
+        archivos.extend(fetch_archivos_visibles_cliente(&[
+            (EntidadAdjunto::OrdenTrabajo, orden_trabajo.orden_id),
+            (EntidadAdjunto::Informe, informe_id),
+        ]).await?);
+    }
+    let limite_adjuntos = obtener_limite_adjuntos_correo().await?;
+    
+    let mut informe = get_informe_by_id(session_token.clone(), informe_id).await?
+        .ok_or_else(|| "Informe no encontrado".to_string())?;
+    
+    // Lo que recibe el cliente queda finalizado y ya no se puede editar. Los
+    // adjuntos se validan antes de sellar para no finalizar un informe que no
+    // se enviará; el PDF de prueba lleva la huella que tendrá el definitivo.
+    // El sello va antes del envío porque el PDF debe llevar la huella, así que
+    // si después falla el correo el informe queda finalizado sin enviar (el
+    // SEND_INFORME no aparece en la auditoría) y basta con reintentar el envío.
+    if informe.finalizado_at.is_none() {
+        let mut prueba = archivos.clone();
+        if adjuntar_pdf {
+            informe.is_borrador = Some(false);
+            informe.finalizado_at = Some(Utc::now());
+            informe.contenido_sha256 = Some(huella_informe(informe_id).await?);
+            prueba.push(pdf_informe(&informe).await?.archivo_correo());
+        }
+        preparar_archivos_correo_auditado(prueba, limite_adjuntos, usuario.usuario_id, "INFORME", informe_id).await?;
+        
+        let sello = sellar_informe(informe_id, usuario.usuario_id).await?;
+        if sello.nuevo {
+            registrar_sello(usuario.usuario_id, informe_id, &sello).await;
+        }
+        // Se obtiene ya sellado, así el PDF lleva la huella del contenido
+        informe = get_informe_by_id(session_token.clone(), informe_id).await?
+            .ok_or_else(|| "Informe no encontrado".to_string())?;
+    }
+    
     // Obtener las piezas del informe
-    let piezas_informe = get_piezas_informe(informe_id).await?;
+    let piezas_informe = get_piezas_informe(session_token.clone(), informe_id).await?;
+    
+    if adjuntar_pdf {
+        archivos.insert(0, pdf_informe(&informe).await?.archivo_correo());
+    }
+    let archivos = preparar_archivos_correo_auditado(archivos, limite_adjuntos, usuario.usuario_id, "INFORME", informe_id).await?;
     
     // Crear el servicio de email
     let email_service = EmailService::new()
@@ -512,19 +1032,21 @@ pub async fn send_informe_to_client(informe_id: i32, sent_by: i32) -> Result<boo
         &informe,
         &orden_trabajo,
         &piezas_informe,
+        &archivos,
     ).await
     .map_err(|e| format!("Error enviando email: {}", e))?;
     
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "SEND_INFORME",
-        Some(sent_by),
+        Some(usuario.usuario_id),
         "INFORME",
         Some(informe_id),
         None,
-        Some(&format!("Informe {} enviado a {}", 
+        Some(&format!("Informe {} enviado a {} ({})", 
             informe.informe_codigo.as_deref().unwrap_or("N/A"),
-            cliente_email
+            cliente_email,
+            resumen_archivos_correo(&archivos, limite_adjuntos)
         ))
     ).await;
     
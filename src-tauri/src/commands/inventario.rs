@@ -0,0 +1,732 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::commands::parametros::{
+    obtener_destinatario_resumen_stock, obtener_dias_consumo, obtener_politica_stock_negativo,
+};
+use crate::email::EmailService;
+use crate::inventario::{
+    aplicar_movimiento, cantidad_sugerida, consumo_diario, dias_de_cobertura, movimiento_por_cambio,
+    validar_niveles, EstadoReserva, PoliticaStockNegativo, TipoMovimiento,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Cantidad de movimientos que se devuelven si no se indica un límite
+const LIMITE_MOVIMIENTOS_POR_DEFECTO: i64 = 100;
+
+/// Unidades reservadas que todavía no salen del stock. A medida que el informe
+/// de la orden registra el consumo, su reserva deja de contar, así la misma
+/// pieza no se descuenta dos veces de lo disponible.
+const SQL_RESERVADO_PENDIENTE: &str =
+    "SELECT CAST(COALESCE(SUM(GREATEST(r.cantidad - COALESCE(pi.cantidad, 0), 0)), 0) AS SIGNED)
+     FROM RESERVA_STOCK r
+     LEFT JOIN ORDEN_TRABAJO ot ON ot.cotizacion_id = r.cotizacion_id
+     LEFT JOIN PIEZAS_INFORME pi ON pi.informe_id = ot.informe_id AND pi.pieza_id = r.pieza_id
+     WHERE r.estado = 'activa'";
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct StockPieza {
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub pieza_marca: Option<String>,
+    pub stock_actual: i32,
+    pub stock_reservado: i64,
+    pub stock_disponible: i64,
+    pub stock_minimo: Option<i32>,
+    pub cantidad_reposicion: Option<i32>,
+    pub ultimo_movimiento_at: Option<DateTime<Utc>>,
+}
+
+/// Pieza cuyo disponible quedó bajo el mínimo, con su ritmo de consumo
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PiezaBajoMinimo {
+    #[serde(flatten)]
+    pub stock: StockPieza,
+    /// Unidades usadas en informes durante el periodo
+    pub consumo_periodo: i64,
+    pub consumo_diario: f64,
+    pub dias_cobertura: Option<f64>,
+    pub cantidad_sugerida: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResumenStockBajo {
+    pub destinatario: Option<String>,
+    pub dias_consumo: i64,
+    pub piezas: Vec<PiezaBajoMinimo>,
+    pub enviado: bool,
+}
+
+/// Reserva activa de una pieza, con la orden que la originó
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ReservaStock {
+    pub reserva_id: i32,
+    pub pieza_id: i32,
+    pub cotizacion_id: Option<i32>,
+    pub cotizacion_codigo: Option<String>,
+    pub orden_id: Option<i32>,
+    pub orden_codigo: Option<String>,
+    pub cantidad: i32,
+    pub estado: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MovimientoStock {
+    pub movimiento_id: i32,
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub tipo: String,
+    pub cantidad: i32,
+    pub stock_resultante: i32,
+    pub informe_id: Option<i32>,
+    pub informe_codigo: Option<String>,
+    pub orden_compra_id: Option<i32>,
+    pub orden_compra_codigo: Option<String>,
+    pub motivo: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_by_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    // Se informa cuando el movimiento dejó el stock negativo
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advertencia: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AjusteStockRequest {
+    pub pieza_id: i32,
+    /// ajuste, recepcion_compra o devolucion; por defecto ajuste
+    pub tipo: Option<TipoMovimiento>,
+    /// Positiva para ingresos, negativa para salidas
+    pub cantidad: i32,
+    pub motivo: String,
+}
+
+/// Datos de un movimiento a registrar dentro de una transacción
+pub(crate) struct NuevoMovimiento<'a> {
+    pub pieza_id: i32,
+    pub tipo: TipoMovimiento,
+    pub cantidad: i32,
+    pub informe_id: Option<i32>,
+    pub orden_compra_id: Option<i32>,
+    pub motivo: Option<&'a str>,
+    pub usuario_id: Option<i32>,
+}
+
+/// Resultado de registrar un movimiento
+pub(crate) struct MovimientoRegistrado {
+    pub movimiento_id: i32,
+    pub stock_anterior: i32,
+    pub stock_resultante: i32,
+    pub advertencia: Option<String>,
+}
+
+/// Obtener el stock actual, reservado y disponible de todas las piezas
+#[tauri::command]
+pub async fn get_stock_piezas(session_token: String) -> Result<Vec<StockPieza>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, StockPieza>(&sql_stock_piezas("ORDER BY s.pieza_nombre ASC"))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener el stock actual, reservado y disponible de una pieza
+#[tauri::command]
+pub async fn get_stock_pieza(session_token: String, pieza_id: i32) -> Result<Option<StockPieza>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, StockPieza>(&sql_stock_piezas("WHERE s.pieza_id = ?"))
+        .bind(pieza_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener las reservas activas de una pieza
+#[tauri::command]
+pub async fn get_reservas_pieza(session_token: String, pieza_id: i32) -> Result<Vec<ReservaStock>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, ReservaStock>(
+        "SELECT r.reserva_id, r.pieza_id, r.cotizacion_id, c.cotizacion_codigo,
+                ot.orden_id, ot.orden_codigo, r.cantidad, r.estado, r.created_at
+         FROM RESERVA_STOCK r
+         LEFT JOIN COTIZACION c ON r.cotizacion_id = c.cotizacion_id
+         LEFT JOIN ORDEN_TRABAJO ot ON ot.cotizacion_id = r.cotizacion_id
+         WHERE r.pieza_id = ? AND r.estado = 'activa'
+         ORDER BY r.created_at ASC"
+    )
+    .bind(pieza_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+fn sql_stock_piezas(filtro: &str) -> String {
+    format!(
+        "SELECT s.*, CAST(s.stock_actual - s.stock_reservado AS SIGNED) as stock_disponible
+         FROM (
+             SELECT p.pieza_id, p.pieza_nombre, p.pieza_marca, p.stock_actual,
+                    p.stock_minimo, p.cantidad_reposicion,
+                    ({} AND r.pieza_id = p.pieza_id) as stock_reservado,
+                    (SELECT MAX(m.created_at) FROM MOVIMIENTO_STOCK m WHERE m.pieza_id = p.pieza_id) as ultimo_movimiento_at
+             FROM PIEZA p
+         ) s
+         {}",
+        SQL_RESERVADO_PENDIENTE, filtro
+    )
+}
+
+/// Definir el stock mínimo y la cantidad de reposición de una pieza.
+/// Sin mínimo la pieza no genera alertas.
+#[tauri::command]
+pub async fn update_niveles_stock(
+    session_token: String,
+    pieza_id: i32,
+    stock_minimo: Option<i32>,
+    cantidad_reposicion: Option<i32>,
+) -> Result<StockPieza, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    validar_niveles(stock_minimo, cantidad_reposicion)?;
+
+    let anterior = sqlx::query_as::<_, (Option<i32>, Option<i32>)>(
+        "SELECT stock_minimo, cantidad_reposicion FROM PIEZA WHERE pieza_id = ?"
+    )
+    .bind(pieza_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Pieza no encontrada".to_string())?;
+
+    sqlx::query("UPDATE PIEZA SET stock_minimo = ?, cantidad_reposicion = ? WHERE pieza_id = ?")
+        .bind(stock_minimo)
+        .bind(cantidad_reposicion)
+        .bind(pieza_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: mínimo|reposición
+    let niveles = |(minimo, reposicion): (Option<i32>, Option<i32>)| format!("{}|{}",
+        minimo.map_or("".to_string(), |v| v.to_string()),
+        reposicion.map_or("".to_string(), |v| v.to_string())
+    );
+    let _ = log_action(
+        "UPDATE_NIVELES_STOCK",
+        Some(usuario.usuario_id),
+        "PIEZA",
+        Some(pieza_id),
+        Some(&niveles(anterior)),
+        Some(&niveles((stock_minimo, cantidad_reposicion)))
+    ).await;
+
+    get_stock_pieza(session_token, pieza_id).await?
+        .ok_or_else(|| "Pieza no encontrada".to_string())
+}
+
+/// Obtener las piezas con disponible bajo el mínimo, con su consumo reciente y
+/// la cantidad sugerida a pedir
+#[tauri::command]
+pub async fn get_piezas_bajo_minimo(session_token: String, dias: Option<i64>) -> Result<Vec<PiezaBajoMinimo>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let dias = match dias {
+        Some(dias) => dias.clamp(1, 365),
+        None => obtener_dias_consumo().await?,
+    };
+    fetch_piezas_bajo_minimo(dias).await
+}
+
+/// Enviar ahora el resumen de stock bajo al destinatario configurado
+#[tauri::command]
+pub async fn enviar_resumen_stock_bajo(session_token: String) -> Result<ResumenStockBajo, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
+
+    let destinatario = obtener_destinatario_resumen_stock().await?
+        .ok_or_else(|| "No hay un destinatario configurado para el resumen de stock".to_string())?;
+    let dias = obtener_dias_consumo().await?;
+    let piezas = fetch_piezas_bajo_minimo(dias).await?;
+
+    enviar_resumen(&destinatario, &piezas, dias).await?;
+
+    let _ = log_action(
+        "RESUMEN_STOCK_BAJO",
+        Some(usuario.usuario_id),
+        "PIEZA",
+        None,
+        None,
+        Some(&format!("{} piezas bajo el mínimo enviadas a {}", piezas.len(), destinatario))
+    ).await;
+
+    Ok(ResumenStockBajo { destinatario: Some(destinatario), dias_consumo: dias, piezas, enviado: true })
+}
+
+/// Envía el resumen diario si está configurado y todavía no se envió hoy.
+/// La tarea de fondo la llama en cada revisión; devuelve None si no hizo nada.
+pub(crate) async fn procesar_resumen_stock_bajo() -> Result<Option<ResumenStockBajo>, String> {
+    let Some(destinatario) = obtener_destinatario_resumen_stock().await? else {
+        return Ok(None);
+    };
+    let pool = get_db_pool_safe()?;
+    let hoy = Utc::now().date_naive();
+
+    // Tomar el día antes de enviar; si otra revisión ya lo tomó, no hay nada que hacer
+    let tomado = sqlx::query("INSERT IGNORE INTO RESUMEN_STOCK_ENVIO (fecha, destinatario) VALUES (?, ?)")
+        .bind(hoy)
+        .bind(&destinatario)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if tomado.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let dias = obtener_dias_consumo().await?;
+    let resultado = match fetch_piezas_bajo_minimo(dias).await {
+        // Un día sin piezas bajo el mínimo no genera correo
+        Ok(piezas) if piezas.is_empty() => Ok(piezas),
+        Ok(piezas) => enviar_resumen(&destinatario, &piezas, dias).await.map(|_| piezas),
+        Err(e) => Err(e),
+    };
+
+    let piezas = match resultado {
+        Ok(piezas) => piezas,
+        Err(e) => {
+            // Liberar el día para reintentar en la próxima revisión
+            let _ = sqlx::query("DELETE FROM RESUMEN_STOCK_ENVIO WHERE fecha = ? AND enviado_at IS NULL")
+                .bind(hoy)
+                .execute(pool)
+                .await;
+            return Err(e);
+        }
+    };
+
+    let enviado = !piezas.is_empty();
+    sqlx::query(
+        "UPDATE RESUMEN_STOCK_ENVIO SET piezas = ?, enviado_at = IF(?, CURRENT_TIMESTAMP, NULL) WHERE fecha = ?"
+    )
+    .bind(piezas.len() as i32)
+    .bind(enviado)
+    .bind(hoy)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if enviado {
+        let _ = log_action(
+            "AUTO_RESUMEN_STOCK_BAJO",
+            None,
+            "PIEZA",
+            None,
+            None,
+            Some(&format!("{} piezas bajo el mínimo enviadas a {}", piezas.len(), destinatario))
+        ).await;
+    }
+
+    Ok(Some(ResumenStockBajo { destinatario: Some(destinatario), dias_consumo: dias, piezas, enviado }))
+}
+
+async fn enviar_resumen(destinatario: &str, piezas: &[PiezaBajoMinimo], dias: i64) -> Result<(), String> {
+    let email_service = EmailService::new()
+        .map_err(|e| format!("Error inicializando servicio de email: {}", e))?;
+    email_service.send_resumen_stock_bajo_email(destinatario, piezas, dias).await
+}
+
+async fn fetch_piezas_bajo_minimo(dias: i64) -> Result<Vec<PiezaBajoMinimo>, String> {
+    let pool = get_db_pool_safe()?;
+
+    let piezas = sqlx::query_as::<_, StockPieza>(&sql_stock_piezas(
+        "WHERE s.stock_minimo IS NOT NULL AND s.stock_actual - s.stock_reservado < s.stock_minimo
+         ORDER BY (s.stock_actual - s.stock_reservado) - s.stock_minimo ASC, s.pieza_nombre ASC"
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Consumo según las piezas de los informes finalizados del periodo; un
+    // informe reemplazado por una enmienda finalizada no se cuenta dos veces
+    let consumos: HashMap<i32, i64> = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT pi.pieza_id, CAST(SUM(COALESCE(pi.cantidad, 1)) AS SIGNED)
+         FROM PIEZAS_INFORME pi
+         INNER JOIN INFORME i ON pi.informe_id = i.informe_id
+         WHERE i.created_at >= DATE_SUB(CURRENT_TIMESTAMP, INTERVAL ? DAY)
+           AND COALESCE(i.is_borrador, FALSE) = FALSE
+           AND NOT EXISTS (SELECT 1 FROM INFORME a WHERE a.enmienda_de = i.informe_id AND a.finalizado_at IS NOT NULL)
+         GROUP BY pi.pieza_id"
+    )
+    .bind(dias)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .into_iter()
+    .collect();
+
+    Ok(piezas.into_iter().map(|stock| {
+        let consumo_periodo = consumos.get(&stock.pieza_id).copied().unwrap_or(0);
+        let diario = consumo_diario(consumo_periodo, dias);
+        PiezaBajoMinimo {
+            consumo_periodo,
+            consumo_diario: diario,
+            dias_cobertura: dias_de_cobertura(stock.stock_disponible, diario),
+            cantidad_sugerida: cantidad_sugerida(
+                stock.stock_disponible,
+                stock.stock_minimo.unwrap_or(0),
+                stock.cantidad_reposicion,
+            ),
+            stock,
+        }
+    }).collect())
+}
+
+/// Obtener el historial de movimientos de una pieza, del más reciente al más antiguo
+#[tauri::command]
+pub async fn get_movimientos_stock(session_token: String, pieza_id: i32, limit: Option<i64>) -> Result<Vec<MovimientoStock>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, MovimientoStock>(
+        "SELECT m.movimiento_id, m.pieza_id, p.pieza_nombre, m.tipo, m.cantidad, m.stock_resultante,
+                m.informe_id, i.informe_codigo, m.orden_compra_id, oc.orden_compra_codigo,
+                m.motivo, m.created_by, u.usuario_nombre as created_by_nombre, m.created_at
+         FROM MOVIMIENTO_STOCK m
+         INNER JOIN PIEZA p ON m.pieza_id = p.pieza_id
+         LEFT JOIN INFORME i ON m.informe_id = i.informe_id
+         LEFT JOIN ORDEN_COMPRA oc ON m.orden_compra_id = oc.orden_compra_id
+         LEFT JOIN USUARIO u ON m.created_by = u.usuario_id
+         WHERE m.pieza_id = ?
+         ORDER BY m.movimiento_id DESC
+         LIMIT ?"
+    )
+    .bind(pieza_id)
+    .bind(limit.unwrap_or(LIMITE_MOVIMIENTOS_POR_DEFECTO).clamp(1, 1000))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Registrar manualmente un ajuste, una recepción de compra o una devolución.
+/// El consumo solo se registra desde los informes.
+#[tauri::command]
+pub async fn ajustar_stock(session_token: String, request: AjusteStockRequest) -> Result<MovimientoStock, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    let tipo = request.tipo.unwrap_or(TipoMovimiento::Ajuste);
+    if tipo == TipoMovimiento::ConsumoInforme {
+        return Err("El consumo de piezas se registra desde el informe".to_string());
+    }
+    let motivo = request.motivo.trim();
+    if motivo.is_empty() {
+        return Err("Debe indicar el motivo del movimiento".to_string());
+    }
+    if motivo.chars().count() > 255 {
+        return Err("El motivo no puede superar los 255 caracteres".to_string());
+    }
+
+    let politica = obtener_politica_stock_negativo().await?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let registrado = registrar_movimiento(&mut tx, NuevoMovimiento {
+        pieza_id: request.pieza_id,
+        tipo,
+        cantidad: request.cantidad,
+        informe_id: None,
+        orden_compra_id: None,
+        motivo: Some(motivo),
+        usuario_id: Some(usuario.usuario_id),
+    }, politica).await?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría: stock|tipo|cantidad|motivo
+    let _ = log_action(
+        "AJUSTE_STOCK",
+        Some(usuario.usuario_id),
+        "PIEZA",
+        Some(request.pieza_id),
+        Some(&registrado.stock_anterior.to_string()),
+        Some(&format!("{}|{}|{}|{}", registrado.stock_resultante, tipo, request.cantidad, motivo))
+    ).await;
+
+    let mut movimiento = fetch_movimiento(registrado.movimiento_id).await?;
+    movimiento.advertencia = registrado.advertencia;
+    Ok(movimiento)
+}
+
+async fn fetch_movimiento(movimiento_id: i32) -> Result<MovimientoStock, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, MovimientoStock>(
+        "SELECT m.movimiento_id, m.pieza_id, p.pieza_nombre, m.tipo, m.cantidad, m.stock_resultante,
+                m.informe_id, i.informe_codigo, m.orden_compra_id, oc.orden_compra_codigo,
+                m.motivo, m.created_by, u.usuario_nombre as created_by_nombre, m.created_at
+         FROM MOVIMIENTO_STOCK m
+         INNER JOIN PIEZA p ON m.pieza_id = p.pieza_id
+         LEFT JOIN INFORME i ON m.informe_id = i.informe_id
+         LEFT JOIN ORDEN_COMPRA oc ON m.orden_compra_id = oc.orden_compra_id
+         LEFT JOIN USUARIO u ON m.created_by = u.usuario_id
+         WHERE m.movimiento_id = ?"
+    )
+    .bind(movimiento_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Movimiento de stock no encontrado".to_string())
+}
+
+/// Agrega un movimiento al registro y actualiza el stock de la pieza en la misma
+/// transacción. La fila de la pieza queda bloqueada hasta el commit, así dos
+/// movimientos simultáneos no calculan el saldo sobre el mismo valor.
+pub(crate) async fn registrar_movimiento(
+    tx: &mut Transaction<'_, MySql>,
+    movimiento: NuevoMovimiento<'_>,
+    politica: PoliticaStockNegativo,
+) -> Result<MovimientoRegistrado, String> {
+    movimiento.tipo.validar_cantidad(movimiento.cantidad)?;
+
+    let (nombre, stock_anterior) = sqlx::query_as::<_, (Option<String>, i32)>(
+        "SELECT pieza_nombre, stock_actual FROM PIEZA WHERE pieza_id = ? FOR UPDATE"
+    )
+    .bind(movimiento.pieza_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("La pieza {} no existe", movimiento.pieza_id))?;
+
+    // El consumo de un informe no puede tomar lo reservado para otras órdenes;
+    // los ajustes reflejan el conteo físico y no miran las reservas
+    let reservado = match (movimiento.tipo, movimiento.informe_id) {
+        (TipoMovimiento::ConsumoInforme, Some(informe_id)) => {
+            let consulta = format!(
+                "{} AND r.pieza_id = ? AND (ot.informe_id IS NULL OR ot.informe_id <> ?)",
+                SQL_RESERVADO_PENDIENTE
+            );
+            let reservado = sqlx::query_scalar::<_, i64>(&consulta)
+                .bind(movimiento.pieza_id)
+                .bind(informe_id)
+                .fetch_one(&mut **tx)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+            i32::try_from(reservado).unwrap_or(i32::MAX)
+        }
+        _ => 0,
+    };
+
+    let nombre = nombre.unwrap_or_else(|| format!("pieza {}", movimiento.pieza_id));
+    let (stock_resultante, advertencia) = aplicar_movimiento(&nombre, stock_anterior, reservado, movimiento.cantidad, politica)?;
+
+    sqlx::query("UPDATE PIEZA SET stock_actual = ? WHERE pieza_id = ?")
+        .bind(stock_resultante)
+        .bind(movimiento.pieza_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    let result = sqlx::query(
+        "INSERT INTO MOVIMIENTO_STOCK (pieza_id, tipo, cantidad, stock_resultante, informe_id, orden_compra_id, motivo, created_by)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(movimiento.pieza_id)
+    .bind(movimiento.tipo.as_str())
+    .bind(movimiento.cantidad)
+    .bind(stock_resultante)
+    .bind(movimiento.informe_id)
+    .bind(movimiento.orden_compra_id)
+    .bind(movimiento.motivo)
+    .bind(movimiento.usuario_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(MovimientoRegistrado {
+        movimiento_id: result.last_insert_id() as i32,
+        stock_anterior,
+        stock_resultante,
+        advertencia,
+    })
+}
+
+/// Registra el movimiento que corresponde al cambiar la cantidad usada de una
+/// pieza en un informe. No hace nada si la cantidad no cambió.
+pub(crate) async fn registrar_cambio_pieza_informe(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+    pieza_id: i32,
+    anterior: i32,
+    nueva: i32,
+    usuario_id: i32,
+    politica: PoliticaStockNegativo,
+) -> Result<Option<String>, String> {
+    let Some((tipo, cantidad)) = movimiento_por_cambio(anterior, nueva) else {
+        return Ok(None);
+    };
+
+    let registrado = registrar_movimiento(tx, NuevoMovimiento {
+        pieza_id,
+        tipo,
+        cantidad,
+        informe_id: Some(informe_id),
+        orden_compra_id: None,
+        motivo: None,
+        usuario_id: Some(usuario_id),
+    }, politica).await?;
+
+    Ok(registrado.advertencia)
+}
+
+/// Descuenta del stock todas las piezas de un informe recién creado y devuelve
+/// las advertencias de stock negativo que se hayan producido
+pub(crate) async fn consumir_piezas_informe(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+    usuario_id: i32,
+    politica: PoliticaStockNegativo,
+) -> Result<Vec<String>, String> {
+    let mut advertencias = Vec::new();
+    for (pieza_id, cantidad) in fetch_lineas_informe(tx, informe_id).await? {
+        let advertencia = registrar_cambio_pieza_informe(tx, informe_id, pieza_id, 0, cantidad, usuario_id, politica).await?;
+        advertencias.extend(advertencia);
+    }
+    Ok(advertencias)
+}
+
+/// Devuelve al stock todas las piezas de un informe que se va a eliminar
+pub(crate) async fn devolver_piezas_informe(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+    motivo: &str,
+    usuario_id: i32,
+) -> Result<(), String> {
+    for (pieza_id, cantidad) in fetch_lineas_informe(tx, informe_id).await? {
+        if cantidad <= 0 {
+            continue;
+        }
+        registrar_movimiento(tx, NuevoMovimiento {
+            pieza_id,
+            tipo: TipoMovimiento::Devolucion,
+            cantidad,
+            informe_id: Some(informe_id),
+            orden_compra_id: None,
+            motivo: Some(motivo),
+            usuario_id: Some(usuario_id),
+        }, PoliticaStockNegativo::Rechazar).await?;
+    }
+    Ok(())
+}
+
+/// Piezas y cantidades de un informe, ordenadas por pieza para bloquear las
+/// filas de PIEZA siempre en el mismo orden
+async fn fetch_lineas_informe(tx: &mut Transaction<'_, MySql>, informe_id: i32) -> Result<Vec<(i32, i32)>, String> {
+    sqlx::query_as::<_, (i32, i32)>(
+        "SELECT pieza_id, COALESCE(cantidad, 1) FROM PIEZAS_INFORME WHERE informe_id = ? ORDER BY pieza_id"
+    )
+    .bind(informe_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Reserva las piezas de una cotización recién aprobada. No hace nada si la
+/// cotización ya tiene reservas activas.
+pub(crate) async fn reservar_piezas_cotizacion(
+    tx: &mut Transaction<'_, MySql>,
+    cotizacion_id: i32,
+    usuario_id: Option<i32>,
+) -> Result<u64, String> {
+    let activas = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM RESERVA_STOCK WHERE cotizacion_id = ? AND estado = 'activa'"
+    )
+    .bind(cotizacion_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    if activas > 0 {
+        return Ok(0);
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO RESERVA_STOCK (pieza_id, cotizacion_id, cantidad, estado, created_by)
+         SELECT pieza_id, cotizacion_id, COALESCE(cantidad, 1), ?, ?
+         FROM PIEZAS_COTIZACION WHERE cotizacion_id = ?"
+    )
+    .bind(EstadoReserva::Activa.as_str())
+    .bind(usuario_id)
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(result.rows_affected())
+}
+
+/// Libera las reservas activas de una cotización (rechazo o eliminación)
+pub(crate) async fn liberar_reservas_cotizacion(
+    tx: &mut Transaction<'_, MySql>,
+    cotizacion_id: i32,
+    motivo: &str,
+) -> Result<u64, String> {
+    sqlx::query(
+        "UPDATE RESERVA_STOCK SET estado = ?, motivo_cierre = ?, cerrada_at = CURRENT_TIMESTAMP
+         WHERE cotizacion_id = ? AND estado = 'activa'"
+    )
+    .bind(EstadoReserva::Liberada.as_str())
+    .bind(motivo)
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Libera las reservas de la cotización de una orden que no se va a reparar
+pub(crate) async fn liberar_reservas_orden(
+    tx: &mut Transaction<'_, MySql>,
+    orden_id: i32,
+    motivo: &str,
+) -> Result<u64, String> {
+    sqlx::query(
+        "UPDATE RESERVA_STOCK r
+         INNER JOIN ORDEN_TRABAJO ot ON ot.cotizacion_id = r.cotizacion_id
+         SET r.estado = ?, r.motivo_cierre = ?, r.cerrada_at = CURRENT_TIMESTAMP
+         WHERE ot.orden_id = ? AND r.estado = 'activa'"
+    )
+    .bind(EstadoReserva::Liberada.as_str())
+    .bind(motivo)
+    .bind(orden_id)
+    .execute(&mut **tx)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Al finalizar un informe, las reservas de su orden pasan a consumidas. Las
+/// salidas de stock ya las registraron las piezas del informe; aquí solo se
+/// cierra la reserva para que deje de contar.
+pub(crate) async fn consumir_reservas_informe(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+) -> Result<u64, String> {
+    sqlx::query(
+        "UPDATE RESERVA_STOCK r
+         INNER JOIN ORDEN_TRABAJO ot ON ot.cotizacion_id = r.cotizacion_id
+         SET r.estado = ?, r.motivo_cierre = 'Informe finalizado', r.cerrada_at = CURRENT_TIMESTAMP
+         WHERE ot.informe_id = ? AND r.estado = 'activa'"
+    )
+    .bind(EstadoReserva::Consumida.as_str())
+    .bind(informe_id)
+    .execute(&mut **tx)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(|e| format!("Database error: {}", e))
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
+use crate::auth::{require_permission, Permiso};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct AuditLog {
@@ -51,7 +52,19 @@ pub struct LogFilters {
 
 /// Crear un nuevo registro de auditoría
 #[tauri::command]
-pub async fn create_audit_log(request: CreateAuditLogRequest) -> Result<AuditLog, String> {
+pub async fn create_audit_log(session_token: String, request: CreateAuditLogRequest) -> Result<AuditLog, String> {
+    require_permission(&session_token, Permiso::GestionarAuditoria).await?;
+    
+    let log_id = insert_audit_log(&request).await?;
+    
+    // Obtener el log recién creado
+    fetch_audit_log_by_id(log_id)
+        .await?
+        .ok_or_else(|| "Failed to retrieve created audit log".to_string())
+}
+
+/// Inserta un registro de auditoría sin verificar la sesión (uso interno)
+async fn insert_audit_log(request: &CreateAuditLogRequest) -> Result<i32, String> {
     let pool = get_db_pool_safe()?;
     
     let result = sqlx::query(
@@ -68,17 +81,17 @@ pub async fn create_audit_log(request: CreateAuditLogRequest) -> Result<AuditLog
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
-    let log_id = result.last_insert_id() as i32;
-    
-    // Obtener el log recién creado
-    get_audit_log_by_id(log_id)
-        .await?
-        .ok_or_else(|| "Failed to retrieve created audit log".to_string())
+    Ok(result.last_insert_id() as i32)
 }
 
 /// Obtener un registro de auditoría por ID
 #[tauri::command]
-pub async fn get_audit_log_by_id(log_id: i32) -> Result<Option<AuditLog>, String> {
+pub async fn get_audit_log_by_id(session_token: String, log_id: i32) -> Result<Option<AuditLog>, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
+    fetch_audit_log_by_id(log_id).await
+}
+
+async fn fetch_audit_log_by_id(log_id: i32) -> Result<Option<AuditLog>, String> {
     let pool = get_db_pool_safe()?;
     
     let log = sqlx::query_as::<_, AuditLog>(
@@ -96,7 +109,8 @@ pub async fn get_audit_log_by_id(log_id: i32) -> Result<Option<AuditLog>, String
 
 /// Obtener todos los registros de auditoría con filtros opcionales
 #[tauri::command]
-pub async fn get_audit_logs(filters: Option<LogFilters>) -> Result<Vec<AuditLogWithUser>, String> {
+pub async fn get_audit_logs(session_token: String, filters: Option<LogFilters>) -> Result<Vec<AuditLogWithUser>, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     let mut query = String::from(
@@ -169,7 +183,8 @@ pub async fn get_audit_logs(filters: Option<LogFilters>) -> Result<Vec<AuditLogW
 
 /// Obtener registros de auditoría por usuario
 #[tauri::command]
-pub async fn get_audit_logs_by_user(usuario_id: i32, limit: Option<i32>) -> Result<Vec<AuditLogWithUser>, String> {
+pub async fn get_audit_logs_by_user(session_token: String, usuario_id: i32, limit: Option<i32>) -> Result<Vec<AuditLogWithUser>, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     let limit_clause = limit.map(|l| format!(" LIMIT {}", l)).unwrap_or_else(|| " LIMIT 50".to_string());
@@ -195,7 +210,8 @@ pub async fn get_audit_logs_by_user(usuario_id: i32, limit: Option<i32>) -> Resu
 
 /// Obtener registros de auditoría por entidad
 #[tauri::command]
-pub async fn get_audit_logs_by_entity(entidad_tabla: String, entidad_id: Option<i32>) -> Result<Vec<AuditLogWithUser>, String> {
+pub async fn get_audit_logs_by_entity(session_token: String, entidad_tabla: String, entidad_id: Option<i32>) -> Result<Vec<AuditLogWithUser>, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     let mut query = String::from(
@@ -228,7 +244,8 @@ pub async fn get_audit_logs_by_entity(entidad_tabla: String, entidad_id: Option<
 
 /// Eliminar registros de auditoría antiguos (cleanup)
 #[tauri::command]
-pub async fn cleanup_old_audit_logs(days_old: i32) -> Result<u64, String> {
+pub async fn cleanup_old_audit_logs(session_token: String, days_old: i32) -> Result<u64, String> {
+    let admin = require_permission(&session_token, Permiso::GestionarAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     let result = sqlx::query(
@@ -239,12 +256,25 @@ pub async fn cleanup_old_audit_logs(days_old: i32) -> Result<u64, String> {
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
-    Ok(result.rows_affected())
+    let deleted_count = result.rows_affected();
+    
+    // Registrar la limpieza para que quede constancia de quién la ejecutó
+    let _ = log_action(
+        "CLEANUP_AUDIT_LOGS",
+        Some(admin.usuario_id),
+        "AUDIT_LOG",
+        None,
+        None,
+        Some(&format!("Registros eliminados: {}", deleted_count))
+    ).await;
+    
+    Ok(deleted_count)
 }
 
 /// Contar total de registros de auditoría
 #[tauri::command]
-pub async fn count_audit_logs() -> Result<i64, String> {
+pub async fn count_audit_logs(session_token: String) -> Result<i64, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM AUDIT_LOG")
@@ -273,13 +303,14 @@ pub async fn log_action(
         log_new_v: new_value.map(|s| s.to_string()),
     };
     
-    create_audit_log(request).await?;
+    insert_audit_log(&request).await?;
     Ok(())
 }
 
 /// Obtener estadísticas de actividad
 #[tauri::command]
-pub async fn get_audit_stats() -> Result<serde_json::Value, String> {
+pub async fn get_audit_stats(session_token: String) -> Result<serde_json::Value, String> {
+    require_permission(&session_token, Permiso::VerAuditoria).await?;
     let pool = get_db_pool_safe()?;
     
     // Contar acciones por tipo
@@ -323,7 +354,7 @@ pub async fn get_audit_stats() -> Result<serde_json::Value, String> {
         "actions_count": actions_count,
         "user_activity": user_activity,
         "table_activity": table_activity,
-        "total_logs": count_audit_logs().await?
+        "total_logs": count_audit_logs(session_token).await?
     });
     
     Ok(stats)
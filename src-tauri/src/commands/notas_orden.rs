@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{authorize, require_permission, Permiso};
+use chrono::{DateTime, Utc};
+
+/// Largo máximo del texto de una nota
+const NOTA_MAX_CARACTERES: usize = 4000;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrdenNota {
+    pub nota_id: i32,
+    pub orden_id: i32,
+    pub nota_texto: String,
+    pub es_interna: bool,
+    pub created_by: Option<i32>,
+    pub autor_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub anulada_by: Option<i32>,
+    pub anulada_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrdenNotaRequest {
+    pub orden_id: i32,
+    pub nota_texto: String,
+    // Por defecto las notas son internas y no se muestran al cliente
+    pub es_interna: Option<bool>,
+}
+
+const SELECT_NOTA: &str =
+    "SELECT n.nota_id, n.orden_id, n.nota_texto, n.es_interna, n.created_by,
+            u.usuario_nombre as autor_nombre, n.created_at, n.anulada_by, n.anulada_at
+     FROM ORDEN_NOTA n
+     LEFT JOIN USUARIO u ON n.created_by = u.usuario_id";
+
+/// Obtener las notas de una orden de trabajo en orden cronológico.
+/// Con `solo_visibles_cliente` se omiten las notas internas y las anuladas.
+#[tauri::command]
+pub async fn get_notas_orden_trabajo(session_token: String, orden_id: i32, solo_visibles_cliente: Option<bool>) -> Result<Vec<OrdenNota>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    fetch_notas_orden_trabajo(orden_id, solo_visibles_cliente.unwrap_or(false)).await
+}
+
+/// Obtener una nota por su ID
+#[tauri::command]
+pub async fn get_nota_orden_trabajo_by_id(session_token: String, nota_id: i32) -> Result<Option<OrdenNota>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    fetch_nota_by_id(nota_id).await
+}
+
+/// Agregar una nota a una orden de trabajo. Las notas no se editan: para
+/// corregir una nota se anula y se agrega una nueva.
+#[tauri::command]
+pub async fn create_nota_orden_trabajo(session_token: String, request: CreateOrdenNotaRequest) -> Result<OrdenNota, String> {
+    let usuario = require_permission(&session_token, Permiso::EditarOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let texto = request.nota_texto.trim();
+    if texto.is_empty() {
+        return Err("La nota no puede estar vacía".to_string());
+    }
+    if texto.chars().count() > NOTA_MAX_CARACTERES {
+        return Err(format!("La nota no puede superar los {} caracteres", NOTA_MAX_CARACTERES));
+    }
+
+    let existe = sqlx::query_scalar::<_, i32>("SELECT orden_id FROM ORDEN_TRABAJO WHERE orden_id = ?")
+        .bind(request.orden_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if existe.is_none() {
+        return Err("Orden de trabajo no encontrada".to_string());
+    }
+
+    let es_interna = request.es_interna.unwrap_or(true);
+    let result = sqlx::query(
+        "INSERT INTO ORDEN_NOTA (orden_id, nota_texto, es_interna, created_by) VALUES (?, ?, ?, ?)"
+    )
+    .bind(request.orden_id)
+    .bind(texto)
+    .bind(es_interna)
+    .bind(usuario.usuario_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let nota_id = result.last_insert_id() as i32;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "CREATE_NOTA_ORDEN",
+        Some(usuario.usuario_id),
+        "ORDEN_TRABAJO",
+        Some(request.orden_id),
+        None,
+        Some(&format!("Nota {} ({})", nota_id, if es_interna { "interna" } else { "visible al cliente" }))
+    ).await;
+
+    fetch_nota_by_id(nota_id).await?
+        .ok_or_else(|| "Failed to retrieve created note".to_string())
+}
+
+/// Anular una nota. La nota se conserva en el historial marcada como anulada;
+/// solo su autor o quien pueda eliminar órdenes puede anularla.
+#[tauri::command]
+pub async fn anular_nota_orden_trabajo(session_token: String, nota_id: i32) -> Result<OrdenNota, String> {
+    let usuario = require_permission(&session_token, Permiso::EditarOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let nota = fetch_nota_by_id(nota_id).await?
+        .ok_or_else(|| "Nota no encontrada".to_string())?;
+    if nota.anulada_at.is_some() {
+        return Err("La nota ya fue anulada".to_string());
+    }
+    if nota.created_by != Some(usuario.usuario_id) {
+        authorize(&usuario, Permiso::EliminarOrdenes).await?;
+    }
+
+    sqlx::query(
+        "UPDATE ORDEN_NOTA SET anulada_by = ?, anulada_at = CURRENT_TIMESTAMP
+         WHERE nota_id = ? AND anulada_at IS NULL"
+    )
+    .bind(usuario.usuario_id)
+    .bind(nota_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "ANULAR_NOTA_ORDEN",
+        Some(usuario.usuario_id),
+        "ORDEN_TRABAJO",
+        Some(nota.orden_id),
+        Some(&format!("Nota {}", nota_id)),
+        None
+    ).await;
+
+    fetch_nota_by_id(nota_id).await?
+        .ok_or_else(|| "Nota no encontrada".to_string())
+}
+
+/// Notas de una orden sin verificar sesión (uso interno)
+pub(crate) async fn fetch_notas_orden_trabajo(orden_id: i32, solo_visibles_cliente: bool) -> Result<Vec<OrdenNota>, String> {
+    let pool = get_db_pool_safe()?;
+
+    let filtro = if solo_visibles_cliente {
+        " AND n.es_interna = FALSE AND n.anulada_at IS NULL"
+    } else {
+        ""
+    };
+    let sql = format!(
+        "{} WHERE n.orden_id = ?{} ORDER BY n.created_at ASC, n.nota_id ASC",
+        SELECT_NOTA, filtro
+    );
+
+    sqlx::query_as::<_, OrdenNota>(&sql)
+        .bind(orden_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+async fn fetch_nota_by_id(nota_id: i32) -> Result<Option<OrdenNota>, String> {
+    let pool = get_db_pool_safe()?;
+    let sql = format!("{} WHERE n.nota_id = ?", SELECT_NOTA);
+
+    sqlx::query_as::<_, OrdenNota>(&sql)
+        .bind(nota_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))
+}
@@ -2,8 +2,19 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use crate::database::get_db_pool_safe;
 use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::estado_orden::EstadoOrden;
 use chrono::{DateTime, Utc};
-use chrono::Datelike;
+use crate::codigos::{siguiente_codigo, TipoDocumento};
+use crate::commands::sla::{asignar_fecha_compromiso, resumen_cumplimiento_sla};
+use sqlx::{MySql, Transaction};
+use crate::adjuntos::EntidadAdjunto;
+use crate::commands::adjuntos::eliminar_adjuntos_entidad;
+use crate::commands::notas_orden::{fetch_notas_orden_trabajo, OrdenNota};
+use crate::commands::inventario::{consumir_reservas_informe, liberar_reservas_orden};
+
+/// Otro cambio de estado se aplicó entre la validación y la escritura
+const ESTADO_MODIFICADO: &str = "El estado de la orden cambió mientras se procesaba el cambio; vuelva a intentarlo";
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct OrdenTrabajo {
@@ -15,11 +26,14 @@ pub struct OrdenTrabajo {
     pub has_garantia: Option<bool>,
     pub equipo_id: Option<i32>,
     pub created_by: Option<i32>,
+    pub tecnico_id: Option<i32>,
     pub cotizacion_id: Option<i32>,
     pub informe_id: Option<i32>,
     pub pre_informe: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
+    pub fecha_compromiso: Option<DateTime<Utc>>,
+    pub fecha_resolucion: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,7 +44,6 @@ pub struct CreateOrdenTrabajoRequest {
     pub estado: String, // 'pendiente', 'en_proceso', 'completado', 'cancelado'
     pub has_garantia: bool,
     pub equipo_id: i32,
-    pub created_by: i32,
     pub pre_informe: String,
     pub cotizacion_id: Option<i32>,
     pub informe_id: Option<i32>,
@@ -48,6 +61,23 @@ pub struct UpdateOrdenTrabajoRequest {
     pub informe_id: Option<i32>,
     pub pre_informe: Option<String>,
     pub finished_at: Option<DateTime<Utc>>,
+    // Comentario opcional que queda en el historial si cambia el estado
+    pub comentario_estado: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrdenEstadoHistorial {
+    pub historial_id: i32,
+    pub orden_id: i32,
+    pub estado_anterior: Option<String>,
+    pub estado_nuevo: String,
+    pub usuario_id: Option<i32>,
+    pub usuario_nombre: Option<String>,
+    pub comentario: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    // Tiempo que la orden permaneció en estado_nuevo (hasta ahora si es el estado vigente)
+    #[sqlx(skip)]
+    pub duracion_segundos: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -60,11 +90,14 @@ pub struct OrdenTrabajoDetallada {
     pub has_garantia: Option<bool>,
     pub equipo_id: Option<i32>,
     pub created_by: Option<i32>,
+    pub tecnico_id: Option<i32>,
     pub cotizacion_id: Option<i32>,
     pub informe_id: Option<i32>,
     pub pre_informe: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
+    pub fecha_compromiso: Option<DateTime<Utc>>,
+    pub fecha_resolucion: Option<DateTime<Utc>>,
     // Información del equipo
     pub numero_serie: Option<String>,
     pub equipo_marca: Option<String>,
@@ -75,11 +108,17 @@ pub struct OrdenTrabajoDetallada {
     pub cliente_nombre: Option<String>,
     // Información del usuario que creó la orden
     pub creador_nombre: Option<String>,
+    // Técnico asignado a la reparación
+    pub tecnico_nombre: Option<String>,
     // Información de cotización
     pub cotizacion_codigo: Option<String>,
     pub costo_total: Option<i32>,
     // Información de informe
     pub informe_codigo: Option<String>,
+    // Notas de la orden, solo si se solicitan
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notas: Option<Vec<OrdenNota>>,
 }
 #[derive(Debug, Deserialize)]
 pub struct Filtros {
@@ -93,12 +132,14 @@ pub struct Filtros {
 
 /// Obtener todas las órdenes de trabajo
 #[tauri::command]
-pub async fn get_ordenes_trabajo() -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo(session_token: String) -> Result<Vec<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
 
     let ordenes = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          ORDER BY created_at DESC"
     )
@@ -111,10 +152,18 @@ pub async fn get_ordenes_trabajo() -> Result<Vec<OrdenTrabajo>, String> {
 
 /// Obtener una orden de trabajo por ID
 #[tauri::command]
-pub async fn get_orden_trabajo_by_id(orden_id: i32) -> Result<Option<OrdenTrabajo>, String> {
-    let pool = get_db_pool_safe()?;    let orden = sqlx::query_as::<_, OrdenTrabajo>(
+pub async fn get_orden_trabajo_by_id(session_token: String, orden_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    fetch_orden_trabajo_by_id(orden_id).await
+}
+
+/// Obtener una orden de trabajo por ID sin verificar sesión (uso interno)
+pub(crate) async fn fetch_orden_trabajo_by_id(orden_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    let pool = get_db_pool_safe()?;
+    let orden = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE orden_id = ?"
     )
@@ -128,10 +177,12 @@ pub async fn get_orden_trabajo_by_id(orden_id: i32) -> Result<Option<OrdenTrabaj
 
 /// Obtener una orden de trabajo por código
 #[tauri::command]
-pub async fn get_orden_trabajo_by_codigo(orden_codigo: String) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn get_orden_trabajo_by_codigo(session_token: String, orden_codigo: String) -> Result<Option<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;    let orden = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE orden_codigo = ?"
     )
@@ -145,11 +196,13 @@ pub async fn get_orden_trabajo_by_codigo(orden_codigo: String) -> Result<Option<
 
 /// Obtener órdenes de trabajo por equipo
 #[tauri::command]
-pub async fn get_ordenes_trabajo_by_equipo(equipo_id: i32) -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo_by_equipo(session_token: String, equipo_id: i32) -> Result<Vec<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     let ordenes = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE equipo_id = ?
          ORDER BY created_at DESC"
@@ -164,11 +217,13 @@ pub async fn get_ordenes_trabajo_by_equipo(equipo_id: i32) -> Result<Vec<OrdenTr
 
 /// Obtener órdenes de trabajo por estado
 #[tauri::command]
-pub async fn get_ordenes_trabajo_by_estado(estado: String) -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo_by_estado(session_token: String, estado: String) -> Result<Vec<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     let ordenes = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE estado = ?
          ORDER BY created_at DESC"
@@ -183,11 +238,13 @@ pub async fn get_ordenes_trabajo_by_estado(estado: String) -> Result<Vec<OrdenTr
 
 /// Obtener órdenes de trabajo por prioridad
 #[tauri::command]
-pub async fn get_ordenes_trabajo_by_prioridad(prioridad: String) -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo_by_prioridad(session_token: String, prioridad: String) -> Result<Vec<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     let ordenes = sqlx::query_as::<_, OrdenTrabajo>(
         "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE prioridad = ?
          ORDER BY created_at DESC"
@@ -200,43 +257,239 @@ pub async fn get_ordenes_trabajo_by_prioridad(prioridad: String) -> Result<Vec<O
     Ok(ordenes)
 }
 
-/// Obtener órdenes de trabajo creadas por un usuario específico
+/// Obtener las órdenes de trabajo de un usuario: las que creó y las que tiene
+/// asignadas como técnico. Con `solo_asignadas` devuelve la cola del técnico,
+/// ordenada por prioridad y antigüedad. Las órdenes entregadas o abandonadas se
+/// incluyen salvo que `incluir_cerradas` sea false; en la cola, solo si es true.
 #[tauri::command]
-pub async fn get_ordenes_trabajo_by_usuario(usuario_id: i32) -> Result<Vec<OrdenTrabajo>, String> {
+pub async fn get_ordenes_trabajo_by_usuario(
+    session_token: String,
+    usuario_id: i32,
+    solo_asignadas: Option<bool>,
+    incluir_cerradas: Option<bool>,
+) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
-    let ordenes = sqlx::query_as::<_, OrdenTrabajo>(
-        "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
-                equipo_id, created_by, cotizacion_id, informe_id, pre_informe, created_at, finished_at 
-         FROM ORDEN_TRABAJO 
-         WHERE created_by = ?
-         ORDER BY created_at DESC"
+    
+    let solo_asignadas = solo_asignadas.unwrap_or(false);
+    let (filtro_usuario, orden) = if solo_asignadas {
+        ("ot.tecnico_id = ?", "FIELD(ot.prioridad, 'alta', 'media', 'baja'), ot.created_at ASC")
+    } else {
+        ("(ot.created_by = ? OR ot.tecnico_id = ?)", "ot.created_at DESC")
+    };
+    let filtro_cerradas = if incluir_cerradas.unwrap_or(!solo_asignadas) {
+        ""
+    } else {
+        " AND ot.estado NOT IN ('entregado', 'abandonado')"
+    };
+    
+    let query = format!(
+        "SELECT 
+            ot.orden_id, ot.orden_codigo, ot.orden_desc, ot.prioridad, ot.estado, 
+            ot.has_garantia, ot.equipo_id, ot.created_by, ot.tecnico_id, ot.cotizacion_id, ot.informe_id, 
+            ot.pre_informe, ot.created_at, ot.finished_at, ot.fecha_compromiso, ot.fecha_resolucion,
+            e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
+            c.cliente_id, c.cliente_nombre,
+            u.usuario_nombre as creador_nombre,
+            tec.usuario_nombre as tecnico_nombre,
+            cot.cotizacion_codigo, cot.costo_total,
+            inf.informe_codigo
+         FROM ORDEN_TRABAJO ot
+         LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+         LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
+         LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id
+         LEFT JOIN COTIZACION cot ON ot.cotizacion_id = cot.cotizacion_id
+         LEFT JOIN INFORME inf ON ot.informe_id = inf.informe_id
+         WHERE {}{}
+         ORDER BY {}",
+        filtro_usuario, filtro_cerradas, orden
+    );
+    
+    let mut consulta = sqlx::query_as::<_, OrdenTrabajoDetallada>(&query).bind(usuario_id);
+    if !solo_asignadas {
+        consulta = consulta.bind(usuario_id);
+    }
+    let ordenes = consulta
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    Ok(ordenes)
+}
+
+/// Obtener la carga de trabajo de cada técnico agrupada por estado
+#[tauri::command]
+pub async fn get_carga_trabajo_tecnicos(session_token: String) -> Result<serde_json::Value, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+    
+    #[derive(Debug, sqlx::FromRow)]
+    struct CargaPorEstado {
+        usuario_id: i32,
+        usuario_nombre: Option<String>,
+        estado: Option<String>,
+        count: i64,
+    }
+    
+    // LEFT JOIN para incluir también a los técnicos sin órdenes asignadas
+    let filas: Vec<CargaPorEstado> = sqlx::query_as(
+        "SELECT u.usuario_id, u.usuario_nombre, ot.estado, COUNT(ot.orden_id) as count 
+         FROM USUARIO u 
+         LEFT JOIN ORDEN_TRABAJO ot ON ot.tecnico_id = u.usuario_id 
+         WHERE u.usuario_rol = 'tecnico' 
+         GROUP BY u.usuario_id, u.usuario_nombre, ot.estado 
+         ORDER BY u.usuario_nombre, u.usuario_id"
     )
-    .bind(usuario_id)
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
-    Ok(ordenes)
+    let sin_asignar: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM ORDEN_TRABAJO 
+         WHERE tecnico_id IS NULL AND estado NOT IN ('entregado', 'abandonado')"
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    
+    let mut tecnicos: Vec<serde_json::Value> = Vec::new();
+    let mut actual: Option<i32> = None;
+    for fila in filas {
+        if actual != Some(fila.usuario_id) {
+            actual = Some(fila.usuario_id);
+            tecnicos.push(serde_json::json!({
+                "tecnico_id": fila.usuario_id,
+                "tecnico_nombre": fila.usuario_nombre,
+                "abiertas": 0,
+                "por_estado": []
+            }));
+        }
+        
+        let Some(estado) = fila.estado else { continue };
+        let Some(tecnico) = tecnicos.last_mut() else { continue };
+        
+        // Las órdenes entregadas o abandonadas no cuentan como carga pendiente
+        let cerrada = estado.parse::<EstadoOrden>().is_ok_and(|e| e.esta_cerrada());
+        if !cerrada {
+            let abiertas = tecnico["abiertas"].as_i64().unwrap_or(0) + fila.count;
+            tecnico["abiertas"] = serde_json::json!(abiertas);
+        }
+        if let Some(por_estado) = tecnico["por_estado"].as_array_mut() {
+            por_estado.push(serde_json::json!({
+                "estado": estado,
+                "count": fila.count
+            }));
+        }
+    }
+    
+    Ok(serde_json::json!({
+        "tecnicos": tecnicos,
+        "sin_asignar": sin_asignar
+    }))
+}
+
+/// Asignar o reasignar el técnico responsable de una orden de trabajo
+#[tauri::command]
+pub async fn asignar_tecnico_orden_trabajo(session_token: String, orden_id: i32, tecnico_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::AsignarTecnicos).await?;
+    let pool = get_db_pool_safe()?;
+    
+    let orden = fetch_orden_trabajo_by_id(orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    
+    // Solo se asignan usuarios con rol técnico
+    let rol = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT usuario_rol FROM USUARIO WHERE usuario_id = ?"
+    )
+    .bind(tecnico_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Técnico no encontrado".to_string())?;
+    
+    if rol.as_deref() != Some("tecnico") {
+        return Err("El usuario asignado debe tener rol 'tecnico'".to_string());
+    }
+    
+    if orden.tecnico_id == Some(tecnico_id) {
+        return Ok(Some(orden));
+    }
+    
+    sqlx::query("UPDATE ORDEN_TRABAJO SET tecnico_id = ? WHERE orden_id = ?")
+        .bind(tecnico_id)
+        .bind(orden_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    // Registrar la acción en el log de auditoría
+    let accion = if orden.tecnico_id.is_some() { "REASSIGN_TECNICO" } else { "ASSIGN_TECNICO" };
+    let _ = log_action(
+        accion,
+        Some(usuario.usuario_id),
+        "ORDEN_TRABAJO",
+        Some(orden_id),
+        orden.tecnico_id.map(|id| id.to_string()).as_deref(),
+        Some(&tecnico_id.to_string())
+    ).await;
+    
+    get_orden_trabajo_by_id(session_token, orden_id).await
+}
+
+/// Quitar el técnico asignado a una orden de trabajo
+#[tauri::command]
+pub async fn desasignar_tecnico_orden_trabajo(session_token: String, orden_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::AsignarTecnicos).await?;
+    let pool = get_db_pool_safe()?;
+    
+    let orden = fetch_orden_trabajo_by_id(orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    
+    let Some(tecnico_anterior) = orden.tecnico_id else {
+        return Ok(Some(orden));
+    };
+    
+    sqlx::query("UPDATE ORDEN_TRABAJO SET tecnico_id = NULL WHERE orden_id = ?")
+        .bind(orden_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "UNASSIGN_TECNICO",
+        Some(usuario.usuario_id),
+        "ORDEN_TRABAJO",
+        Some(orden_id),
+        Some(&tecnico_anterior.to_string()),
+        None
+    ).await;
+    
+    get_orden_trabajo_by_id(session_token, orden_id).await
 }
 
 /// Obtener órdenes de trabajo con información detallada (con JOINs)
 #[tauri::command]
-pub async fn get_ordenes_trabajo_detalladas() -> Result<Vec<OrdenTrabajoDetallada>, String> {
+pub async fn get_ordenes_trabajo_detalladas(session_token: String) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
 
     let ordenes = sqlx::query_as::<_, OrdenTrabajoDetallada>(
         "SELECT 
             ot.orden_id, ot.orden_codigo, ot.orden_desc, ot.prioridad, ot.estado, 
-            ot.has_garantia, ot.equipo_id, ot.created_by, ot.cotizacion_id, ot.informe_id, 
-            ot.pre_informe, ot.created_at, ot.finished_at,
+            ot.has_garantia, ot.equipo_id, ot.created_by, ot.tecnico_id, ot.cotizacion_id, ot.informe_id, 
+            ot.pre_informe, ot.created_at, ot.finished_at, ot.fecha_compromiso, ot.fecha_resolucion,
             e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
             c.cliente_id, c.cliente_nombre,
             u.usuario_nombre as creador_nombre,
+            tec.usuario_nombre as tecnico_nombre,
             cot.cotizacion_codigo, cot.costo_total,
             inf.informe_codigo
          FROM ORDEN_TRABAJO ot
          LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
          LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
          LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id
          LEFT JOIN COTIZACION cot ON ot.cotizacion_id = cot.cotizacion_id
          LEFT JOIN INFORME inf ON ot.informe_id = inf.informe_id
@@ -251,20 +504,23 @@ pub async fn get_ordenes_trabajo_detalladas() -> Result<Vec<OrdenTrabajoDetallad
 
 /// Obtener orden de trabajo detallada por ID
 #[tauri::command]
-pub async fn get_orden_trabajo_detallada_by_id(orden_id: i32) -> Result<Option<OrdenTrabajoDetallada>, String> {
-    let pool = get_db_pool_safe()?;    let orden = sqlx::query_as::<_, OrdenTrabajoDetallada>(
+pub async fn get_orden_trabajo_detallada_by_id(session_token: String, orden_id: i32, incluir_notas: Option<bool>) -> Result<Option<OrdenTrabajoDetallada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;    let mut orden = sqlx::query_as::<_, OrdenTrabajoDetallada>(
         "SELECT 
             ot.orden_id, ot.orden_codigo, ot.orden_desc, ot.prioridad, ot.estado, 
-            ot.has_garantia, ot.equipo_id, ot.created_by, ot.cotizacion_id, ot.informe_id, 
-            ot.pre_informe, ot.created_at, ot.finished_at,
+            ot.has_garantia, ot.equipo_id, ot.created_by, ot.tecnico_id, ot.cotizacion_id, ot.informe_id, 
+            ot.pre_informe, ot.created_at, ot.finished_at, ot.fecha_compromiso, ot.fecha_resolucion,
             e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
             c.cliente_id, c.cliente_nombre,
             u.usuario_nombre as creador_nombre,
+            tec.usuario_nombre as tecnico_nombre,
             cot.cotizacion_codigo, cot.costo_total,
             inf.informe_codigo
          FROM ORDEN_TRABAJO ot
          LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
          LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
          LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id
          LEFT JOIN COTIZACION cot ON ot.cotizacion_id = cot.cotizacion_id
          LEFT JOIN INFORME inf ON ot.informe_id = inf.informe_id
@@ -275,39 +531,31 @@ pub async fn get_orden_trabajo_detallada_by_id(orden_id: i32) -> Result<Option<O
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
+    if let Some(orden) = orden.as_mut() {
+        if incluir_notas.unwrap_or(false) {
+            orden.notas = Some(fetch_notas_orden_trabajo(orden_id, false).await?);
+        }
+    }
+    
     Ok(orden)
 }
 
 /// Crear una nueva orden de trabajo
 #[tauri::command]
-pub async fn create_orden_trabajo(request: CreateOrdenTrabajoRequest) -> Result<OrdenTrabajo, String> {
+pub async fn create_orden_trabajo(session_token: String, request: CreateOrdenTrabajoRequest) -> Result<OrdenTrabajo, String> {
+    let usuario = require_permission(&session_token, Permiso::CrearOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
-    // Generar código automático: OT-YYYY-XXX
-    let year = chrono::Utc::now().year();
+    // Toda orden nace en el estado inicial de la máquina de estados
+    let estado = request.estado.parse::<EstadoOrden>()?;
+    if estado != EstadoOrden::INICIAL {
+        return Err(format!("Una orden de trabajo nueva debe iniciar en estado '{}'", EstadoOrden::INICIAL));
+    }
     
-    // Buscar el mayor número correlativo existente para el año actual
-    let last_codigo: Option<String> = sqlx::query_scalar(
-        "SELECT orden_codigo FROM ORDEN_TRABAJO WHERE orden_codigo LIKE ? ORDER BY orden_id DESC LIMIT 1"
-    )
-    .bind(format!("OT-{}-%", year))
-    .fetch_one(pool)
-    .await
-    .ok();
-    
-    let next_number = if let Some(codigo) = last_codigo {
-        // Extraer el número correlativo actual y sumarle 1
-        let parts: Vec<&str> = codigo.split('-').collect();
-        if parts.len() == 3 {
-            parts[2].parse::<u32>().unwrap_or(0) + 1
-        } else {
-            1
-        }
-    } else {
-        1
-    };
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
     
-    let codigo = format!("OT-{}-{:03}", year, next_number);
+    // Generar código automático con el correlativo compartido
+    let codigo = siguiente_codigo(&mut tx, TipoDocumento::OrdenTrabajo).await?;
     
     let result = sqlx::query(
         "INSERT INTO ORDEN_TRABAJO (orden_codigo, orden_desc, prioridad, estado, has_garantia, 
@@ -317,22 +565,29 @@ pub async fn create_orden_trabajo(request: CreateOrdenTrabajoRequest) -> Result<
     .bind(&codigo)
     .bind(&request.orden_desc)
     .bind(&request.prioridad)
-    .bind(&request.estado)
+    .bind(estado.as_str())
     .bind(request.has_garantia)
     .bind(request.equipo_id)
-    .bind(request.created_by)
+    .bind(usuario.usuario_id)
     .bind(request.cotizacion_id)
     .bind(request.informe_id)
     .bind(&request.pre_informe)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
     let orden_id = result.last_insert_id() as i32;
-      // Registrar la acción en el log de auditoría
+    
+    // Punto de partida de la línea de tiempo
+    registrar_historial_estado(&mut tx, orden_id, None, estado, Some(usuario.usuario_id), None).await?;
+    asignar_fecha_compromiso(&mut tx, orden_id).await?;
+    
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_ORDEN_TRABAJO",
-        Some(request.created_by),
+        Some(usuario.usuario_id),
         "ORDEN_TRABAJO",
         Some(orden_id),
         None,
@@ -340,21 +595,22 @@ pub async fn create_orden_trabajo(request: CreateOrdenTrabajoRequest) -> Result<
     ).await;
     
     // Enviar notificación automática por email
-    let _ = send_orden_trabajo_notification(orden_id, request.created_by).await;
+    let _ = send_orden_trabajo_notification(session_token.clone(), orden_id).await;
     
     // Obtener la orden recién creada
-    get_orden_trabajo_by_id(orden_id)
+    get_orden_trabajo_by_id(session_token, orden_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created orden de trabajo".to_string())
 }
 
 /// Actualizar una orden de trabajo
 #[tauri::command]
-pub async fn update_orden_trabajo(orden_id: i32, request: UpdateOrdenTrabajoRequest, updated_by: i32) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn update_orden_trabajo(session_token: String, orden_id: i32, request: UpdateOrdenTrabajoRequest) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::EditarOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener la orden actual para logging
-    let current_orden = get_orden_trabajo_by_id(orden_id).await?;
+    let current_orden = get_orden_trabajo_by_id(session_token.clone(), orden_id).await?;
     
     let mut query_parts = Vec::new();
     let mut bindings = Vec::new();
@@ -373,13 +629,27 @@ pub async fn update_orden_trabajo(orden_id: i32, request: UpdateOrdenTrabajoRequ
         query_parts.push("prioridad = ?");
         bindings.push(prioridad.clone());
     }
-      if let Some(estado) = &request.estado {
-        query_parts.push("estado = ?");
-        bindings.push(estado.clone());
+    let mut cambio_estado = None;
+    if let Some(estado) = &request.estado {
+        let destino = estado.parse::<EstadoOrden>()?;
+        let orden = current_orden.as_ref()
+            .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
         
-        // Si el estado es 'entregado', actualizar finished_at
-        if estado == "entregado" {
-            query_parts.push("finished_at = CURRENT_TIMESTAMP");
+        // Solo se valida la transición si el estado realmente cambia
+        if orden.estado.as_deref() != Some(destino.as_str()) {
+            let actual = validar_cambio_estado(orden, destino).await?;
+            cambio_estado = Some((actual, destino));
+            query_parts.push("estado = ?");
+            bindings.push(destino.as_str().to_string());
+            
+            // Si el estado es 'entregado', actualizar finished_at
+            if destino == EstadoOrden::Entregado {
+                query_parts.push("finished_at = CURRENT_TIMESTAMP");
+            }
+            // El reloj del SLA se detiene la primera vez que la orden deja de depender del taller
+            if destino.detiene_sla() {
+                query_parts.push("fecha_resolucion = COALESCE(fecha_resolucion, CURRENT_TIMESTAMP)");
+            }
         }
     }
     
@@ -416,23 +686,57 @@ pub async fn update_orden_trabajo(orden_id: i32, request: UpdateOrdenTrabajoRequ
         return Ok(current_orden);
     }
     
-    let query = format!("UPDATE ORDEN_TRABAJO SET {} WHERE orden_id = ?", query_parts.join(", "));
+    // Con cambio de estado, la escritura exige que el estado siga siendo el validado
+    let mut query = format!("UPDATE ORDEN_TRABAJO SET {} WHERE orden_id = ?", query_parts.join(", "));
+    if cambio_estado.is_some() {
+        query.push_str(" AND estado = ?");
+    }
     
     let mut query_builder = sqlx::query(&query);
     for binding in bindings {
         query_builder = query_builder.bind(binding);
     }
     query_builder = query_builder.bind(orden_id);
+    if let Some((actual, _)) = cambio_estado {
+        query_builder = query_builder.bind(actual.as_str());
+    }
     
-    query_builder
-        .execute(pool)
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    let result = query_builder
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
     
+    if cambio_estado.is_some() && result.rows_affected() == 0 {
+        return Err(ESTADO_MODIFICADO.to_string());
+    }
+    
+    // La fecha de compromiso depende de la prioridad y la garantía
+    if request.prioridad.is_some() || request.has_garantia.is_some() {
+        asignar_fecha_compromiso(&mut tx, orden_id).await?;
+    }
+    
+    if let Some((actual, destino)) = cambio_estado {
+        registrar_historial_estado(
+            &mut tx,
+            orden_id,
+            Some(actual),
+            destino,
+            Some(usuario.usuario_id),
+            request.comentario_estado.as_deref()
+        ).await?;
+        if destino.libera_reservas() {
+            liberar_reservas_orden(&mut tx, orden_id, &format!("Orden en estado {}", destino.as_str())).await?;
+        }
+    }
+    
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "UPDATE_ORDEN_TRABAJO",
-        Some(updated_by),
+        Some(usuario.usuario_id),
         "ORDEN_TRABAJO",
         Some(orden_id),
         current_orden.as_ref().and_then(|o| o.orden_codigo.as_deref()),
@@ -440,61 +744,254 @@ pub async fn update_orden_trabajo(orden_id: i32, request: UpdateOrdenTrabajoRequ
     ).await;
     
     // Obtener la orden actualizada
-    get_orden_trabajo_by_id(orden_id).await
+    get_orden_trabajo_by_id(session_token, orden_id).await
 }
 
 /// Cambiar el estado de una orden de trabajo
 #[tauri::command]
-pub async fn cambiar_estado_orden_trabajo(orden_id: i32, nuevo_estado: String, updated_by: i32) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn cambiar_estado_orden_trabajo(session_token: String, orden_id: i32, nuevo_estado: String, comentario: Option<String>) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::CambiarEstadoOrden).await?;
+    let destino = nuevo_estado.parse::<EstadoOrden>()?;
+    
+    aplicar_cambio_estado(orden_id, destino, Some(usuario.usuario_id), comentario.as_deref()).await?;
+    
+    get_orden_trabajo_by_id(session_token, orden_id).await
+}
+
+/// Obtener los estados a los que puede pasar una orden de trabajo
+#[tauri::command]
+pub async fn get_estados_siguientes_orden_trabajo(session_token: String, orden_id: i32) -> Result<Vec<EstadoOrden>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    
+    let orden = fetch_orden_trabajo_by_id(orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    let actual = estado_actual(&orden)?;
+    
+    // Solo se devuelven los estados cuyas condiciones se cumplen
+    let mut siguientes = Vec::new();
+    for destino in actual.siguientes() {
+        if verificar_condiciones_estado(&orden, *destino).await.is_ok() {
+            siguientes.push(*destino);
+        }
+    }
+    
+    Ok(siguientes)
+}
+
+/// Aplica un cambio de estado validando la transición y sus condiciones.
+/// `usuario_id` es `None` cuando el cambio lo realiza un proceso del sistema.
+pub(crate) async fn aplicar_cambio_estado(
+    orden_id: i32,
+    destino: EstadoOrden,
+    usuario_id: Option<i32>,
+    comentario: Option<&str>,
+) -> Result<(), String> {
     let pool = get_db_pool_safe()?;
-      // Validar que el estado sea válido
-    let estados_validos = vec![
-        "recibido",
-        "cotizacion_enviada", 
-        "aprobacion_pendiente",
-        "en_reparacion",
-        "espera_de_retiro",
-        "entregado",
-        "abandonado",
-        "equipo_no_reparable",
-        "cotizacion_rechazada"
-    ];
-    if !estados_validos.contains(&nuevo_estado.as_str()) {
-        return Err("Estado no válido".to_string());
-    }    let current_orden = get_orden_trabajo_by_id(orden_id).await?;
     
+    let orden = fetch_orden_trabajo_by_id(orden_id).await?
+        .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
+    let actual = validar_cambio_estado(&orden, destino).await?;
+    
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    escribir_cambio_estado(&mut tx, orden_id, actual, destino, usuario_id, comentario).await?;
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
+    registrar_cambio_estado(orden_id, actual, destino, usuario_id).await;
+    
+    Ok(())
+}
+
+/// Escribe un cambio de estado ya validado dentro de la transacción del llamador.
+/// El estado validado se exige también al escribir: si otro cambio se adelantó,
+/// la transición ya no es válida.
+pub(crate) async fn escribir_cambio_estado(
+    tx: &mut Transaction<'_, MySql>,
+    orden_id: i32,
+    actual: EstadoOrden,
+    destino: EstadoOrden,
+    usuario_id: Option<i32>,
+    comentario: Option<&str>,
+) -> Result<(), String> {
+    let mut cambios = vec!["estado = ?"];
     // Si el estado es 'entregado', actualizar finished_at
-    let query_builder = if nuevo_estado == "entregado" {
-        sqlx::query("UPDATE ORDEN_TRABAJO SET estado = ?, finished_at = CURRENT_TIMESTAMP WHERE orden_id = ?")
-            .bind(&nuevo_estado)
-            .bind(orden_id)
-    } else {
-        sqlx::query("UPDATE ORDEN_TRABAJO SET estado = ? WHERE orden_id = ?")
-            .bind(&nuevo_estado)
-            .bind(orden_id)
-    };
+    if destino == EstadoOrden::Entregado {
+        cambios.push("finished_at = CURRENT_TIMESTAMP");
+    }
+    // El reloj del SLA se detiene la primera vez que la orden deja de depender del taller
+    if destino.detiene_sla() {
+        cambios.push("fecha_resolucion = COALESCE(fecha_resolucion, CURRENT_TIMESTAMP)");
+    }
+    let query = format!("UPDATE ORDEN_TRABAJO SET {} WHERE orden_id = ? AND estado = ?", cambios.join(", "));
     
-    query_builder
-        .execute(pool)
+    let result = sqlx::query(&query)
+        .bind(destino.as_str())
+        .bind(orden_id)
+        .bind(actual.as_str())
+        .execute(&mut **tx)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
     
-    // Registrar la acción en el log de auditoría
+    if result.rows_affected() == 0 {
+        return Err(ESTADO_MODIFICADO.to_string());
+    }
+    
+    registrar_historial_estado(tx, orden_id, Some(actual), destino, usuario_id, comentario).await?;
+    
+    // Si la reparación no se hará, las piezas reservadas quedan para otras órdenes
+    if destino.libera_reservas() {
+        liberar_reservas_orden(tx, orden_id, &format!("Orden en estado {}", destino.as_str())).await?;
+    }
+    
+    Ok(())
+}
+
+/// Auditoría de un cambio de estado ya confirmado
+pub(crate) async fn registrar_cambio_estado(orden_id: i32, actual: EstadoOrden, destino: EstadoOrden, usuario_id: Option<i32>) {
     let _ = log_action(
         "CHANGE_ORDER_STATUS",
-        Some(updated_by),
+        usuario_id,
         "ORDEN_TRABAJO",
         Some(orden_id),
-        current_orden.as_ref().and_then(|o| o.estado.as_deref()),
-        Some(&nuevo_estado)
+        Some(actual.as_str()),
+        Some(destino.as_str())
     ).await;
+}
+
+/// Inserta una fila en ORDEN_ESTADO_HISTORIAL dentro de la transacción del cambio
+async fn registrar_historial_estado(
+    tx: &mut Transaction<'_, MySql>,
+    orden_id: i32,
+    anterior: Option<EstadoOrden>,
+    nuevo: EstadoOrden,
+    usuario_id: Option<i32>,
+    comentario: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO ORDEN_ESTADO_HISTORIAL (orden_id, estado_anterior, estado_nuevo, usuario_id, comentario) 
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(orden_id)
+    .bind(anterior.map(|e| e.as_str()))
+    .bind(nuevo.as_str())
+    .bind(usuario_id)
+    .bind(comentario)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    
+    Ok(())
+}
+
+/// Obtener la línea de tiempo de estados de una orden de trabajo
+#[tauri::command]
+pub async fn get_orden_trabajo_timeline(session_token: String, orden_id: i32) -> Result<Vec<OrdenEstadoHistorial>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+    
+    let mut historial = sqlx::query_as::<_, OrdenEstadoHistorial>(
+        "SELECT h.historial_id, h.orden_id, h.estado_anterior, h.estado_nuevo, h.usuario_id, 
+                u.usuario_nombre, h.comentario, h.created_at 
+         FROM ORDEN_ESTADO_HISTORIAL h 
+         LEFT JOIN USUARIO u ON h.usuario_id = u.usuario_id 
+         WHERE h.orden_id = ? 
+         ORDER BY h.created_at ASC, h.historial_id ASC"
+    )
+    .bind(orden_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    
+    // Cada estado dura hasta el siguiente cambio; el vigente se mide hasta ahora,
+    // salvo que sea un estado final
+    let ahora = Utc::now();
+    let inicios: Vec<Option<DateTime<Utc>>> = historial.iter().map(|h| h.created_at).collect();
+    for (i, entrada) in historial.iter_mut().enumerate() {
+        let fin = match inicios.get(i + 1) {
+            Some(siguiente) => *siguiente,
+            None => {
+                let es_final = entrada.estado_nuevo
+                    .parse::<EstadoOrden>()
+                    .is_ok_and(|e| e.es_final());
+                if es_final { None } else { Some(ahora) }
+            }
+        };
+        entrada.duracion_segundos = match (entrada.created_at, fin) {
+            (Some(inicio), Some(fin)) => Some((fin - inicio).num_seconds()),
+            _ => None,
+        };
+    }
+    
+    Ok(historial)
+}
+
+/// Estado actual de la orden como `EstadoOrden`
+pub(crate) fn estado_actual(orden: &OrdenTrabajo) -> Result<EstadoOrden, String> {
+    orden.estado
+        .as_deref()
+        .ok_or_else(|| "La orden de trabajo no tiene estado".to_string())?
+        .parse::<EstadoOrden>()
+}
+
+/// Verifica que la transición esté en la tabla y que se cumplan sus condiciones.
+/// Devuelve el estado actual de la orden.
+async fn validar_cambio_estado(orden: &OrdenTrabajo, destino: EstadoOrden) -> Result<EstadoOrden, String> {
+    let actual = estado_actual(orden)?;
+    actual.validar_transicion(destino)?;
+    verificar_condiciones_estado(orden, destino).await?;
+    Ok(actual)
+}
+
+/// Condiciones de negocio para entrar a ciertos estados
+async fn verificar_condiciones_estado(orden: &OrdenTrabajo, destino: EstadoOrden) -> Result<(), String> {
+    let pool = get_db_pool_safe()?;
     
-    get_orden_trabajo_by_id(orden_id).await
+    match destino {
+        // No se repara sin una cotización aprobada
+        EstadoOrden::EnReparacion => {
+            let cotizacion_id = orden.cotizacion_id
+                .ok_or_else(|| "La orden no tiene una cotización asociada".to_string())?;
+            
+            let is_aprobada = sqlx::query_scalar::<_, Option<bool>>(
+                "SELECT is_aprobada FROM COTIZACION WHERE cotizacion_id = ?"
+            )
+            .bind(cotizacion_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .flatten()
+            .unwrap_or(false);
+            
+            if !is_aprobada {
+                return Err("No se puede pasar a 'en_reparacion' sin una cotización aprobada".to_string());
+            }
+        }
+        // No se avisa el retiro sin un informe técnico
+        EstadoOrden::EsperaDeRetiro => {
+            let informe_id = orden.informe_id
+                .ok_or_else(|| "La orden no tiene un informe asociado".to_string())?;
+            
+            let existe = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM INFORME WHERE informe_id = ?"
+            )
+            .bind(informe_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+            
+            if existe == 0 {
+                return Err("No se puede pasar a 'espera_de_retiro' sin un informe".to_string());
+            }
+        }
+        _ => {}
+    }
+    
+    Ok(())
 }
 
 /// Asignar cotización a una orden de trabajo
 #[tauri::command]
-pub async fn asignar_cotizacion_orden_trabajo(orden_id: i32, cotizacion_id: i32, updated_by: i32) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn asignar_cotizacion_orden_trabajo(session_token: String, orden_id: i32, cotizacion_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::EditarOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     sqlx::query("UPDATE ORDEN_TRABAJO SET cotizacion_id = ? WHERE orden_id = ?")
@@ -507,48 +1004,81 @@ pub async fn asignar_cotizacion_orden_trabajo(orden_id: i32, cotizacion_id: i32,
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "ASSIGN_COTIZACION",
-        Some(updated_by),
+        Some(usuario.usuario_id),
         "ORDEN_TRABAJO",
         Some(orden_id),
         None,
         Some(&format!("Cotización {} asignada", cotizacion_id))
     ).await;
     
-    get_orden_trabajo_by_id(orden_id).await
+    get_orden_trabajo_by_id(session_token, orden_id).await
 }
 
 /// Asignar informe a una orden de trabajo
 #[tauri::command]
-pub async fn asignar_informe_orden_trabajo(orden_id: i32, informe_id: i32, updated_by: i32) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn asignar_informe_orden_trabajo(session_token: String, orden_id: i32, informe_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    let usuario = require_permission(&session_token, Permiso::EditarOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    
     sqlx::query("UPDATE ORDEN_TRABAJO SET informe_id = ? WHERE orden_id = ?")
         .bind(informe_id)
         .bind(orden_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
     
+    // Si el informe ya está finalizado, las reservas de la orden se dan por consumidas
+    // como al finalizarlo
+    let finalizado = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        "SELECT finalizado_at FROM INFORME WHERE informe_id = ?"
+    )
+    .bind(informe_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .flatten()
+    .is_some();
+    let reservas_consumidas = if finalizado {
+        consumir_reservas_informe(&mut tx, informe_id).await?
+    } else {
+        0
+    };
+    
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+    
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "ASSIGN_INFORME",
-        Some(updated_by),
+        Some(usuario.usuario_id),
         "ORDEN_TRABAJO",
         Some(orden_id),
         None,
         Some(&format!("Informe {} asignado", informe_id))
     ).await;
+    if reservas_consumidas > 0 {
+        let _ = log_action(
+            "CONSUMIR_RESERVA_STOCK",
+            Some(usuario.usuario_id),
+            "INFORME",
+            Some(informe_id),
+            None,
+            Some(&format!("{} piezas", reservas_consumidas))
+        ).await;
+    }
     
-    get_orden_trabajo_by_id(orden_id).await
+    get_orden_trabajo_by_id(session_token, orden_id).await
 }
 
 /// Eliminar una orden de trabajo
 #[tauri::command]
-pub async fn delete_orden_trabajo(orden_id: i32, deleted_by: i32) -> Result<bool, String> {
+pub async fn delete_orden_trabajo(session_token: String, orden_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EliminarOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener información de la orden antes de eliminarla
-    let orden = get_orden_trabajo_by_id(orden_id).await?;
+    let orden = get_orden_trabajo_by_id(session_token.clone(), orden_id).await?;
     
     let result = sqlx::query("DELETE FROM ORDEN_TRABAJO WHERE orden_id = ?")
         .bind(orden_id)
@@ -557,6 +1087,9 @@ pub async fn delete_orden_trabajo(orden_id: i32, deleted_by: i32) -> Result<bool
         .map_err(|e| format!("Database error: {}", e))?;
     
     if result.rows_affected() > 0 {
+        // ADJUNTO no tiene clave foránea hacia la orden, se limpia aquí
+        let _ = eliminar_adjuntos_entidad(EntidadAdjunto::OrdenTrabajo, orden_id).await;
+        
         // Registrar la acción en el log de auditoría
         let orden_info = orden.as_ref()
             .map(|o| format!("{} - {}", 
@@ -566,7 +1099,7 @@ pub async fn delete_orden_trabajo(orden_id: i32, deleted_by: i32) -> Result<bool
             
         let _ = log_action(
             "DELETE_ORDEN_TRABAJO",
-            Some(deleted_by),
+            Some(usuario.usuario_id),
             "ORDEN_TRABAJO",
             Some(orden_id),
             Some(&orden_info),
@@ -581,7 +1114,8 @@ pub async fn delete_orden_trabajo(orden_id: i32, deleted_by: i32) -> Result<bool
 
 /// Obtener estadísticas de órdenes de trabajo
 #[tauri::command]
-pub async fn get_ordenes_trabajo_stats() -> Result<serde_json::Value, String> {
+pub async fn get_ordenes_trabajo_stats(session_token: String) -> Result<serde_json::Value, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     // Estructura para mapear resultados
@@ -624,7 +1158,11 @@ pub async fn get_ordenes_trabajo_stats() -> Result<serde_json::Value, String> {
         .fetch_one(pool)
         .await
         .map_err(|e| format!("Database error: {}", e))?;
-      let stats = serde_json::json!({
+    
+    // Cumplimiento de SLA
+    let sla = resumen_cumplimiento_sla().await?;
+    
+    let stats = serde_json::json!({
         "total": total.count,
         "con_garantia": con_garantia.count,
         "por_estado": stats_estado.into_iter().map(|r| serde_json::json!({
@@ -634,7 +1172,8 @@ pub async fn get_ordenes_trabajo_stats() -> Result<serde_json::Value, String> {
         "por_prioridad": stats_prioridad.into_iter().map(|r| serde_json::json!({
             "prioridad": r.prioridad,
             "count": r.count
-        })).collect::<Vec<_>>()
+        })).collect::<Vec<_>>(),
+        "sla": sla
     });
     
     Ok(stats)
@@ -642,22 +1181,25 @@ pub async fn get_ordenes_trabajo_stats() -> Result<serde_json::Value, String> {
 
 /// Buscar órdenes de trabajo por texto
 #[tauri::command]
-pub async fn search_ordenes_trabajo(search_term: String) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+pub async fn search_ordenes_trabajo(session_token: String, search_term: String) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     let search_pattern = format!("%{}%", search_term);
       let ordenes = sqlx::query_as::<_, OrdenTrabajoDetallada>(
         "SELECT 
             ot.orden_id, ot.orden_codigo, ot.orden_desc, ot.prioridad, ot.estado, 
-            ot.has_garantia, ot.equipo_id, ot.created_by, ot.cotizacion_id, ot.informe_id, 
-            ot.pre_informe, ot.created_at, ot.finished_at,
+            ot.has_garantia, ot.equipo_id, ot.created_by, ot.tecnico_id, ot.cotizacion_id, ot.informe_id, 
+            ot.pre_informe, ot.created_at, ot.finished_at, ot.fecha_compromiso, ot.fecha_resolucion,
             e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
             c.cliente_id, c.cliente_nombre,
             u.usuario_nombre as creador_nombre,
+            tec.usuario_nombre as tecnico_nombre,
             cot.cotizacion_codigo, cot.costo_total,
             inf.informe_codigo
          FROM ORDEN_TRABAJO ot
          LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
          LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
          LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id
          LEFT JOIN COTIZACION cot ON ot.cotizacion_id = cot.cotizacion_id
          LEFT JOIN INFORME inf ON ot.informe_id = inf.informe_id
@@ -680,16 +1222,17 @@ pub async fn search_ordenes_trabajo(search_term: String) -> Result<Vec<OrdenTrab
 
 /// Enviar notificación de orden de trabajo por email
 #[tauri::command]
-pub async fn send_orden_trabajo_notification(orden_id: i32, sent_by: i32) -> Result<bool, String> {
+pub async fn send_orden_trabajo_notification(session_token: String, orden_id: i32) -> Result<bool, String> {
+    let usuario = require_permission(&session_token, Permiso::EnviarNotificaciones).await?;
     use crate::email::EmailService;
     use crate::commands::equipos::get_equipo_by_id;
     
     // Obtener la orden de trabajo
-    let orden_trabajo = get_orden_trabajo_by_id(orden_id).await?
+    let orden_trabajo = get_orden_trabajo_by_id(session_token.clone(), orden_id).await?
         .ok_or_else(|| "Orden de trabajo no encontrada".to_string())?;
     
     // Obtener información del equipo
-    let equipo = get_equipo_by_id(orden_trabajo.equipo_id.unwrap_or(0)).await?
+    let equipo = get_equipo_by_id(session_token.clone(), orden_trabajo.equipo_id.unwrap_or(0)).await?
         .ok_or_else(|| "Equipo no encontrado".to_string())?;
     
     // Obtener información del cliente
@@ -718,7 +1261,7 @@ pub async fn send_orden_trabajo_notification(orden_id: i32, sent_by: i32) -> Res
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "SEND_ORDEN_NOTIFICATION",
-        Some(sent_by),
+        Some(usuario.usuario_id),
         "ORDEN_TRABAJO",
         Some(orden_id),
         None,
@@ -732,13 +1275,14 @@ pub async fn send_orden_trabajo_notification(orden_id: i32, sent_by: i32) -> Res
 
 /// Obtener orden de trabajo por informe_id
 #[tauri::command]
-pub async fn get_orden_trabajo_by_informe_id(informe_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+pub async fn get_orden_trabajo_by_informe_id(session_token: String, informe_id: i32) -> Result<Option<OrdenTrabajo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     let orden = sqlx::query_as::<_, OrdenTrabajo>(
-        "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, 
-                has_garantia, equipo_id, cotizacion_id, informe_id, 
-                created_by, created_at 
+        "SELECT orden_id, orden_codigo, orden_desc, prioridad, estado, has_garantia, 
+                equipo_id, created_by, tecnico_id, cotizacion_id, informe_id, pre_informe, created_at, finished_at, 
+                fecha_compromiso, fecha_resolucion 
          FROM ORDEN_TRABAJO 
          WHERE informe_id = ?"
     )
@@ -752,22 +1296,25 @@ pub async fn get_orden_trabajo_by_informe_id(informe_id: i32) -> Result<Option<O
 //Fltros Unificados
 /// Obtener órdenes de trabajo con filtros unificados
 #[tauri::command]
-pub async fn get_ordenes_trabajo_filtradas(filtros: Filtros) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+pub async fn get_ordenes_trabajo_filtradas(session_token: String, filtros: Filtros) -> Result<Vec<OrdenTrabajoDetallada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     let mut query = String::from(
         "SELECT 
             ot.orden_id, ot.orden_codigo, ot.orden_desc, ot.prioridad, ot.estado, 
-            ot.has_garantia, ot.equipo_id, ot.created_by, ot.cotizacion_id, ot.informe_id, 
-            ot.pre_informe, ot.created_at, ot.finished_at,
+            ot.has_garantia, ot.equipo_id, ot.created_by, ot.tecnico_id, ot.cotizacion_id, ot.informe_id, 
+            ot.pre_informe, ot.created_at, ot.finished_at, ot.fecha_compromiso, ot.fecha_resolucion,
             e.numero_serie, e.equipo_marca, e.equipo_modelo, e.equipo_tipo,
             c.cliente_id, c.cliente_nombre,
             u.usuario_nombre as creador_nombre,
+            tec.usuario_nombre as tecnico_nombre,
             cot.cotizacion_codigo, cot.costo_total,
             inf.informe_codigo
          FROM ORDEN_TRABAJO ot
          LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
          LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
          LEFT JOIN USUARIO u ON ot.created_by = u.usuario_id
          LEFT JOIN COTIZACION cot ON ot.cotizacion_id = cot.cotizacion_id
          LEFT JOIN INFORME inf ON ot.informe_id = inf.informe_id
@@ -841,7 +1388,8 @@ pub async fn get_ordenes_trabajo_filtradas(filtros: Filtros) -> Result<Vec<Orden
         .map_err(|e| format!("Database error: {}", e))
 }
 #[tauri::command]
-pub async fn get_modelos_disponibles() -> Result<Vec<String>, String> {
+pub async fn get_modelos_disponibles(session_token: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     let modelos = sqlx::query_scalar::<_, String>(
@@ -854,7 +1402,8 @@ pub async fn get_modelos_disponibles() -> Result<Vec<String>, String> {
     Ok(modelos)
 }
 #[tauri::command]
-pub async fn get_marcas_disponibles() -> Result<Vec<String>, String> {
+pub async fn get_marcas_disponibles(session_token: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     let marcas = sqlx::query_scalar::<_, String>(
@@ -867,7 +1416,8 @@ pub async fn get_marcas_disponibles() -> Result<Vec<String>, String> {
     Ok(marcas)
 }
 #[tauri::command]
-pub async fn get_clientes_disponibles() -> Result<Vec<String>, String> {
+pub async fn get_clientes_disponibles(session_token: String) -> Result<Vec<String>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
     let pool = get_db_pool_safe()?;
     
     let clientes = sqlx::query_scalar::<_, String>(
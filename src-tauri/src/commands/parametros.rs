@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::abandono::PoliticaAbandono;
+use crate::adjuntos::{limite_bytes, limite_correo_bytes, Almacenamiento, CORREO_MAX_MB_POR_DEFECTO, MAX_MB_POR_DEFECTO};
+use crate::aprobacion::{
+    validar_puerto, validar_url_base, validar_validez_horas, PUERTO_POR_DEFECTO, VALIDEZ_HORAS_POR_DEFECTO,
+};
+use crate::calculo_cotizacion::{
+    validar_dias_validez, validar_iva, PoliticaDiferenciaTotal, IVA_POR_DEFECTO, VALIDEZ_DIAS_POR_DEFECTO,
+};
+use crate::documentos_pdf::{validar_nombre_empresa, Empresa};
+use crate::inventario::{
+    validar_destinatario_resumen, validar_dias_consumo, PoliticaStockNegativo, CONSUMO_DIAS_POR_DEFECTO,
+};
+use chrono::{DateTime, Utc};
+
+pub const ABANDONO_DIAS: &str = "abandono_dias";
+pub const ABANDONO_RECORDATORIOS_DIAS: &str = "abandono_recordatorios_dias";
+pub const APROBACION_PUERTO: &str = "aprobacion_puerto";
+pub const APROBACION_URL_BASE: &str = "aprobacion_url_base";
+pub const APROBACION_VALIDEZ_HORAS: &str = "aprobacion_validez_horas";
+pub const ADJUNTOS_ALMACENAMIENTO: &str = "adjuntos_almacenamiento";
+pub const ADJUNTOS_MAX_MB: &str = "adjuntos_max_mb";
+pub const CORREO_ADJUNTOS_MAX_MB: &str = "correo_adjuntos_max_mb";
+pub const COTIZACION_DIFERENCIA_TOTAL: &str = "cotizacion_diferencia_total";
+pub const COTIZACION_VALIDEZ_DIAS: &str = "cotizacion_validez_dias";
+pub const EMPRESA_CORREO: &str = "empresa_correo";
+pub const EMPRESA_DIRECCION: &str = "empresa_direccion";
+pub const EMPRESA_NOMBRE: &str = "empresa_nombre";
+pub const EMPRESA_RUT: &str = "empresa_rut";
+pub const EMPRESA_TELEFONO: &str = "empresa_telefono";
+pub const IVA_PORCENTAJE: &str = "iva_porcentaje";
+pub const STOCK_CONSUMO_DIAS: &str = "stock_consumo_dias";
+pub const STOCK_NEGATIVO: &str = "stock_negativo";
+pub const STOCK_RESUMEN_DESTINATARIO: &str = "stock_resumen_destinatario";
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ParametroSistema {
+    pub parametro_clave: String,
+    pub parametro_valor: String,
+    pub parametro_desc: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Obtener todos los parámetros del sistema
+#[tauri::command]
+pub async fn get_parametros_sistema(session_token: String) -> Result<Vec<ParametroSistema>, String> {
+    require_permission(&session_token, Permiso::Configuracion).await?;
+    let pool = get_db_pool_safe()?;
+
+    let parametros = sqlx::query_as::<_, ParametroSistema>(
+        "SELECT parametro_clave, parametro_valor, parametro_desc, updated_at
+         FROM PARAMETRO_SISTEMA ORDER BY parametro_clave"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(parametros)
+}
+
+/// Actualizar el valor de un parámetro existente
+#[tauri::command]
+pub async fn update_parametro_sistema(session_token: String, clave: String, valor: String) -> Result<ParametroSistema, String> {
+    let usuario = require_permission(&session_token, Permiso::Configuracion).await?;
+    let pool = get_db_pool_safe()?;
+
+    let anterior = obtener_parametro(&clave).await?
+        .ok_or_else(|| format!("Parámetro no encontrado: {}", clave))?;
+
+    validar_parametro(&clave, &valor).await?;
+
+    sqlx::query("UPDATE PARAMETRO_SISTEMA SET parametro_valor = ? WHERE parametro_clave = ?")
+        .bind(&valor)
+        .bind(&clave)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "UPDATE_PARAMETRO",
+        Some(usuario.usuario_id),
+        "PARAMETRO_SISTEMA",
+        None,
+        Some(&format!("{}={}", clave, anterior)),
+        Some(&format!("{}={}", clave, valor))
+    ).await;
+
+    sqlx::query_as::<_, ParametroSistema>(
+        "SELECT parametro_clave, parametro_valor, parametro_desc, updated_at
+         FROM PARAMETRO_SISTEMA WHERE parametro_clave = ?"
+    )
+    .bind(&clave)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Leer el valor de un parámetro sin verificar sesión (uso interno)
+pub(crate) async fn obtener_parametro(clave: &str) -> Result<Option<String>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_scalar::<_, String>(
+        "SELECT parametro_valor FROM PARAMETRO_SISTEMA WHERE parametro_clave = ?"
+    )
+    .bind(clave)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Política de abandono vigente
+pub(crate) async fn obtener_politica_abandono() -> Result<PoliticaAbandono, String> {
+    let dias = obtener_parametro(ABANDONO_DIAS).await?.unwrap_or_else(|| "90".to_string());
+    let recordatorios = obtener_parametro(ABANDONO_RECORDATORIOS_DIAS).await?.unwrap_or_default();
+    PoliticaAbandono::from_parametros(&dias, &recordatorios)
+}
+
+/// Dónde guardar los adjuntos nuevos
+pub(crate) async fn obtener_almacenamiento_adjuntos() -> Result<Almacenamiento, String> {
+    match obtener_parametro(ADJUNTOS_ALMACENAMIENTO).await? {
+        Some(valor) => valor.parse(),
+        None => Ok(Almacenamiento::Archivo),
+    }
+}
+
+/// Tamaño máximo de un adjunto, en bytes
+pub(crate) async fn obtener_limite_adjuntos() -> Result<u64, String> {
+    match obtener_parametro(ADJUNTOS_MAX_MB).await? {
+        Some(valor) => limite_bytes(&valor),
+        None => Ok(MAX_MB_POR_DEFECTO * 1024 * 1024),
+    }
+}
+
+/// Tamaño total que pueden sumar los archivos adjuntos a un correo, en bytes
+pub(crate) async fn obtener_limite_adjuntos_correo() -> Result<u64, String> {
+    match obtener_parametro(CORREO_ADJUNTOS_MAX_MB).await? {
+        Some(valor) => limite_correo_bytes(&valor),
+        None => Ok(CORREO_MAX_MB_POR_DEFECTO * 1024 * 1024),
+    }
+}
+
+/// Qué hacer cuando el total enviado de una cotización no coincide con el calculado
+pub(crate) async fn obtener_politica_diferencia_total() -> Result<PoliticaDiferenciaTotal, String> {
+    match obtener_parametro(COTIZACION_DIFERENCIA_TOTAL).await? {
+        Some(valor) => valor.parse(),
+        None => Ok(PoliticaDiferenciaTotal::Rechazar),
+    }
+}
+
+/// Días de validez con que se emiten las cotizaciones
+pub(crate) async fn obtener_validez_cotizacion() -> Result<i64, String> {
+    match obtener_parametro(COTIZACION_VALIDEZ_DIAS).await? {
+        Some(valor) => validar_dias_validez(&valor),
+        None => Ok(VALIDEZ_DIAS_POR_DEFECTO),
+    }
+}
+
+/// Tasa de IVA vigente para las cotizaciones nuevas
+pub(crate) async fn obtener_iva_porcentaje() -> Result<i32, String> {
+    match obtener_parametro(IVA_PORCENTAJE).await? {
+        Some(valor) => validar_iva(&valor),
+        None => Ok(IVA_POR_DEFECTO),
+    }
+}
+
+/// Horas durante las que se aceptan los enlaces de respuesta a una cotización
+pub(crate) async fn obtener_validez_enlaces() -> Result<i64, String> {
+    match obtener_parametro(APROBACION_VALIDEZ_HORAS).await? {
+        Some(valor) => validar_validez_horas(&valor),
+        None => Ok(VALIDEZ_HORAS_POR_DEFECTO),
+    }
+}
+
+/// Puerto del servidor local de respuestas (0 = desactivado)
+pub(crate) async fn obtener_puerto_aprobacion() -> Result<u16, String> {
+    match obtener_parametro(APROBACION_PUERTO).await? {
+        Some(valor) => validar_puerto(&valor),
+        None => Ok(PUERTO_POR_DEFECTO),
+    }
+}
+
+/// Dirección pública usada para armar los enlaces del correo
+pub(crate) async fn obtener_url_aprobacion() -> Result<String, String> {
+    match obtener_parametro(APROBACION_URL_BASE).await? {
+        Some(valor) => validar_url_base(&valor),
+        None => Ok(format!("http://localhost:{}", PUERTO_POR_DEFECTO)),
+    }
+}
+
+/// Si un movimiento puede dejar el stock de una pieza bajo cero
+pub(crate) async fn obtener_politica_stock_negativo() -> Result<PoliticaStockNegativo, String> {
+    match obtener_parametro(STOCK_NEGATIVO).await? {
+        Some(valor) => valor.parse(),
+        None => Ok(PoliticaStockNegativo::Rechazar),
+    }
+}
+
+/// Días de historial de informes con que se calcula el consumo de las piezas
+pub(crate) async fn obtener_dias_consumo() -> Result<i64, String> {
+    match obtener_parametro(STOCK_CONSUMO_DIAS).await? {
+        Some(valor) => validar_dias_consumo(&valor),
+        None => Ok(CONSUMO_DIAS_POR_DEFECTO),
+    }
+}
+
+/// Quién recibe el resumen diario de stock bajo, si está activado
+pub(crate) async fn obtener_destinatario_resumen_stock() -> Result<Option<String>, String> {
+    match obtener_parametro(STOCK_RESUMEN_DESTINATARIO).await? {
+        Some(valor) => validar_destinatario_resumen(&valor),
+        None => Ok(None),
+    }
+}
+
+/// Datos de la empresa para el encabezado de los documentos
+pub(crate) async fn obtener_empresa() -> Result<Empresa, String> {
+    let nombre = match obtener_parametro(EMPRESA_NOMBRE).await? {
+        Some(valor) => validar_nombre_empresa(&valor)?,
+        None => "Toscanini".to_string(),
+    };
+    let opcional = |valor: Option<String>| valor.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    Ok(Empresa {
+        nombre,
+        rut: opcional(obtener_parametro(EMPRESA_RUT).await?),
+        direccion: opcional(obtener_parametro(EMPRESA_DIRECCION).await?),
+        telefono: opcional(obtener_parametro(EMPRESA_TELEFONO).await?),
+        correo: opcional(obtener_parametro(EMPRESA_CORREO).await?),
+    })
+}
+
+/// Valida el nuevo valor en combinación con el resto de los parámetros relacionados
+async fn validar_parametro(clave: &str, valor: &str) -> Result<(), String> {
+    match clave {
+        ABANDONO_DIAS => {
+            let recordatorios = obtener_parametro(ABANDONO_RECORDATORIOS_DIAS).await?.unwrap_or_default();
+            PoliticaAbandono::from_parametros(valor, &recordatorios).map(|_| ())
+        }
+        ABANDONO_RECORDATORIOS_DIAS => {
+            let dias = obtener_parametro(ABANDONO_DIAS).await?.unwrap_or_else(|| "90".to_string());
+            PoliticaAbandono::from_parametros(&dias, valor).map(|_| ())
+        }
+        APROBACION_PUERTO => validar_puerto(valor).map(|_| ()),
+        APROBACION_URL_BASE => validar_url_base(valor).map(|_| ()),
+        APROBACION_VALIDEZ_HORAS => validar_validez_horas(valor).map(|_| ()),
+        ADJUNTOS_ALMACENAMIENTO => valor.parse::<Almacenamiento>().map(|_| ()),
+        ADJUNTOS_MAX_MB => limite_bytes(valor).map(|_| ()),
+        CORREO_ADJUNTOS_MAX_MB => limite_correo_bytes(valor).map(|_| ()),
+        COTIZACION_DIFERENCIA_TOTAL => valor.parse::<PoliticaDiferenciaTotal>().map(|_| ()),
+        COTIZACION_VALIDEZ_DIAS => validar_dias_validez(valor).map(|_| ()),
+        EMPRESA_NOMBRE => validar_nombre_empresa(valor).map(|_| ()),
+        IVA_PORCENTAJE => validar_iva(valor).map(|_| ()),
+        STOCK_CONSUMO_DIAS => validar_dias_consumo(valor).map(|_| ()),
+        STOCK_NEGATIVO => valor.parse::<PoliticaStockNegativo>().map(|_| ()),
+        STOCK_RESUMEN_DESTINATARIO => validar_destinatario_resumen(valor).map(|_| ()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::precios::{resumir_margen, validar_precio, validar_vigencia, LineaMargen, ResumenMargen, TipoPrecio};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PrecioPieza {
+    pub precio_id: i32,
+    pub pieza_id: i32,
+    pub tipo: String,
+    pub precio: i32,
+    pub vigente_desde: DateTime<Utc>,
+    // NULL mientras sea el precio vigente
+    pub vigente_hasta: Option<DateTime<Utc>>,
+    pub created_by: Option<i32>,
+    pub created_by_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegistrarPrecioRequest {
+    pub tipo: TipoPrecio,
+    pub precio: i32,
+    // Si no se indica, el precio rige desde ahora
+    pub vigente_desde: Option<DateTime<Utc>>,
+}
+
+/// Resultado de registrar un precio que difiere del vigente
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CambioPrecio {
+    pub anterior: Option<i32>,
+    pub nuevo: i32,
+}
+
+/// Pieza de un informe valorizada con los precios vigentes al usarla
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct LineaMargenInforme {
+    pub informe_id: i32,
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub cantidad: i32,
+    pub usado_at: Option<DateTime<Utc>>,
+    pub mes: String,
+    pub precio_venta: Option<i32>,
+    pub costo: Option<i32>,
+    #[sqlx(skip)]
+    pub ingreso: Option<i64>,
+    #[sqlx(skip)]
+    pub costo_total: Option<i64>,
+    #[sqlx(skip)]
+    pub margen: Option<i64>,
+}
+
+impl LineaMargenInforme {
+    fn linea_margen(&self) -> LineaMargen {
+        LineaMargen {
+            pieza_id: self.pieza_id,
+            cantidad: self.cantidad,
+            precio_venta: self.precio_venta,
+            costo: self.costo,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MargenInforme {
+    pub informe_id: i32,
+    pub informe_codigo: Option<String>,
+    pub lineas: Vec<LineaMargenInforme>,
+    #[serde(flatten)]
+    pub resumen: ResumenMargen,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MargenMensual {
+    // Formato YYYY-MM
+    pub mes: String,
+    pub informes: usize,
+    #[serde(flatten)]
+    pub resumen: ResumenMargen,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReporteMargenMensual {
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+    pub meses: Vec<MargenMensual>,
+    pub total: ResumenMargen,
+}
+
+// Cada línea toma el último precio de cada tipo que empezó a regir antes de usarse la pieza
+const SELECT_LINEAS_MARGEN: &str =
+    "SELECT pi.informe_id, pi.pieza_id, p.pieza_nombre, COALESCE(pi.cantidad, 0) as cantidad,
+            COALESCE(pi.usado_at, i.created_at) as usado_at,
+            DATE_FORMAT(COALESCE(pi.usado_at, i.created_at), '%Y-%m') as mes,
+            (SELECT pp.precio FROM PRECIO_PIEZA pp
+             WHERE pp.pieza_id = pi.pieza_id AND pp.tipo = 'venta'
+               AND pp.vigente_desde <= COALESCE(pi.usado_at, i.created_at)
+             ORDER BY pp.vigente_desde DESC LIMIT 1) as precio_venta,
+            (SELECT pp.precio FROM PRECIO_PIEZA pp
+             WHERE pp.pieza_id = pi.pieza_id AND pp.tipo = 'costo'
+               AND pp.vigente_desde <= COALESCE(pi.usado_at, i.created_at)
+             ORDER BY pp.vigente_desde DESC LIMIT 1) as costo
+     FROM PIEZAS_INFORME pi
+     INNER JOIN INFORME i ON pi.informe_id = i.informe_id
+     INNER JOIN PIEZA p ON pi.pieza_id = p.pieza_id";
+
+/// Obtener el historial de precios de una pieza, del más reciente al más antiguo.
+/// Los costos solo los ve quien tiene acceso a compras.
+#[tauri::command]
+pub async fn get_historial_precios_pieza(session_token: String, pieza_id: i32, tipo: Option<TipoPrecio>) -> Result<Vec<PrecioPieza>, String> {
+    let permiso = match tipo {
+        Some(TipoPrecio::Venta) => Permiso::VerPiezas,
+        _ => Permiso::VerCompras,
+    };
+    require_permission(&session_token, permiso).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, PrecioPieza>(
+        "SELECT pp.precio_id, pp.pieza_id, pp.tipo, pp.precio, pp.vigente_desde, pp.vigente_hasta,
+                pp.created_by, u.usuario_nombre as created_by_nombre, pp.created_at
+         FROM PRECIO_PIEZA pp
+         LEFT JOIN USUARIO u ON pp.created_by = u.usuario_id
+         WHERE pp.pieza_id = ? AND (? IS NULL OR pp.tipo = ?)
+         ORDER BY pp.vigente_desde DESC, pp.precio_id DESC"
+    )
+    .bind(pieza_id)
+    .bind(tipo.map(|t| t.as_str()))
+    .bind(tipo.map(|t| t.as_str()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Registrar un nuevo precio de venta o costo para una pieza. El precio
+/// anterior queda cerrado en el historial con la fecha de inicio del nuevo.
+#[tauri::command]
+pub async fn registrar_precio_pieza(session_token: String, pieza_id: i32, request: RegistrarPrecioRequest) -> Result<Vec<PrecioPieza>, String> {
+    let permiso = match request.tipo {
+        TipoPrecio::Venta => Permiso::GestionarPiezas,
+        TipoPrecio::Costo => Permiso::GestionarCompras,
+    };
+    let usuario = require_permission(&session_token, permiso).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    let cambio = registrar_precio(
+        &mut tx, pieza_id, request.tipo, request.precio, request.vigente_desde, Some(usuario.usuario_id)
+    ).await?;
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(cambio) = cambio {
+        // Registrar la acción en el log de auditoría: tipo|precio
+        let _ = log_action(
+            "REGISTRAR_PRECIO_PIEZA",
+            Some(usuario.usuario_id),
+            "PIEZA",
+            Some(pieza_id),
+            cambio.anterior.map(|p| format!("{}|{}", request.tipo, p)).as_deref(),
+            Some(&format!("{}|{}", request.tipo, cambio.nuevo))
+        ).await;
+    }
+
+    get_historial_precios_pieza(session_token, pieza_id, Some(request.tipo)).await
+}
+
+/// Obtener el margen de las piezas usadas en un informe
+#[tauri::command]
+pub async fn get_margen_informe(session_token: String, informe_id: i32) -> Result<MargenInforme, String> {
+    require_permission(&session_token, Permiso::VerMargenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let informe_codigo = sqlx::query_scalar::<_, Option<String>>("SELECT informe_codigo FROM INFORME WHERE informe_id = ?")
+        .bind(informe_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Informe no encontrado".to_string())?;
+
+    let mut lineas = sqlx::query_as::<_, LineaMargenInforme>(&format!(
+        "{} WHERE pi.informe_id = ? ORDER BY p.pieza_nombre", SELECT_LINEAS_MARGEN
+    ))
+    .bind(informe_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let resumen = resumir_margen(&lineas.iter().map(LineaMargenInforme::linea_margen).collect::<Vec<_>>());
+    for linea in &mut lineas {
+        let calculo = linea.linea_margen();
+        linea.ingreso = calculo.ingreso();
+        linea.costo_total = calculo.costo_total();
+        linea.margen = calculo.margen();
+    }
+
+    Ok(MargenInforme { informe_id, informe_codigo, lineas, resumen })
+}
+
+/// Obtener el margen de piezas por mes entre dos fechas (inclusive). Solo
+/// cuenta informes que no son borrador, y de un informe enmendado solo su
+/// enmienda finalizada, para no contar dos veces las mismas piezas.
+#[tauri::command]
+pub async fn get_margen_mensual(session_token: String, desde: NaiveDate, hasta: NaiveDate) -> Result<ReporteMargenMensual, String> {
+    require_permission(&session_token, Permiso::VerMargenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    if desde > hasta {
+        return Err("La fecha de inicio debe ser anterior a la fecha de término".to_string());
+    }
+    let hasta_exclusivo = hasta.checked_add_days(Days::new(1))
+        .ok_or_else(|| "Fecha de término no válida".to_string())?;
+
+    let lineas = sqlx::query_as::<_, LineaMargenInforme>(&format!(
+        "{} WHERE COALESCE(i.is_borrador, FALSE) = FALSE
+             AND COALESCE(pi.usado_at, i.created_at) >= ? AND COALESCE(pi.usado_at, i.created_at) < ?
+             AND NOT EXISTS (SELECT 1 FROM INFORME a WHERE a.enmienda_de = i.informe_id AND a.finalizado_at IS NOT NULL)",
+        SELECT_LINEAS_MARGEN
+    ))
+    .bind(desde)
+    .bind(hasta_exclusivo)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut por_mes: BTreeMap<&str, (HashSet<i32>, Vec<LineaMargen>)> = BTreeMap::new();
+    for linea in &lineas {
+        let (informes, lineas_mes) = por_mes.entry(linea.mes.as_str()).or_default();
+        informes.insert(linea.informe_id);
+        lineas_mes.push(linea.linea_margen());
+    }
+
+    let meses = por_mes.into_iter()
+        .map(|(mes, (informes, lineas_mes))| MargenMensual {
+            mes: mes.to_string(),
+            informes: informes.len(),
+            resumen: resumir_margen(&lineas_mes),
+        })
+        .collect();
+    let total = resumir_margen(&lineas.iter().map(LineaMargenInforme::linea_margen).collect::<Vec<_>>());
+
+    Ok(ReporteMargenMensual { desde, hasta, meses, total })
+}
+
+/// Cierra el precio vigente del tipo indicado y abre uno nuevo. Si el precio
+/// no cambia no se registra nada y se devuelve None. Los precios de venta se
+/// copian además a PIEZA.pieza_precio, que sigue siendo el precio actual.
+pub(crate) async fn registrar_precio(
+    tx: &mut Transaction<'_, MySql>,
+    pieza_id: i32,
+    tipo: TipoPrecio,
+    precio: i32,
+    vigente_desde: Option<DateTime<Utc>>,
+    usuario_id: Option<i32>,
+) -> Result<Option<CambioPrecio>, String> {
+    validar_precio(tipo, precio)?;
+
+    // Bloquear la pieza serializa los cambios de precio concurrentes
+    sqlx::query_scalar::<_, i32>("SELECT pieza_id FROM PIEZA WHERE pieza_id = ? FOR UPDATE")
+        .bind(pieza_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| "Pieza no encontrada".to_string())?;
+
+    let vigente = sqlx::query_as::<_, (i32, i32, DateTime<Utc>)>(
+        "SELECT precio_id, precio, vigente_desde FROM PRECIO_PIEZA
+         WHERE pieza_id = ? AND tipo = ? AND vigente_hasta IS NULL
+         ORDER BY vigente_desde DESC LIMIT 1"
+    )
+    .bind(pieza_id)
+    .bind(tipo.as_str())
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if vigente.is_some_and(|(_, actual, _)| actual == precio) {
+        return Ok(None);
+    }
+
+    // TIMESTAMP guarda segundos; se trunca para comparar igual que en la base
+    let ahora = DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap_or_else(Utc::now);
+    let desde = vigente_desde.unwrap_or(ahora);
+    validar_vigencia(desde, vigente.map(|(_, _, desde)| desde), ahora)?;
+
+    if let Some((precio_id, _, _)) = vigente {
+        sqlx::query("UPDATE PRECIO_PIEZA SET vigente_hasta = ? WHERE precio_id = ?")
+            .bind(desde)
+            .bind(precio_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    sqlx::query(
+        "INSERT INTO PRECIO_PIEZA (pieza_id, tipo, precio, vigente_desde, created_by) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(pieza_id)
+    .bind(tipo.as_str())
+    .bind(precio)
+    .bind(desde)
+    .bind(usuario_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if tipo == TipoPrecio::Venta {
+        sqlx::query("UPDATE PIEZA SET pieza_precio = ? WHERE pieza_id = ?")
+            .bind(precio)
+            .bind(pieza_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    Ok(Some(CambioPrecio { anterior: vigente.map(|(_, precio, _)| precio), nuevo: precio }))
+}
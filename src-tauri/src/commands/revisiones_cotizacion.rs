@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::calculo_cotizacion::{diferencias_lineas, CambioLinea, LineaCotizacion};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CotizacionRevision {
+    pub cotizacion_id: i32,
+    pub revision: i32,
+    pub costo_revision: Option<i32>,
+    pub costo_reparacion: Option<i32>,
+    pub subtotal_piezas: Option<i32>,
+    pub descuento_global_pct: Option<i32>,
+    pub iva_porcentaje: Option<i32>,
+    pub monto_descuento: Option<i32>,
+    pub monto_neto: Option<i32>,
+    pub monto_exento: Option<i32>,
+    pub monto_iva: Option<i32>,
+    pub costo_total: Option<i32>,
+    pub informe: String,
+    // Autor y fecha de la revisión
+    pub created_by: Option<i32>,
+    pub created_by_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    pub es_vigente: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PiezaRevision {
+    pub pieza_id: i32,
+    pub cantidad: Option<i32>,
+    pub precio_unitario: Option<i32>,
+    pub descuento_pct: Option<i32>,
+    pub es_exenta: Option<bool>,
+    pub pieza_nombre: Option<String>,
+    pub pieza_marca: Option<String>,
+}
+
+impl PiezaRevision {
+    fn linea(&self) -> LineaCotizacion {
+        LineaCotizacion {
+            pieza_id: self.pieza_id,
+            cantidad: self.cantidad.unwrap_or(1),
+            precio_unitario: self.precio_unitario.unwrap_or(0),
+            descuento_pct: self.descuento_pct.unwrap_or(0),
+            es_exenta: self.es_exenta.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CotizacionRevisionDetallada {
+    pub revision: CotizacionRevision,
+    pub piezas: Vec<PiezaRevision>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CambioCampo {
+    pub campo: String,
+    pub antes: Option<String>,
+    pub despues: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiferenciasRevision {
+    pub cotizacion_id: i32,
+    pub revision_desde: i32,
+    pub revision_hasta: i32,
+    pub campos: Vec<CambioCampo>,
+    pub piezas: Vec<CambioLinea>,
+}
+
+const SELECT_REVISION_VIGENTE: &str =
+    "SELECT c.cotizacion_id, c.revision, c.costo_revision, c.costo_reparacion, c.subtotal_piezas,
+            c.descuento_global_pct, c.iva_porcentaje, c.monto_descuento, c.monto_neto, c.monto_exento,
+            c.monto_iva, c.costo_total, c.informe,
+            COALESCE(c.revision_by, c.created_by) as created_by,
+            u.usuario_nombre as created_by_nombre,
+            COALESCE(c.revision_at, c.created_at) as created_at
+     FROM COTIZACION c
+     LEFT JOIN USUARIO u ON u.usuario_id = COALESCE(c.revision_by, c.created_by)";
+
+const SELECT_REVISION_ARCHIVADA: &str =
+    "SELECT r.cotizacion_id, r.revision, r.costo_revision, r.costo_reparacion, r.subtotal_piezas,
+            r.descuento_global_pct, r.iva_porcentaje, r.monto_descuento, r.monto_neto, r.monto_exento,
+            r.monto_iva, r.costo_total, r.informe, r.created_by,
+            u.usuario_nombre as created_by_nombre, r.created_at
+     FROM COTIZACION_REVISION r
+     LEFT JOIN USUARIO u ON r.created_by = u.usuario_id";
+
+/// Obtener todas las revisiones de una cotización, de la primera a la vigente
+#[tauri::command]
+pub async fn get_revisiones_cotizacion(session_token: String, cotizacion_id: i32) -> Result<Vec<CotizacionRevision>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    let pool = get_db_pool_safe()?;
+
+    let mut revisiones = sqlx::query_as::<_, CotizacionRevision>(
+        &format!("{} WHERE r.cotizacion_id = ? ORDER BY r.revision ASC", SELECT_REVISION_ARCHIVADA)
+    )
+    .bind(cotizacion_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(mut vigente) = fetch_revision_vigente(cotizacion_id).await? {
+        vigente.es_vigente = true;
+        revisiones.push(vigente);
+    }
+
+    Ok(revisiones)
+}
+
+/// Obtener una revisión de una cotización con sus piezas
+#[tauri::command]
+pub async fn get_revision_cotizacion(session_token: String, cotizacion_id: i32, revision: i32) -> Result<Option<CotizacionRevisionDetallada>, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+    fetch_revision(cotizacion_id, revision).await
+}
+
+/// Comparar dos revisiones de una cotización
+#[tauri::command]
+pub async fn diff_revisiones_cotizacion(session_token: String, cotizacion_id: i32, revision_desde: i32, revision_hasta: i32) -> Result<DiferenciasRevision, String> {
+    require_permission(&session_token, Permiso::VerCotizaciones).await?;
+
+    let desde = fetch_revision(cotizacion_id, revision_desde).await?
+        .ok_or_else(|| format!("Revisión {} no encontrada", revision_desde))?;
+    let hasta = fetch_revision(cotizacion_id, revision_hasta).await?
+        .ok_or_else(|| format!("Revisión {} no encontrada", revision_hasta))?;
+
+    let (a, b) = (&desde.revision, &hasta.revision);
+    let mut campos = Vec::new();
+    comparar(&mut campos, "costo_revision", &a.costo_revision, &b.costo_revision);
+    comparar(&mut campos, "costo_reparacion", &a.costo_reparacion, &b.costo_reparacion);
+    comparar(&mut campos, "descuento_global_pct", &a.descuento_global_pct, &b.descuento_global_pct);
+    comparar(&mut campos, "iva_porcentaje", &a.iva_porcentaje, &b.iva_porcentaje);
+    comparar(&mut campos, "subtotal_piezas", &a.subtotal_piezas, &b.subtotal_piezas);
+    comparar(&mut campos, "monto_descuento", &a.monto_descuento, &b.monto_descuento);
+    comparar(&mut campos, "monto_neto", &a.monto_neto, &b.monto_neto);
+    comparar(&mut campos, "monto_exento", &a.monto_exento, &b.monto_exento);
+    comparar(&mut campos, "monto_iva", &a.monto_iva, &b.monto_iva);
+    comparar(&mut campos, "costo_total", &a.costo_total, &b.costo_total);
+    comparar(&mut campos, "informe", &Some(&a.informe), &Some(&b.informe));
+
+    let lineas_desde: Vec<LineaCotizacion> = desde.piezas.iter().map(PiezaRevision::linea).collect();
+    let lineas_hasta: Vec<LineaCotizacion> = hasta.piezas.iter().map(PiezaRevision::linea).collect();
+
+    Ok(DiferenciasRevision {
+        cotizacion_id,
+        revision_desde,
+        revision_hasta,
+        campos,
+        piezas: diferencias_lineas(&lineas_desde, &lineas_hasta),
+    })
+}
+
+/// Archiva la versión vigente de una cotización ya enviada y abre la siguiente
+/// revisión. Las cotizaciones en borrador se editan sin generar revisiones.
+/// Devuelve el número de la nueva revisión, si se creó una.
+pub(crate) async fn abrir_nueva_revision(tx: &mut Transaction<'_, MySql>, cotizacion_id: i32, usuario_id: i32) -> Result<Option<i32>, String> {
+    #[derive(Debug, sqlx::FromRow)]
+    struct EstadoRevision {
+        is_borrador: Option<bool>,
+        revision: i32,
+    }
+
+    let estado = sqlx::query_as::<_, EstadoRevision>(
+        "SELECT is_borrador, revision FROM COTIZACION WHERE cotizacion_id = ? FOR UPDATE"
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    if estado.is_borrador.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    sqlx::query(
+        "INSERT INTO COTIZACION_REVISION (cotizacion_id, revision, costo_revision, costo_reparacion,
+                subtotal_piezas, descuento_global_pct, iva_porcentaje, monto_descuento, monto_neto,
+                monto_exento, monto_iva, costo_total, informe, created_by, created_at)
+         SELECT cotizacion_id, revision, costo_revision, costo_reparacion,
+                subtotal_piezas, descuento_global_pct, iva_porcentaje, monto_descuento, monto_neto,
+                monto_exento, monto_iva, costo_total, informe,
+                COALESCE(revision_by, created_by), COALESCE(revision_at, created_at)
+         FROM COTIZACION WHERE cotizacion_id = ?"
+    )
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO PIEZAS_COTIZACION_REVISION (cotizacion_id, revision, pieza_id, cantidad,
+                precio_unitario, descuento_pct, es_exenta)
+         SELECT pc.cotizacion_id, ?, pc.pieza_id, pc.cantidad, pc.precio_unitario, pc.descuento_pct, pc.es_exenta
+         FROM PIEZAS_COTIZACION pc WHERE pc.cotizacion_id = ?"
+    )
+    .bind(estado.revision)
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let nueva = estado.revision + 1;
+    sqlx::query(
+        "UPDATE COTIZACION SET revision = ?, revision_by = ?, revision_at = CURRENT_TIMESTAMP WHERE cotizacion_id = ?"
+    )
+    .bind(nueva)
+    .bind(usuario_id)
+    .bind(cotizacion_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(Some(nueva))
+}
+
+/// Deja constancia en auditoría de una nueva revisión
+pub(crate) async fn registrar_nueva_revision(usuario_id: i32, cotizacion_id: i32, revision: Option<i32>) {
+    if let Some(revision) = revision {
+        let _ = log_action(
+            "CREATE_REVISION_COTIZACION",
+            Some(usuario_id),
+            "COTIZACION",
+            Some(cotizacion_id),
+            Some(&format!("Revisión {}", revision - 1)),
+            Some(&format!("Revisión {}", revision))
+        ).await;
+    }
+}
+
+async fn fetch_revision_vigente(cotizacion_id: i32) -> Result<Option<CotizacionRevision>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, CotizacionRevision>(
+        &format!("{} WHERE c.cotizacion_id = ?", SELECT_REVISION_VIGENTE)
+    )
+    .bind(cotizacion_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Busca la revisión en la cotización vigente o, si es anterior, en el archivo
+async fn fetch_revision(cotizacion_id: i32, revision: i32) -> Result<Option<CotizacionRevisionDetallada>, String> {
+    let pool = get_db_pool_safe()?;
+
+    if let Some(mut vigente) = fetch_revision_vigente(cotizacion_id).await? {
+        if vigente.revision == revision {
+            vigente.es_vigente = true;
+            let piezas = sqlx::query_as::<_, PiezaRevision>(
+                "SELECT pc.pieza_id, pc.cantidad, pc.precio_unitario, pc.descuento_pct, pc.es_exenta,
+                        p.pieza_nombre, p.pieza_marca
+                 FROM PIEZAS_COTIZACION pc
+                 LEFT JOIN PIEZA p ON pc.pieza_id = p.pieza_id
+                 WHERE pc.cotizacion_id = ?
+                 ORDER BY pc.pieza_id"
+            )
+            .bind(cotizacion_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+            return Ok(Some(CotizacionRevisionDetallada { revision: vigente, piezas }));
+        }
+    }
+
+    let archivada = sqlx::query_as::<_, CotizacionRevision>(
+        &format!("{} WHERE r.cotizacion_id = ? AND r.revision = ?", SELECT_REVISION_ARCHIVADA)
+    )
+    .bind(cotizacion_id)
+    .bind(revision)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let archivada = match archivada {
+        Some(archivada) => archivada,
+        None => return Ok(None),
+    };
+
+    let piezas = sqlx::query_as::<_, PiezaRevision>(
+        "SELECT pr.pieza_id, pr.cantidad, pr.precio_unitario, pr.descuento_pct, pr.es_exenta,
+                p.pieza_nombre, p.pieza_marca
+         FROM PIEZAS_COTIZACION_REVISION pr
+         LEFT JOIN PIEZA p ON pr.pieza_id = p.pieza_id
+         WHERE pr.cotizacion_id = ? AND pr.revision = ?
+         ORDER BY pr.pieza_id"
+    )
+    .bind(cotizacion_id)
+    .bind(revision)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(Some(CotizacionRevisionDetallada { revision: archivada, piezas }))
+}
+
+fn comparar<T: ToString + PartialEq>(cambios: &mut Vec<CambioCampo>, campo: &str, antes: &Option<T>, despues: &Option<T>) {
+    if antes != despues {
+        cambios.push(CambioCampo {
+            campo: campo.to_string(),
+            antes: antes.as_ref().map(T::to_string),
+            despues: despues.as_ref().map(T::to_string),
+        });
+    }
+}
@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::inventario::{validar_series, EstadoUnidad};
+use chrono::{DateTime, Utc};
+
+/// Unidad de una pieza con número de serie y, si está instalada, el informe,
+/// la orden y el equipo donde quedó. El informe es donde se instaló; la orden
+/// se busca siguiendo sus enmiendas, que pasan a ser el informe de la orden.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UnidadPieza {
+    pub unidad_id: i32,
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub pieza_marca: Option<String>,
+    pub numero_serie: String,
+    pub estado: String,
+    pub orden_compra_id: Option<i32>,
+    pub orden_compra_codigo: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub informe_id: Option<i32>,
+    pub informe_codigo: Option<String>,
+    pub instalada_at: Option<DateTime<Utc>>,
+    pub orden_id: Option<i32>,
+    pub orden_codigo: Option<String>,
+    pub equipo_id: Option<i32>,
+    pub equipo_numero_serie: Option<String>,
+    pub equipo_marca: Option<String>,
+    pub equipo_modelo: Option<String>,
+    pub cliente_nombre: Option<String>,
+}
+
+const SELECT_UNIDAD_PIEZA: &str =
+    "SELECT u.unidad_id, u.pieza_id, p.pieza_nombre, p.pieza_marca, u.numero_serie, u.estado,
+            u.orden_compra_id, oc.orden_compra_codigo, u.created_at,
+            u.informe_id, i.informe_codigo, u.instalada_at,
+            ot.orden_id, ot.orden_codigo, e.equipo_id, e.numero_serie as equipo_numero_serie,
+            e.equipo_marca, e.equipo_modelo, cl.cliente_nombre
+     FROM UNIDAD_PIEZA u
+     INNER JOIN PIEZA p ON u.pieza_id = p.pieza_id
+     LEFT JOIN ORDEN_COMPRA oc ON u.orden_compra_id = oc.orden_compra_id
+     LEFT JOIN INFORME i ON u.informe_id = i.informe_id
+     LEFT JOIN ORDEN_TRABAJO ot ON ot.informe_id IN
+         (SELECT c.informe_id FROM INFORME_CADENA c WHERE c.ancestro_id = u.informe_id)
+     LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+     LEFT JOIN CLIENTE cl ON e.cliente_id = cl.cliente_id";
+
+/// Obtener las unidades registradas de una pieza, opcionalmente por estado
+#[tauri::command]
+pub async fn get_unidades_pieza(session_token: String, pieza_id: i32, estado: Option<EstadoUnidad>) -> Result<Vec<UnidadPieza>, String> {
+    require_permission(&session_token, Permiso::VerPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, UnidadPieza>(&format!(
+        "{} WHERE u.pieza_id = ? AND (? IS NULL OR u.estado = ?) ORDER BY u.numero_serie",
+        SELECT_UNIDAD_PIEZA
+    ))
+    .bind(pieza_id)
+    .bind(estado.map(|e| e.as_str()))
+    .bind(estado.map(|e| e.as_str()))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Registrar los números de serie de unidades que ya están en stock. En una
+/// pieza sin control por serie hay que indicar las series de todo su stock, y
+/// al registrarlas queda activado el control.
+#[tauri::command]
+pub async fn registrar_series_pieza(session_token: String, pieza_id: i32, series: Vec<String>) -> Result<Vec<UnidadPieza>, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarPiezas).await?;
+    let pool = get_db_pool_safe()?;
+
+    if series.is_empty() {
+        return Err("Debe indicar al menos un número de serie".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let (nombre, requiere_serie, stock_actual) = sqlx::query_as::<_, (Option<String>, bool, Option<i32>)>(
+        "SELECT pieza_nombre, requiere_serie, stock_actual FROM PIEZA WHERE pieza_id = ? FOR UPDATE"
+    )
+    .bind(pieza_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Pieza no encontrada".to_string())?;
+    let nombre = nombre.unwrap_or_else(|| format!("Pieza {}", pieza_id));
+    let series = if requiere_serie {
+        validar_series(&nombre, &series, series.len() as i32)?
+    } else {
+        validar_series(&nombre, &series, stock_actual.unwrap_or(0))?
+    };
+
+    // No puede haber más series disponibles que unidades en bodega
+    let disponibles = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM UNIDAD_PIEZA WHERE pieza_id = ? AND estado = ?"
+    )
+    .bind(pieza_id)
+    .bind(EstadoUnidad::Disponible.as_str())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+    let stock_actual = i64::from(stock_actual.unwrap_or(0));
+    if disponibles + series.len() as i64 > stock_actual {
+        return Err(format!(
+            "{} tiene {} unidades en stock y {} ya tienen número de serie registrado",
+            nombre, stock_actual, disponibles
+        ));
+    }
+
+    registrar_unidades(&mut tx, pieza_id, &series, None, Some(usuario.usuario_id)).await?;
+    if !requiere_serie {
+        sqlx::query("UPDATE PIEZA SET requiere_serie = TRUE WHERE pieza_id = ?")
+            .bind(pieza_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    if !requiere_serie {
+        let _ = log_action(
+            "ACTIVAR_SERIE_PIEZA",
+            Some(usuario.usuario_id),
+            "PIEZA",
+            Some(pieza_id),
+            None,
+            Some(&format!("{} unidades en stock", series.len()))
+        ).await;
+    }
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "REGISTRAR_SERIES_PIEZA",
+        Some(usuario.usuario_id),
+        "PIEZA",
+        Some(pieza_id),
+        None,
+        Some(&series.join(","))
+    ).await;
+
+    get_unidades_pieza(session_token, pieza_id, Some(EstadoUnidad::Disponible)).await
+}
+
+/// Trazabilidad desde el número de serie de una pieza: de qué compra vino y
+/// en qué equipo y orden de trabajo quedó instalada
+#[tauri::command]
+pub async fn get_trazabilidad_serie(session_token: String, numero_serie: String) -> Result<Vec<UnidadPieza>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, UnidadPieza>(&format!(
+        "{} WHERE u.numero_serie = ? ORDER BY p.pieza_nombre", SELECT_UNIDAD_PIEZA
+    ))
+    .bind(numero_serie.trim())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Unidades con número de serie instaladas en un equipo, por su número de
+/// serie, a lo largo de todas sus órdenes de trabajo
+#[tauri::command]
+pub async fn get_unidades_equipo(session_token: String, numero_serie: String) -> Result<Vec<UnidadPieza>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, UnidadPieza>(&format!(
+        "{} WHERE e.numero_serie = ? AND u.estado = ? ORDER BY u.instalada_at DESC, p.pieza_nombre",
+        SELECT_UNIDAD_PIEZA
+    ))
+    .bind(numero_serie.trim())
+    .bind(EstadoUnidad::Instalada.as_str())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Unidades con número de serie instaladas en una orden de trabajo
+#[tauri::command]
+pub async fn get_unidades_orden(session_token: String, orden_id: i32) -> Result<Vec<UnidadPieza>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, UnidadPieza>(&format!(
+        "{} WHERE ot.orden_id = ? AND u.estado = ? ORDER BY p.pieza_nombre, u.numero_serie",
+        SELECT_UNIDAD_PIEZA
+    ))
+    .bind(orden_id)
+    .bind(EstadoUnidad::Instalada.as_str())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Rechaza activar o desactivar el control por serie cuando dejaría stock sin
+/// series o series sin control. Bloquea la fila de la pieza.
+pub(crate) async fn verificar_cambio_control_serie(tx: &mut Transaction<'_, MySql>, pieza_id: i32, requiere_serie: bool) -> Result<(), String> {
+    let (nombre, actual, stock_actual) = sqlx::query_as::<_, (Option<String>, bool, Option<i32>)>(
+        "SELECT pieza_nombre, requiere_serie, stock_actual FROM PIEZA WHERE pieza_id = ? FOR UPDATE"
+    )
+    .bind(pieza_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| "Pieza no encontrada".to_string())?;
+    let nombre = nombre.unwrap_or_else(|| format!("Pieza {}", pieza_id));
+    let stock_actual = stock_actual.unwrap_or(0);
+
+    if requiere_serie && !actual && stock_actual > 0 {
+        return Err(format!(
+            "{} tiene {} unidades en stock sin número de serie; para activar el control por serie registre sus series con registrar_series_pieza",
+            nombre, stock_actual
+        ));
+    }
+    if !requiere_serie && actual {
+        let en_bodega = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM UNIDAD_PIEZA WHERE pieza_id = ? AND estado = ?"
+        )
+        .bind(pieza_id)
+        .bind(EstadoUnidad::Disponible.as_str())
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+        if en_bodega > 0 {
+            return Err(format!(
+                "{} tiene {} unidades con número de serie en bodega; no se puede desactivar el control por serie",
+                nombre, en_bodega
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Nombre de la pieza y si se controla por número de serie
+pub(crate) async fn pieza_requiere_serie(tx: &mut Transaction<'_, MySql>, pieza_id: i32) -> Result<(String, bool), String> {
+    let (nombre, requiere_serie) = sqlx::query_as::<_, (Option<String>, bool)>(
+        "SELECT pieza_nombre, requiere_serie FROM PIEZA WHERE pieza_id = ?"
+    )
+    .bind(pieza_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?
+    .ok_or_else(|| format!("Pieza {} no encontrada", pieza_id))?;
+
+    Ok((nombre.unwrap_or_else(|| format!("Pieza {}", pieza_id)), requiere_serie))
+}
+
+/// Da de alta unidades disponibles con las series indicadas (ya validadas)
+pub(crate) async fn registrar_unidades(
+    tx: &mut Transaction<'_, MySql>,
+    pieza_id: i32,
+    series: &[String],
+    orden_compra_id: Option<i32>,
+    usuario_id: Option<i32>,
+) -> Result<(), String> {
+    for serie in series {
+        sqlx::query(
+            "INSERT INTO UNIDAD_PIEZA (pieza_id, numero_serie, estado, orden_compra_id, created_by)
+             VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(pieza_id)
+        .bind(serie)
+        .bind(EstadoUnidad::Disponible.as_str())
+        .bind(orden_compra_id)
+        .bind(usuario_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(db) if db.is_unique_violation() => format!("El número de serie {} ya está registrado para la pieza", serie),
+            _ => format!("Database error: {}", e),
+        })?;
+    }
+    Ok(())
+}
+
+/// Valida las series de una línea del informe y las deja instaladas en él.
+/// Las piezas sin control por serie no aceptan series.
+pub(crate) async fn asignar_series_linea(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+    pieza_id: i32,
+    cantidad: i32,
+    series: Option<&[String]>,
+) -> Result<(), String> {
+    let (nombre, requiere_serie) = pieza_requiere_serie(tx, pieza_id).await?;
+
+    match (requiere_serie, series) {
+        (false, None) => Ok(()),
+        (false, Some([])) => Ok(()),
+        (false, Some(_)) => Err(format!("{} no se controla por número de serie", nombre)),
+        (true, None) => Err(format!("Indique los números de serie de {} usados en el informe", nombre)),
+        (true, Some(series)) => {
+            let series = validar_series(&nombre, series, cantidad)?;
+            asignar_series_informe(tx, informe_id, pieza_id, &nombre, &series).await
+        }
+    }
+}
+
+/// Deja instaladas en el informe exactamente las unidades indicadas; las que
+/// tenía antes y ya no figuran vuelven a quedar disponibles. En una enmienda
+/// las unidades heredadas que se mantienen siguen ligadas al original.
+async fn asignar_series_informe(
+    tx: &mut Transaction<'_, MySql>,
+    informe_id: i32,
+    pieza_id: i32,
+    nombre: &str,
+    series: &[String],
+) -> Result<(), String> {
+    let cadena = sqlx::query_scalar::<_, i32>("SELECT ancestro_id FROM INFORME_CADENA WHERE informe_id = ?")
+        .bind(informe_id)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    let actuales = sqlx::query_scalar::<_, i32>(
+        "SELECT unidad_id FROM UNIDAD_PIEZA
+         WHERE informe_id IN (SELECT ancestro_id FROM INFORME_CADENA WHERE informe_id = ?) AND pieza_id = ?
+         FOR UPDATE"
+    )
+    .bind(informe_id)
+    .bind(pieza_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut elegidas = Vec::with_capacity(series.len());
+    for serie in series {
+        let (unidad_id, estado, instalada_en) = sqlx::query_as::<_, (i32, String, Option<i32>)>(
+            "SELECT unidad_id, estado, informe_id FROM UNIDAD_PIEZA WHERE pieza_id = ? AND numero_serie = ? FOR UPDATE"
+        )
+        .bind(pieza_id)
+        .bind(serie)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("El número de serie {} no está registrado para {}", serie, nombre))?;
+
+        let en_la_cadena = instalada_en.is_some_and(|id| cadena.contains(&id));
+        if estado.parse::<EstadoUnidad>()? == EstadoUnidad::Instalada && !en_la_cadena {
+            return Err(format!("La unidad {} de {} ya está instalada en otro equipo", serie, nombre));
+        }
+        elegidas.push(unidad_id);
+    }
+
+    for unidad_id in actuales.iter().filter(|id| !elegidas.contains(id)) {
+        sqlx::query(
+            "UPDATE UNIDAD_PIEZA SET estado = ?, informe_id = NULL, instalada_at = NULL WHERE unidad_id = ?"
+        )
+        .bind(EstadoUnidad::Disponible.as_str())
+        .bind(unidad_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    for unidad_id in elegidas.into_iter().filter(|id| !actuales.contains(id)) {
+        sqlx::query(
+            "UPDATE UNIDAD_PIEZA SET estado = ?, informe_id = ?, instalada_at = COALESCE(instalada_at, CURRENT_TIMESTAMP)
+             WHERE unidad_id = ?"
+        )
+        .bind(EstadoUnidad::Instalada.as_str())
+        .bind(informe_id)
+        .bind(unidad_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Devuelve a bodega las unidades instaladas en el informe, incluidas las
+/// heredadas si es una enmienda, de una pieza o de todas
+pub(crate) async fn liberar_series_informe(tx: &mut Transaction<'_, MySql>, informe_id: i32, pieza_id: Option<i32>) -> Result<u64, String> {
+    let result = sqlx::query(
+        "UPDATE UNIDAD_PIEZA SET estado = ?, informe_id = NULL, instalada_at = NULL
+         WHERE informe_id IN (SELECT ancestro_id FROM INFORME_CADENA WHERE informe_id = ?)
+           AND (? IS NULL OR pieza_id = ?)"
+    )
+    .bind(EstadoUnidad::Disponible.as_str())
+    .bind(informe_id)
+    .bind(pieza_id)
+    .bind(pieza_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(result.rows_affected())
+}
+
+/// Piezas con control por serie cuyas series elegidas no cubren la cantidad
+/// usada en el informe, como las que vienen copiadas de la cotización
+pub(crate) async fn piezas_sin_series(tx: &mut Transaction<'_, MySql>, informe_id: i32) -> Result<Vec<String>, String> {
+    let lineas = sqlx::query_as::<_, (Option<String>, i32, i64)>(
+        "SELECT p.pieza_nombre, COALESCE(pi.cantidad, 1),
+                (SELECT COUNT(*) FROM UNIDAD_PIEZA u
+                 INNER JOIN INFORME_CADENA c ON c.ancestro_id = u.informe_id
+                 WHERE c.informe_id = pi.informe_id AND u.pieza_id = pi.pieza_id)
+         FROM PIEZAS_INFORME pi
+         INNER JOIN PIEZA p ON pi.pieza_id = p.pieza_id
+         WHERE pi.informe_id = ? AND p.requiere_serie = TRUE
+         ORDER BY p.pieza_nombre"
+    )
+    .bind(informe_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(lineas.into_iter()
+        .filter(|(_, cantidad, elegidas)| i64::from(*cantidad) != *elegidas)
+        .map(|(nombre, cantidad, elegidas)| format!("{} ({} de {} series)", nombre.unwrap_or_default(), elegidas, cantidad))
+        .collect())
+}
+
+/// Series instaladas con el informe, incluidas las heredadas si es una enmienda
+pub(crate) async fn series_instaladas_informe(tx: &mut Transaction<'_, MySql>, informe_id: i32) -> Result<Vec<(i32, String)>, String> {
+    sqlx::query_as::<_, (i32, String)>(
+        "SELECT u.pieza_id, u.numero_serie
+         FROM UNIDAD_PIEZA u
+         INNER JOIN INFORME_CADENA c ON c.ancestro_id = u.informe_id
+         WHERE c.informe_id = ?
+         ORDER BY u.pieza_id, u.numero_serie"
+    )
+    .bind(informe_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySql, Transaction};
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use chrono::{DateTime, Utc};
+
+/// Horas de aviso por defecto para considerar que una orden está por vencer
+const HORAS_AVISO_POR_DEFECTO: i64 = 24;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SlaObjetivo {
+    pub prioridad: String,
+    pub has_garantia: bool,
+    pub horas: i32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSlaObjetivoRequest {
+    pub prioridad: String,
+    pub has_garantia: bool,
+    pub horas: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrdenSlaAlerta {
+    pub orden_id: i32,
+    pub orden_codigo: Option<String>,
+    pub prioridad: Option<String>,
+    pub estado: Option<String>,
+    pub has_garantia: Option<bool>,
+    pub tecnico_id: Option<i32>,
+    pub tecnico_nombre: Option<String>,
+    pub cliente_nombre: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub fecha_compromiso: Option<DateTime<Utc>>,
+    // Negativo si la orden ya está vencida
+    pub minutos_restantes: i64,
+    #[sqlx(skip)]
+    pub vencida: bool,
+}
+
+/// Obtener los objetivos de SLA configurados
+#[tauri::command]
+pub async fn get_sla_objetivos(session_token: String) -> Result<Vec<SlaObjetivo>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let objetivos = sqlx::query_as::<_, SlaObjetivo>(
+        "SELECT prioridad, has_garantia, horas, updated_at
+         FROM SLA_OBJETIVO
+         ORDER BY FIELD(prioridad, 'alta', 'media', 'baja'), has_garantia"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(objetivos)
+}
+
+/// Actualizar el objetivo de SLA de una prioridad.
+/// Las órdenes existentes conservan la fecha de compromiso ya informada.
+#[tauri::command]
+pub async fn update_sla_objetivo(session_token: String, request: UpdateSlaObjetivoRequest) -> Result<SlaObjetivo, String> {
+    let usuario = require_permission(&session_token, Permiso::Configuracion).await?;
+    let pool = get_db_pool_safe()?;
+
+    if !["baja", "media", "alta"].contains(&request.prioridad.as_str()) {
+        return Err("Prioridad no válida".to_string());
+    }
+    if request.horas <= 0 {
+        return Err("El objetivo de SLA debe ser de al menos una hora".to_string());
+    }
+
+    let horas_previas = sqlx::query_scalar::<_, i32>(
+        "SELECT horas FROM SLA_OBJETIVO WHERE prioridad = ? AND has_garantia = ?"
+    )
+    .bind(&request.prioridad)
+    .bind(request.has_garantia)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO SLA_OBJETIVO (prioridad, has_garantia, horas) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE horas = VALUES(horas)"
+    )
+    .bind(&request.prioridad)
+    .bind(request.has_garantia)
+    .bind(request.horas)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    // Registrar la acción en el log de auditoría
+    let garantia = if request.has_garantia { "garantia" } else { "normal" };
+    let _ = log_action(
+        "UPDATE_SLA_OBJETIVO",
+        Some(usuario.usuario_id),
+        "SLA_OBJETIVO",
+        None,
+        horas_previas.map(|h| format!("{}/{}: {}h", request.prioridad, garantia, h)).as_deref(),
+        Some(&format!("{}/{}: {}h", request.prioridad, garantia, request.horas))
+    ).await;
+
+    sqlx::query_as::<_, SlaObjetivo>(
+        "SELECT prioridad, has_garantia, horas, updated_at
+         FROM SLA_OBJETIVO WHERE prioridad = ? AND has_garantia = ?"
+    )
+    .bind(&request.prioridad)
+    .bind(request.has_garantia)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
+
+/// Obtener las órdenes abiertas vencidas o que vencen dentro de `horas_aviso`
+#[tauri::command]
+pub async fn get_ordenes_trabajo_sla_en_riesgo(session_token: String, horas_aviso: Option<i64>) -> Result<Vec<OrdenSlaAlerta>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    let pool = get_db_pool_safe()?;
+
+    let horas_aviso = horas_aviso.unwrap_or(HORAS_AVISO_POR_DEFECTO).max(0);
+
+    let mut alertas = sqlx::query_as::<_, OrdenSlaAlerta>(
+        "SELECT ot.orden_id, ot.orden_codigo, ot.prioridad, ot.estado, ot.has_garantia,
+                ot.tecnico_id, tec.usuario_nombre as tecnico_nombre, c.cliente_nombre,
+                ot.created_at, ot.fecha_compromiso,
+                TIMESTAMPDIFF(MINUTE, NOW(), ot.fecha_compromiso) as minutos_restantes
+         FROM ORDEN_TRABAJO ot
+         LEFT JOIN USUARIO tec ON ot.tecnico_id = tec.usuario_id
+         LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+         LEFT JOIN CLIENTE c ON e.cliente_id = c.cliente_id
+         WHERE ot.fecha_resolucion IS NULL
+           AND ot.fecha_compromiso IS NOT NULL
+           AND ot.fecha_compromiso <= DATE_ADD(NOW(), INTERVAL ? HOUR)
+         ORDER BY ot.fecha_compromiso ASC"
+    )
+    .bind(horas_aviso)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    for alerta in alertas.iter_mut() {
+        alerta.vencida = alerta.minutos_restantes < 0;
+    }
+
+    Ok(alertas)
+}
+
+/// Calcula la fecha de compromiso de una orden a partir de su creación y del
+/// objetivo vigente para su prioridad y garantía
+pub(crate) async fn asignar_fecha_compromiso(tx: &mut Transaction<'_, MySql>, orden_id: i32) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE ORDEN_TRABAJO ot
+         LEFT JOIN SLA_OBJETIVO s
+           ON s.prioridad = ot.prioridad AND s.has_garantia = COALESCE(ot.has_garantia, FALSE)
+         SET ot.fecha_compromiso = DATE_ADD(ot.created_at, INTERVAL s.horas HOUR)
+         WHERE ot.orden_id = ?"
+    )
+    .bind(orden_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    Ok(())
+}
+
+/// Porcentaje con un decimal, `None` si no hay órdenes evaluadas
+fn porcentaje(cumplidas: i64, evaluadas: i64) -> Option<f64> {
+    if evaluadas == 0 {
+        return None;
+    }
+    Some((cumplidas as f64 * 1000.0 / evaluadas as f64).round() / 10.0)
+}
+
+/// Resumen de cumplimiento de SLA para las estadísticas de órdenes.
+/// Una orden resuelta cumple si se resolvió antes de su fecha de compromiso;
+/// una orden abierta con el plazo vencido cuenta como incumplida.
+pub(crate) async fn resumen_cumplimiento_sla() -> Result<serde_json::Value, String> {
+    let pool = get_db_pool_safe()?;
+
+    #[derive(Debug, sqlx::FromRow)]
+    struct CumplimientoPorPrioridad {
+        prioridad: Option<String>,
+        evaluadas: i64,
+        cumplidas: i64,
+        vencidas_abiertas: i64,
+    }
+
+    let filas: Vec<CumplimientoPorPrioridad> = sqlx::query_as(
+        "SELECT prioridad,
+                CAST(SUM(fecha_resolucion IS NOT NULL OR fecha_compromiso < NOW()) AS SIGNED) as evaluadas,
+                CAST(SUM(fecha_resolucion IS NOT NULL AND fecha_resolucion <= fecha_compromiso) AS SIGNED) as cumplidas,
+                CAST(SUM(fecha_resolucion IS NULL AND fecha_compromiso < NOW()) AS SIGNED) as vencidas_abiertas
+         FROM ORDEN_TRABAJO
+         WHERE fecha_compromiso IS NOT NULL
+         GROUP BY prioridad"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let evaluadas: i64 = filas.iter().map(|f| f.evaluadas).sum();
+    let cumplidas: i64 = filas.iter().map(|f| f.cumplidas).sum();
+    let vencidas_abiertas: i64 = filas.iter().map(|f| f.vencidas_abiertas).sum();
+
+    Ok(serde_json::json!({
+        "evaluadas": evaluadas,
+        "cumplidas": cumplidas,
+        "vencidas_abiertas": vencidas_abiertas,
+        "porcentaje_cumplimiento": porcentaje(cumplidas, evaluadas),
+        "por_prioridad": filas.into_iter().map(|f| serde_json::json!({
+            "prioridad": f.prioridad,
+            "evaluadas": f.evaluadas,
+            "cumplidas": f.cumplidas,
+            "vencidas_abiertas": f.vencidas_abiertas,
+            "porcentaje_cumplimiento": porcentaje(f.cumplidas, f.evaluadas)
+        })).collect::<Vec<_>>()
+    }))
+}
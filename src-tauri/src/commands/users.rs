@@ -4,6 +4,7 @@ use crate::database::get_db_pool_safe;
 use crate::utils::{hash_password, verify_password};
 use crate::commands::logs::log_action;
 use crate::email::EmailService;
+use crate::auth::{authorize, require_permission, require_session, Permiso, Rol};
 use chrono::{DateTime, Utc, Duration};
 use uuid::Uuid;
 
@@ -28,7 +29,7 @@ pub struct CreateUsuarioRequest {
     pub usuario_correo: String,
     pub usuario_contrasena: String,
     pub usuario_telefono: Option<String>,
-    pub usuario_rol: String, // 'admin', 'tecnico', 'cliente'
+    pub usuario_rol: String, // 'admin', 'tecnico', 'recepcion'
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,7 +76,8 @@ pub struct ChangeEmailRequest {
 }
 
 #[tauri::command]
-pub async fn get_usuarios() -> Result<Vec<Usuario>, String> {
+pub async fn get_usuarios(session_token: String) -> Result<Vec<Usuario>, String> {
+    require_permission(&session_token, Permiso::VerUsuarios).await?;
     let pool = get_db_pool_safe()?;
       let usuarios = sqlx::query_as::<_, Usuario>(
         "SELECT usuario_id, usuario_rut, usuario_nombre, usuario_correo, usuario_contrasena, usuario_telefono, usuario_rol, last_login_at, session_expires_at, session_token FROM USUARIO"
@@ -88,7 +90,19 @@ pub async fn get_usuarios() -> Result<Vec<Usuario>, String> {
 }
 
 #[tauri::command]
-pub async fn get_usuario_by_id(usuario_id: i32) -> Result<Option<Usuario>, String> {
+pub async fn get_usuario_by_id(session_token: String, usuario_id: i32) -> Result<Option<Usuario>, String> {
+    let sesion = require_session(&session_token).await?;
+    
+    // Cada usuario puede consultar su propio perfil
+    if sesion.usuario_id != usuario_id {
+        authorize(&sesion, Permiso::VerUsuarios).await?;
+    }
+    
+    fetch_usuario_by_id(usuario_id).await
+}
+
+/// Obtiene un usuario por ID sin verificar la sesión (uso interno)
+async fn fetch_usuario_by_id(usuario_id: i32) -> Result<Option<Usuario>, String> {
     let pool = get_db_pool_safe()?;
       let usuario = sqlx::query_as::<_, Usuario>(
         "SELECT usuario_id, usuario_rut, usuario_nombre, usuario_correo, usuario_contrasena, usuario_telefono, usuario_rol, last_login_at, session_expires_at, session_token FROM USUARIO WHERE usuario_id = ?"
@@ -102,7 +116,8 @@ pub async fn get_usuario_by_id(usuario_id: i32) -> Result<Option<Usuario>, Strin
 }
 
 #[tauri::command]
-pub async fn get_usuario_by_rut(usuario_rut: String) -> Result<Option<Usuario>, String> {
+pub async fn get_usuario_by_rut(session_token: String, usuario_rut: String) -> Result<Option<Usuario>, String> {
+    require_permission(&session_token, Permiso::VerUsuarios).await?;
     let pool = get_db_pool_safe()?;
       let usuario = sqlx::query_as::<_, Usuario>(
         "SELECT usuario_id, usuario_rut, usuario_nombre, usuario_correo, usuario_contrasena, usuario_telefono, usuario_rol, last_login_at, session_expires_at, session_token FROM USUARIO WHERE usuario_rut = ?"
@@ -116,9 +131,12 @@ pub async fn get_usuario_by_rut(usuario_rut: String) -> Result<Option<Usuario>,
 }
 
 #[tauri::command]
-pub async fn create_usuario(request: CreateUsuarioRequest) -> Result<Usuario, String> {
+pub async fn create_usuario(session_token: String, request: CreateUsuarioRequest) -> Result<Usuario, String> {
+    let admin = require_permission(&session_token, Permiso::GestionarUsuarios).await?;
     let pool = get_db_pool_safe()?;
     
+    request.usuario_rol.parse::<Rol>()?;
+    
     // Encriptar la contraseña antes de guardarla
     let hashed_password = hash_password(&request.usuario_contrasena)?;
     
@@ -140,7 +158,7 @@ pub async fn create_usuario(request: CreateUsuarioRequest) -> Result<Usuario, St
     // Registrar la acción en el log de auditoría
     let _ = log_action(
         "CREATE_USER",
-        None, // No hay usuario autenticado en la creación
+        Some(admin.usuario_id),
         "USUARIO",
         Some(usuario_id),
         None,
@@ -148,17 +166,28 @@ pub async fn create_usuario(request: CreateUsuarioRequest) -> Result<Usuario, St
     ).await;
     
     // Obtener el usuario recién creado
-    get_usuario_by_id(usuario_id)
+    fetch_usuario_by_id(usuario_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created usuario".to_string())
 }
 
 #[tauri::command]
-pub async fn update_usuario(usuario_id: i32, request: UpdateUsuarioRequest) -> Result<Option<Usuario>, String> {
+pub async fn update_usuario(session_token: String, usuario_id: i32, request: UpdateUsuarioRequest) -> Result<Option<Usuario>, String> {
+    let sesion = require_session(&session_token).await?;
+    
+    // Un usuario puede editar su propio perfil, pero solo un administrador puede cambiar roles o editar a otros
+    if sesion.usuario_id != usuario_id || request.usuario_rol.is_some() {
+        authorize(&sesion, Permiso::GestionarUsuarios).await?;
+    }
+    
+    if let Some(ref rol) = request.usuario_rol {
+        rol.parse::<Rol>()?;
+    }
+    
     let pool = get_db_pool_safe()?;
     
     // Obtener el usuario actual para logging
-    let current_user = get_usuario_by_id(usuario_id).await?;
+    let current_user = fetch_usuario_by_id(usuario_id).await?;
     
     // Encriptar la contraseña si se proporciona
     let hashed_password = if let Some(ref password) = request.usuario_contrasena {
@@ -207,7 +236,7 @@ pub async fn update_usuario(usuario_id: i32, request: UpdateUsuarioRequest) -> R
         
         let _ = log_action(
             "UPDATE_USER",
-            None,
+            Some(sesion.usuario_id),
             "USUARIO",
             Some(usuario_id),
             Some(&prev_data),
@@ -215,15 +244,16 @@ pub async fn update_usuario(usuario_id: i32, request: UpdateUsuarioRequest) -> R
         ).await;
     }
     
-    get_usuario_by_id(usuario_id).await
+    fetch_usuario_by_id(usuario_id).await
 }
 
 #[tauri::command]
-pub async fn delete_usuario(usuario_id: i32) -> Result<bool, String> {
+pub async fn delete_usuario(session_token: String, usuario_id: i32) -> Result<bool, String> {
+    let admin = require_permission(&session_token, Permiso::GestionarUsuarios).await?;
     let pool = get_db_pool_safe()?;
     
     // Obtener el usuario antes de eliminarlo para logging
-    let user_to_delete = get_usuario_by_id(usuario_id).await?;
+    let user_to_delete = fetch_usuario_by_id(usuario_id).await?;
     
     let result = sqlx::query("DELETE FROM USUARIO WHERE usuario_id = ?")
         .bind(usuario_id)
@@ -238,7 +268,7 @@ pub async fn delete_usuario(usuario_id: i32) -> Result<bool, String> {
         if let Some(ref user) = user_to_delete {
             let _ = log_action(
                 "DELETE_USER",
-                None,
+                Some(admin.usuario_id),
                 "USUARIO",
                 Some(usuario_id),
                 Some(&format!("Usuario eliminado: {} ({})", 
@@ -412,7 +442,7 @@ pub async fn create_admin_user() -> Result<Usuario, String> {
     ).await;
     
     // Obtener el usuario recién creado y retornarlo sin la contraseña
-    let created_user = get_usuario_by_id(usuario_id)
+    let created_user = fetch_usuario_by_id(usuario_id)
         .await?
         .ok_or_else(|| "Failed to retrieve created admin user".to_string())?;
       Ok(Usuario {
@@ -607,7 +637,8 @@ pub async fn reset_password_with_code(request: ResetPasswordRequest) -> Result<S
 }
 
 #[tauri::command]
-pub async fn cleanup_expired_reset_codes() -> Result<u64, String> {
+pub async fn cleanup_expired_reset_codes(session_token: String) -> Result<u64, String> {
+    let admin = require_permission(&session_token, Permiso::Mantenimiento).await?;
     let pool = get_db_pool_safe()?;
       let result = sqlx::query(
         "DELETE FROM PASSWORD_RESET WHERE expires_at < UTC_TIMESTAMP() OR used = TRUE"
@@ -621,7 +652,7 @@ pub async fn cleanup_expired_reset_codes() -> Result<u64, String> {
     // Registrar limpieza
     let _ = log_action(
         "CLEANUP_RESET_CODES",
-        None,
+        Some(admin.usuario_id),
         "PASSWORD_RESET",
         None,
         None,
@@ -631,21 +662,28 @@ pub async fn cleanup_expired_reset_codes() -> Result<u64, String> {
     Ok(deleted_count)
 }
 
-#[tauri::command]
-pub async fn validate_session(session_token: String) -> Result<Option<Usuario>, String> {
+/// Busca el usuario asociado a un token de sesión válido y no expirado
+pub async fn find_usuario_by_session(session_token: &str) -> Result<Option<Usuario>, String> {
     let pool = get_db_pool_safe()?;
     
-    // Buscar usuario por token de sesión válido y no expirado
     let usuario = sqlx::query_as::<_, Usuario>(
         "SELECT usuario_id, usuario_rut, usuario_nombre, usuario_correo, usuario_contrasena, usuario_telefono, usuario_rol, last_login_at, session_expires_at, session_token
          FROM USUARIO 
          WHERE session_token = ? AND session_expires_at > UTC_TIMESTAMP()"
     )
-    .bind(&session_token)
+    .bind(session_token)
     .fetch_optional(pool)
     .await
     .map_err(|e| format!("Database error: {}", e))?;
     
+    Ok(usuario)
+}
+
+#[tauri::command]
+pub async fn validate_session(session_token: String) -> Result<Option<Usuario>, String> {
+    // Buscar usuario por token de sesión válido y no expirado
+    let usuario = find_usuario_by_session(&session_token).await?;
+    
     match usuario {
         Some(user) => {
             // Registrar validación exitosa
@@ -717,7 +755,8 @@ pub async fn logout_user(session_token: String) -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub async fn cleanup_expired_sessions() -> Result<u64, String> {
+pub async fn cleanup_expired_sessions(session_token: String) -> Result<u64, String> {
+    let admin = require_permission(&session_token, Permiso::Mantenimiento).await?;
     let pool = get_db_pool_safe()?;
     
     let result = sqlx::query(
@@ -733,7 +772,7 @@ pub async fn cleanup_expired_sessions() -> Result<u64, String> {
     // Registrar limpieza
     let _ = log_action(
         "CLEANUP_EXPIRED_SESSIONS",
-        None,
+        Some(admin.usuario_id),
         "USUARIO",
         None,
         None,
@@ -743,13 +782,12 @@ pub async fn cleanup_expired_sessions() -> Result<u64, String> {
 }
 
 #[tauri::command]
-pub async fn change_user_password(usuario_id: i32, request: ChangePasswordRequest) -> Result<bool, String> {
+pub async fn change_user_password(session_token: String, request: ChangePasswordRequest) -> Result<bool, String> {
+    // El usuario se obtiene de la sesión; solo se puede cambiar la contraseña propia
+    let user = require_session(&session_token).await?;
+    let usuario_id = user.usuario_id;
     let pool = get_db_pool_safe()?;
     
-    // Obtener el usuario actual
-    let user = get_usuario_by_id(usuario_id).await?
-        .ok_or_else(|| "Usuario no encontrado".to_string())?;
-    
     // Verificar la contraseña actual
     if let Some(ref stored_password) = user.usuario_contrasena {
         if !verify_password(&request.current_password, stored_password)? {
@@ -804,13 +842,12 @@ pub async fn change_user_password(usuario_id: i32, request: ChangePasswordReques
 }
 
 #[tauri::command]
-pub async fn change_user_email(usuario_id: i32, request: ChangeEmailRequest) -> Result<Option<Usuario>, String> {
+pub async fn change_user_email(session_token: String, request: ChangeEmailRequest) -> Result<Option<Usuario>, String> {
+    // El usuario se obtiene de la sesión; solo se puede cambiar el email propio
+    let user = require_session(&session_token).await?;
+    let usuario_id = user.usuario_id;
     let pool = get_db_pool_safe()?;
     
-    // Obtener el usuario actual
-    let user = get_usuario_by_id(usuario_id).await?
-        .ok_or_else(|| "Usuario no encontrado".to_string())?;
-    
     // Verificar la contraseña para confirmar la identidad
     if let Some(ref stored_password) = user.usuario_contrasena {
         if !verify_password(&request.password, stored_password)? {
@@ -873,7 +910,7 @@ pub async fn change_user_email(usuario_id: i32, request: ChangeEmailRequest) ->
         ).await;
         
         // Retornar el usuario actualizado
-        get_usuario_by_id(usuario_id).await
+        fetch_usuario_by_id(usuario_id).await
     } else {
         Err("No se pudo actualizar el email".to_string())
     }
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use crate::database::get_db_pool_safe;
+use crate::commands::logs::log_action;
+use crate::auth::{require_permission, Permiso};
+use crate::calculo_cotizacion::{esta_vencida, fecha_vencimiento};
+use crate::commands::cotizacion::{get_cotizacion_by_id, recalcular_totales_cotizacion, Cotizacion};
+use crate::commands::parametros::{obtener_politica_diferencia_total, obtener_validez_cotizacion};
+use crate::commands::revisiones_cotizacion::{abrir_nueva_revision, registrar_nueva_revision};
+use chrono::{NaiveDate, Utc};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResumenVencimientoCotizaciones {
+    pub marcadas_vencidas: i64,
+    pub ordenes_bloqueadas: Vec<OrdenBloqueada>,
+    pub errores: Vec<String>,
+}
+
+/// Orden en `cotizacion_enviada` cuya cotización venció sin ser aprobada
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OrdenBloqueada {
+    pub orden_id: i32,
+    pub orden_codigo: Option<String>,
+    pub cotizacion_id: i32,
+    pub cotizacion_codigo: Option<String>,
+    pub valido_hasta: Option<NaiveDate>,
+    pub cliente_nombre: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CambioPrecioPieza {
+    pub pieza_id: i32,
+    pub pieza_nombre: Option<String>,
+    pub precio_anterior: Option<i32>,
+    pub precio_nuevo: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecalculoCotizacion {
+    pub cotizacion: Cotizacion,
+    pub total_anterior: Option<i32>,
+    pub precios_actualizados: Vec<CambioPrecioPieza>,
+}
+
+/// Ejecutar manualmente la revisión de cotizaciones vencidas
+#[tauri::command]
+pub async fn ejecutar_revision_vencimiento_cotizaciones(session_token: String) -> Result<ResumenVencimientoCotizaciones, String> {
+    require_permission(&session_token, Permiso::Mantenimiento).await?;
+    procesar_cotizaciones_vencidas().await
+}
+
+/// Obtener las órdenes detenidas en `cotizacion_enviada` por una cotización vencida
+#[tauri::command]
+pub async fn get_ordenes_bloqueadas_por_vencimiento(session_token: String) -> Result<Vec<OrdenBloqueada>, String> {
+    require_permission(&session_token, Permiso::VerOrdenes).await?;
+    fetch_ordenes_bloqueadas().await
+}
+
+/// Actualiza una cotización vencida con los precios actuales de las piezas y
+/// le da una nueva vigencia. Si ya fue enviada, el cambio queda como revisión.
+#[tauri::command]
+pub async fn recalcular_cotizacion_vencida(session_token: String, cotizacion_id: i32) -> Result<RecalculoCotizacion, String> {
+    let usuario = require_permission(&session_token, Permiso::GestionarCotizaciones).await?;
+    let pool = get_db_pool_safe()?;
+
+    let actual = get_cotizacion_by_id(session_token.clone(), cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    if actual.is_aprobada.unwrap_or(false) {
+        return Err("No se puede recalcular una cotización aprobada".to_string());
+    }
+    let hoy = Utc::now().date_naive();
+    if !esta_vencida(actual.valido_hasta, hoy) {
+        return Err(match actual.valido_hasta {
+            Some(fecha) => format!("La cotización está vigente hasta el {}", fecha.format("%d/%m/%Y")),
+            None => "La cotización no tiene fecha de vencimiento".to_string(),
+        });
+    }
+
+    let politica = obtener_politica_diferencia_total().await?;
+    let valido_hasta = fecha_vencimiento(hoy, obtener_validez_cotizacion().await?);
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+
+    let nueva_revision = abrir_nueva_revision(&mut tx, cotizacion_id, usuario.usuario_id).await?;
+
+    let mut precios = sqlx::query_as::<_, CambioPrecioPieza>(
+        "SELECT pc.pieza_id, p.pieza_nombre, pc.precio_unitario as precio_anterior, p.pieza_precio as precio_nuevo
+         FROM PIEZAS_COTIZACION pc
+         INNER JOIN PIEZA p ON pc.pieza_id = p.pieza_id
+         WHERE pc.cotizacion_id = ?
+         ORDER BY pc.pieza_id
+         FOR UPDATE"
+    )
+    .bind(cotizacion_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    if let Some(sin_precio) = precios.iter().find(|p| p.precio_nuevo.is_none()) {
+        return Err(format!("La pieza {} no tiene un precio definido", sin_precio.pieza_id));
+    }
+    precios.retain(|p| p.precio_anterior != p.precio_nuevo);
+
+    sqlx::query(
+        "UPDATE PIEZAS_COTIZACION pc
+         INNER JOIN PIEZA p ON pc.pieza_id = p.pieza_id
+         SET pc.precio_unitario = p.pieza_precio
+         WHERE pc.cotizacion_id = ?"
+    )
+    .bind(cotizacion_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let (totales, _) = recalcular_totales_cotizacion(&mut tx, cotizacion_id, None, politica).await?;
+
+    sqlx::query("UPDATE COTIZACION SET valido_hasta = ?, vencida_at = NULL WHERE cotizacion_id = ?")
+        .bind(valido_hasta)
+        .bind(cotizacion_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    registrar_nueva_revision(usuario.usuario_id, cotizacion_id, nueva_revision).await;
+
+    // Registrar la acción en el log de auditoría
+    let _ = log_action(
+        "RECALCULAR_COTIZACION_VENCIDA",
+        Some(usuario.usuario_id),
+        "COTIZACION",
+        Some(cotizacion_id),
+        Some(&format!("{}|{}",
+            actual.costo_total.map_or("".to_string(), |t| t.to_string()),
+            actual.valido_hasta.map_or("".to_string(), |f| f.to_string())
+        )),
+        Some(&format!("{}|{}", totales.costo_total, valido_hasta))
+    ).await;
+
+    let cotizacion = get_cotizacion_by_id(session_token, cotizacion_id).await?
+        .ok_or_else(|| "Cotización no encontrada".to_string())?;
+
+    Ok(RecalculoCotizacion {
+        cotizacion,
+        total_anterior: actual.costo_total,
+        precios_actualizados: precios,
+    })
+}
+
+/// Marca como vencidas las cotizaciones no aprobadas cuya vigencia terminó y
+/// reporta las órdenes que quedaron detenidas en `cotizacion_enviada`.
+/// La ejecuta periódicamente la tarea de fondo.
+pub(crate) async fn procesar_cotizaciones_vencidas() -> Result<ResumenVencimientoCotizaciones, String> {
+    let pool = get_db_pool_safe()?;
+    let hoy = Utc::now().date_naive();
+
+    let vencidas = sqlx::query_as::<_, (i32, Option<NaiveDate>)>(
+        "SELECT cotizacion_id, valido_hasta FROM COTIZACION
+         WHERE vencida_at IS NULL AND COALESCE(is_aprobada, FALSE) = FALSE AND valido_hasta < ?"
+    )
+    .bind(hoy)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))?;
+
+    let mut resumen = ResumenVencimientoCotizaciones::default();
+
+    for (cotizacion_id, valido_hasta) in vencidas {
+        // La condición se repite por si la cotización se aprobó o renovó entretanto
+        let marcada = sqlx::query(
+            "UPDATE COTIZACION SET vencida_at = CURRENT_TIMESTAMP
+             WHERE cotizacion_id = ? AND vencida_at IS NULL
+               AND COALESCE(is_aprobada, FALSE) = FALSE AND valido_hasta < ?"
+        )
+        .bind(cotizacion_id)
+        .bind(hoy)
+        .execute(pool)
+        .await;
+
+        match marcada {
+            Ok(result) if result.rows_affected() > 0 => {
+                resumen.marcadas_vencidas += 1;
+                let _ = log_action(
+                    "AUTO_VENCIMIENTO_COTIZACION",
+                    None,
+                    "COTIZACION",
+                    Some(cotizacion_id),
+                    None,
+                    Some(&format!("Vencida desde {}", valido_hasta.map_or("".to_string(), |f| f.to_string())))
+                ).await;
+            }
+            Ok(_) => {}
+            Err(e) => resumen.errores.push(format!("Cotización {}: Database error: {}", cotizacion_id, e)),
+        }
+    }
+
+    resumen.ordenes_bloqueadas = fetch_ordenes_bloqueadas().await?;
+    Ok(resumen)
+}
+
+async fn fetch_ordenes_bloqueadas() -> Result<Vec<OrdenBloqueada>, String> {
+    let pool = get_db_pool_safe()?;
+
+    sqlx::query_as::<_, OrdenBloqueada>(
+        "SELECT ot.orden_id, ot.orden_codigo, c.cotizacion_id, c.cotizacion_codigo, c.valido_hasta,
+                cl.cliente_nombre
+         FROM ORDEN_TRABAJO ot
+         INNER JOIN COTIZACION c ON ot.cotizacion_id = c.cotizacion_id
+         LEFT JOIN EQUIPO e ON ot.equipo_id = e.equipo_id
+         LEFT JOIN CLIENTE cl ON e.cliente_id = cl.cliente_id
+         WHERE ot.estado = 'cotizacion_enviada'
+           AND c.vencida_at IS NOT NULL
+           AND COALESCE(c.is_aprobada, FALSE) = FALSE
+         ORDER BY c.valido_hasta ASC, ot.orden_id ASC"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Database error: {}", e))
+}
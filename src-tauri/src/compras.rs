@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Estados de una orden de compra (ORDEN_COMPRA.estado)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoOrdenCompra {
+    Borrador,
+    Enviada,
+    RecibidaParcial,
+    Recibida,
+}
+
+impl FromStr for EstadoOrdenCompra {
+    type Err = String;
+
+    fn from_str(estado: &str) -> Result<Self, Self::Err> {
+        match estado {
+            "borrador" => Ok(EstadoOrdenCompra::Borrador),
+            "enviada" => Ok(EstadoOrdenCompra::Enviada),
+            "recibida_parcial" => Ok(EstadoOrdenCompra::RecibidaParcial),
+            "recibida" => Ok(EstadoOrdenCompra::Recibida),
+            _ => Err(format!("Estado de orden de compra no válido: {}", estado)),
+        }
+    }
+}
+
+impl fmt::Display for EstadoOrdenCompra {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl EstadoOrdenCompra {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoOrdenCompra::Borrador => "borrador",
+            EstadoOrdenCompra::Enviada => "enviada",
+            EstadoOrdenCompra::RecibidaParcial => "recibida_parcial",
+            EstadoOrdenCompra::Recibida => "recibida",
+        }
+    }
+
+    /// Solo un borrador admite cambios en sus líneas o su eliminación
+    pub fn es_editable(&self) -> bool {
+        *self == EstadoOrdenCompra::Borrador
+    }
+
+    /// Se puede registrar mercadería una vez enviada y hasta recibirla completa
+    pub fn admite_recepcion(&self) -> bool {
+        matches!(self, EstadoOrdenCompra::Enviada | EstadoOrdenCompra::RecibidaParcial)
+    }
+}
+
+/// Línea de una orden de compra: cantidad pedida y recibida hasta ahora
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvanceLinea {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    pub cantidad_recibida: i32,
+}
+
+impl AvanceLinea {
+    pub fn pendiente(&self) -> i32 {
+        (self.cantidad - self.cantidad_recibida).max(0)
+    }
+}
+
+/// Valida una recepción contra lo pendiente de la línea
+pub fn validar_recepcion(linea: &AvanceLinea, cantidad: i32) -> Result<(), String> {
+    if cantidad <= 0 {
+        return Err(format!("La cantidad recibida de la pieza {} debe ser mayor a cero", linea.pieza_id));
+    }
+    if cantidad > linea.pendiente() {
+        return Err(format!(
+            "Se reciben {} unidades de la pieza {} pero solo quedan {} pendientes",
+            cantidad, linea.pieza_id, linea.pendiente()
+        ));
+    }
+    Ok(())
+}
+
+/// Estado de la orden según lo recibido en sus líneas
+pub fn estado_segun_recepcion(lineas: &[AvanceLinea]) -> EstadoOrdenCompra {
+    if lineas.iter().all(|l| l.pendiente() == 0) {
+        EstadoOrdenCompra::Recibida
+    } else if lineas.iter().any(|l| l.cantidad_recibida > 0) {
+        EstadoOrdenCompra::RecibidaParcial
+    } else {
+        EstadoOrdenCompra::Enviada
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linea(pieza_id: i32, cantidad: i32, cantidad_recibida: i32) -> AvanceLinea {
+        AvanceLinea { pieza_id, cantidad, cantidad_recibida }
+    }
+
+    #[test]
+    fn test_estado_desde_texto() {
+        for estado in [
+            EstadoOrdenCompra::Borrador,
+            EstadoOrdenCompra::Enviada,
+            EstadoOrdenCompra::RecibidaParcial,
+            EstadoOrdenCompra::Recibida,
+        ] {
+            assert_eq!(estado.as_str().parse::<EstadoOrdenCompra>(), Ok(estado));
+        }
+        assert!("anulada".parse::<EstadoOrdenCompra>().is_err());
+    }
+
+    #[test]
+    fn test_validar_recepcion() {
+        let pedida = linea(1, 10, 4);
+        assert!(validar_recepcion(&pedida, 6).is_ok());
+        assert!(validar_recepcion(&pedida, 7).is_err());
+        assert!(validar_recepcion(&pedida, 0).is_err());
+    }
+
+    #[test]
+    fn test_estado_segun_recepcion() {
+        assert_eq!(estado_segun_recepcion(&[linea(1, 2, 0), linea(2, 1, 0)]), EstadoOrdenCompra::Enviada);
+        assert_eq!(estado_segun_recepcion(&[linea(1, 2, 2), linea(2, 1, 0)]), EstadoOrdenCompra::RecibidaParcial);
+        assert_eq!(estado_segun_recepcion(&[linea(1, 2, 2), linea(2, 1, 1)]), EstadoOrdenCompra::Recibida);
+        assert!(EstadoOrdenCompra::RecibidaParcial.admite_recepcion());
+        assert!(!EstadoOrdenCompra::Borrador.admite_recepcion());
+        assert!(!EstadoOrdenCompra::Recibida.admite_recepcion());
+    }
+}
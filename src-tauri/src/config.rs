@@ -11,7 +11,7 @@ use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::PathBuf;
 
-const APP_NAME: &str = "ToscaniniApp";
+pub const APP_NAME: &str = "ToscaniniApp";
 const CONFIG_FILE: &str = "config.enc";
 const KEYRING_SERVICE: &str = "toscanini_db_config";
 const KEYRING_USERNAME: &str = "database";
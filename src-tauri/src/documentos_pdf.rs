@@ -0,0 +1,564 @@
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point};
+use std::path::{Path, PathBuf};
+
+/// Largo máximo del nombre de la empresa que va en el encabezado
+pub const EMPRESA_NOMBRE_MAX_LARGO: usize = 120;
+
+// Hoja A4 en milímetros
+const ANCHO_PAGINA: f32 = 210.0;
+const ALTO_PAGINA: f32 = 297.0;
+const MARGEN: f32 = 15.0;
+const ANCHO_UTIL: f32 = ANCHO_PAGINA - 2.0 * MARGEN;
+// Bajo esta altura solo van el pie y el número de página
+const LIMITE_INFERIOR: f32 = 24.0;
+
+const PT_A_MM: f32 = 0.3528;
+
+/// Datos de la empresa impresos en el encabezado de cada página
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Empresa {
+    pub nombre: String,
+    pub rut: Option<String>,
+    pub direccion: Option<String>,
+    pub telefono: Option<String>,
+    pub correo: Option<String>,
+}
+
+/// Un documento listo para maquetar: encabezado, bloques en orden y un pie
+/// opcional que se repite en todas las páginas
+#[derive(Debug, Clone, PartialEq)]
+pub struct Documento {
+    pub titulo: String,
+    pub codigo: Option<String>,
+    pub fecha: Option<String>,
+    pub bloques: Vec<Bloque>,
+    pub pie: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bloque {
+    /// Pares etiqueta/valor bajo un título
+    Datos { titulo: String, campos: Vec<(String, String)> },
+    /// Párrafos de texto libre bajo un título
+    Texto { titulo: String, texto: String },
+    /// Tabla con encabezado; se repite el encabezado si cambia de página
+    Tabla { titulo: Option<String>, columnas: Vec<Columna>, filas: Vec<Vec<String>> },
+    /// Montos alineados a la derecha; el último va destacado
+    Totales(Vec<(String, String)>),
+    /// Líneas de firma con su leyenda
+    Firmas(Vec<String>),
+    /// Texto en letra chica, como condiciones o aclaraciones
+    Nota(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alineacion {
+    Izquierda,
+    Derecha,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Columna {
+    pub titulo: String,
+    /// Fracción del ancho útil de la página
+    pub ancho: f32,
+    pub alineacion: Alineacion,
+}
+
+impl Columna {
+    pub fn new(titulo: &str, ancho: f32, alineacion: Alineacion) -> Self {
+        Columna { titulo: titulo.to_string(), ancho, alineacion }
+    }
+}
+
+/// Monto en pesos con separador de miles: $1.234.567
+pub fn formatear_monto(monto: i64) -> String {
+    let digitos = monto.unsigned_abs().to_string();
+    let mut agrupado = String::with_capacity(digitos.len() + digitos.len() / 3);
+    for (i, digito) in digitos.chars().enumerate() {
+        if i > 0 && (digitos.len() - i).is_multiple_of(3) {
+            agrupado.push('.');
+        }
+        agrupado.push(digito);
+    }
+    if monto < 0 {
+        format!("-${}", agrupado)
+    } else {
+        format!("${}", agrupado)
+    }
+}
+
+pub fn validar_nombre_empresa(nombre: &str) -> Result<String, String> {
+    let nombre = nombre.trim();
+    if nombre.is_empty() {
+        return Err("El nombre de la empresa no puede quedar vacío".to_string());
+    }
+    if nombre.chars().count() > EMPRESA_NOMBRE_MAX_LARGO {
+        return Err(format!("El nombre de la empresa no puede superar los {} caracteres", EMPRESA_NOMBRE_MAX_LARGO));
+    }
+    Ok(nombre.to_string())
+}
+
+/// La ruta elegida para guardar un PDF debe ser absoluta y terminar en .pdf
+pub fn validar_ruta_pdf(ruta: &str) -> Result<PathBuf, String> {
+    let ruta = Path::new(ruta.trim());
+    if ruta.as_os_str().is_empty() {
+        return Err("Indique dónde guardar el PDF".to_string());
+    }
+    if !ruta.is_absolute() {
+        return Err("La ruta del PDF debe ser absoluta".to_string());
+    }
+    let es_pdf = ruta.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if !es_pdf || ruta.file_stem().is_none() {
+        return Err("El archivo debe tener extensión .pdf".to_string());
+    }
+    Ok(ruta.to_path_buf())
+}
+
+/// Ancho aproximado de un texto en Helvetica, en milímetros. Usa las
+/// métricas de la fuente estándar; la negrita se estima un 6% más ancha.
+pub fn ancho_texto(texto: &str, tamano: f32, negrita: bool) -> f32 {
+    let milesimas: u32 = texto.chars().map(ancho_caracter).sum();
+    let factor = if negrita { 1.06 } else { 1.0 };
+    milesimas as f32 / 1000.0 * tamano * PT_A_MM * factor
+}
+
+fn ancho_caracter(c: char) -> u32 {
+    const ASCII: [u32; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ' '..'/'
+        556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // '0'..'?'
+        1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // '@'..'O'
+        667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // 'P'..'_'
+        333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // '`'..'o'
+        556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p'..'~'
+    ];
+    let base = match c {
+        'á' | 'à' | 'ä' | 'â' => 'a',
+        'é' | 'è' | 'ë' | 'ê' => 'e',
+        'í' | 'ì' | 'ï' | 'î' => 'i',
+        'ó' | 'ò' | 'ö' | 'ô' => 'o',
+        'ú' | 'ù' | 'ü' | 'û' => 'u',
+        'ñ' => 'n',
+        'Á' | 'À' | 'Ä' | 'Â' => 'A',
+        'É' | 'È' | 'Ë' | 'Ê' => 'E',
+        'Í' | 'Ì' | 'Ï' | 'Î' => 'I',
+        'Ó' | 'Ò' | 'Ö' | 'Ô' => 'O',
+        'Ú' | 'Ù' | 'Ü' | 'Û' => 'U',
+        'Ñ' => 'N',
+        '¿' | '¡' => '?',
+        otro => otro,
+    };
+    match base as u32 {
+        32..=126 => ASCII[base as usize - 32],
+        _ => 556,
+    }
+}
+
+/// Parte un texto en líneas que caben en el ancho dado. Respeta los saltos
+/// de línea del original y corta las palabras más largas que el ancho.
+pub fn ajustar_texto(texto: &str, ancho_max: f32, tamano: f32, negrita: bool) -> Vec<String> {
+    let cabe = |s: &str| ancho_texto(s, tamano, negrita) <= ancho_max;
+    let mut lineas = Vec::new();
+
+    for parrafo in texto.replace("\r\n", "\n").replace('\t', " ").split('\n') {
+        let mut actual = String::new();
+        for palabra in parrafo.split_whitespace() {
+            let candidata = if actual.is_empty() { palabra.to_string() } else { format!("{} {}", actual, palabra) };
+            if cabe(&candidata) {
+                actual = candidata;
+                continue;
+            }
+            if !actual.is_empty() {
+                lineas.push(std::mem::take(&mut actual));
+            }
+            // Una palabra que no cabe sola se corta donde se llene la línea
+            for c in palabra.chars() {
+                actual.push(c);
+                if !cabe(&actual) && actual.chars().count() > 1 {
+                    actual.pop();
+                    lineas.push(std::mem::replace(&mut actual, c.to_string()));
+                }
+            }
+        }
+        lineas.push(actual);
+    }
+
+    lineas
+}
+
+fn alto_linea(tamano: f32) -> f32 {
+    tamano * PT_A_MM * 1.3
+}
+
+/// Maqueta el documento en hojas A4 y devuelve el PDF. Usa solo las fuentes
+/// estándar del formato, así que no depende de nada instalado en el equipo.
+pub fn generar_pdf(empresa: &Empresa, documento: &Documento) -> Result<Vec<u8>, String> {
+    let lienzo = maquetar(empresa, documento)?;
+    lienzo.doc.save_to_bytes().map_err(|e| format!("Error generando PDF: {}", e))
+}
+
+fn maquetar<'a>(empresa: &'a Empresa, documento: &'a Documento) -> Result<Lienzo<'a>, String> {
+    let mut lienzo = Lienzo::new(empresa, documento)?;
+    for bloque in &documento.bloques {
+        lienzo.bloque(bloque);
+    }
+    lienzo.pies();
+    Ok(lienzo)
+}
+
+struct Lienzo<'a> {
+    doc: PdfDocumentReference,
+    capas: Vec<PdfLayerReference>,
+    regular: IndirectFontRef,
+    negrita: IndirectFontRef,
+    y: f32,
+    empresa: &'a Empresa,
+    documento: &'a Documento,
+}
+
+impl<'a> Lienzo<'a> {
+    fn new(empresa: &'a Empresa, documento: &'a Documento) -> Result<Self, String> {
+        let (doc, pagina, capa) = PdfDocument::new(&documento.titulo, Mm(ANCHO_PAGINA), Mm(ALTO_PAGINA), "Contenido");
+        let fuente = |f| doc.add_builtin_font(f).map_err(|e| format!("Error cargando fuente: {}", e));
+        let regular = fuente(BuiltinFont::Helvetica)?;
+        let negrita = fuente(BuiltinFont::HelveticaBold)?;
+        let capa = doc.get_page(pagina).get_layer(capa);
+
+        let mut lienzo = Lienzo { doc, capas: vec![capa], regular, negrita, y: 0.0, empresa, documento };
+        lienzo.encabezado();
+        Ok(lienzo)
+    }
+
+    fn capa(&self) -> &PdfLayerReference {
+        self.capas.last().expect("el documento siempre tiene al menos una página")
+    }
+
+    fn texto(&self, texto: &str, tamano: f32, negrita: bool, x: f32, y: f32) {
+        let fuente = if negrita { &self.negrita } else { &self.regular };
+        self.capa().use_text(texto, tamano, Mm(x), Mm(y), fuente);
+    }
+
+    fn texto_derecha(&self, texto: &str, tamano: f32, negrita: bool, x_derecha: f32, y: f32) {
+        self.texto(texto, tamano, negrita, x_derecha - ancho_texto(texto, tamano, negrita), y);
+    }
+
+    fn texto_centrado(&self, texto: &str, tamano: f32, negrita: bool, x_centro: f32, y: f32) {
+        self.texto(texto, tamano, negrita, x_centro - ancho_texto(texto, tamano, negrita) / 2.0, y);
+    }
+
+    fn linea(&self, x1: f32, x2: f32, y: f32, grosor: f32) {
+        let capa = self.capa();
+        capa.set_outline_thickness(grosor);
+        capa.add_line(Line {
+            points: vec![(Point::new(Mm(x1), Mm(y)), false), (Point::new(Mm(x2), Mm(y)), false)],
+            is_closed: false,
+        });
+    }
+
+    fn nueva_pagina(&mut self) {
+        let (pagina, capa) = self.doc.add_page(Mm(ANCHO_PAGINA), Mm(ALTO_PAGINA), "Contenido");
+        self.capas.push(self.doc.get_page(pagina).get_layer(capa));
+        self.encabezado();
+    }
+
+    /// Pasa a una página nueva si no queda espacio para `alto` milímetros
+    fn reservar(&mut self, alto: f32) -> bool {
+        if self.y - alto < LIMITE_INFERIOR {
+            self.nueva_pagina();
+            return true;
+        }
+        false
+    }
+
+    fn encabezado(&mut self) {
+        let derecha = ANCHO_PAGINA - MARGEN;
+        let mut y = ALTO_PAGINA - MARGEN - 5.0;
+
+        self.texto(&self.empresa.nombre, 14.0, true, MARGEN, y);
+        self.texto_derecha(&self.documento.titulo, 13.0, true, derecha, y);
+
+        let contacto = [&self.empresa.telefono, &self.empresa.correo]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("  ·  ");
+        let izquierda = [
+            self.empresa.rut.as_ref().map(|rut| format!("RUT {}", rut)),
+            self.empresa.direccion.clone(),
+            Some(contacto).filter(|c| !c.is_empty()),
+        ];
+        let derecha_lineas = [self.documento.codigo.clone(), self.documento.fecha.clone()];
+
+        let mut y_derecha = y;
+        y -= 1.5;
+        for linea in izquierda.into_iter().flatten() {
+            y -= alto_linea(8.5);
+            self.texto(&linea, 8.5, false, MARGEN, y);
+        }
+        for (i, linea) in derecha_lineas.into_iter().flatten().enumerate() {
+            y_derecha -= alto_linea(10.0) + if i == 0 { 1.5 } else { 0.0 };
+            self.texto_derecha(&linea, 10.0, i == 0, derecha, y_derecha);
+        }
+
+        let y_separador = y.min(y_derecha) - 3.0;
+        self.linea(MARGEN, derecha, y_separador, 0.8);
+        self.y = y_separador - 6.0;
+    }
+
+    /// Número de página y pie en todas las hojas, una vez que se sabe cuántas son
+    fn pies(&mut self) {
+        let total = self.capas.len();
+        let pie = self.documento.pie.as_deref().map(|p| ajustar_texto(p, ANCHO_UTIL, 7.0, false));
+        for (i, capa) in self.capas.iter().enumerate() {
+            let numero = format!("Página {} de {}", i + 1, total);
+            let x = ANCHO_PAGINA / 2.0 - ancho_texto(&numero, 8.0, false) / 2.0;
+            capa.use_text(numero, 8.0, Mm(x), Mm(10.0), &self.regular);
+
+            let mut y = 10.0 + alto_linea(8.0) + 1.0;
+            for linea in pie.iter().flatten().rev() {
+                capa.use_text(linea.as_str(), 7.0, Mm(MARGEN), Mm(y), &self.regular);
+                y += alto_linea(7.0);
+            }
+        }
+    }
+
+    fn titulo_seccion(&mut self, titulo: &str) {
+        self.reservar(alto_linea(10.5) + 3.0 * alto_linea(9.0));
+        self.y -= alto_linea(10.5);
+        self.texto(titulo, 10.5, true, MARGEN, self.y);
+        self.y -= 1.5;
+        self.linea(MARGEN, MARGEN + ANCHO_UTIL, self.y, 0.3);
+        self.y -= 1.5;
+    }
+
+    fn parrafos(&mut self, texto: &str, tamano: f32, x: f32, ancho: f32) {
+        for linea in ajustar_texto(texto, ancho, tamano, false) {
+            self.reservar(alto_linea(tamano));
+            self.y -= alto_linea(tamano);
+            self.texto(&linea, tamano, false, x, self.y);
+        }
+    }
+
+    fn bloque(&mut self, bloque: &Bloque) {
+        match bloque {
+            Bloque::Datos { titulo, campos } => {
+                self.titulo_seccion(titulo);
+                let ancho_etiqueta = 42.0;
+                for (etiqueta, valor) in campos {
+                    let lineas = ajustar_texto(valor, ANCHO_UTIL - ancho_etiqueta, 9.0, false);
+                    for (i, linea) in lineas.iter().enumerate() {
+                        self.reservar(alto_linea(9.0));
+                        self.y -= alto_linea(9.0);
+                        if i == 0 {
+                            self.texto(etiqueta, 9.0, true, MARGEN, self.y);
+                        }
+                        self.texto(linea, 9.0, false, MARGEN + ancho_etiqueta, self.y);
+                    }
+                }
+                self.y -= 4.0;
+            }
+            Bloque::Texto { titulo, texto } => {
+                self.titulo_seccion(titulo);
+                self.parrafos(texto, 9.0, MARGEN, ANCHO_UTIL);
+                self.y -= 4.0;
+            }
+            Bloque::Tabla { titulo, columnas, filas } => {
+                if let Some(titulo) = titulo {
+                    self.titulo_seccion(titulo);
+                }
+                self.tabla(columnas, filas);
+            }
+            Bloque::Totales(totales) => {
+                let x_etiqueta = MARGEN + ANCHO_UTIL * 0.55;
+                let derecha = MARGEN + ANCHO_UTIL;
+                self.reservar(totales.len() as f32 * alto_linea(9.5) + 3.0);
+                for (i, (etiqueta, monto)) in totales.iter().enumerate() {
+                    let destacado = i + 1 == totales.len();
+                    if destacado {
+                        self.y -= 1.0;
+                        self.linea(x_etiqueta, derecha, self.y, 0.5);
+                    }
+                    self.y -= alto_linea(9.5);
+                    self.texto(etiqueta, 9.5, destacado, x_etiqueta, self.y);
+                    self.texto_derecha(monto, 9.5, destacado, derecha, self.y);
+                }
+                self.y -= 5.0;
+            }
+            Bloque::Firmas(leyendas) => {
+                if leyendas.is_empty() {
+                    return;
+                }
+                self.reservar(30.0);
+                self.y -= 22.0;
+                let ancho = ANCHO_UTIL / leyendas.len() as f32;
+                for (i, leyenda) in leyendas.iter().enumerate() {
+                    let x = MARGEN + ancho * i as f32;
+                    self.linea(x + 8.0, x + ancho - 8.0, self.y, 0.5);
+                    self.texto_centrado(leyenda, 8.5, false, x + ancho / 2.0, self.y - alto_linea(8.5));
+                }
+                self.y -= alto_linea(8.5) + 6.0;
+            }
+            Bloque::Nota(texto) => {
+                self.parrafos(texto, 7.5, MARGEN, ANCHO_UTIL);
+                self.y -= 3.0;
+            }
+        }
+    }
+
+    fn tabla(&mut self, columnas: &[Columna], filas: &[Vec<String>]) {
+        let tamano = 8.5;
+        let relleno = 1.5;
+        let anchos: Vec<f32> = columnas.iter().map(|c| c.ancho * ANCHO_UTIL).collect();
+
+        let celdas = |valores: &[String], negrita: bool| -> Vec<Vec<String>> {
+            anchos.iter().enumerate()
+                .map(|(i, ancho)| {
+                    let valor = valores.get(i).map(String::as_str).unwrap_or("");
+                    ajustar_texto(valor, ancho - 2.0 * relleno, tamano, negrita)
+                })
+                .collect()
+        };
+        let titulos: Vec<String> = columnas.iter().map(|c| c.titulo.clone()).collect();
+        let encabezado = celdas(&titulos, true);
+
+        // El encabezado no queda solo al final de una página
+        self.reservar(3.0 * alto_linea(tamano) + 6.0);
+        self.y -= 2.0;
+        self.fila(columnas, &anchos, &encabezado, true);
+        for fila in filas {
+            let contenido = celdas(fila, false);
+            let alto = contenido.iter().map(Vec::len).max().unwrap_or(1) as f32 * alto_linea(tamano) + 2.0;
+            if self.reservar(alto) {
+                self.fila(columnas, &anchos, &encabezado, true);
+            }
+            self.fila(columnas, &anchos, &contenido, false);
+        }
+        self.y -= 3.0;
+    }
+
+    fn fila(&mut self, columnas: &[Columna], anchos: &[f32], celdas: &[Vec<String>], encabezado: bool) {
+        let tamano = 8.5;
+        let relleno = 1.5;
+        let lineas = celdas.iter().map(Vec::len).max().unwrap_or(1);
+        self.reservar(lineas as f32 * alto_linea(tamano) + 2.0);
+
+        let tope = self.y;
+        let mut x = MARGEN;
+        for ((columna, ancho), lineas_celda) in columnas.iter().zip(anchos).zip(celdas) {
+            let mut y = tope;
+            for linea in lineas_celda {
+                y -= alto_linea(tamano);
+                match columna.alineacion {
+                    Alineacion::Izquierda => self.texto(linea, tamano, encabezado, x + relleno, y),
+                    Alineacion::Derecha => self.texto_derecha(linea, tamano, encabezado, x + ancho - relleno, y),
+                }
+            }
+            x += ancho;
+        }
+
+        self.y = tope - lineas as f32 * alto_linea(tamano) - 2.0;
+        self.linea(MARGEN, MARGEN + ANCHO_UTIL, self.y + 0.5, if encabezado { 0.6 } else { 0.2 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empresa() -> Empresa {
+        Empresa {
+            nombre: "Toscanini".to_string(),
+            rut: Some("76.123.456-7".to_string()),
+            direccion: Some("Av. Siempre Viva 742".to_string()),
+            telefono: None,
+            correo: Some("contacto@toscanini.cl".to_string()),
+        }
+    }
+
+    fn documento(filas: usize) -> Documento {
+        Documento {
+            titulo: "Cotización".to_string(),
+            codigo: Some("COT-2026-001".to_string()),
+            fecha: Some("17/10/2026".to_string()),
+            bloques: vec![
+                Bloque::Datos {
+                    titulo: "Cliente".to_string(),
+                    campos: vec![("Nombre".to_string(), "Ana Pérez".to_string())],
+                },
+                Bloque::Tabla {
+                    titulo: Some("Detalle".to_string()),
+                    columnas: vec![
+                        Columna::new("Pieza", 0.7, Alineacion::Izquierda),
+                        Columna::new("Total", 0.3, Alineacion::Derecha),
+                    ],
+                    filas: (0..filas).map(|i| vec![format!("Pieza {}", i), formatear_monto(1_000)]).collect(),
+                },
+                Bloque::Totales(vec![("Total".to_string(), formatear_monto(filas as i64 * 1_000))]),
+                Bloque::Firmas(vec!["Firma del cliente".to_string()]),
+            ],
+            pie: Some("Documento de prueba".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_formatear_monto() {
+        assert_eq!(formatear_monto(0), "$0");
+        assert_eq!(formatear_monto(999), "$999");
+        assert_eq!(formatear_monto(1_000), "$1.000");
+        assert_eq!(formatear_monto(1_234_567), "$1.234.567");
+        assert_eq!(formatear_monto(-45_000), "-$45.000");
+    }
+
+    #[test]
+    fn test_ancho_texto() {
+        // "Hola" en Helvetica: 722 + 556 + 222 + 556 = 2056 milésimas
+        assert!((ancho_texto("Hola", 10.0, false) - 2.056 * 10.0 * PT_A_MM).abs() < 1e-4);
+        assert_eq!(ancho_texto("canción", 10.0, false), ancho_texto("cancion", 10.0, false));
+        assert!(ancho_texto("Total", 10.0, true) > ancho_texto("Total", 10.0, false));
+    }
+
+    #[test]
+    fn test_ajustar_texto() {
+        let ancho = ancho_texto("cambio de fuente", 9.0, false);
+        assert_eq!(
+            ajustar_texto("cambio de fuente y limpieza", ancho, 9.0, false),
+            vec!["cambio de fuente", "y limpieza"]
+        );
+        assert_eq!(ajustar_texto("uno\n\ndos", 100.0, 9.0, false), vec!["uno", "", "dos"]);
+        assert_eq!(ajustar_texto("", 100.0, 9.0, false), vec![""]);
+
+        let cortada = ajustar_texto("ABCDEFGHIJ", ancho_texto("ABCD", 9.0, false), 9.0, false);
+        assert_eq!(cortada, vec!["ABCD", "EFGH", "IJ"]);
+    }
+
+    #[test]
+    fn test_validar_ruta_pdf() {
+        let ruta = std::env::temp_dir().join("informe.pdf");
+        assert_eq!(validar_ruta_pdf(ruta.to_str().unwrap()), Ok(ruta.clone()));
+        assert!(validar_ruta_pdf(std::env::temp_dir().join("informe.PDF").to_str().unwrap()).is_ok());
+        assert!(validar_ruta_pdf(std::env::temp_dir().join("informe.txt").to_str().unwrap()).is_err());
+        assert!(validar_ruta_pdf("informe.pdf").is_err());
+        assert!(validar_ruta_pdf("  ").is_err());
+    }
+
+    #[test]
+    fn test_validar_nombre_empresa() {
+        assert_eq!(validar_nombre_empresa(" Toscanini "), Ok("Toscanini".to_string()));
+        assert!(validar_nombre_empresa("").is_err());
+        assert!(validar_nombre_empresa(&"x".repeat(EMPRESA_NOMBRE_MAX_LARGO + 1)).is_err());
+    }
+
+    #[test]
+    fn test_generar_pdf() {
+        let pdf = generar_pdf(&empresa(), &documento(3)).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn test_tabla_larga_ocupa_varias_paginas() {
+        let (empresa, corto, largo) = (empresa(), documento(3), documento(120));
+        assert_eq!(maquetar(&empresa, &corto).unwrap().capas.len(), 1);
+        assert!(maquetar(&empresa, &largo).unwrap().capas.len() > 1);
+    }
+}
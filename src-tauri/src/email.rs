@@ -1,4 +1,4 @@
-use resend_rs::{Resend, types::CreateEmailBaseOptions};
+use resend_rs::{Resend, types::{Attachment, CreateEmailBaseOptions}};
 use std::env;
 use chrono::{DateTime, Utc};
 
@@ -62,7 +62,8 @@ impl EmailService {
         client_name: &str, 
         informe: &crate::commands::informe::Informe,
         orden_trabajo: &crate::commands::ordenes_trabajo::OrdenTrabajo,
-        piezas: &[crate::commands::informe::PiezaInforme]
+        piezas: &[crate::commands::informe::PiezaInforme],
+        adjuntos: &[crate::adjuntos::ArchivoCorreo]
     ) -> Result<(), String> {
         let from = "onboarding@resend.dev"; // Cambiar por tu dominio verificado
         let to = vec![to_email.to_string()];
@@ -203,8 +204,10 @@ impl EmailService {
             }
         );
 
-        let email = CreateEmailBaseOptions::new(from, to, subject)
-            .with_html(&html_content);
+        let email = con_adjuntos(
+            CreateEmailBaseOptions::new(from, to, subject).with_html(&html_content),
+            adjuntos,
+        );
 
         self.resend.emails.send(email).await
             .map_err(|e| format!("Error sending email: {}", e))?;        Ok(())
@@ -322,4 +325,363 @@ impl EmailService {
 
         Ok(())
     }
+
+    pub async fn send_recordatorio_retiro_email(
+        &self,
+        to_email: &str,
+        client_name: &str,
+        orden_trabajo: &crate::commands::ordenes_trabajo::OrdenTrabajo,
+        nivel: i64,
+        total_niveles: i64,
+        fecha_limite: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let from = "onboarding@resend.dev"; // Cambiar por tu dominio verificado
+        let to = vec![to_email.to_string()];
+        let codigo = orden_trabajo.orden_codigo.as_deref().unwrap_or("N/A");
+
+        // El tono del correo escala con cada recordatorio
+        let (subject, titulo, color, mensaje) = if nivel >= total_niveles {
+            (
+                format!("Último aviso: retiro de su equipo {} - Toscanini", codigo),
+                "Último Aviso de Retiro",
+                "#dc3545",
+                "Este es el último aviso antes de que su equipo sea considerado abandonado. \
+                 Si no lo retira antes de la fecha indicada, el taller podrá disponer de él \
+                 según las condiciones del servicio.",
+            )
+        } else if nivel > 1 {
+            (
+                format!("Recordatorio: su equipo {} sigue esperando retiro - Toscanini", codigo),
+                "Su Equipo Sigue Esperando Retiro",
+                "#fd7e14",
+                "Le recordamos nuevamente que su equipo se encuentra listo en nuestro taller. \
+                 Le solicitamos coordinar su retiro a la brevedad.",
+            )
+        } else {
+            (
+                format!("Su equipo {} está listo para retiro - Toscanini", codigo),
+                "Su Equipo Está Listo para Retiro",
+                "#007bff",
+                "Le recordamos que su equipo se encuentra listo para ser retirado en nuestro taller.",
+            )
+        };
+
+        let html_content = format!(
+            r#"
+            <div style="font-family: Arial, sans-serif; max-width: 600px; margin: 0 auto; padding: 20px;">
+                <div style="text-align: center; margin-bottom: 30px;">
+                    <h1 style="color: #333; margin: 0;">Toscanini</h1>
+                    <p style="color: #666; margin: 5px 0;">Servicio Técnico Especializado</p>
+                </div>
+                
+                <h2 style="color: {}; border-bottom: 2px solid {}; padding-bottom: 10px;">{}</h2>
+                
+                <p>Estimado/a <strong>{}</strong>,</p>
+                <p>{}</p>
+                
+                <div style="background-color: #f8f9fa; padding: 20px; margin: 20px 0; border-radius: 5px;">
+                    <p><strong>Código de Orden:</strong> {}</p>
+                    <p><strong>Descripción:</strong> {}</p>
+                    <p><strong>Fecha límite de retiro:</strong> {}</p>
+                </div>
+                
+                <p style="color: #666;">Recordatorio {} de {}.</p>
+                
+                <hr style="margin: 30px 0; border: 1px solid #eee;">
+                <p style="color: #666; font-size: 12px; text-align: center;">
+                    Este es un correo automático, por favor no respondas a este mensaje.<br>
+                    Para consultas, contacta directamente con nuestro equipo de soporte.
+                </p>
+            </div>
+            "#,
+            color, color, titulo,
+            client_name,
+            mensaje,
+            codigo,
+            orden_trabajo.orden_desc.as_deref().unwrap_or("Sin descripción"),
+            fecha_limite.format("%d/%m/%Y"),
+            nivel, total_niveles
+        );
+
+        let email = CreateEmailBaseOptions::new(from, to, subject)
+            .with_html(&html_content);
+
+        self.resend.emails.send(email).await
+            .map_err(|e| format!("Error sending email: {}", e))?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_cotizacion_email(
+        &self,
+        to_email: &str,
+        client_name: &str,
+        cotizacion: &crate::commands::cotizacion::CotizacionDetallada,
+        orden_trabajo: &crate::commands::ordenes_trabajo::OrdenTrabajo,
+        piezas: &[crate::commands::cotizacion::PiezaCotizacion],
+        enlaces: Option<&crate::commands::aprobacion_cotizacion::EnlacesCotizacion>,
+        adjuntos: &[crate::adjuntos::ArchivoCorreo]
+    ) -> Result<(), String> {
+        let from = "onboarding@resend.dev"; // Cambiar por tu dominio verificado
+        let to = vec![to_email.to_string()];
+        let subject = format!("Cotización {} - Toscanini",
+            cotizacion.cotizacion_codigo.as_deref().unwrap_or("N/A"));
+
+        let celda = "border: 1px solid #dee2e6; padding: 12px;";
+
+        let mut filas = String::new();
+        for (concepto, monto) in [
+            ("Revisión", cotizacion.costo_revision),
+            ("Reparación", cotizacion.costo_reparacion),
+        ] {
+            if let Some(monto) = monto.filter(|m| *m > 0) {
+                filas.push_str(&format!(
+                    r#"<tr>
+                        <td style="{celda}">{}</td>
+                        <td style="{celda} text-align: center;">1</td>
+                        <td style="{celda} text-align: right;">${}</td>
+                        <td style="{celda} text-align: center;">-</td>
+                        <td style="{celda} text-align: right;">${}</td>
+                    </tr>"#,
+                    concepto, monto, monto, celda = celda
+                ));
+            }
+        }
+        for pieza in piezas {
+            let descuento = pieza.descuento_pct.unwrap_or(0);
+            filas.push_str(&format!(
+                r#"<tr>
+                    <td style="{celda}">{}{}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: right;">${}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: right;">${}</td>
+                </tr>"#,
+                pieza.pieza_nombre.as_deref().unwrap_or("N/A"),
+                if pieza.es_exenta.unwrap_or(false) { " (exento)" } else { "" },
+                pieza.cantidad.unwrap_or(1),
+                pieza.precio_unitario.unwrap_or(0),
+                if descuento > 0 { format!("{}%", descuento) } else { "-".to_string() },
+                pieza.total_linea.unwrap_or(0),
+                celda = celda
+            ));
+        }
+
+        // Desglose en el mismo orden que la factura: neto, IVA, exento y total
+        let fila_resumen = |concepto: &str, monto: String| format!(
+            r#"<tr>
+                <td colspan="4" style="{celda} text-align: right;">{}</td>
+                <td style="{celda} text-align: right;">{}</td>
+            </tr>"#,
+            concepto, monto, celda = celda
+        );
+        let mut resumen = String::new();
+        if cotizacion.monto_descuento.unwrap_or(0) > 0 {
+            let global = cotizacion.descuento_global_pct.unwrap_or(0);
+            let concepto = if global > 0 {
+                format!("Descuentos (incluye {}% global):", global)
+            } else {
+                "Descuentos:".to_string()
+            };
+            resumen.push_str(&fila_resumen(&concepto, format!("-${}", cotizacion.monto_descuento.unwrap_or(0))));
+        }
+        resumen.push_str(&fila_resumen("Neto:", format!("${}", cotizacion.monto_neto.unwrap_or(0))));
+        resumen.push_str(&fila_resumen(
+            &format!("IVA ({}%):", cotizacion.iva_porcentaje.unwrap_or(0)),
+            format!("${}", cotizacion.monto_iva.unwrap_or(0)),
+        ));
+        if cotizacion.monto_exento.unwrap_or(0) > 0 {
+            resumen.push_str(&fila_resumen("Exento:", format!("${}", cotizacion.monto_exento.unwrap_or(0))));
+        }
+
+        // Botones de respuesta, solo si la orden espera la aprobación del cliente
+        let respuesta = match enlaces {
+            Some(enlaces) => format!(
+                r#"<div style="text-align: center; margin: 30px 0;">
+                    <p>¿Desea realizar la reparación?</p>
+                    <a href="{}" style="background-color: #28a745; color: #fff; padding: 12px 24px; border-radius: 5px; text-decoration: none; margin: 0 10px;">Aprobar</a>
+                    <a href="{}" style="background-color: #dc3545; color: #fff; padding: 12px 24px; border-radius: 5px; text-decoration: none; margin: 0 10px;">Rechazar</a>
+                    <p style="color: #666; font-size: 12px; margin-top: 20px;">Los enlaces son válidos hasta el {} y solo pueden usarse una vez.</p>
+                </div>"#,
+                enlaces.url_aprobar,
+                enlaces.url_rechazar,
+                enlaces.expira_at.format("%d/%m/%Y %H:%M UTC")
+            ),
+            None => String::new(),
+        };
+
+        let html_content = format!(
+            r#"
+            <div style="font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px;">
+                <div style="text-align: center; margin-bottom: 30px;">
+                    <h1 style="color: #333; margin: 0;">Toscanini</h1>
+                    <p style="color: #666; margin: 5px 0;">Servicio Técnico Especializado</p>
+                </div>
+
+                <h2 style="color: #007bff; border-bottom: 2px solid #007bff; padding-bottom: 10px;">
+                    Cotización {}
+                </h2>
+
+                <p>Estimado/a <strong>{}</strong>,</p>
+
+                <p>Le enviamos la cotización para la reparación de su equipo.</p>
+
+                <div style="background-color: #f8f9fa; padding: 20px; margin: 20px 0; border-radius: 5px;">
+                    <p><strong>Código de Orden:</strong> {}</p>
+                    <p><strong>Descripción:</strong> {}</p>
+                    <p><strong>Válida hasta:</strong> {}</p>
+                </div>
+
+                <div style="background-color: #ffffff; padding: 20px; margin: 20px 0; border: 1px solid #dee2e6; border-radius: 5px;">
+                    <h3 style="margin-top: 0; color: #333;">Diagnóstico</h3>
+                    <p style="margin: 0; line-height: 1.6;">{}</p>
+                </div>
+
+                <table style="width: 100%; border-collapse: collapse; margin: 20px 0;">
+                    <thead>
+                        <tr style="background-color: #f8f9fa;">
+                            <th style="{celda} text-align: left;">Concepto</th>
+                            <th style="{celda} text-align: center;">Cantidad</th>
+                            <th style="{celda} text-align: right;">Precio Unit.</th>
+                            <th style="{celda} text-align: center;">Descuento</th>
+                            <th style="{celda} text-align: right;">Subtotal</th>
+                        </tr>
+                    </thead>
+                    <tbody>{}</tbody>
+                    <tfoot>
+                        {}
+                        <tr style="background-color: #e9ecef; font-weight: bold;">
+                            <td colspan="4" style="{celda} text-align: right;">Total:</td>
+                            <td style="{celda} text-align: right;">${}</td>
+                        </tr>
+                    </tfoot>
+                </table>
+
+                {}
+
+                <hr style="margin: 30px 0; border: 1px solid #eee;">
+                <p style="color: #666; font-size: 12px; text-align: center;">
+                    Este es un correo automático, por favor no respondas a este mensaje.<br>
+                    Para consultas, contacta directamente con nuestro equipo de soporte.
+                </p>
+            </div>
+            "#,
+            cotizacion.cotizacion_codigo.as_deref().unwrap_or("N/A"),
+            client_name,
+            orden_trabajo.orden_codigo.as_deref().unwrap_or("N/A"),
+            orden_trabajo.orden_desc.as_deref().unwrap_or("Sin descripción"),
+            cotizacion.valido_hasta.map_or("Sin vencimiento".to_string(), |f| f.format("%d/%m/%Y").to_string()),
+            cotizacion.informe,
+            filas,
+            resumen,
+            cotizacion.costo_total.unwrap_or(0),
+            respuesta,
+            celda = celda
+        );
+
+        let email = con_adjuntos(
+            CreateEmailBaseOptions::new(from, to, subject).with_html(&html_content),
+            adjuntos,
+        );
+
+        self.resend.emails.send(email).await
+            .map_err(|e| format!("Error sending email: {}", e))?;
+
+        Ok(())
+    }
+
+    pub async fn send_resumen_stock_bajo_email(
+        &self,
+        to_email: &str,
+        piezas: &[crate::commands::inventario::PiezaBajoMinimo],
+        dias_consumo: i64,
+    ) -> Result<(), String> {
+        let from = "onboarding@resend.dev"; // Cambiar por tu dominio verificado
+        let to = vec![to_email.to_string()];
+        let subject = format!("Resumen de stock: {} piezas bajo el mínimo - Toscanini", piezas.len());
+
+        let celda = "border: 1px solid #dee2e6; padding: 8px;";
+
+        let mut filas = String::new();
+        for pieza in piezas {
+            filas.push_str(&format!(
+                r#"<tr>
+                    <td style="{celda}">{}{}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: center;">{:.1}</td>
+                    <td style="{celda} text-align: center;">{}</td>
+                    <td style="{celda} text-align: center;"><strong>{}</strong></td>
+                </tr>"#,
+                pieza.stock.pieza_nombre.as_deref().unwrap_or("N/A"),
+                pieza.stock.pieza_marca.as_deref().map_or(String::new(), |m| format!(" ({})", m)),
+                pieza.stock.stock_disponible,
+                pieza.stock.stock_reservado,
+                pieza.stock.stock_minimo.unwrap_or(0),
+                pieza.consumo_diario,
+                pieza.dias_cobertura.map_or("-".to_string(), |d| format!("{:.0}", d)),
+                pieza.cantidad_sugerida,
+                celda = celda
+            ));
+        }
+
+        let html_content = format!(
+            r#"
+            <div style="font-family: Arial, sans-serif; max-width: 700px; margin: 0 auto; padding: 20px;">
+                <div style="text-align: center; margin-bottom: 30px;">
+                    <h1 style="color: #333; margin: 0;">Toscanini</h1>
+                    <p style="color: #666; margin: 5px 0;">Resumen diario de stock</p>
+                </div>
+                
+                <h2 style="color: #fd7e14; border-bottom: 2px solid #fd7e14; padding-bottom: 10px;">Piezas bajo el mínimo</h2>
+                
+                <p>Las siguientes piezas tienen menos unidades disponibles que su stock mínimo.
+                   El consumo considera los informes de los últimos {} días.</p>
+                
+                <table style="width: 100%; border-collapse: collapse; margin: 20px 0; font-size: 14px;">
+                    <thead>
+                        <tr style="background-color: #f8f9fa;">
+                            <th style="{celda} text-align: left;">Pieza</th>
+                            <th style="{celda}">Disponible</th>
+                            <th style="{celda}">Reservado</th>
+                            <th style="{celda}">Mínimo</th>
+                            <th style="{celda}">Consumo diario</th>
+                            <th style="{celda}">Días de cobertura</th>
+                            <th style="{celda}">Pedir</th>
+                        </tr>
+                    </thead>
+                    <tbody>{}</tbody>
+                </table>
+                
+                <hr style="margin: 30px 0; border: 1px solid #eee;">
+                <p style="color: #666; font-size: 12px; text-align: center;">
+                    Este es un correo automático generado por el sistema de gestión del taller.
+                </p>
+            </div>
+            "#,
+            dias_consumo, filas, celda = celda
+        );
+
+        let email = CreateEmailBaseOptions::new(from, to, subject)
+            .with_html(&html_content);
+
+        self.resend.emails.send(email).await
+            .map_err(|e| format!("Error sending email: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Agrega los archivos al correo con su nombre y tipo
+fn con_adjuntos(mut email: CreateEmailBaseOptions, adjuntos: &[crate::adjuntos::ArchivoCorreo]) -> CreateEmailBaseOptions {
+    for archivo in adjuntos {
+        email = email.with_attachment(
+            Attachment::from_content(archivo.contenido.clone())
+                .with_filename(&archivo.nombre_archivo)
+                .with_content_type(&archivo.mime_type),
+        );
+    }
+    email
 }
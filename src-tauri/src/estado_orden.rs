@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Estados posibles de ORDEN_TRABAJO.estado
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoOrden {
+    Recibido,
+    CotizacionEnviada,
+    AprobacionPendiente,
+    EnReparacion,
+    EsperaDeRetiro,
+    Entregado,
+    Abandonado,
+    EquipoNoReparable,
+    CotizacionRechazada,
+}
+
+impl FromStr for EstadoOrden {
+    type Err = String;
+
+    fn from_str(estado: &str) -> Result<Self, Self::Err> {
+        match estado {
+            "recibido" => Ok(EstadoOrden::Recibido),
+            "cotizacion_enviada" => Ok(EstadoOrden::CotizacionEnviada),
+            "aprobacion_pendiente" => Ok(EstadoOrden::AprobacionPendiente),
+            "en_reparacion" => Ok(EstadoOrden::EnReparacion),
+            "espera_de_retiro" => Ok(EstadoOrden::EsperaDeRetiro),
+            "entregado" => Ok(EstadoOrden::Entregado),
+            "abandonado" => Ok(EstadoOrden::Abandonado),
+            "equipo_no_reparable" => Ok(EstadoOrden::EquipoNoReparable),
+            "cotizacion_rechazada" => Ok(EstadoOrden::CotizacionRechazada),
+            _ => Err("Estado no válido".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for EstadoOrden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl EstadoOrden {
+    /// Estado con el que se crea toda orden de trabajo
+    pub const INICIAL: EstadoOrden = EstadoOrden::Recibido;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoOrden::Recibido => "recibido",
+            EstadoOrden::CotizacionEnviada => "cotizacion_enviada",
+            EstadoOrden::AprobacionPendiente => "aprobacion_pendiente",
+            EstadoOrden::EnReparacion => "en_reparacion",
+            EstadoOrden::EsperaDeRetiro => "espera_de_retiro",
+            EstadoOrden::Entregado => "entregado",
+            EstadoOrden::Abandonado => "abandonado",
+            EstadoOrden::EquipoNoReparable => "equipo_no_reparable",
+            EstadoOrden::CotizacionRechazada => "cotizacion_rechazada",
+        }
+    }
+
+    /// Tabla de transiciones: estados a los que se puede pasar desde el actual
+    pub fn siguientes(&self) -> &'static [EstadoOrden] {
+        use EstadoOrden::*;
+        match self {
+            Recibido => &[CotizacionEnviada, EquipoNoReparable],
+            CotizacionEnviada => &[AprobacionPendiente, CotizacionRechazada],
+            AprobacionPendiente => &[EnReparacion, CotizacionRechazada],
+            EnReparacion => &[EsperaDeRetiro, EquipoNoReparable],
+            EsperaDeRetiro => &[Entregado, Abandonado],
+            CotizacionRechazada => &[Entregado, Abandonado],
+            EquipoNoReparable => &[Entregado, Abandonado],
+            Abandonado => &[Entregado],
+            Entregado => &[],
+        }
+    }
+
+    pub fn puede_pasar_a(&self, destino: EstadoOrden) -> bool {
+        self.siguientes().contains(&destino)
+    }
+
+    /// Valida la transición y devuelve un mensaje legible si no está permitida
+    pub fn validar_transicion(&self, destino: EstadoOrden) -> Result<(), String> {
+        if *self == destino {
+            return Err(format!("La orden ya se encuentra en estado '{}'", destino));
+        }
+        if !self.puede_pasar_a(destino) {
+            return Err(format!(
+                "Transición no permitida: '{}' → '{}'",
+                self, destino
+            ));
+        }
+        Ok(())
+    }
+
+    /// Un estado final no admite más cambios
+    pub fn es_final(&self) -> bool {
+        self.siguientes().is_empty()
+    }
+
+    /// El equipo ya salió del taller (retirado o abandonado)
+    pub fn esta_cerrada(&self) -> bool {
+        matches!(self, EstadoOrden::Entregado | EstadoOrden::Abandonado)
+    }
+
+    /// Estados en que la orden deja de depender del taller y se detiene el reloj del SLA
+    pub fn detiene_sla(&self) -> bool {
+        matches!(
+            self,
+            EstadoOrden::EsperaDeRetiro
+                | EstadoOrden::Entregado
+                | EstadoOrden::Abandonado
+                | EstadoOrden::EquipoNoReparable
+                | EstadoOrden::CotizacionRechazada
+        )
+    }
+
+    /// Estados en que la reparación ya no se hará y las piezas reservadas vuelven a estar disponibles
+    pub fn libera_reservas(&self) -> bool {
+        matches!(
+            self,
+            EstadoOrden::Abandonado | EstadoOrden::EquipoNoReparable | EstadoOrden::CotizacionRechazada
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabla_de_transiciones() {
+        assert!(EstadoOrden::CotizacionEnviada.puede_pasar_a(EstadoOrden::AprobacionPendiente));
+        assert!(EstadoOrden::CotizacionEnviada.puede_pasar_a(EstadoOrden::CotizacionRechazada));
+        assert!(EstadoOrden::EsperaDeRetiro.puede_pasar_a(EstadoOrden::Abandonado));
+
+        // No se puede retroceder ni saltarse etapas
+        assert!(!EstadoOrden::Entregado.puede_pasar_a(EstadoOrden::Recibido));
+        assert!(!EstadoOrden::Recibido.puede_pasar_a(EstadoOrden::Entregado));
+        assert!(EstadoOrden::Entregado.es_final());
+    }
+
+    #[test]
+    fn test_estados_que_detienen_sla() {
+        assert!(EstadoOrden::EsperaDeRetiro.detiene_sla());
+        assert!(EstadoOrden::CotizacionRechazada.detiene_sla());
+        assert!(!EstadoOrden::AprobacionPendiente.detiene_sla());
+        assert!(!EstadoOrden::EnReparacion.detiene_sla());
+
+        // Toda orden cerrada tiene el SLA detenido
+        assert!(EstadoOrden::Entregado.esta_cerrada() && EstadoOrden::Entregado.detiene_sla());
+        assert!(EstadoOrden::Abandonado.esta_cerrada() && EstadoOrden::Abandonado.detiene_sla());
+    }
+
+    #[test]
+    fn test_estados_que_liberan_reservas() {
+        assert!(EstadoOrden::Abandonado.libera_reservas());
+        assert!(EstadoOrden::CotizacionRechazada.libera_reservas());
+        assert!(!EstadoOrden::EnReparacion.libera_reservas());
+        // Una orden entregada consume sus reservas al finalizar el informe, no las libera
+        assert!(!EstadoOrden::Entregado.libera_reservas());
+    }
+
+    #[test]
+    fn test_validar_transicion() {
+        assert!(EstadoOrden::Recibido.validar_transicion(EstadoOrden::CotizacionEnviada).is_ok());
+        assert!(EstadoOrden::Recibido.validar_transicion(EstadoOrden::Recibido).is_err());
+        assert!(EstadoOrden::Recibido.validar_transicion(EstadoOrden::EnReparacion).is_err());
+    }
+
+    #[test]
+    fn test_estado_from_str() {
+        for estado in [
+            EstadoOrden::Recibido,
+            EstadoOrden::CotizacionEnviada,
+            EstadoOrden::AprobacionPendiente,
+            EstadoOrden::EnReparacion,
+            EstadoOrden::EsperaDeRetiro,
+            EstadoOrden::Entregado,
+            EstadoOrden::Abandonado,
+            EstadoOrden::EquipoNoReparable,
+            EstadoOrden::CotizacionRechazada,
+        ] {
+            assert_eq!(estado.as_str().parse::<EstadoOrden>(), Ok(estado));
+        }
+        assert!("pendiente".parse::<EstadoOrden>().is_err());
+    }
+}
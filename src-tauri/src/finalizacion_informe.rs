@@ -0,0 +1,143 @@
+use crate::adjuntos::hash_sha256;
+use serde::Serialize;
+
+/// Versión del formato canónico. Va dentro del contenido firmado, así un
+/// cambio de formato no se confunde con una alteración del informe.
+pub const VERSION_CONTENIDO: u32 = 2;
+
+/// Largo máximo de INFORME.motivo_enmienda
+pub const MOTIVO_ENMIENDA_MAX_LARGO: usize = 512;
+
+/// Lo que cubre el digest de un informe finalizado
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContenidoInforme {
+    pub version: u32,
+    pub informe_codigo: Option<String>,
+    // Código del informe que esta enmienda corrige
+    pub enmienda_de: Option<String>,
+    pub informe_acciones: Option<String>,
+    pub informe_obs: Option<String>,
+    pub diagnostico: Option<String>,
+    pub recomendaciones: Option<String>,
+    pub solucion_aplicada: Option<String>,
+    pub tecnico_responsable: Option<String>,
+    pub piezas: Vec<LineaContenido>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LineaContenido {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    // Números de serie de las unidades instaladas con esta línea
+    pub series: Vec<String>,
+}
+
+impl ContenidoInforme {
+    /// JSON con los campos en orden fijo y las piezas y sus series ordenadas,
+    /// para que el mismo informe produzca siempre el mismo texto
+    pub fn canonico(&self) -> String {
+        let mut contenido = self.clone();
+        for linea in &mut contenido.piezas {
+            linea.series.sort();
+        }
+        contenido.piezas.sort();
+        serde_json::to_string(&contenido).expect("el contenido del informe siempre se puede serializar")
+    }
+
+    /// SHA-256 del contenido canónico en hexadecimal
+    pub fn digest(&self) -> String {
+        hash_sha256(self.canonico().as_bytes())
+    }
+}
+
+/// El motivo de una enmienda es obligatorio y queda en el informe nuevo
+pub fn validar_motivo_enmienda(motivo: &str) -> Result<String, String> {
+    let motivo = motivo.trim();
+    if motivo.is_empty() {
+        return Err("Indique el motivo de la enmienda".to_string());
+    }
+    if motivo.chars().count() > MOTIVO_ENMIENDA_MAX_LARGO {
+        return Err(format!("El motivo de la enmienda no puede superar los {} caracteres", MOTIVO_ENMIENDA_MAX_LARGO));
+    }
+    Ok(motivo.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contenido(piezas: Vec<LineaContenido>) -> ContenidoInforme {
+        ContenidoInforme {
+            version: VERSION_CONTENIDO,
+            informe_codigo: Some("INF-2026-001".to_string()),
+            enmienda_de: None,
+            informe_acciones: Some("Cambio de amplificador".to_string()),
+            informe_obs: None,
+            diagnostico: Some("Sin potencia de salida".to_string()),
+            recomendaciones: None,
+            solucion_aplicada: None,
+            tecnico_responsable: Some("Ana".to_string()),
+            piezas,
+        }
+    }
+
+    fn linea(pieza_id: i32, cantidad: i32) -> LineaContenido {
+        LineaContenido { pieza_id, cantidad, series: Vec::new() }
+    }
+
+    fn linea_con_series(pieza_id: i32, series: &[&str]) -> LineaContenido {
+        LineaContenido {
+            pieza_id,
+            cantidad: series.len() as i32,
+            series: series.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_canonico() {
+        assert_eq!(
+            contenido(vec![linea(7, 1)]).canonico(),
+            "{\"version\":2,\"informe_codigo\":\"INF-2026-001\",\"enmienda_de\":null,\
+             \"informe_acciones\":\"Cambio de amplificador\",\"informe_obs\":null,\
+             \"diagnostico\":\"Sin potencia de salida\",\"recomendaciones\":null,\
+             \"solucion_aplicada\":null,\"tecnico_responsable\":\"Ana\",\
+             \"piezas\":[{\"pieza_id\":7,\"cantidad\":1,\"series\":[]}]}"
+        );
+    }
+
+    #[test]
+    fn test_digest_no_depende_del_orden_de_las_piezas() {
+        let a = contenido(vec![linea(3, 2), linea(1, 1)]);
+        let b = contenido(vec![linea(1, 1), linea(3, 2)]);
+        assert_eq!(a.digest(), b.digest());
+        assert_eq!(a.digest().len(), 64);
+    }
+
+    #[test]
+    fn test_digest_detecta_cambios() {
+        let original = contenido(vec![linea(1, 1)]);
+        assert_ne!(original.digest(), contenido(vec![linea(1, 2)]).digest());
+
+        let mut editado = original.clone();
+        editado.informe_obs = Some("".to_string());
+        assert_ne!(original.digest(), editado.digest());
+
+        let mut enmienda = original.clone();
+        enmienda.enmienda_de = Some("INF-2026-000".to_string());
+        assert_ne!(original.digest(), enmienda.digest());
+    }
+
+    #[test]
+    fn test_digest_cubre_las_series() {
+        let original = contenido(vec![linea_con_series(4, &["AMP-002", "AMP-001"])]);
+        assert_eq!(original.digest(), contenido(vec![linea_con_series(4, &["AMP-001", "AMP-002"])]).digest());
+        assert_ne!(original.digest(), contenido(vec![linea_con_series(4, &["AMP-001", "AMP-003"])]).digest());
+    }
+
+    #[test]
+    fn test_validar_motivo_enmienda() {
+        assert_eq!(validar_motivo_enmienda("  Cantidad mal digitada "), Ok("Cantidad mal digitada".to_string()));
+        assert!(validar_motivo_enmienda("   ").is_err());
+        assert!(validar_motivo_enmienda(&"x".repeat(MOTIVO_ENMIENDA_MAX_LARGO + 1)).is_err());
+    }
+}
@@ -0,0 +1,403 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Días de historial de informes con que se calcula el consumo
+pub const CONSUMO_DIAS_POR_DEFECTO: i64 = 30;
+
+/// Tipos de movimiento del registro de stock (MOVIMIENTO_STOCK.tipo)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoMovimiento {
+    RecepcionCompra,
+    ConsumoInforme,
+    Ajuste,
+    Devolucion,
+}
+
+impl FromStr for TipoMovimiento {
+    type Err = String;
+
+    fn from_str(tipo: &str) -> Result<Self, Self::Err> {
+        match tipo {
+            "recepcion_compra" => Ok(TipoMovimiento::RecepcionCompra),
+            "consumo_informe" => Ok(TipoMovimiento::ConsumoInforme),
+            "ajuste" => Ok(TipoMovimiento::Ajuste),
+            "devolucion" => Ok(TipoMovimiento::Devolucion),
+            _ => Err(format!("Tipo de movimiento no válido: {}", tipo)),
+        }
+    }
+}
+
+impl fmt::Display for TipoMovimiento {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TipoMovimiento {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipoMovimiento::RecepcionCompra => "recepcion_compra",
+            TipoMovimiento::ConsumoInforme => "consumo_informe",
+            TipoMovimiento::Ajuste => "ajuste",
+            TipoMovimiento::Devolucion => "devolucion",
+        }
+    }
+
+    /// Valida el signo de la cantidad: las recepciones y devoluciones suman,
+    /// el consumo resta y un ajuste puede ir en cualquier sentido
+    pub fn validar_cantidad(&self, cantidad: i32) -> Result<(), String> {
+        let valida = match self {
+            TipoMovimiento::RecepcionCompra | TipoMovimiento::Devolucion => cantidad > 0,
+            TipoMovimiento::ConsumoInforme => cantidad < 0,
+            TipoMovimiento::Ajuste => cantidad != 0,
+        };
+        if !valida {
+            return Err(format!("Cantidad {} no válida para un movimiento de tipo '{}'", cantidad, self));
+        }
+        Ok(())
+    }
+}
+
+/// Qué hacer cuando un movimiento dejaría el stock bajo cero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoliticaStockNegativo {
+    /// No se registra el movimiento
+    Rechazar,
+    /// Se registra igual y se devuelve una advertencia
+    Advertir,
+}
+
+impl FromStr for PoliticaStockNegativo {
+    type Err = String;
+
+    fn from_str(valor: &str) -> Result<Self, Self::Err> {
+        match valor.trim() {
+            "rechazar" => Ok(PoliticaStockNegativo::Rechazar),
+            "advertir" => Ok(PoliticaStockNegativo::Advertir),
+            _ => Err("La política de stock negativo debe ser 'rechazar' o 'advertir'".to_string()),
+        }
+    }
+}
+
+/// Estado de una reserva de stock (RESERVA_STOCK.estado)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoReserva {
+    /// Cotización aprobada, reparación pendiente
+    Activa,
+    /// Rechazo, abandono o eliminación de la cotización
+    Liberada,
+    /// El informe se finalizó y las piezas quedaron consumidas
+    Consumida,
+}
+
+impl EstadoReserva {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoReserva::Activa => "activa",
+            EstadoReserva::Liberada => "liberada",
+            EstadoReserva::Consumida => "consumida",
+        }
+    }
+}
+
+/// Estado de una unidad con número de serie (UNIDAD_PIEZA.estado)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstadoUnidad {
+    /// En bodega, se puede elegir en un informe
+    Disponible,
+    /// Montada en el equipo de la orden del informe
+    Instalada,
+}
+
+impl FromStr for EstadoUnidad {
+    type Err = String;
+
+    fn from_str(estado: &str) -> Result<Self, Self::Err> {
+        match estado {
+            "disponible" => Ok(EstadoUnidad::Disponible),
+            "instalada" => Ok(EstadoUnidad::Instalada),
+            _ => Err(format!("Estado de unidad no válido: {}", estado)),
+        }
+    }
+}
+
+impl EstadoUnidad {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstadoUnidad::Disponible => "disponible",
+            EstadoUnidad::Instalada => "instalada",
+        }
+    }
+}
+
+/// Largo máximo de UNIDAD_PIEZA.numero_serie
+pub const SERIE_MAX_LARGO: usize = 64;
+
+/// Normaliza la lista de números de serie de una línea: sin espacios en los
+/// extremos, sin vacíos ni repetidos, y exactamente una serie por unidad.
+pub fn validar_series(pieza: &str, series: &[String], cantidad: i32) -> Result<Vec<String>, String> {
+    let mut normalizadas: Vec<String> = Vec::with_capacity(series.len());
+    for serie in series {
+        let serie = serie.trim();
+        if serie.is_empty() {
+            return Err(format!("Hay un número de serie vacío para {}", pieza));
+        }
+        if serie.chars().count() > SERIE_MAX_LARGO {
+            return Err(format!("El número de serie {} supera los {} caracteres", serie, SERIE_MAX_LARGO));
+        }
+        if normalizadas.iter().any(|s| s.eq_ignore_ascii_case(serie)) {
+            return Err(format!("El número de serie {} está repetido", serie));
+        }
+        normalizadas.push(serie.to_string());
+    }
+    if normalizadas.len() != cantidad.max(0) as usize {
+        return Err(format!(
+            "{} requiere número de serie: se indicaron {} series para {} unidades",
+            pieza, normalizadas.len(), cantidad
+        ));
+    }
+    Ok(normalizadas)
+}
+
+/// Stock que resulta de aplicar el movimiento. Si el movimiento resta, se
+/// verifica contra lo disponible (stock menos lo reservado para otras órdenes)
+/// y se devuelve una advertencia si no alcanza y la política lo permite.
+pub fn aplicar_movimiento(
+    pieza: &str,
+    stock_actual: i32,
+    reservado: i32,
+    cantidad: i32,
+    politica: PoliticaStockNegativo,
+) -> Result<(i32, Option<String>), String> {
+    let resultante = stock_actual
+        .checked_add(cantidad)
+        .ok_or_else(|| format!("La cantidad excede el máximo permitido para la pieza {}", pieza))?;
+    let disponible = stock_actual.saturating_sub(reservado);
+
+    // Un movimiento que no reduce el stock nunca se rechaza, aunque ya esté negativo
+    if cantidad < 0 && disponible.saturating_add(cantidad) < 0 {
+        let mensaje = if reservado > 0 {
+            format!(
+                "Stock insuficiente de {}: hay {} disponibles ({} reservadas para otras órdenes) y se necesitan {}",
+                pieza, disponible, reservado, -cantidad
+            )
+        } else {
+            format!(
+                "Stock insuficiente de {}: hay {} y se necesitan {}",
+                pieza, stock_actual, -cantidad
+            )
+        };
+        return match politica {
+            PoliticaStockNegativo::Rechazar => Err(mensaje),
+            PoliticaStockNegativo::Advertir => Ok((resultante, Some(mensaje))),
+        };
+    }
+    Ok((resultante, None))
+}
+
+/// Movimiento que corresponde al cambiar la cantidad usada de una pieza en un
+/// informe: más unidades se consumen, menos unidades vuelven al stock
+pub fn movimiento_por_cambio(anterior: i32, nueva: i32) -> Option<(TipoMovimiento, i32)> {
+    match nueva - anterior {
+        0 => None,
+        delta if delta > 0 => Some((TipoMovimiento::ConsumoInforme, -delta)),
+        delta => Some((TipoMovimiento::Devolucion, -delta)),
+    }
+}
+
+/// Valida el stock mínimo y la cantidad de reposición de una pieza
+pub fn validar_niveles(stock_minimo: Option<i32>, cantidad_reposicion: Option<i32>) -> Result<(), String> {
+    if stock_minimo.is_some_and(|minimo| minimo < 0) {
+        return Err("El stock mínimo no puede ser negativo".to_string());
+    }
+    if cantidad_reposicion.is_some_and(|cantidad| cantidad <= 0) {
+        return Err("La cantidad de reposición debe ser mayor a cero".to_string());
+    }
+    Ok(())
+}
+
+/// Valida el parámetro con los días de historial usados para el consumo
+pub fn validar_dias_consumo(dias: &str) -> Result<i64, String> {
+    match dias.trim().parse::<i64>() {
+        Ok(dias) if (1..=365).contains(&dias) => Ok(dias),
+        _ => Err("Los días de consumo deben ser un número entre 1 y 365".to_string()),
+    }
+}
+
+/// Valida el destinatario del resumen diario de stock. Vacío lo desactiva.
+pub fn validar_destinatario_resumen(correo: &str) -> Result<Option<String>, String> {
+    let correo = correo.trim();
+    if correo.is_empty() {
+        return Ok(None);
+    }
+    let valido = !correo.contains(char::is_whitespace)
+        && correo.split_once('@').is_some_and(|(usuario, dominio)| {
+            !usuario.is_empty() && dominio.contains('.') && !dominio.starts_with('.') && !dominio.ends_with('.')
+        });
+    if !valido {
+        return Err(format!("Correo no válido para el resumen de stock: {}", correo));
+    }
+    Ok(Some(correo.to_string()))
+}
+
+/// Unidades usadas por día en el periodo
+pub fn consumo_diario(unidades: i64, dias: i64) -> f64 {
+    if dias <= 0 {
+        return 0.0;
+    }
+    unidades.max(0) as f64 / dias as f64
+}
+
+/// Días que alcanza lo disponible al ritmo de consumo actual. Sin consumo no
+/// hay estimación.
+pub fn dias_de_cobertura(disponible: i64, consumo_diario: f64) -> Option<f64> {
+    if consumo_diario <= 0.0 {
+        return None;
+    }
+    Some((disponible.max(0) as f64 / consumo_diario).floor())
+}
+
+/// Unidades a pedir: la cantidad de reposición configurada, o lo que falte
+/// para volver al mínimo si eso es más
+pub fn cantidad_sugerida(disponible: i64, stock_minimo: i32, cantidad_reposicion: Option<i32>) -> i64 {
+    let faltante = (i64::from(stock_minimo) - disponible).max(0);
+    faltante.max(cantidad_reposicion.map_or(0, i64::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tipo_desde_texto() {
+        for tipo in [
+            TipoMovimiento::RecepcionCompra,
+            TipoMovimiento::ConsumoInforme,
+            TipoMovimiento::Ajuste,
+            TipoMovimiento::Devolucion,
+        ] {
+            assert_eq!(tipo.as_str().parse::<TipoMovimiento>(), Ok(tipo));
+        }
+        assert!("venta".parse::<TipoMovimiento>().is_err());
+    }
+
+    #[test]
+    fn test_signo_de_la_cantidad() {
+        assert!(TipoMovimiento::RecepcionCompra.validar_cantidad(5).is_ok());
+        assert!(TipoMovimiento::RecepcionCompra.validar_cantidad(-5).is_err());
+        assert!(TipoMovimiento::ConsumoInforme.validar_cantidad(-1).is_ok());
+        assert!(TipoMovimiento::ConsumoInforme.validar_cantidad(1).is_err());
+        assert!(TipoMovimiento::Ajuste.validar_cantidad(-3).is_ok());
+        assert!(TipoMovimiento::Ajuste.validar_cantidad(0).is_err());
+    }
+
+    #[test]
+    fn test_aplicar_movimiento() {
+        use PoliticaStockNegativo::*;
+        assert_eq!(aplicar_movimiento("Pantalla", 5, 0, -3, Rechazar), Ok((2, None)));
+        assert_eq!(aplicar_movimiento("Pantalla", 2, 0, -2, Rechazar), Ok((0, None)));
+        assert!(aplicar_movimiento("Pantalla", 2, 0, -3, Rechazar).unwrap_err().contains("Stock insuficiente"));
+
+        let (resultante, advertencia) = aplicar_movimiento("Pantalla", 2, 0, -3, Advertir).unwrap();
+        assert_eq!(resultante, -1);
+        assert!(advertencia.is_some());
+
+        // Una recepción sobre stock negativo se acepta aunque no alcance a cubrirlo
+        assert_eq!(aplicar_movimiento("Pantalla", -4, 0, 1, Rechazar), Ok((-3, None)));
+        assert!(aplicar_movimiento("Pantalla", i32::MAX, 0, 1, Rechazar).is_err());
+    }
+
+    #[test]
+    fn test_reservas_de_otras_ordenes() {
+        use PoliticaStockNegativo::*;
+        // Queda una unidad libre: se puede tomar, la segunda está reservada
+        assert_eq!(aplicar_movimiento("Batería", 2, 1, -1, Rechazar), Ok((1, None)));
+        let error = aplicar_movimiento("Batería", 2, 1, -2, Rechazar).unwrap_err();
+        assert!(error.contains("1 disponibles"));
+
+        // Con advertencia el stock físico no queda negativo, pero la reserva queda sin cubrir
+        let (resultante, advertencia) = aplicar_movimiento("Batería", 2, 2, -1, Advertir).unwrap();
+        assert_eq!(resultante, 1);
+        assert!(advertencia.unwrap().contains("reservadas"));
+
+        // Devolver piezas siempre se permite
+        assert_eq!(aplicar_movimiento("Batería", 0, 3, 1, Rechazar), Ok((1, None)));
+    }
+
+    #[test]
+    fn test_estado_reserva_texto() {
+        assert_eq!(EstadoReserva::Activa.as_str(), "activa");
+        assert_eq!(EstadoReserva::Consumida.as_str(), "consumida");
+    }
+
+    #[test]
+    fn test_estado_unidad_texto() {
+        assert_eq!("instalada".parse::<EstadoUnidad>().unwrap(), EstadoUnidad::Instalada);
+        assert_eq!(EstadoUnidad::Disponible.as_str(), "disponible");
+        assert!("baja".parse::<EstadoUnidad>().is_err());
+    }
+
+    #[test]
+    fn test_validar_series() {
+        let series = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            validar_series("Amplificador", &series(&[" PA-001 ", "PA-002"]), 2),
+            Ok(series(&["PA-001", "PA-002"]))
+        );
+        assert!(validar_series("Amplificador", &series(&["PA-001"]), 2).is_err());
+        assert!(validar_series("Amplificador", &series(&["PA-001", "pa-001"]), 2).is_err());
+        assert!(validar_series("Amplificador", &series(&["PA-001", "  "]), 2).is_err());
+        assert!(validar_series("Amplificador", &series(&[&"X".repeat(SERIE_MAX_LARGO + 1)]), 1).is_err());
+        assert_eq!(validar_series("Amplificador", &[], 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_movimiento_por_cambio() {
+        assert_eq!(movimiento_por_cambio(0, 2), Some((TipoMovimiento::ConsumoInforme, -2)));
+        assert_eq!(movimiento_por_cambio(3, 1), Some((TipoMovimiento::Devolucion, 2)));
+        assert_eq!(movimiento_por_cambio(2, 0), Some((TipoMovimiento::Devolucion, 2)));
+        assert_eq!(movimiento_por_cambio(2, 2), None);
+    }
+
+    #[test]
+    fn test_politica_desde_texto() {
+        assert_eq!("advertir".parse::<PoliticaStockNegativo>(), Ok(PoliticaStockNegativo::Advertir));
+        assert!("ignorar".parse::<PoliticaStockNegativo>().is_err());
+    }
+
+    #[test]
+    fn test_validar_niveles() {
+        assert!(validar_niveles(Some(0), Some(1)).is_ok());
+        assert!(validar_niveles(None, None).is_ok());
+        assert!(validar_niveles(Some(-1), None).is_err());
+        assert!(validar_niveles(Some(2), Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_destinatario_resumen() {
+        assert_eq!(validar_destinatario_resumen("  "), Ok(None));
+        assert_eq!(validar_destinatario_resumen(" compras@taller.cl "), Ok(Some("compras@taller.cl".to_string())));
+        assert!(validar_destinatario_resumen("compras").is_err());
+        assert!(validar_destinatario_resumen("compras@taller").is_err());
+        assert!(validar_destinatario_resumen("a b@taller.cl").is_err());
+    }
+
+    #[test]
+    fn test_sugerencia_de_reposicion() {
+        assert_eq!(consumo_diario(15, 30), 0.5);
+        assert_eq!(consumo_diario(5, 0), 0.0);
+        assert_eq!(dias_de_cobertura(3, 0.5), Some(6.0));
+        assert_eq!(dias_de_cobertura(-2, 0.5), Some(0.0));
+        assert_eq!(dias_de_cobertura(3, 0.0), None);
+
+        // Se pide la cantidad de reposición, salvo que falte más para llegar al mínimo
+        assert_eq!(cantidad_sugerida(1, 4, Some(10)), 10);
+        assert_eq!(cantidad_sugerida(-8, 4, Some(10)), 12);
+        assert_eq!(cantidad_sugerida(1, 4, None), 3);
+    }
+}
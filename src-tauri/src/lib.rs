@@ -3,6 +3,20 @@ pub mod database;
 pub mod utils;
 pub mod email;
 pub mod config;
+pub mod auth;
+pub mod estado_orden;
+pub mod codigos;
+pub mod abandono;
+pub mod adjuntos;
+pub mod calculo_cotizacion;
+pub mod tareas;
+pub mod aprobacion;
+pub mod servidor_aprobacion;
+pub mod inventario;
+pub mod compras;
+pub mod precios;
+pub mod finalizacion_informe;
+pub mod documentos_pdf;
 
 use database::init_database;
 
@@ -19,7 +33,15 @@ pub fn run() {
             eprintln!("Warning: Failed to initialize database: {}", e);
             // No terminar la aplicación, solo mostrar advertencia
         }
-    });tauri::Builder::default()
+    });
+    
+    // Revisiones periódicas (abandono de equipos en espera de retiro, cotizaciones vencidas)
+    tareas::iniciar_tareas_programadas();
+    
+    // Servidor local para las respuestas de clientes a las cotizaciones
+    servidor_aprobacion::iniciar_servidor_aprobacion();
+    
+    tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())        
         .invoke_handler(tauri::generate_handler![
             commands::users::get_usuarios,
@@ -86,15 +108,23 @@ pub fn run() {
             commands::ordenes_trabajo::get_ordenes_trabajo_by_estado,
             commands::ordenes_trabajo::get_ordenes_trabajo_by_prioridad,
             commands::ordenes_trabajo::get_ordenes_trabajo_by_usuario,
+            commands::ordenes_trabajo::get_carga_trabajo_tecnicos,
+            commands::ordenes_trabajo::asignar_tecnico_orden_trabajo,
+            commands::ordenes_trabajo::desasignar_tecnico_orden_trabajo,
             commands::ordenes_trabajo::get_ordenes_trabajo_detalladas,
             commands::ordenes_trabajo::get_orden_trabajo_detallada_by_id,
             commands::ordenes_trabajo::create_orden_trabajo,
             commands::ordenes_trabajo::update_orden_trabajo,
             commands::ordenes_trabajo::cambiar_estado_orden_trabajo,
+            commands::ordenes_trabajo::get_estados_siguientes_orden_trabajo,
+            commands::ordenes_trabajo::get_orden_trabajo_timeline,
             commands::ordenes_trabajo::asignar_cotizacion_orden_trabajo,
             commands::ordenes_trabajo::asignar_informe_orden_trabajo,            
             commands::ordenes_trabajo::delete_orden_trabajo,
             commands::ordenes_trabajo::get_ordenes_trabajo_stats,
+            commands::sla::get_sla_objetivos,
+            commands::sla::update_sla_objetivo,
+            commands::sla::get_ordenes_trabajo_sla_en_riesgo,
             commands::ordenes_trabajo::search_ordenes_trabajo,
             commands::ordenes_trabajo::send_orden_trabajo_notification,
             commands::cotizacion::get_cotizaciones,
@@ -112,7 +142,61 @@ pub fn run() {
             commands::cotizacion::create_pieza,
             commands::cotizacion::update_pieza,
             commands::cotizacion::delete_pieza,              
-            commands::cotizacion::get_piezas_cotizacion,           
+            commands::cotizacion::get_piezas_cotizacion,
+            commands::cotizacion::upsert_pieza_cotizacion,
+            commands::cotizacion::remove_pieza_cotizacion,
+            commands::cotizacion::get_cotizacion_detallada_by_id,
+            commands::cotizacion::send_cotizacion_to_client,
+            commands::revisiones_cotizacion::get_revisiones_cotizacion,
+            commands::revisiones_cotizacion::get_revision_cotizacion,
+            commands::revisiones_cotizacion::diff_revisiones_cotizacion,
+            commands::aprobacion_cotizacion::get_enlaces_respuesta_cotizacion,
+            commands::vencimiento_cotizacion::ejecutar_revision_vencimiento_cotizaciones,
+            commands::vencimiento_cotizacion::get_ordenes_bloqueadas_por_vencimiento,
+            commands::vencimiento_cotizacion::recalcular_cotizacion_vencida,
+            commands::inventario::get_stock_piezas,
+            commands::inventario::get_stock_pieza,
+            commands::inventario::get_movimientos_stock,
+            commands::inventario::get_reservas_pieza,
+            commands::inventario::ajustar_stock,
+            commands::inventario::update_niveles_stock,
+            commands::inventario::get_piezas_bajo_minimo,
+            commands::inventario::enviar_resumen_stock_bajo,
+            commands::compras::get_proveedores,
+            commands::compras::get_proveedor_by_id,
+            commands::compras::create_proveedor,
+            commands::compras::update_proveedor,
+            commands::compras::delete_proveedor,
+            commands::compras::get_piezas_proveedor,
+            commands::compras::get_proveedores_pieza,
+            commands::compras::upsert_pieza_proveedor,
+            commands::compras::remove_pieza_proveedor,
+            commands::compras::get_ordenes_compra,
+            commands::compras::get_orden_compra,
+            commands::compras::create_orden_compra,
+            commands::compras::upsert_linea_orden_compra,
+            commands::compras::remove_linea_orden_compra,
+            commands::compras::enviar_orden_compra,
+            commands::compras::recibir_orden_compra,
+            commands::compras::delete_orden_compra,
+            commands::precios::get_historial_precios_pieza,
+            commands::precios::registrar_precio_pieza,
+            commands::precios::get_margen_informe,
+            commands::precios::get_margen_mensual,
+            commands::series::get_unidades_pieza,
+            commands::series::registrar_series_pieza,
+            commands::series::get_trazabilidad_serie,
+            commands::series::get_unidades_equipo,
+            commands::series::get_unidades_orden,
+            commands::finalizacion_informe::finalizar_informe,
+            commands::finalizacion_informe::verificar_informe,
+            commands::finalizacion_informe::create_enmienda_informe,
+            commands::documentos_pdf::get_informe_pdf,
+            commands::documentos_pdf::guardar_informe_pdf,
+            commands::documentos_pdf::get_cotizacion_pdf,
+            commands::documentos_pdf::guardar_cotizacion_pdf,
+            commands::documentos_pdf::get_comprobante_recepcion_pdf,
+            commands::documentos_pdf::guardar_comprobante_recepcion_pdf,
             commands::informe::get_informes,
             commands::informe::get_informe_by_id,
             commands::informe::get_informe_by_codigo,
@@ -124,7 +208,24 @@ pub fn run() {
             commands::informe::count_informes,
             commands::informe::get_informes_with_pagination,
             commands::informe::get_piezas_informe,
+            commands::informe::upsert_pieza_informe,
+            commands::informe::remove_pieza_informe,
+            commands::informe::create_informe_desde_cotizacion,
+            commands::informe::get_diferencias_cotizacion_informe,
             commands::informe::send_informe_to_client,
+            commands::codigos::get_formatos_codigo,
+            commands::codigos::update_formato_codigo,
+            commands::parametros::get_parametros_sistema,
+            commands::parametros::update_parametro_sistema,
+            commands::abandono::ejecutar_revision_abandono,
+            commands::notas_orden::get_notas_orden_trabajo,
+            commands::notas_orden::get_nota_orden_trabajo_by_id,
+            commands::notas_orden::create_nota_orden_trabajo,
+            commands::notas_orden::anular_nota_orden_trabajo,
+            commands::adjuntos::get_adjuntos,
+            commands::adjuntos::get_adjunto_contenido,
+            commands::adjuntos::upload_adjunto,
+            commands::adjuntos::delete_adjunto,
             commands::database::get_database_status,
             commands::database::check_database_connection,
             commands::database::retry_database_connection,
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Tipos de precio registrados en PRECIO_PIEZA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoPrecio {
+    /// Precio al que se cobra la pieza al cliente
+    Venta,
+    /// Lo que cuesta comprar la pieza
+    Costo,
+}
+
+impl FromStr for TipoPrecio {
+    type Err = String;
+
+    fn from_str(tipo: &str) -> Result<Self, Self::Err> {
+        match tipo {
+            "venta" => Ok(TipoPrecio::Venta),
+            "costo" => Ok(TipoPrecio::Costo),
+            _ => Err(format!("Tipo de precio no válido: {}", tipo)),
+        }
+    }
+}
+
+impl fmt::Display for TipoPrecio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TipoPrecio {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipoPrecio::Venta => "venta",
+            TipoPrecio::Costo => "costo",
+        }
+    }
+}
+
+pub fn validar_precio(tipo: TipoPrecio, precio: i32) -> Result<(), String> {
+    if precio < 0 {
+        return Err(format!("El precio de {} no puede ser negativo", tipo));
+    }
+    Ok(())
+}
+
+/// El historial solo crece hacia adelante: un precio nuevo no puede empezar
+/// en el futuro ni antes del que está vigente.
+pub fn validar_vigencia(
+    desde: DateTime<Utc>,
+    vigente_desde_actual: Option<DateTime<Utc>>,
+    ahora: DateTime<Utc>,
+) -> Result<(), String> {
+    if desde > ahora {
+        return Err("La vigencia del precio no puede comenzar en el futuro".to_string());
+    }
+    if let Some(actual) = vigente_desde_actual {
+        if desde <= actual {
+            return Err(format!(
+                "La vigencia debe comenzar después del precio actual, vigente desde el {}",
+                actual.format("%d/%m/%Y %H:%M")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Pieza usada en un informe con los precios vigentes cuando se usó
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineaMargen {
+    pub pieza_id: i32,
+    pub cantidad: i32,
+    pub precio_venta: Option<i32>,
+    pub costo: Option<i32>,
+}
+
+impl LineaMargen {
+    pub fn ingreso(&self) -> Option<i64> {
+        self.precio_venta.map(|p| self.cantidad as i64 * p as i64)
+    }
+
+    pub fn costo_total(&self) -> Option<i64> {
+        self.costo.map(|c| self.cantidad as i64 * c as i64)
+    }
+
+    pub fn margen(&self) -> Option<i64> {
+        Some(self.ingreso()? - self.costo_total()?)
+    }
+}
+
+/// Totales de un conjunto de líneas. Ingreso, costo y margen suman solo las
+/// líneas con ambos precios, para que el margen no se infle con piezas sin
+/// costo registrado; las demás quedan contadas aparte.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResumenMargen {
+    pub ingreso: i64,
+    pub costo: i64,
+    pub margen: i64,
+    pub margen_pct: Option<f64>,
+    pub lineas_sin_precio: i32,
+    pub lineas_sin_costo: i32,
+}
+
+pub fn resumir_margen(lineas: &[LineaMargen]) -> ResumenMargen {
+    let mut resumen = ResumenMargen::default();
+
+    for linea in lineas {
+        match (linea.ingreso(), linea.costo_total()) {
+            (Some(ingreso), Some(costo)) => {
+                resumen.ingreso += ingreso;
+                resumen.costo += costo;
+            }
+            (None, _) => resumen.lineas_sin_precio += 1,
+            (Some(_), None) => resumen.lineas_sin_costo += 1,
+        }
+    }
+
+    resumen.margen = resumen.ingreso - resumen.costo;
+    resumen.margen_pct = margen_porcentaje(resumen.margen, resumen.ingreso);
+    resumen
+}
+
+/// Margen sobre el ingreso, con un decimal
+pub fn margen_porcentaje(margen: i64, ingreso: i64) -> Option<f64> {
+    if ingreso == 0 {
+        return None;
+    }
+    Some((margen as f64 * 1000.0 / ingreso as f64).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fecha(dia: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, dia, 12, 0, 0).unwrap()
+    }
+
+    fn linea(cantidad: i32, precio_venta: Option<i32>, costo: Option<i32>) -> LineaMargen {
+        LineaMargen { pieza_id: 1, cantidad, precio_venta, costo }
+    }
+
+    #[test]
+    fn test_tipo_desde_texto() {
+        assert_eq!("venta".parse::<TipoPrecio>().unwrap(), TipoPrecio::Venta);
+        assert_eq!("costo".parse::<TipoPrecio>().unwrap(), TipoPrecio::Costo);
+        assert!("lista".parse::<TipoPrecio>().is_err());
+        assert_eq!(TipoPrecio::Costo.to_string(), "costo");
+    }
+
+    #[test]
+    fn test_validar_vigencia() {
+        assert!(validar_vigencia(fecha(10), None, fecha(10)).is_ok());
+        assert!(validar_vigencia(fecha(10), Some(fecha(5)), fecha(12)).is_ok());
+        assert!(validar_vigencia(fecha(13), Some(fecha(5)), fecha(12)).is_err());
+        assert!(validar_vigencia(fecha(5), Some(fecha(5)), fecha(12)).is_err());
+        assert!(validar_vigencia(fecha(4), Some(fecha(5)), fecha(12)).is_err());
+        assert!(validar_precio(TipoPrecio::Venta, 0).is_ok());
+        assert!(validar_precio(TipoPrecio::Costo, -1).is_err());
+    }
+
+    #[test]
+    fn test_margen_linea() {
+        let l = linea(3, Some(10_000), Some(6_000));
+        assert_eq!((l.ingreso(), l.costo_total(), l.margen()), (Some(30_000), Some(18_000), Some(12_000)));
+        assert_eq!(linea(2, Some(10_000), None).margen(), None);
+    }
+
+    #[test]
+    fn test_resumir_margen_excluye_lineas_incompletas() {
+        let resumen = resumir_margen(&[
+            linea(2, Some(10_000), Some(7_500)),
+            linea(1, Some(4_000), Some(1_000)),
+            linea(5, Some(2_000), None),
+            linea(1, None, Some(500)),
+        ]);
+        assert_eq!((resumen.ingreso, resumen.costo, resumen.margen), (24_000, 16_000, 8_000));
+        assert_eq!(resumen.margen_pct, Some(33.3));
+        assert_eq!((resumen.lineas_sin_precio, resumen.lineas_sin_costo), (1, 1));
+
+        assert_eq!(resumir_margen(&[]).margen_pct, None);
+        assert_eq!(margen_porcentaje(-500, 1_000), Some(-50.0));
+    }
+}
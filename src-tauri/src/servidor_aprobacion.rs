@@ -0,0 +1,177 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use crate::aprobacion::{
+    ip_cliente, pagina_mensaje, parsear_solicitud, SolicitudHttp, MAX_BYTES_SOLICITUD,
+    PUERTO_POR_DEFECTO, RUTA_ESTADO, RUTA_RESPUESTA,
+};
+use crate::commands::aprobacion_cotizacion::{preparar_respuesta, registrar_respuesta, FalloRespuesta};
+use crate::commands::parametros::obtener_puerto_aprobacion;
+use crate::database::get_db_pool_safe;
+
+/// Tiempo máximo para recibir una solicitud completa
+const TIEMPO_LECTURA: Duration = Duration::from_secs(10);
+
+/// Respuesta HTTP armada por el servidor
+struct RespuestaHttp {
+    codigo: u16,
+    tipo: &'static str,
+    cuerpo: String,
+}
+
+impl RespuestaHttp {
+    fn html(codigo: u16, cuerpo: String) -> Self {
+        RespuestaHttp { codigo, tipo: "text/html; charset=utf-8", cuerpo }
+    }
+
+    fn mensaje(codigo: u16, titulo: &str, mensaje: &str) -> Self {
+        Self::html(codigo, pagina_mensaje(titulo, mensaje))
+    }
+}
+
+/// Inicia el servidor local que recibe las respuestas de los clientes a las
+/// cotizaciones. Escucha solo en 127.0.0.1; para que los clientes lleguen desde
+/// fuera se publica con un túnel o proxy inverso (ver `aprobacion_url_base`).
+pub fn iniciar_servidor_aprobacion() {
+    tauri::async_runtime::spawn(async {
+        let puerto = if get_db_pool_safe().is_ok() {
+            obtener_puerto_aprobacion().await.unwrap_or_else(|e| {
+                eprintln!("Puerto de respuestas no válido, se usa {}: {}", PUERTO_POR_DEFECTO, e);
+                PUERTO_POR_DEFECTO
+            })
+        } else {
+            PUERTO_POR_DEFECTO
+        };
+        if puerto == 0 {
+            return;
+        }
+
+        match TcpListener::bind(("127.0.0.1", puerto)).await {
+            Ok(listener) => servir(listener).await,
+            Err(e) => eprintln!("No se pudo iniciar el servidor de respuestas en el puerto {}: {}", puerto, e),
+        }
+    });
+}
+
+/// Atiende conexiones hasta que se cierre el listener
+pub async fn servir(listener: TcpListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, origen)) => {
+                tokio::spawn(atender_conexion(stream, origen));
+            }
+            Err(e) => eprintln!("Error aceptando conexión en el servidor de respuestas: {}", e),
+        }
+    }
+}
+
+async fn atender_conexion(mut stream: TcpStream, origen: SocketAddr) {
+    let respuesta = match tokio::time::timeout(TIEMPO_LECTURA, leer_solicitud(&mut stream)).await {
+        Ok(Ok(solicitud)) => enrutar(&solicitud, origen).await,
+        Ok(Err(e)) => RespuestaHttp::mensaje(400, "Solicitud no válida", &e),
+        Err(_) => RespuestaHttp::mensaje(408, "Tiempo agotado", "La solicitud tardó demasiado"),
+    };
+
+    let texto_codigo = match respuesta.codigo {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        _ => "Internal Server Error",
+    };
+    // El token viaja en la URL: no se guarda en caché ni se envía como Referer
+    let cabecera = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nReferrer-Policy: no-referrer\r\nX-Frame-Options: DENY\r\nConnection: close\r\n\r\n",
+        respuesta.codigo, texto_codigo, respuesta.tipo, respuesta.cuerpo.len()
+    );
+
+    let _ = stream.write_all(cabecera.as_bytes()).await;
+    let _ = stream.write_all(respuesta.cuerpo.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn leer_solicitud(stream: &mut TcpStream) -> Result<SolicitudHttp, String> {
+    let mut datos = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let leidos = stream.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if leidos == 0 {
+            return Err("Conexión cerrada antes de completar la solicitud".to_string());
+        }
+        datos.extend_from_slice(&buffer[..leidos]);
+        if let Some(solicitud) = parsear_solicitud(&datos)? {
+            return Ok(solicitud);
+        }
+        if datos.len() > MAX_BYTES_SOLICITUD {
+            return Err("Solicitud demasiado grande".to_string());
+        }
+    }
+}
+
+async fn enrutar(solicitud: &SolicitudHttp, origen: SocketAddr) -> RespuestaHttp {
+    match (solicitud.metodo.as_str(), solicitud.ruta.as_str()) {
+        ("GET", RUTA_ESTADO) => RespuestaHttp { codigo: 200, tipo: "text/plain; charset=utf-8", cuerpo: "ok".to_string() },
+        ("GET" | "POST", RUTA_RESPUESTA) => {
+            let Some(token) = solicitud.parametro("token").filter(|t| !t.is_empty()) else {
+                return RespuestaHttp::mensaje(400, "Enlace no válido", "Falta el código de respuesta en el enlace");
+            };
+
+            let resultado = if solicitud.metodo == "GET" {
+                preparar_respuesta(token).await
+            } else {
+                let ip = ip_cliente(origen, solicitud.encabezado("x-forwarded-for"));
+                registrar_respuesta(token, &ip, solicitud.encabezado("user-agent")).await
+            };
+
+            match resultado {
+                Ok(pagina) => RespuestaHttp::html(200, pagina),
+                Err(FalloRespuesta::Rechazada(mensaje)) => RespuestaHttp::mensaje(400, "No se pudo registrar la respuesta", &mensaje),
+                Err(FalloRespuesta::Interna(e)) => {
+                    eprintln!("Error atendiendo respuesta de cotización: {}", e);
+                    RespuestaHttp::mensaje(500, "Error", "No fue posible procesar su respuesta. Intente nuevamente más tarde o contacte al taller.")
+                }
+            }
+        }
+        (_, RUTA_RESPUESTA) => RespuestaHttp::mensaje(405, "Método no permitido", "Use el enlace recibido por correo"),
+        _ => RespuestaHttp::mensaje(404, "Página no encontrada", "La dirección solicitada no existe"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn solicitar(direccion: SocketAddr, solicitud: &str) -> String {
+        let mut stream = TcpStream::connect(direccion).await.unwrap();
+        stream.write_all(solicitud.as_bytes()).await.unwrap();
+        let mut respuesta = String::new();
+        stream.read_to_string(&mut respuesta).await.unwrap();
+        respuesta
+    }
+
+    #[tokio::test]
+    async fn test_servidor_local() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let direccion = listener.local_addr().unwrap();
+        tokio::spawn(servir(listener));
+
+        let estado = solicitar(direccion, "GET /estado HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(estado.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(estado.ends_with("\r\n\r\nok"));
+
+        let sin_token = solicitar(direccion, "GET /cotizacion/respuesta HTTP/1.1\r\n\r\n").await;
+        assert!(sin_token.starts_with("HTTP/1.1 400 "));
+        assert!(sin_token.contains("Cache-Control: no-store"));
+
+        let metodo = solicitar(direccion, "DELETE /cotizacion/respuesta?token=x HTTP/1.1\r\n\r\n").await;
+        assert!(metodo.starts_with("HTTP/1.1 405 "));
+
+        let ruta = solicitar(direccion, "GET /otra HTTP/1.1\r\n\r\n").await;
+        assert!(ruta.starts_with("HTTP/1.1 404 "));
+
+        let invalida = solicitar(direccion, "basura\r\n\r\n").await;
+        assert!(invalida.starts_with("HTTP/1.1 400 "));
+    }
+}
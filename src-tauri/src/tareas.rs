@@ -0,0 +1,73 @@
+use std::time::Duration;
+use crate::commands::abandono::procesar_ordenes_en_espera_de_retiro;
+use crate::commands::inventario::procesar_resumen_stock_bajo;
+use crate::commands::vencimiento_cotizacion::procesar_cotizaciones_vencidas;
+use crate::database::get_db_pool_safe;
+
+/// Frecuencia de las revisiones automáticas
+const INTERVALO_REVISION: Duration = Duration::from_secs(60 * 60);
+
+/// Inicia las tareas periódicas del backend. La primera revisión se ejecuta
+/// al iniciar y las siguientes cada `INTERVALO_REVISION`.
+pub fn iniciar_tareas_programadas() {
+    tauri::async_runtime::spawn(async {
+        let mut intervalo = tokio::time::interval(INTERVALO_REVISION);
+        loop {
+            intervalo.tick().await;
+
+            // Sin conexión a la base de datos no hay nada que revisar
+            if get_db_pool_safe().is_err() {
+                continue;
+            }
+
+            match procesar_ordenes_en_espera_de_retiro().await {
+                Ok(resumen) => {
+                    if resumen.recordatorios_enviados + resumen.recordatorios_fallidos + resumen.abandonadas > 0 {
+                        println!(
+                            "Revisión de abandono: {} recordatorios enviados, {} fallidos, {} órdenes abandonadas",
+                            resumen.recordatorios_enviados, resumen.recordatorios_fallidos, resumen.abandonadas
+                        );
+                    }
+                    for error in resumen.errores {
+                        eprintln!("Error en revisión de abandono: {}", error);
+                    }
+                }
+                Err(e) => eprintln!("Error en revisión de abandono: {}", e),
+            }
+
+            match procesar_cotizaciones_vencidas().await {
+                Ok(resumen) => {
+                    // Las órdenes bloqueadas se informan solo cuando vence alguna cotización nueva
+                    if resumen.marcadas_vencidas > 0 {
+                        println!(
+                            "Revisión de vencimiento: {} cotizaciones vencidas, {} órdenes detenidas en cotizacion_enviada",
+                            resumen.marcadas_vencidas, resumen.ordenes_bloqueadas.len()
+                        );
+                        for orden in &resumen.ordenes_bloqueadas {
+                            println!(
+                                "  Orden {} bloqueada por la cotización {} (válida hasta {})",
+                                orden.orden_codigo.as_deref().unwrap_or("N/A"),
+                                orden.cotizacion_codigo.as_deref().unwrap_or("N/A"),
+                                orden.valido_hasta.map_or("N/A".to_string(), |f| f.to_string())
+                            );
+                        }
+                    }
+                    for error in resumen.errores {
+                        eprintln!("Error en revisión de vencimiento: {}", error);
+                    }
+                }
+                Err(e) => eprintln!("Error en revisión de vencimiento: {}", e),
+            }
+
+            // El resumen se envía una vez al día, en la primera revisión de la jornada
+            match procesar_resumen_stock_bajo().await {
+                Ok(Some(resumen)) if resumen.enviado => println!(
+                    "Resumen de stock bajo enviado: {} piezas bajo el mínimo",
+                    resumen.piezas.len()
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("Error enviando el resumen de stock bajo: {}", e),
+            }
+        }
+    });
+}
@@ -31,7 +31,6 @@ interface CreateClienteRequest {
   cliente_correo: string;
   cliente_telefono?: string;
   cliente_direccion?: string;
-  created_by: number;
 }
 
 interface UpdateClienteRequest {
@@ -57,7 +56,7 @@ export function ClienteFormDialog({
   cliente,
   isEditing = false,
 }: ClienteFormDialogProps) {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [loading, setLoading] = useState(false);
   const [showConfirmationDialog, setShowConfirmationDialog] = useState(false);
@@ -142,9 +141,9 @@ export function ClienteFormDialog({
         };
 
         const updatedCliente = await invoke<Cliente>("update_cliente", {
+          sessionToken,
           clienteId: cliente.cliente_id,
           request: updateRequest,
-          updatedBy: user?.usuario_id || 0,
         });
 
         if (updatedCliente) {
@@ -164,10 +163,10 @@ export function ClienteFormDialog({
           cliente_correo: formData.cliente_correo,
           cliente_telefono: formData.cliente_telefono || undefined,
           cliente_direccion: formData.cliente_direccion || undefined,
-          created_by: user?.usuario_id || 0,
         };
 
         await invoke<Cliente>("create_cliente", {
+          sessionToken,
           request: createRequest,
         });
 
@@ -28,7 +28,7 @@ interface Cliente {
 }
 
 export function ClientesView() {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [clientes, setClientes] = useState<Cliente[]>([]);
   const [loading, setLoading] = useState(true);
@@ -43,10 +43,11 @@ export function ClientesView() {
 
       if (searchTerm.trim()) {
         clientesData = await invoke<Cliente[]>("search_clientes", {
+          sessionToken,
           searchTerm: searchTerm.trim(),
         });
       } else {
-        clientesData = await invoke<Cliente[]>("get_clientes");
+        clientesData = await invoke<Cliente[]>("get_clientes", { sessionToken });
       }
 
       setClientes(clientesData);
@@ -90,8 +91,8 @@ export function ClientesView() {
 
     try {
       const result = await invoke<boolean>("delete_cliente", {
+        sessionToken,
         clienteId: cliente.cliente_id,
-        deletedBy: user.usuario_id,
       });
 
       if (result) {
@@ -100,7 +100,7 @@ export default function CotizacionFormDialog({
   ordenTrabajoId,
   onSendToClient,
 }: CotizacionFormDialogProps) {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [loading, setLoading] = useState(false);
   const [piezas, setPiezas] = useState<Pieza[]>([]);
@@ -110,6 +110,8 @@ export default function CotizacionFormDialog({
   const [cantidad, setCantidad] = useState<string>("1");
   const [showConfirmationDialog, setShowConfirmationDialog] = useState(false);
   const [estadoOrden, setEstadoOrden] = useState<string>("");
+  // Estados a los que la orden puede pasar ahora; decide qué acciones se muestran
+  const [estadosSiguientes, setEstadosSiguientes] = useState<string[]>([]);
   const [showAprobarConfirmDialog, setShowAprobarConfirmDialog] = useState(false);
   const [showRechazarConfirmDialog, setShowRechazarConfirmDialog] = useState(false);
   const [showNoReparableConfirmDialog, setShowNoReparableConfirmDialog] = useState(false);
@@ -125,8 +127,8 @@ export default function CotizacionFormDialog({
 
   const [errors, setErrors] = useState<FormErrors>({});
 
-  // Calcular costo total automáticamente
-  const calculateTotal = () => {
+  // Subtotal neto de referencia; el IVA y el total los calcula el backend al guardar
+  const calculateNeto = () => {
     const costoRevision = parseInt(formData.costo_revision) || 0;
     const costoReparacion = parseInt(formData.costo_reparacion) || 0;
     const costoPiezas = selectedPiezas.reduce(
@@ -146,7 +148,7 @@ export default function CotizacionFormDialog({
       // Si hay ordenTrabajoId, obtener el estado de la orden
       console.log("ordenTrabajoId:", ordenTrabajoId);
       if (ordenTrabajoId) {
-        invoke<{ estado: string }>("get_orden_trabajo_by_id", { ordenId: ordenTrabajoId })
+        invoke<{ estado: string }>("get_orden_trabajo_by_id", { sessionToken, ordenId: ordenTrabajoId })
           .then((orden) => { 
             setEstadoOrden(orden.estado);
             console.log("Estado de la orden:", orden.estado);
@@ -154,6 +156,17 @@ export default function CotizacionFormDialog({
           .catch((err) => {
             console.error("Error obteniendo estado de orden:", err);
           });
+        invoke<string[]>("get_estados_siguientes_orden_trabajo", {
+          sessionToken,
+          ordenId: ordenTrabajoId,
+        })
+          .then(setEstadosSiguientes)
+          .catch((err) => {
+            console.error("Error obteniendo estados siguientes:", err);
+            setEstadosSiguientes([]);
+          });
+      } else {
+        setEstadosSiguientes([]);
       }
     }
   }, [open]);
@@ -183,7 +196,7 @@ export default function CotizacionFormDialog({
   const loadPiezas = async () => {
     try {
       setLoadingPiezas(true);
-      const piezasData = await invoke<Pieza[]>("get_piezas");
+      const piezasData = await invoke<Pieza[]>("get_piezas", { sessionToken });
       setPiezas(piezasData);
     } catch (error) {
       console.error("Error cargando piezas:", error);
@@ -200,6 +213,7 @@ export default function CotizacionFormDialog({
       const piezasCotizacion = await invoke<PiezaCotizacion[]>(
         "get_piezas_cotizacion",
         {
+          sessionToken,
           cotizacionId: cotizacion.cotizacion_id,
         }
       );
@@ -344,8 +358,6 @@ export default function CotizacionFormDialog({
     try {
       setLoading(true);
 
-      const costoTotal = calculateTotal();
-
       if (isEditing && cotizacion) {
         // Actualizar cotización existente
         const updateData = {
@@ -357,8 +369,6 @@ export default function CotizacionFormDialog({
             parseInt(formData.costo_reparacion) !== cotizacion.costo_reparacion
               ? parseInt(formData.costo_reparacion)
               : undefined,
-          costo_total:
-            costoTotal !== cotizacion.costo_total ? costoTotal : undefined,
           is_aprobada:
             formData.is_aprobada !== cotizacion.is_aprobada
               ? formData.is_aprobada
@@ -370,9 +380,9 @@ export default function CotizacionFormDialog({
         };
 
         const result = await invoke<boolean>("update_cotizacion", {
+          sessionToken,
           cotizacionId: cotizacion.cotizacion_id,
           request: updateData,
-          updatedBy: user.usuario_id,
         });
 
         if (result) {
@@ -392,10 +402,8 @@ export default function CotizacionFormDialog({
         const createData = {
           costo_revision: parseInt(formData.costo_revision),
           costo_reparacion: parseInt(formData.costo_reparacion),
-          costo_total: costoTotal,
           is_aprobada: formData.is_aprobada,
           is_borrador: true, // Siempre crear como borrador
-          created_by: user.usuario_id,
           informe: formData.informe,
           piezas:
             selectedPiezas.length > 0
@@ -407,6 +415,7 @@ export default function CotizacionFormDialog({
         };
 
         const cotizacionResult = await invoke<any>("create_cotizacion", {
+          sessionToken,
           request: createData,
         });
         const cotizacionId =
@@ -429,9 +438,9 @@ export default function CotizacionFormDialog({
         if (ordenTrabajoId) {
           try {
             const asociada = await invoke<boolean>("update_orden_trabajo", {
+              sessionToken,
               ordenId: ordenTrabajoId,
               request: { cotizacion_id: cotizacionId },
-              updatedBy: user.usuario_id,
             });
             asociadaAOrden = !!asociada;
             if (!asociadaAOrden) {
@@ -455,6 +464,9 @@ export default function CotizacionFormDialog({
         success(
           "Cotización creada",
           `La cotización ha sido creada exitosamente.` +
+            (cotizacionResult?.costo_total !== undefined
+              ? ` Total con IVA: $${Number(cotizacionResult.costo_total).toLocaleString()}.`
+              : "") +
             (ordenTrabajoId
               ? asociadaAOrden
                 ? " (Asociada a la orden de trabajo)"
@@ -513,9 +525,9 @@ export default function CotizacionFormDialog({
 
       // Actualizar is_borrador a false para marcar como enviada
       const result = await invoke<boolean>("update_cotizacion", {
+        sessionToken,
         cotizacionId: cotizacion.cotizacion_id,
         request: { is_borrador: false },
-        updatedBy: user.usuario_id,
       });
 
       if (result) {
@@ -552,18 +564,27 @@ export default function CotizacionFormDialog({
   }
   try {
     setLoading(true);
-    // Aprobar la cotización
+    // La orden pasa primero por "aprobacion_pendiente": desde "cotizacion_enviada"
+    // no se puede ir directo a reparación
+    if (estadoOrden === "cotizacion_enviada") {
+      await invoke("cambiar_estado_orden_trabajo", {
+        sessionToken,
+        ordenId: ordenTrabajoId,
+        nuevoEstado: "aprobacion_pendiente",
+      });
+    }
+    // Aprobar la cotización; "en_reparacion" exige una cotización aprobada
     const result = await invoke<boolean>("update_cotizacion", {
+      sessionToken,
       cotizacionId: cotizacion.cotizacion_id,
       request: { is_aprobada: true },
-      updatedBy: user.usuario_id,
     });
     if (result) {
       // Cambiar estado de la orden a "en_reparacion"
       await invoke("cambiar_estado_orden_trabajo", {
+        sessionToken,
         ordenId: ordenTrabajoId,
         nuevoEstado: "en_reparacion",
-        updatedBy: user.usuario_id,
       });
       success("Cotización aprobada", "La cotización ha sido aprobada y la orden está en reparación.");
       onCotizacionAdded();
@@ -586,19 +607,19 @@ export default function CotizacionFormDialog({
     }
     try {
       setLoading(true);
+      // Primero el cambio de estado: si no está permitido, la cotización no se toca
+      await invoke("cambiar_estado_orden_trabajo", {
+        sessionToken,
+        ordenId: ordenTrabajoId,
+        nuevoEstado: "cotizacion_rechazada",
+      });
       // Rechazar la cotización
       const result = await invoke<boolean>("update_cotizacion", {
+        sessionToken,
         cotizacionId: cotizacion.cotizacion_id,
         request: { is_aprobada: false },
-        updatedBy: user.usuario_id,
       });
       if (result) {
-        // Cambiar estado de la orden a "aprobacion_pendiente"
-        await invoke("cambiar_estado_orden_trabajo", {
-          ordenId: ordenTrabajoId,
-          nuevoEstado: "cotizacion_rechazada",
-          updatedBy: user.usuario_id,
-        });
         success("Cotización rechazada", "La cotización ha sido rechazada y la orden está cotizacion rechazada.");
         onCotizacionAdded();
         onOpenChange(false);
@@ -629,9 +650,9 @@ export default function CotizacionFormDialog({
 
       // Cambiar estado de la orden a "no_reparable"
       await invoke("cambiar_estado_orden_trabajo", {
+        sessionToken,
         ordenId: ordenTrabajoId,
         nuevoEstado: "equipo_no_reparable",
-        updatedBy: user.usuario_id,
         comentario: comentarioNoReparable, // Enviar comentario
       });
 
@@ -664,10 +685,10 @@ export default function CotizacionFormDialog({
       setLoading(true);
       // Cambiar estado de la orden a "abandonado"
       await invoke("cambiar_estado_orden_trabajo", {
+        sessionToken,
         ordenId: ordenTrabajoId,
         nuevoEstado: "abandonado",
         comentario: abandonoComentario,
-        updatedBy: user.usuario_id,
       });
 
       success("Equipo declarado como abandono", "El equipo fue marcado como abandonado exitosamente.");
@@ -719,13 +740,13 @@ export default function CotizacionFormDialog({
               />
             </div>
 
-            {/* Costo total (solo lectura) */}
+            {/* Subtotal neto (solo lectura); el total con IVA lo calcula el sistema */}
             <div className="space-y-2">
-              <Label htmlFor="costo_total">Costo Total</Label>
+              <Label htmlFor="costo_total">Subtotal neto (sin IVA)</Label>
               <Input
                 id="costo_total"
                 type="text"
-                value={`$${calculateTotal()}`}
+                value={`$${calculateNeto()}`}
                 readOnly
                 className="bg-gray-50 font-semibold"
               />
@@ -928,15 +949,9 @@ export default function CotizacionFormDialog({
               </Button>
             )}
 
+            {/* Solo mientras la orden espera la respuesta del cliente */}
             {isEditing &&
-              estadoOrden &&
-              estadoOrden
-                .toLowerCase()
-                .normalize("NFD")
-                .replace(/[\u0300-\u036f]/g, "")
-                .replace(/\s+/g, " ")
-                .trim() ===
-                "cotizacion_enviada" && (
+              estadosSiguientes.includes("cotizacion_rechazada") && (
                 <>
                   <Button
                     type="button"
@@ -960,8 +975,7 @@ export default function CotizacionFormDialog({
             )}
 
             {isEditing &&
-              estadoOrden &&
-              estadoOrden.toLowerCase().trim() === "recibido" && (
+              estadosSiguientes.includes("equipo_no_reparable") && (
               <Button
                 type="button"
                 variant="destructive"
@@ -973,9 +987,8 @@ export default function CotizacionFormDialog({
               </Button>
             )}
 
-            {isEditing && 
-              estadoOrden.toLowerCase() !== "recibido" &&
-              estadoOrden.toLowerCase() !== "abandonado" && (
+            {isEditing &&
+              estadosSiguientes.includes("abandonado") && (
               <Button
                 type="button"
                 variant="destructive"
@@ -1027,7 +1040,14 @@ export default function CotizacionFormDialog({
               seleccionada(s)
             </div>
             <div>
-              <strong>Costo Total:</strong> ${calculateTotal().toLocaleString()}
+              <strong>Subtotal neto:</strong> ${calculateNeto().toLocaleString()}
+              {isEditing && cotizacion?.costo_total !== undefined && (
+                <>
+                  {" "}
+                  (total actual con IVA: $
+                  {cotizacion.costo_total.toLocaleString()})
+                </>
+              )}
             </div>
             <div>
               <strong>Estado:</strong>{" "}
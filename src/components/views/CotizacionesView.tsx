@@ -66,7 +66,7 @@ const getEstadoText = (cotizacion: Cotizacion) => {
 };
 
 export function CotizacionesView() {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [cotizaciones, setCotizaciones] = useState<CotizacionDetallada[]>([]);
   const [loading, setLoading] = useState(true);
@@ -80,7 +80,8 @@ export function CotizacionesView() {
     try {
       setLoading(true);
       const cotizacionesData = await invoke<CotizacionDetallada[]>(
-        "get_cotizaciones_detalladas"
+        "get_cotizaciones_detalladas",
+        { sessionToken }
       );
       setCotizaciones(cotizacionesData);
     } catch (error) {
@@ -123,6 +124,7 @@ export function CotizacionesView() {
 
     try {
       const result = await invoke<boolean>("delete_cotizacion", {
+        sessionToken,
         cotizacionId: cotizacion.cotizacion_id,
       });
 
@@ -154,6 +156,7 @@ export function CotizacionesView() {
       };
 
       const result = await invoke<boolean>("update_cotizacion", {
+        sessionToken,
         cotizacionId: cotizacion.cotizacion_id,
         request: updateData,
       });
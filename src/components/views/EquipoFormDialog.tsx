@@ -63,7 +63,6 @@ interface CreateEquipoRequest {
   equipo_precio?: number;
   equipo_ubicacion?: string;
   cliente_id: number;
-  created_by: number;
 }
 
 interface UpdateEquipoRequest {
@@ -83,7 +82,6 @@ interface CreateOrdenTrabajoRequest {
   estado: string;
   has_garantia: boolean;
   equipo_id: number;
-  created_by: number;
   pre_informe: string;
   cotizacion_id?: number;
   informe_id?: number;
@@ -95,7 +93,6 @@ interface CreateClienteRequest {
   cliente_correo: string;
   cliente_telefono?: string;
   cliente_direccion?: string;
-  created_by: number;
 }
 
 export function EquipoFormDialog({
@@ -105,7 +102,7 @@ export function EquipoFormDialog({
   equipo,
   isEditing = false,
 }: EquipoFormDialogProps) {
-  const { user } = useAuth();
+  const { sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [loading, setLoading] = useState(false);
   const [clientes, setClientes] = useState<Cliente[]>([]);
@@ -153,7 +150,6 @@ export function EquipoFormDialog({
     equipo_tipo: "",
     equipo_ubicacion: "",
     cliente_id: undefined,
-    created_by: user?.usuario_id || 0,
   });
   const [errors, setErrors] = useState<Record<string, string>>({}); // Cargar clientes al abrir el diálogo
   useEffect(() => {
@@ -175,7 +171,6 @@ export function EquipoFormDialog({
         equipo_precio: equipo.equipo_precio || 0,
         equipo_ubicacion: equipo.equipo_ubicacion || "",
         cliente_id: equipo.cliente_id || undefined,
-        created_by: user?.usuario_id || 0,
       });
       // Load models for the brand when editing
       if (equipo.equipo_marca) {
@@ -190,15 +185,16 @@ export function EquipoFormDialog({
         equipo_tipo: "",
         equipo_ubicacion: "",
         cliente_id: undefined,
-        created_by: user?.usuario_id || 0,
       });
     }
     setErrors({});
-  }, [isEditing, equipo, open, user?.usuario_id]);
+  }, [isEditing, equipo, open]);
 
   const loadClientes = async () => {
     try {
-      const clientesData = await invoke<Cliente[]>("get_clientes");
+      const clientesData = await invoke<Cliente[]>("get_clientes", {
+        sessionToken,
+      });
       setClientes(clientesData);
     } catch (error) {
       console.error("Error cargando clientes:", error);
@@ -206,7 +202,9 @@ export function EquipoFormDialog({
   };
   const loadMarcas = async () => {
     try {
-      const marcasData = await invoke<string[]>("get_equipos_marcas");
+      const marcasData = await invoke<string[]>("get_equipos_marcas", {
+        sessionToken,
+      });
       setMarcas(marcasData);
     } catch (error) {
       console.error("Error cargando marcas:", error);
@@ -215,7 +213,9 @@ export function EquipoFormDialog({
 
   const loadUbicaciones = async () => {
     try {
-      const ubicacionesData = await invoke<string[]>("get_equipos_ubicaciones");
+      const ubicacionesData = await invoke<string[]>("get_equipos_ubicaciones", {
+        sessionToken,
+      });
       setUbicaciones(ubicacionesData);
     } catch (error) {
       console.error("Error cargando ubicaciones:", error);
@@ -225,7 +225,7 @@ export function EquipoFormDialog({
     try {
       const modelosData = await invoke<string[]>(
         "get_equipos_modelos_by_marca",
-        { marca }
+        { sessionToken, marca }
       );
       setModelos(modelosData);
     } catch (error) {
@@ -263,9 +263,9 @@ export function EquipoFormDialog({
       setLoading(true);
       const clienteRequest: CreateClienteRequest = {
         ...newClienteData,
-        created_by: user?.usuario_id || 0,
       };
       const nuevoCliente = await invoke<Cliente>("create_cliente", {
+        sessionToken,
         request: clienteRequest,
       }); // Agregar el nuevo cliente a la lista inmediatamente
       setClientes((prev) => [...prev, nuevoCliente]);
@@ -397,9 +397,9 @@ export function EquipoFormDialog({
           cliente_id: formData.cliente_id || undefined,
         };
         await invoke("update_equipo", {
+          sessionToken,
           equipoId: equipo.equipo_id,
           request: updateData,
-          updatedBy: user?.usuario_id || 0,
         });
 
         success(
@@ -416,10 +416,10 @@ export function EquipoFormDialog({
           equipo_precio: 0, // Precio fijo en 0
           equipo_ubicacion: formData.equipo_ubicacion || undefined,
           cliente_id: formData.cliente_id!,
-          created_by: user?.usuario_id || 0,
         };
 
         const equipoCreado = await invoke<Equipo>("create_equipo", {
+          sessionToken,
           request: equipoData,
         });
 
@@ -436,13 +436,13 @@ export function EquipoFormDialog({
             estado: "recibido",
             has_garantia: false,
             equipo_id: equipoCreado.equipo_id,
-            created_by: user?.usuario_id || 0,
             pre_informe: preInforme,
             cotizacion_id: undefined,
             informe_id: undefined,
           };
 
           const ordenCreada = await invoke<any>("create_orden_trabajo", {
+            sessionToken,
             request: ordenData,
           });
           ordenCodigo = ordenCreada?.orden_codigo || "Código no disponible";
@@ -471,7 +471,6 @@ export function EquipoFormDialog({
         equipo_tipo: "",
         equipo_ubicacion: "",
         cliente_id: undefined,
-        created_by: user?.usuario_id || 0,
       });
       setTipoIngreso("almacenamiento");
       setPreInforme("");
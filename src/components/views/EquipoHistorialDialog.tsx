@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
 import { Button } from "@/components/ui/button";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import {
   Dialog,
   DialogContent,
@@ -63,6 +64,7 @@ export function EquipoHistorialDialog({
   onOpenChange: (open: boolean) => void;
   equipo: Equipo | null;
 }) {
+  const { sessionToken } = useAuth();
   const [ordenes, setOrdenes] = useState<OrdenTrabajo[]>([]);
   const [loading, setLoading] = useState(false);
   const [informe, setInforme] = useState<Informe | null>(null);
@@ -74,6 +76,7 @@ export function EquipoHistorialDialog({
     if (open && equipo) {
       setLoading(true);
       invoke<OrdenTrabajo[]>("get_ordenes_trabajo_by_equipo", {
+        sessionToken,
         equipoId: equipo.equipo_id,
       })
         .then((data) => {
@@ -92,6 +95,7 @@ export function EquipoHistorialDialog({
   useEffect(() => {
     if (ordenes.length > 0 && ordenes[0].informe_id) {
       invoke<any>("get_informe_by_id", {
+        sessionToken,
         informeId: ordenes[0].informe_id,
       })
         .then((data) => {
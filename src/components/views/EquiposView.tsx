@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import {
   Table,
   TableBody,
@@ -31,6 +32,7 @@ interface Equipo {
 }
 
 export function EquiposView() {
+  const { sessionToken } = useAuth();
   const [equipos, setEquipos] = useState<Equipo[]>([]);
   const [loading, setLoading] = useState(true);
   const [searchTerm, setSearchTerm] = useState("");
@@ -40,7 +42,9 @@ export function EquiposView() {
   const loadEquipos = async () => {
     try {
       setLoading(true);
-      const equiposData = await invoke<Equipo[]>("get_equipos_with_cliente");
+      const equiposData = await invoke<Equipo[]>("get_equipos_with_cliente", {
+        sessionToken,
+      });
       setEquipos(equiposData);
     } catch (error) {
       console.error("Error cargando equipos:", error);
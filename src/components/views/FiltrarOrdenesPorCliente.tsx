@@ -2,6 +2,7 @@ import { useState, useEffect } from "react";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import {
   Dialog,
   DialogContent,
@@ -16,6 +17,7 @@ interface Props {
 }
 
 export function FiltrarOrdenesPorCliente({ onChange, resetKey }: Props) {
+  const { sessionToken } = useAuth();
   const [open, setOpen] = useState(false);
   const [seleccionadas, setSeleccionadas] = useState<string[]>([]);
   const [clientesDisponibles, setClientesDisponibles] = useState<string[]>([]);
@@ -51,7 +53,9 @@ export function FiltrarOrdenesPorCliente({ onChange, resetKey }: Props) {
     setLoading(true);
     setError(null);
     try {
-      const clientes = await invoke<string[]>("get_clientes_disponibles");
+      const clientes = await invoke<string[]>("get_clientes_disponibles", {
+        sessionToken,
+      });
       setClientesDisponibles(clientes);
       setClientesFiltrados(clientes);
     } catch (err) {
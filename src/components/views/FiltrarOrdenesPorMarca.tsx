@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { Button } from "@/components/ui/button";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import {
   Dialog,
   DialogContent,
@@ -15,6 +16,7 @@ interface Props {
 }
 
 export function FiltrarOrdenesPorMarca({ onChange, resetKey }: Props) {
+  const { sessionToken } = useAuth();
   const [open, setOpen] = useState(false);
   const [seleccionadas, setSeleccionadas] = useState<string[]>([]);
   const [marcasDisponibles, setMarcasDisponibles] = useState<string[]>([]);
@@ -39,7 +41,9 @@ export function FiltrarOrdenesPorMarca({ onChange, resetKey }: Props) {
     setError(null);
     try {
       console.log("🔄 Cargando marcas disponibles...");
-      const marcas = await invoke<string[]>("get_marcas_disponibles");
+      const marcas = await invoke<string[]>("get_marcas_disponibles", {
+        sessionToken,
+      });
       console.log("🏭 Marcas cargadas:", marcas);
       setMarcasDisponibles(marcas);
     } catch (err) {
@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { Button } from "@/components/ui/button";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import {
   Dialog,
   DialogContent,
@@ -15,6 +16,7 @@ interface Props {
 }
 
 export function FiltrarOrdenesPorModelo({ onChange, resetKey }: Props) {
+  const { sessionToken } = useAuth();
   const [open, setOpen] = useState(false);
   const [seleccionadas, setSeleccionadas] = useState<string[]>([]);
   const [modelosDisponibles, setModelosDisponibles] = useState<string[]>([]);
@@ -40,7 +42,9 @@ export function FiltrarOrdenesPorModelo({ onChange, resetKey }: Props) {
 
     try {
       console.log("🔄 Cargando modelos disponibles...");
-      const modelos = await invoke<string[]>("get_modelos_disponibles");
+      const modelos = await invoke<string[]>("get_modelos_disponibles", {
+        sessionToken,
+      });
       console.log("📱 Modelos cargados:", modelos);
       setModelosDisponibles(modelos);
     } catch (err) {
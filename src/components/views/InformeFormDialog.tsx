@@ -98,7 +98,7 @@ export default function InformeFormDialog({
   isEditing = false,
   ordenTrabajoId,
 }: InformeFormDialogProps) {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [loading, setLoading] = useState(false);
   const [loadingSendToClient, setLoadingSendToClient] = useState(false);
@@ -159,7 +159,7 @@ export default function InformeFormDialog({
   const loadPiezas = async () => {
     try {
       setLoadingPiezas(true);
-      const piezasData = await invoke<Pieza[]>("get_piezas");
+      const piezasData = await invoke<Pieza[]>("get_piezas", { sessionToken });
       setPiezas(piezasData);
     } catch (error) {
       console.error("Error cargando piezas:", error);
@@ -173,6 +173,7 @@ export default function InformeFormDialog({
 
     try {
       const piezasInforme = await invoke<PiezaInforme[]>("get_piezas_informe", {
+        sessionToken,
         informeId: informe.informe_id,
       });
 
@@ -207,6 +208,7 @@ export default function InformeFormDialog({
     try {
       // Primero obtener la orden de trabajo para conseguir la cotización asociada
       const ordenTrabajo = await invoke<any>("get_orden_trabajo_by_id", {
+        sessionToken,
         ordenId: ordenTrabajoId,
       });
 
@@ -217,6 +219,7 @@ export default function InformeFormDialog({
 
       // Obtener la cotización completa para acceder al campo informe
       const cotizacion = await invoke<any>("get_cotizacion_by_id", {
+        sessionToken,
         cotizacionId: ordenTrabajo.cotizacion_id,
       });
 
@@ -231,6 +234,7 @@ export default function InformeFormDialog({
 
       // Obtener las piezas de la cotización
       const piezasCotizacion = await invoke<any[]>("get_piezas_cotizacion", {
+        sessionToken,
         cotizacionId: ordenTrabajo.cotizacion_id,
       });
 
@@ -387,9 +391,9 @@ export default function InformeFormDialog({
               : undefined,
         };
         const result = await invoke<boolean>("update_informe", {
+          sessionToken,
           informeId: informe.informe_id,
           request: updateData,
-          updatedBy: user.usuario_id,
         });
 
         if (result) {
@@ -412,7 +416,6 @@ export default function InformeFormDialog({
           // Campos antiguos para compatibilidad con el backend
           informe_acciones: formData.diagnostico, // Mapear diagnóstico a informe_acciones
           informe_obs: formData.recomendaciones.trim() || undefined, // Mapear recomendaciones a informe_obs
-          created_by: user.usuario_id,
           piezas:
             selectedPiezas.length > 0
               ? selectedPiezas.map((pieza) => ({
@@ -420,9 +423,12 @@ export default function InformeFormDialog({
                   cantidad: pieza.cantidad,
                 }))
               : undefined,
+          // La orden se asocia en la misma transacción que descuenta el stock
+          orden_id: ordenTrabajoId || undefined,
         };
 
         const informeResult = await invoke<any>("create_informe", {
+          sessionToken,
           request: createData,
         });
         const informeId = informeResult?.informe_id ?? informeResult;
@@ -436,41 +442,10 @@ export default function InformeFormDialog({
           return;
         }
 
-        let asociadoAOrden = false; // Si se proporciona ordenTrabajoId, asociar el informe a la orden
-        if (ordenTrabajoId) {
-          try {
-            const asociado = await invoke<boolean>(
-              "asignar_informe_orden_trabajo",
-              {
-                ordenId: ordenTrabajoId,
-                informeId: informeId,
-                updatedBy: user.usuario_id,
-              }
-            );
-            asociadoAOrden = !!asociado;
-            if (!asociadoAOrden) {
-              showError(
-                "Advertencia",
-                "El informe se creó pero no se pudo asociar a la orden de trabajo."
-              );
-            }
-          } catch (error) {
-            console.error("Error asociando informe a orden de trabajo:", error);
-            showError(
-              "Advertencia",
-              "El informe se creó pero no se pudo asociar a la orden de trabajo."
-            );
-          }
-        }
-
         success(
           "Informe creado",
           `El informe ha sido creado exitosamente.` +
-            (ordenTrabajoId
-              ? asociadoAOrden
-                ? " (Asociado a la orden de trabajo)"
-                : " (No se pudo asociar a la orden de trabajo)"
-              : "")
+            (ordenTrabajoId ? " (Asociado a la orden de trabajo)" : "")
         );
         onInformeAdded();
       }
@@ -511,7 +486,6 @@ export default function InformeFormDialog({
           // Campos antiguos para compatibilidad con el backend
           informe_acciones: formData.diagnostico, // Mapear diagnóstico a informe_acciones
           informe_obs: formData.recomendaciones.trim() || undefined, // Mapear recomendaciones a informe_obs
-          created_by: user.usuario_id,
           piezas:
             selectedPiezas.length > 0
               ? selectedPiezas.map((pieza) => ({
@@ -519,9 +493,12 @@ export default function InformeFormDialog({
                   cantidad: pieza.cantidad,
                 }))
               : undefined,
+          // La orden se asocia en la misma transacción que descuenta el stock
+          orden_id: ordenTrabajoId || undefined,
         };
 
         const informeResult = await invoke<any>("create_informe", {
+          sessionToken,
           request: createData,
         });
         const informeId = informeResult?.informe_id ?? informeResult;
@@ -535,43 +512,17 @@ export default function InformeFormDialog({
           return;
         }
 
-        // Asociar el informe a la orden de trabajo si corresponde
-        let asociadoAOrden = false;
-        if (ordenTrabajoId) {
-          try {
-            const asociado = await invoke<boolean>(
-              "asignar_informe_orden_trabajo",
-              {
-                ordenId: ordenTrabajoId,
-                informeId: informeId,
-                updatedBy: user.usuario_id,
-              }
-            );
-            asociadoAOrden = !!asociado;
-          } catch (error) {
-            console.error("Error asociando informe a orden de trabajo:", error);
-            showError(
-              "Advertencia",
-              "El informe se creó pero no se pudo asociar a la orden de trabajo."
-            );
-          }
-        }
-
         // Enviar el informe al cliente
         try {
           await invoke<boolean>("send_informe_to_client", {
+            sessionToken,
             informeId: informeId,
-            sentBy: user.usuario_id,
           });
 
           success(
             "Informe creado y enviado",
             `El informe ha sido creado y enviado al cliente exitosamente.` +
-              (ordenTrabajoId
-                ? asociadoAOrden
-                  ? " (Asociado a la orden de trabajo)"
-                  : " (No se pudo asociar a la orden de trabajo)"
-                : "")
+              (ordenTrabajoId ? " (Asociado a la orden de trabajo)" : "")
           );
           onInformeAdded();
           onOpenChange(false);
@@ -609,17 +560,10 @@ export default function InformeFormDialog({
     try {
       setLoadingSendExisting(true);
 
-      // Enviar el informe existente al cliente
+      // Enviar el informe existente al cliente; el backend lo finaliza al enviarlo
       await invoke<boolean>("send_informe_to_client", {
+        sessionToken,
         informeId: informe.informe_id,
-        sentBy: user.usuario_id,
-      });
-
-      // Actualizar el estado del informe para que ya no sea borrador
-      await invoke<boolean>("update_informe", {
-        informeId: informe.informe_id,
-        request: { is_borrador: false },
-        updatedBy: user.usuario_id,
       });
 
       success(
@@ -655,6 +599,7 @@ export default function InformeFormDialog({
       if (isEditing && informe) {
         // Generar PDF para informe existente
         await invoke<string>("generate_informe_pdf", {
+          sessionToken,
           informeId: informe.informe_id,
         });
       } else {
@@ -673,6 +618,7 @@ export default function InformeFormDialog({
         };
 
         await invoke<string>("generate_informe_preview_pdf", {
+          sessionToken,
           data: pdfData,
         });
       }
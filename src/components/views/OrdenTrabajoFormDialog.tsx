@@ -96,7 +96,7 @@ export default function OrdenTrabajoFormDialog({
   orden,
   isEditing = false,
 }: OrdenTrabajoFormDialogProps) {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [loading, setLoading] = useState(false);
   const [equipos, setEquipos] = useState<Equipo[]>([]);
@@ -211,7 +211,9 @@ export default function OrdenTrabajoFormDialog({
   const loadEquipos = async () => {
     try {
       setLoadingEquipos(true);
-      const equiposData = await invoke<Equipo[]>("get_equipos_with_cliente");
+      const equiposData = await invoke<Equipo[]>("get_equipos_with_cliente", {
+        sessionToken,
+      });
       setEquipos(equiposData);
     } catch (error) {
       console.error("Error cargando equipos:", error);
@@ -343,9 +345,9 @@ export default function OrdenTrabajoFormDialog({
               : undefined,
         };
         const result = await invoke<boolean>("update_orden_trabajo", {
+          sessionToken,
           ordenId: orden.orden_id,
           request: updateData,
-          updatedBy: user.usuario_id,
         });
 
         if (result) {
@@ -365,12 +367,12 @@ export default function OrdenTrabajoFormDialog({
           estado: formData.estado,
           has_garantia: formData.has_garantia,
           equipo_id: parseInt(formData.equipo_id),
-          created_by: user.usuario_id,
           pre_informe: formData.pre_informe,
           cotizacion_id: null,
           informe_id: null,
         };
         const result = await invoke<number>("create_orden_trabajo", {
+          sessionToken,
           request: createData,
         });
 
@@ -139,7 +139,7 @@ const getTiempoEnEstado = (createdAt?: string) => {
 };
 
 export function OrdenesTrabajoView() {
-  const { user } = useAuth();
+  const { user, sessionToken } = useAuth();
   const { success, error: showError } = useToastContext();
   const [ordenes, setOrdenes] = useState<OrdenTrabajo[]>([]);
   const [loading, setLoading] = useState(true);
@@ -170,7 +170,9 @@ export function OrdenesTrabajoView() {
   const loadOrdenes = async () => {
     try {
       setLoading(true);
-      const ordenesData = await invoke<OrdenTrabajo[]>("get_ordenes_trabajo");
+      const ordenesData = await invoke<OrdenTrabajo[]>("get_ordenes_trabajo", {
+        sessionToken,
+      });
       setOrdenes(ordenesData);
     } catch (error) {
       console.error("Error cargando órdenes de trabajo:", error);
@@ -276,8 +278,8 @@ export function OrdenesTrabajoView() {
 
     try {
       const result = await invoke<boolean>("delete_orden_trabajo", {
+        sessionToken,
         ordenId: orden.orden_id,
-        deletedBy: user.usuario_id,
       });
 
       if (result) {
@@ -311,6 +313,7 @@ export function OrdenesTrabajoView() {
 
       // Obtener los detalles de la cotización
       const cotizacion = await invoke("get_cotizacion_by_id", {
+        sessionToken,
         cotizacionId: orden.cotizacion_id,
       });
 
@@ -360,6 +363,7 @@ export function OrdenesTrabajoView() {
     try {
       // Cargar el informe desde el backend
       const informeData = await invoke<any>("get_informe_by_id", {
+        sessionToken,
         informeId: orden.informe_id,
       });
 
@@ -643,9 +647,9 @@ export function OrdenesTrabajoView() {
             if (!user || !selectedOrdenForCotizacion) return;
 
             await invoke("cambiar_estado_orden_trabajo", {
+              sessionToken,
               ordenId: selectedOrdenForCotizacion.orden_id,
               nuevoEstado: "cotizacion_enviada",
-              updatedBy: user.usuario_id,
             });
 
             success(
@@ -19,6 +19,7 @@ import {
 import { Label } from "@/components/ui/label";
 import { Plus, Edit, Trash2 } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 
 interface Pieza {
   pieza_id: number;
@@ -37,6 +38,7 @@ interface FormData {
 }
 
 export default function PiezasView() {
+  const { sessionToken } = useAuth();
   const [piezas, setPiezas] = useState<Pieza[]>([]);
   const [loading, setLoading] = useState(true);
   const [showForm, setShowForm] = useState(false);
@@ -52,7 +54,7 @@ export default function PiezasView() {
   const loadPiezas = async () => {
     setLoading(true);
     try {
-      const data = await invoke<Pieza[]>("get_piezas");
+      const data = await invoke<Pieza[]>("get_piezas", { sessionToken });
       setPiezas(data);
     } catch (e) {
       // Manejo de error
@@ -114,6 +116,7 @@ export default function PiezasView() {
     try {
       if (editingPieza) {
         await invoke("update_pieza", {
+          sessionToken,
           piezaId: editingPieza.pieza_id,
           request: {
             pieza_nombre: formData.pieza_nombre,
@@ -126,6 +129,7 @@ export default function PiezasView() {
         });
       } else {
         await invoke("create_pieza", {
+          sessionToken,
           request: {
             pieza_nombre: formData.pieza_nombre,
             pieza_marca: formData.pieza_marca,
@@ -146,7 +150,7 @@ export default function PiezasView() {
   const handleDelete = async (pieza: Pieza) => {
     if (!window.confirm(`¿Eliminar la pieza "${pieza.pieza_nombre}"?`)) return;
     try {
-      await invoke("delete_pieza", { piezaId: pieza.pieza_id });
+      await invoke("delete_pieza", { sessionToken, piezaId: pieza.pieza_id });
       loadPiezas();
     } catch (e) {
       // Manejo de error
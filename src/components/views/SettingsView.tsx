@@ -28,7 +28,7 @@ interface ChangeEmailRequest {
 }
 
 export function SettingsView() {
-  const { user, validateSession } = useAuth();
+  const { user, sessionToken, validateSession } = useAuth();
 
   // Estados para cambio de contraseña
   const [currentPassword, setCurrentPassword] = useState("");
@@ -79,7 +79,7 @@ export function SettingsView() {
       };
 
       const result = await invoke<boolean>("change_user_password", {
-        usuarioId: user!.usuario_id,
+        sessionToken,
         request,
       });
 
@@ -128,7 +128,7 @@ export function SettingsView() {
       };
 
       const result = await invoke<any>("change_user_email", {
-        usuarioId: user!.usuario_id,
+        sessionToken,
         request,
       });
 
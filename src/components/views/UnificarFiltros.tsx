@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { useAuth } from "@/contexts/AuthContext";
 import { Button } from "@/components/ui/button";
 
 import { FiltrarOrdenesPorFecha } from "./FiltrarOrdenesPorFecha";
@@ -23,6 +24,7 @@ interface Props {
 }
 
 export function UnificarFiltros({ onFiltrar }: Props) {
+  const { sessionToken } = useAuth();
   const filtrosIniciales = {
     fecha_inicio: null as string | null,
     fecha_fin: null as string | null,
@@ -62,7 +64,7 @@ export function UnificarFiltros({ onFiltrar }: Props) {
 
       const ordenes = await invoke<OrdenTrabajo[]>(
         "get_ordenes_trabajo_filtradas",
-        { filtros: filtrosParaBackend }
+        { sessionToken, filtros: filtrosParaBackend }
       );
 
       console.log("📨 Órdenes filtradas recibidas:", ordenes.length);
@@ -17,6 +17,8 @@ interface AuthContextType {
   user: User | null;
   isAuthenticated: boolean;
   isLoading: boolean;
+  // Token que exigen los comandos del backend para identificar al usuario
+  sessionToken: string | null;
   login: (
     email: string,
     password: string
@@ -32,6 +34,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
   const [isLoading, setIsLoading] = useState(true);
 
   const isAuthenticated = user !== null;
+  const sessionToken = user?.session_token ?? null;
 
   // Función para verificar si la sesión ha expirado
   const isSessionExpired = (sessionExpiresAt: string | null): boolean => {
@@ -151,6 +154,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
         user,
         isAuthenticated,
         isLoading,
+        sessionToken,
         login,
         logout,
         validateSession,
@@ -118,8 +118,8 @@ async function checkOrdenPrioridadNoAtendida(ordenesData:OrdenTrabajoDetallada[]
 }
 
 // Funcion que llama a todas las posibles notifiaciones
-async function checkOrdenesAllNotifications() {
-    const ordenesData = await invoke<OrdenTrabajoDetallada[]>("get_ordenes_trabajo_detalladas");
+async function checkOrdenesAllNotifications(sessionToken: string | null) {
+    const ordenesData = await invoke<OrdenTrabajoDetallada[]>("get_ordenes_trabajo_detalladas", { sessionToken });
     const result_sin_cotizacion = await checkOrdenSinCotizacion(ordenesData);
     const result_cot_no_enviada = await checkOrdenCotNoEnviada(ordenesData);
     const result_prioridad_no_atendida = await checkOrdenPrioridadNoAtendida(ordenesData);
@@ -128,12 +128,12 @@ async function checkOrdenesAllNotifications() {
 };
 
 // Funcion para las notificaciones del tecnico, se ejecuta cuando carga en la view
-async function notificacionesTecnico(minutes: number) {
+async function notificacionesTecnico(minutes: number, sessionToken: string | null) {
     const { info } = useToastContext();
     useEffect(() => {
         const checkAndNotify = async () => {
             // Llamada a todas las notificaciones posibles para el tecnico
-            const results = await checkOrdenesAllNotifications();
+            const results = await checkOrdenesAllNotifications(sessionToken);
             // Iterando entre los resultados y creando los mensajes
             for (const result of results) {
                 if (result.hasOldOrders) {
@@ -154,13 +154,13 @@ async function notificacionesTecnico(minutes: number) {
         return () => {
             clearInterval(intervalId);
         };
-    }, [info, minutes]);
+    }, [info, minutes, sessionToken]);
 }
 
 // Funcion que se llama en el App, repitiendose con el tiempo del parametro
 export function usePeriodicNotification(intervalMinutes: number = 5) {
     const { currentView } = useView();
-    const { user } = useAuth();
+    const { user, sessionToken } = useAuth();
 
     // Evitar notificaciones en el login
     if (currentView == "login"){
@@ -168,6 +168,6 @@ export function usePeriodicNotification(intervalMinutes: number = 5) {
     }
     // Notificaciones para técnico
     if (user?.usuario_rol == "tecnico" || user?.usuario_rol == "admin"){
-        notificacionesTecnico(intervalMinutes);
+        notificacionesTecnico(intervalMinutes, sessionToken);
     }
 }
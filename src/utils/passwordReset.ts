@@ -61,9 +61,13 @@ export async function resetPasswordWithCode(
 }
 
 // Función para limpiar códigos expirados (función de administración)
-export async function cleanupExpiredResetCodes(): Promise<number> {
+export async function cleanupExpiredResetCodes(
+  sessionToken: string
+): Promise<number> {
   try {
-    const deletedCount = await invoke<number>("cleanup_expired_reset_codes");
+    const deletedCount = await invoke<number>("cleanup_expired_reset_codes", {
+      sessionToken,
+    });
     return deletedCount;
   } catch (error) {
     throw new Error(error as string);